@@ -0,0 +1,12 @@
+#version 330 core
+
+in vec2 texCoords;
+
+out vec4 FragColor;
+
+uniform sampler2D graphic;
+
+void main()
+{
+    FragColor = texture(graphic, texCoords);
+}
@@ -27,6 +27,10 @@ uniform vec4 projection;
 uniform int backgroundPass;
 
 #define WIDE_CHAR 1
+#define DOUBLE_WIDTH 4
+#define DOUBLE_HEIGHT_TOP 8
+#define DOUBLE_HEIGHT_BOTTOM 16
+#define DOUBLE_HEIGHT (DOUBLE_HEIGHT_TOP | DOUBLE_HEIGHT_BOTTOM)
 
 void main() {
     vec2 projectionOffset = projection.xy;
@@ -40,12 +44,18 @@ void main() {
     // Position of cell from top-left
     vec2 cellPosition = cellDim * gridCoords;
 
+    int flags = int(textColor.a);
+    bool doubleWidth = (flags & (DOUBLE_WIDTH | DOUBLE_HEIGHT)) != 0;
+
     if (backgroundPass != 0) {
         vec2 backgroundDim = cellDim;
-        if ((int(textColor.a) & WIDE_CHAR) != 0) {
+        if ((flags & WIDE_CHAR) != 0) {
             // Update wide char x dimension so it'll cover the following spacer.
             backgroundDim.x *= 2;
         }
+        if (doubleWidth) {
+            backgroundDim.x *= 2;
+        }
         vec2 finalPosition = cellPosition + backgroundDim * position;
         gl_Position =
             vec4(projectionOffset + projectionScale * finalPosition, 0.0, 1.0);
@@ -56,12 +66,36 @@ void main() {
         vec2 glyphOffset = glyph.xy;
         glyphOffset.y = cellDim.y - glyphOffset.y;
 
+        vec2 uvOffset = uv.xy;
+        vec2 uvSize = uv.zw;
+
+        if (doubleWidth) {
+            glyphSize.x *= 2.;
+            glyphOffset.x *= 2.;
+        }
+
+        if ((flags & DOUBLE_HEIGHT) != 0) {
+            glyphSize.y *= 2.;
+            glyphOffset.y *= 2.;
+
+            // The bottom half shows the lower part of the scaled glyph.
+            if ((flags & DOUBLE_HEIGHT_BOTTOM) != 0) {
+                glyphOffset.y -= cellDim.y;
+            }
+
+            // Clip the glyph to the cell, adjusting the texture coordinates to match.
+            float top = clamp(glyphOffset.y, 0., cellDim.y);
+            float bottom = clamp(glyphOffset.y + glyphSize.y, 0., cellDim.y);
+            uvOffset.y += uvSize.y * (top - glyphOffset.y) / glyphSize.y;
+            uvSize.y *= (bottom - top) / glyphSize.y;
+            glyphOffset.y = top;
+            glyphSize.y = bottom - top;
+        }
+
         vec2 finalPosition = cellPosition + glyphSize * position + glyphOffset;
         gl_Position =
             vec4(projectionOffset + projectionScale * finalPosition, 0.0, 1.0);
 
-        vec2 uvOffset = uv.xy;
-        vec2 uvSize = uv.zw;
         TexCoords = uvOffset + position * uvSize;
     }
 
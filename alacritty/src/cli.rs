@@ -1,7 +1,10 @@
 use std::cmp::max;
+use std::ops::RangeInclusive;
 use std::path::PathBuf;
 
 use clap::{crate_authors, crate_description, crate_name, crate_version, App, Arg};
+#[cfg(unix)]
+use clap::{AppSettings, ArgMatches, SubCommand};
 use log::{self, error, LevelFilter};
 use serde_yaml::Value;
 
@@ -10,6 +13,8 @@ use alacritty_terminal::config::Program;
 use crate::config::serde_utils;
 use crate::config::window::DEFAULT_NAME;
 use crate::config::Config;
+#[cfg(unix)]
+use crate::ipc::SocketMessage;
 
 #[cfg(not(any(target_os = "macos", windows)))]
 const CONFIG_PATH: &str = "$XDG_CONFIG_HOME/alacritty/alacritty.yml";
@@ -18,6 +23,9 @@ const CONFIG_PATH: &str = "%APPDATA%\\alacritty\\alacritty.yml";
 #[cfg(target_os = "macos")]
 const CONFIG_PATH: &str = "$HOME/.config/alacritty/alacritty.yml";
 
+/// Range of supported playback speeds for replaying recordings.
+const REPLAY_SPEEDS: RangeInclusive<f64> = 0.01..=100.;
+
 /// Options specified on the command line.
 pub struct Options {
     pub print_events: bool,
@@ -29,9 +37,27 @@ pub struct Options {
     pub log_level: LevelFilter,
     pub command: Option<Program>,
     pub hold: bool,
+    pub restore: bool,
+    pub record: Option<PathBuf>,
+    pub replay: Option<PathBuf>,
+    pub replay_speed: f64,
+    pub headless: bool,
+    pub script: Option<PathBuf>,
     pub working_directory: Option<PathBuf>,
     pub config_path: Option<PathBuf>,
     pub config_options: Value,
+    #[cfg(unix)]
+    pub msg: Option<MessageOptions>,
+}
+
+/// Options for the `msg` subcommand.
+#[cfg(unix)]
+pub struct MessageOptions {
+    /// IPC socket connection path override.
+    pub socket: Option<PathBuf>,
+
+    /// Message which should be sent to the running instance.
+    pub message: SocketMessage,
 }
 
 impl Default for Options {
@@ -46,9 +72,17 @@ impl Default for Options {
             log_level: LevelFilter::Warn,
             command: None,
             hold: false,
+            restore: false,
+            record: None,
+            replay: None,
+            replay_speed: 1.,
+            headless: false,
+            script: None,
             working_directory: None,
             config_path: None,
             config_options: Value::Null,
+            #[cfg(unix)]
+            msg: None,
         }
     }
 }
@@ -130,6 +164,45 @@ impl Options {
                     .help("Command and args to execute (must be last argument)"),
             )
             .arg(Arg::with_name("hold").long("hold").help("Remain open after child process exits"))
+            .arg(Arg::with_name("restore").long("restore").help("Restore the last saved session"))
+            .arg(
+                Arg::with_name("record")
+                    .long("record")
+                    .takes_value(true)
+                    .value_name("file")
+                    .help("Record the terminal in the asciicast format"),
+            )
+            .arg(
+                Arg::with_name("replay")
+                    .long("replay")
+                    .takes_value(true)
+                    .value_name("file")
+                    .conflicts_with_all(&["command", "restore", "record"])
+                    .help("Replay an asciicast recording instead of starting a shell"),
+            )
+            .arg(
+                Arg::with_name("replay-speed")
+                    .long("replay-speed")
+                    .takes_value(true)
+                    .requires("replay")
+                    .help(
+                        "Playback speed of the replayed recording, from 0.01 to 100 [default: 1.0]",
+                    ),
+            )
+            .arg(
+                Arg::with_name("headless")
+                    .long("headless")
+                    .conflicts_with_all(&["replay", "restore", "embed"])
+                    .help("Run the shell without a window, driven by a script"),
+            )
+            .arg(
+                Arg::with_name("script")
+                    .long("script")
+                    .takes_value(true)
+                    .value_name("file")
+                    .requires("headless")
+                    .help("Script for the headless mode [default: STDIN]"),
+            )
             .arg(
                 Arg::with_name("option")
                     .long("option")
@@ -138,6 +211,7 @@ impl Options {
                     .takes_value(true)
                     .help("Override configuration file options [example: cursor.style=Beam]"),
             )
+            .subcommands(subcommands())
             .get_matches();
 
         if matches.is_present("ref-test") {
@@ -190,6 +264,23 @@ impl Options {
             options.hold = true;
         }
 
+        if matches.is_present("restore") {
+            options.restore = true;
+        }
+
+        options.record = matches.value_of("record").map(PathBuf::from);
+        options.replay = matches.value_of("replay").map(PathBuf::from);
+
+        if let Some(speed) = matches.value_of("replay-speed") {
+            match speed.parse::<f64>() {
+                Ok(speed) if REPLAY_SPEEDS.contains(&speed) => options.replay_speed = speed,
+                _ => eprintln!("Invalid replay speed: {:?}", speed),
+            }
+        }
+
+        options.headless = matches.is_present("headless");
+        options.script = matches.value_of("script").map(PathBuf::from);
+
         if let Some(config_options) = matches.values_of("option") {
             for option in config_options {
                 match option_as_value(option) {
@@ -201,6 +292,11 @@ impl Options {
             }
         }
 
+        #[cfg(unix)]
+        if let Some(msg_matches) = matches.subcommand_matches("msg") {
+            options.msg = Some(MessageOptions::new(msg_matches));
+        }
+
         options
     }
 
@@ -253,8 +349,82 @@ impl Options {
     }
 }
 
+#[cfg(unix)]
+impl MessageOptions {
+    /// Build `MessageOptions` from the `msg` subcommand arguments.
+    fn new(matches: &ArgMatches<'_>) -> Self {
+        let socket = matches.value_of("socket").map(PathBuf::from);
+
+        let message = match matches.subcommand() {
+            ("create-window", _) => SocketMessage::CreateWindow,
+            ("reload-config", _) => SocketMessage::ReloadConfig,
+            ("config", Some(matches)) => {
+                let options = matches.values_of("options").into_iter().flatten();
+                SocketMessage::Config { options: options.map(String::from).collect() }
+            },
+            ("action", Some(matches)) => {
+                let action = matches.value_of("action").unwrap_or_default().to_owned();
+                SocketMessage::Action { action }
+            },
+            ("get-working-directory", _) => SocketMessage::GetWorkingDirectory,
+            // Clap enforces that one of the subcommands above is present.
+            _ => unreachable!(),
+        };
+
+        Self { socket, message }
+    }
+}
+
+/// Subcommands available on the current platform.
+#[cfg(unix)]
+fn subcommands() -> Vec<App<'static, 'static>> {
+    vec![msg_subcommand()]
+}
+
+/// Subcommands available on the current platform.
+#[cfg(not(unix))]
+fn subcommands() -> Vec<App<'static, 'static>> {
+    Vec::new()
+}
+
+/// Arguments for the `msg` subcommand.
+#[cfg(unix)]
+fn msg_subcommand() -> App<'static, 'static> {
+    SubCommand::with_name("msg")
+        .about("Send a message to a running Alacritty instance")
+        .setting(AppSettings::SubcommandRequiredElseHelp)
+        .arg(
+            Arg::with_name("socket")
+                .long("socket")
+                .short("s")
+                .takes_value(true)
+                .help("IPC socket connection path override [default: $ALACRITTY_SOCKET]"),
+        )
+        .subcommand(SubCommand::with_name("create-window").about("Create a new terminal window"))
+        .subcommand(SubCommand::with_name("reload-config").about("Reload the configuration file"))
+        .subcommand(
+            SubCommand::with_name("config").about("Override configuration options").arg(
+                Arg::with_name("options")
+                    .required(true)
+                    .multiple(true)
+                    .help("Configuration file options [example: cursor.style=Beam]"),
+            ),
+        )
+        .subcommand(
+            SubCommand::with_name("action").about("Execute a key binding action").arg(
+                Arg::with_name("action")
+                    .required(true)
+                    .help("Name of the action [example: ScrollToTop]"),
+            ),
+        )
+        .subcommand(
+            SubCommand::with_name("get-working-directory")
+                .about("Print the working directory of the focused window"),
+        )
+}
+
 /// Format an option in the format of `parent.field=value` to a serde Value.
-fn option_as_value(option: &str) -> Result<Value, serde_yaml::Error> {
+pub fn option_as_value(option: &str) -> Result<Value, serde_yaml::Error> {
     let mut yaml_text = String::with_capacity(option.len());
     let mut closing_brackets = String::new();
 
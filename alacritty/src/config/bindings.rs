@@ -16,6 +16,8 @@ use alacritty_terminal::config::Program;
 use alacritty_terminal::term::TermMode;
 use alacritty_terminal::vi_mode::ViMotion;
 
+use crate::config::ui_config::Hint;
+
 /// Describes a state and action to take in that state.
 ///
 /// This is the shared component of `MouseBinding` and `KeyBinding`.
@@ -103,12 +105,21 @@ pub enum Action {
     #[config(skip)]
     SearchAction(SearchAction),
 
+    /// Regex keyboard hints.
+    #[config(skip)]
+    Hint(Hint),
+
     /// Paste contents of system clipboard.
     Paste,
 
     /// Store current selection into clipboard.
     Copy,
 
+    /// Store current selection into clipboard as HTML, preserving its colors.
+    ///
+    /// The clipboard only supports plain text, so the HTML source is stored.
+    CopySelectionAsHtml,
+
     #[cfg(not(any(target_os = "macos", windows)))]
     /// Store current selection into selection buffer.
     CopySelection,
@@ -149,6 +160,15 @@ pub enum Action {
     /// Scroll all the way to the bottom.
     ScrollToBottom,
 
+    /// Scroll to the previous shell prompt.
+    ScrollToPreviousPrompt,
+
+    /// Scroll to the next shell prompt.
+    ScrollToNextPrompt,
+
+    /// Select the output of the last command and copy it to the clipboard.
+    SelectLastCommandOutput,
+
     /// Clear the display buffer(s) to remove history.
     ClearHistory,
 
@@ -167,6 +187,18 @@ pub enum Action {
     /// Spawn a new instance of Alacritty.
     SpawnNewInstance,
 
+    /// Create a new Alacritty window.
+    CreateNewWindow,
+
+    /// Save all windows for restoring them on the next launch.
+    SaveSession,
+
+    /// Start or stop recording the terminal in the asciicast format.
+    ToggleRecording,
+
+    /// Write the scrollback history to an HTML file in the home directory.
+    ExportScrollback,
+
     /// Toggle fullscreen.
     ToggleFullscreen,
 
@@ -249,6 +281,8 @@ pub enum ViAction {
     SearchEnd,
     /// Launch the URL below the vi mode cursor.
     Open,
+    /// Jump to the first line which arrived after the time entered in the search bar.
+    JumpToTime,
 }
 
 /// Search mode specific actions.
@@ -504,6 +538,10 @@ pub fn default_key_bindings() -> Vec<KeyBinding> {
             ViMotion::WordRightEnd;
         Key5,   ModifiersState::SHIFT, +BindingMode::VI, ~BindingMode::SEARCH;
             ViMotion::Bracket;
+        LBracket,                      +BindingMode::VI, ~BindingMode::SEARCH;
+            ViMotion::PreviousPrompt;
+        RBracket,                      +BindingMode::VI, ~BindingMode::SEARCH;
+            ViMotion::NextPrompt;
         Return,                        +BindingMode::SEARCH, +BindingMode::VI;
             SearchAction::SearchConfirm;
         Escape,                        +BindingMode::SEARCH; SearchAction::SearchCancel;
@@ -674,7 +712,7 @@ pub fn platform_key_bindings() -> Vec<KeyBinding> {
             Action::Esc("\x0c".into());
         K, ModifiersState::LOGO, ~BindingMode::VI, ~BindingMode::SEARCH;  Action::ClearHistory;
         V, ModifiersState::LOGO, ~BindingMode::VI; Action::Paste;
-        N, ModifiersState::LOGO; Action::SpawnNewInstance;
+        N, ModifiersState::LOGO; Action::CreateNewWindow;
         F, ModifiersState::CTRL | ModifiersState::LOGO; Action::ToggleFullscreen;
         C, ModifiersState::LOGO; Action::Copy;
         C, ModifiersState::LOGO, +BindingMode::VI, ~BindingMode::SEARCH; Action::ClearSelection;
@@ -1135,6 +1173,10 @@ impl<'a> Deserialize<'a> for KeyBinding {
 pub struct ModsWrapper(ModifiersState);
 
 impl ModsWrapper {
+    pub fn new(mods: ModifiersState) -> Self {
+        Self(mods)
+    }
+
     pub fn into_inner(self) -> ModifiersState {
         self.0
     }
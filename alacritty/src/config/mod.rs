@@ -12,7 +12,10 @@ use alacritty_terminal::config::{Config as TermConfig, LOG_TARGET_CONFIG};
 pub mod debug;
 pub mod font;
 pub mod monitor;
+pub mod notification;
 pub mod serde_utils;
+pub mod session;
+pub mod timestamps;
 pub mod ui_config;
 pub mod window;
 
@@ -20,7 +23,9 @@ mod bindings;
 mod mouse;
 
 use crate::cli::Options;
-pub use crate::config::bindings::{Action, Binding, BindingMode, Key, SearchAction, ViAction};
+pub use crate::config::bindings::{
+    default_key_bindings, Action, Binding, BindingMode, Key, SearchAction, ViAction,
+};
 #[cfg(test)]
 pub use crate::config::mouse::{ClickHandler, Mouse};
 use crate::config::ui_config::UIConfig;
@@ -119,18 +124,41 @@ pub fn load(options: &Options) -> Config {
     // Override config with CLI options.
     options.override_config(&mut config);
 
+    // Report the application's version to terminal queries.
+    config.version = String::from(env!("CARGO_PKG_VERSION"));
+
+    // Create key bindings for regex hints.
+    config.ui_config.generate_hint_bindings();
+
     config
 }
 
 /// Attempt to reload the configuration file.
-pub fn reload(config_path: &PathBuf, options: &Options) -> Result<Config> {
+///
+/// Without a configuration file, only the CLI overrides are applied to the default config.
+pub fn reload(config_path: Option<&PathBuf>, options: &Options) -> Result<Config> {
     // Load config, propagating errors.
     let config_options = options.config_options().clone();
-    let mut config = load_from(&config_path, config_options)?;
+    let mut config = match config_path {
+        Some(config_path) => load_from(config_path, config_options)?,
+        None => match Config::deserialize(config_options) {
+            Ok(config) => config,
+            Err(err) => {
+                error!(target: LOG_TARGET_CONFIG, "Unable to apply config options: {}", err);
+                return Err(err.into());
+            },
+        },
+    };
 
     // Override config with CLI options.
     options.override_config(&mut config);
 
+    // Report the application's version to terminal queries.
+    config.version = String::from(env!("CARGO_PKG_VERSION"));
+
+    // Create key bindings for regex hints.
+    config.ui_config.generate_hint_bindings();
+
     Ok(config)
 }
 
@@ -0,0 +1,12 @@
+use alacritty_config_derive::ConfigDeserialize;
+use alacritty_terminal::config::Program;
+
+/// Desktop notifications requested through `OSC 9` and `OSC 777`.
+#[derive(ConfigDeserialize, Clone, Debug, Default, PartialEq, Eq)]
+pub struct Notification {
+    /// Command to run, with the notification title and body appended as arguments.
+    pub command: Option<Program>,
+
+    /// Only run the command while the window is unfocused.
+    pub only_when_unfocused: bool,
+}
@@ -0,0 +1,8 @@
+use alacritty_config_derive::ConfigDeserialize;
+
+/// Saving and restoring of terminal sessions.
+#[derive(ConfigDeserialize, Clone, Debug, Default, PartialEq, Eq)]
+pub struct SessionConfig {
+    /// Save the terminal on exit and restore it on the next launch.
+    pub persist: bool,
+}
@@ -0,0 +1,77 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use glutin::event::ModifiersState;
+use serde::de::Error as SerdeError;
+use serde::{Deserialize, Deserializer};
+
+use alacritty_config_derive::ConfigDeserialize;
+
+use crate::config::bindings::ModsWrapper;
+
+/// Arrival times of terminal lines.
+#[derive(ConfigDeserialize, Clone, Debug, PartialEq, Eq)]
+pub struct Timestamps {
+    /// Show the arrival time next to every line.
+    pub gutter: bool,
+
+    /// Format of the timestamps, using `strftime` syntax.
+    pub format: String,
+
+    /// Modifier used to include timestamps in copied text.
+    copy_modifiers: CopyModifiers,
+}
+
+impl Default for Timestamps {
+    fn default() -> Self {
+        Self { gutter: false, format: String::from("%H:%M:%S"), copy_modifiers: Default::default() }
+    }
+}
+
+impl Timestamps {
+    pub fn copy_mods(&self) -> ModifiersState {
+        self.copy_modifiers.0
+    }
+
+    /// Number of columns reserved for the gutter.
+    pub fn gutter_width(&self) -> usize {
+        if self.gutter {
+            // Leave one column between the terminal and the timestamps.
+            self.format(SystemTime::now()).chars().count() + 1
+        } else {
+            0
+        }
+    }
+
+    /// Format a timestamp in the local timezone.
+    pub fn format(&self, timestamp: SystemTime) -> String {
+        let duration = timestamp.duration_since(UNIX_EPOCH).unwrap_or_default();
+        let timespec = time::Timespec::new(duration.as_secs() as i64, 0);
+        time::at(timespec).strftime(&self.format).map(|tm| tm.to_string()).unwrap_or_default()
+    }
+}
+
+/// Modifiers which include timestamps in copied text.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+struct CopyModifiers(ModifiersState);
+
+impl Default for CopyModifiers {
+    fn default() -> Self {
+        Self(ModifiersState::ALT)
+    }
+}
+
+impl<'de> Deserialize<'de> for CopyModifiers {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let mods = ModsWrapper::deserialize(deserializer)?.into_inner();
+
+        // Without modifiers, timestamps would be included in every copy.
+        if mods.is_empty() {
+            Err(SerdeError::custom("copy modifiers must not be empty"))
+        } else {
+            Ok(Self(mods))
+        }
+    }
+}
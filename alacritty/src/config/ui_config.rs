@@ -1,15 +1,27 @@
+use std::cell::RefCell;
+use std::mem;
 use std::path::PathBuf;
+use std::rc::Rc;
 
+use glutin::event::{ModifiersState, VirtualKeyCode};
 use log::error;
+use serde::de::Error as SerdeError;
 use serde::{Deserialize, Deserializer};
+use unicode_width::UnicodeWidthChar;
 
 use alacritty_config_derive::ConfigDeserialize;
-use alacritty_terminal::config::{Percentage, LOG_TARGET_CONFIG};
+use alacritty_terminal::config::{Percentage, Program, LOG_TARGET_CONFIG};
+use alacritty_terminal::term::search::RegexSearch;
 
-use crate::config::bindings::{self, Binding, KeyBinding, MouseBinding};
+use crate::config::bindings::{
+    self, Action, Binding, BindingMode, Key, KeyBinding, ModsWrapper, MouseBinding,
+};
 use crate::config::debug::Debug;
 use crate::config::font::Font;
 use crate::config::mouse::Mouse;
+use crate::config::notification::Notification;
+use crate::config::session::SessionConfig;
+use crate::config::timestamps::Timestamps;
 use crate::config::window::WindowConfig;
 
 #[derive(ConfigDeserialize, Debug, PartialEq)]
@@ -31,6 +43,10 @@ pub struct UIConfig {
     /// Live config reload.
     pub live_config_reload: bool,
 
+    /// Offer IPC through a unix socket.
+    #[cfg(unix)]
+    pub ipc_socket: bool,
+
     /// Path where config was loaded from.
     #[config(skip)]
     pub config_paths: Vec<PathBuf>,
@@ -43,6 +59,18 @@ pub struct UIConfig {
 
     /// Background opacity from 0.0 to 1.0.
     background_opacity: Percentage,
+
+    /// Regex hints for interacting with terminal content.
+    pub hints: Hints,
+
+    /// Desktop notifications requested by the application.
+    pub notification: Notification,
+
+    /// Saving and restoring of terminal sessions.
+    pub session: SessionConfig,
+
+    /// Arrival times of terminal lines.
+    pub timestamps: Timestamps,
 }
 
 impl Default for UIConfig {
@@ -50,6 +78,8 @@ impl Default for UIConfig {
         Self {
             alt_send_esc: true,
             live_config_reload: true,
+            #[cfg(unix)]
+            ipc_socket: true,
             font: Default::default(),
             window: Default::default(),
             mouse: Default::default(),
@@ -58,11 +88,30 @@ impl Default for UIConfig {
             key_bindings: Default::default(),
             mouse_bindings: Default::default(),
             background_opacity: Default::default(),
+            hints: Default::default(),
+            notification: Default::default(),
+            session: Default::default(),
+            timestamps: Default::default(),
         }
     }
 }
 
 impl UIConfig {
+    /// Generate key bindings for all keyboard hints.
+    pub fn generate_hint_bindings(&mut self) {
+        for hint in self.hints.enabled.iter() {
+            let binding = KeyBinding {
+                trigger: hint.binding.key,
+                mods: hint.binding.mods.into_inner(),
+                mode: BindingMode::empty(),
+                notmode: BindingMode::empty(),
+                action: Action::Hint(hint.clone()),
+            };
+
+            self.key_bindings.0.push(binding);
+        }
+    }
+
     #[inline]
     pub fn background_opacity(&self) -> f32 {
         self.background_opacity.as_f32()
@@ -155,3 +204,211 @@ pub struct Delta<T: Default> {
     /// Vertical change.
     pub y: T,
 }
+
+/// Keyboard regex hints.
+#[derive(ConfigDeserialize, Clone, Debug, PartialEq, Eq)]
+pub struct Hints {
+    /// Characters for the hint labels.
+    alphabet: HintsAlphabet,
+
+    /// All configured terminal hints.
+    pub enabled: Vec<Hint>,
+}
+
+impl Default for Hints {
+    fn default() -> Self {
+        // Add URL hint by default when no other hint is present.
+        let pattern = LazyRegex::new(
+            "(mailto:|gemini:|gopher:|https:|http:|news:|file:|git:|ssh:|ftp:)\
+             [^\u{0000}-\u{001F}\u{007F}-\u{009F}<>\"\\s{-}\\^⟨⟩`]+",
+        );
+
+        #[cfg(not(any(target_os = "macos", windows)))]
+        let action = HintAction::Command(Program::Just(String::from("xdg-open")));
+        #[cfg(target_os = "macos")]
+        let action = HintAction::Command(Program::Just(String::from("open")));
+        #[cfg(windows)]
+        let action = HintAction::Command(Program::WithArgs {
+            program: String::from("cmd"),
+            args: vec!["/c".to_string(), "start".to_string(), "".to_string()],
+        });
+
+        Self {
+            enabled: vec![Hint {
+                regex: pattern,
+                action,
+                binding: HintBinding {
+                    key: Key::Keycode(VirtualKeyCode::U),
+                    mods: ModsWrapper::new(ModifiersState::SHIFT | ModifiersState::CTRL),
+                },
+            }],
+            alphabet: Default::default(),
+        }
+    }
+}
+
+impl Hints {
+    /// Characters for the hint labels.
+    pub fn alphabet(&self) -> &str {
+        &self.alphabet.0
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct HintsAlphabet(String);
+
+impl Default for HintsAlphabet {
+    fn default() -> Self {
+        Self(String::from("jfkdls;ahgurieowpq"))
+    }
+}
+
+impl<'de> Deserialize<'de> for HintsAlphabet {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let value = String::deserialize(deserializer)?;
+
+        let mut character_count = 0;
+        for character in value.chars() {
+            if character.width() != Some(1) {
+                return Err(D::Error::custom("characters must be of width 1"));
+            }
+            character_count += 1;
+        }
+
+        if character_count < 2 {
+            return Err(D::Error::custom("must include at least 2 characters"));
+        }
+
+        Ok(Self(value))
+    }
+}
+
+/// Built-in actions for hint mode.
+#[derive(ConfigDeserialize, Clone, Debug, PartialEq, Eq)]
+pub enum HintInternalAction {
+    /// Copy the text to the clipboard.
+    Copy,
+    /// Write the text to the PTY/search.
+    Paste,
+}
+
+/// Actions for hint bindings.
+#[derive(Deserialize, Clone, Debug, PartialEq, Eq)]
+pub enum HintAction {
+    /// Built-in hint action.
+    #[serde(rename = "action")]
+    Action(HintInternalAction),
+
+    /// Command the text will be piped to.
+    #[serde(rename = "command")]
+    Command(Program),
+}
+
+/// Hint configuration.
+#[derive(Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct Hint {
+    /// Regex for finding matches.
+    pub regex: LazyRegex,
+
+    /// Action executed when this hint is triggered.
+    #[serde(flatten)]
+    pub action: HintAction,
+
+    /// Binding required to search for this hint.
+    binding: HintBinding,
+}
+
+/// Binding for triggering a keyboard hint.
+#[derive(Deserialize, Copy, Clone, Debug, PartialEq, Eq)]
+pub struct HintBinding {
+    pub key: Key,
+    #[serde(default)]
+    pub mods: ModsWrapper,
+}
+
+/// Lazy regex with interior mutability.
+#[derive(Clone, Debug)]
+pub struct LazyRegex(Rc<RefCell<LazyRegexVariant>>);
+
+impl LazyRegex {
+    fn new(pattern: &str) -> Self {
+        Self(Rc::new(RefCell::new(LazyRegexVariant::Pattern(pattern.to_owned()))))
+    }
+
+    /// Execute a function with the compiled regex DFAs as parameter.
+    ///
+    /// Returns `None` if the pattern could not be compiled.
+    pub fn with_compiled<T, F>(&self, f: F) -> Option<T>
+    where
+        F: FnOnce(&RegexSearch) -> T,
+    {
+        self.0.borrow_mut().compiled().map(f)
+    }
+}
+
+impl<'de> Deserialize<'de> for LazyRegex {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        Ok(Self::new(&String::deserialize(deserializer)?))
+    }
+}
+
+/// Regex equality simply checks for identical strings.
+impl PartialEq for LazyRegex {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.borrow().pattern() == other.0.borrow().pattern()
+    }
+}
+impl Eq for LazyRegex {}
+
+/// Regex which is compiled on demand, to avoid expensive computations at startup.
+#[derive(Clone, Debug)]
+enum LazyRegexVariant {
+    Compiled(String, Box<RegexSearch>),
+    Invalid(String),
+    Pattern(String),
+}
+
+impl LazyRegexVariant {
+    /// Get a reference to the compiled regex.
+    ///
+    /// If the regex is not already compiled, this will compile the DFAs and store them for future
+    /// access.
+    fn compiled(&mut self) -> Option<&RegexSearch> {
+        // Check if the regex has already been compiled.
+        let pattern = match self {
+            Self::Compiled(_, regex_search) => return Some(regex_search),
+            Self::Invalid(_) => return None,
+            Self::Pattern(pattern) => mem::take(pattern),
+        };
+
+        // Compile the regex, logging errors only once.
+        *self = match RegexSearch::new(&pattern) {
+            Ok(regex_search) => Self::Compiled(pattern, Box::new(regex_search)),
+            Err(err) => {
+                error!(
+                    target: LOG_TARGET_CONFIG,
+                    "Config error: invalid hint regex {:?}: {}", pattern, err
+                );
+                Self::Invalid(pattern)
+            },
+        };
+
+        match self {
+            Self::Compiled(_, regex_search) => Some(regex_search),
+            _ => None,
+        }
+    }
+
+    /// Get the original regex pattern.
+    fn pattern(&self) -> &str {
+        match self {
+            Self::Compiled(pattern, _) | Self::Invalid(pattern) | Self::Pattern(pattern) => pattern,
+        }
+    }
+}
@@ -49,7 +49,7 @@ pub struct WindowConfig {
     padding: Delta<u8>,
 
     /// Initial dimensions.
-    dimensions: Dimensions,
+    pub dimensions: Dimensions,
 }
 
 impl Default for WindowConfig {
@@ -10,22 +10,21 @@ use std::time::Instant;
 
 use glutin::dpi::{PhysicalPosition, PhysicalSize};
 use glutin::event::ModifiersState;
-use glutin::event_loop::EventLoop;
-#[cfg(not(any(target_os = "macos", windows)))]
+use glutin::event_loop::EventLoopWindowTarget;
+#[cfg(all(feature = "x11", not(any(target_os = "macos", windows))))]
 use glutin::platform::unix::EventLoopWindowTargetExtUnix;
 use glutin::window::CursorIcon;
 use log::{debug, info};
 use parking_lot::MutexGuard;
 use unicode_width::UnicodeWidthChar;
 #[cfg(all(feature = "wayland", not(any(target_os = "macos", windows))))]
-use wayland_client::{Display as WaylandDisplay, EventQueue};
-
-use crossfont::{self, Rasterize, Rasterizer};
+use wayland_client::EventQueue;
 
 use alacritty_terminal::event::{EventListener, OnResize};
 use alacritty_terminal::grid::Dimensions as _;
 use alacritty_terminal::index::{Column, Direction, Line, Point};
 use alacritty_terminal::selection::Selection;
+use alacritty_terminal::term::color::Rgb;
 use alacritty_terminal::term::{SizeInfo, Term, TermMode, MIN_COLS, MIN_SCREEN_LINES};
 
 use crate::config::font::Font;
@@ -35,16 +34,21 @@ use crate::config::window::StartupMode;
 use crate::config::Config;
 use crate::cursor::IntoRects;
 use crate::event::{Mouse, SearchState};
+use crate::hint::HintState;
 use crate::message_bar::{MessageBuffer, MessageType};
 use crate::meter::Meter;
+use crate::renderer::graphics::RenderGraphic;
 use crate::renderer::rects::{RenderLines, RenderRect};
-use crate::renderer::{self, GlyphCache, QuadRenderer};
+use crate::renderer::{self, GlyphCache, QuadRenderer, Rasterizers};
 use crate::url::{Url, Urls};
 use crate::window::{self, Window};
 
 const FORWARD_SEARCH_LABEL: &str = "Search: ";
 const BACKWARD_SEARCH_LABEL: &str = "Backward Search: ";
 
+/// Label for the time of day entered in the search bar.
+const TIME_JUMP_LABEL: &str = "Jump to time: ";
+
 #[derive(Debug)]
 pub enum Error {
     /// Error with window management.
@@ -153,28 +157,37 @@ pub struct Display {
     /// Currently highlighted URL.
     pub highlighted_url: Option<Url>,
 
-    #[cfg(all(feature = "wayland", not(any(target_os = "macos", windows))))]
-    pub wayland_event_queue: Option<EventQueue>,
-
     #[cfg(not(any(target_os = "macos", windows)))]
     pub is_x11: bool,
 
     /// UI cursor visibility for blinking.
     pub cursor_hidden: bool,
 
+    /// Regex hint selection state.
+    pub hint_state: HintState,
+
     renderer: QuadRenderer,
     glyph_cache: GlyphCache,
+    rasterizers: Rasterizers,
     meter: Meter,
 }
 
 impl Display {
-    pub fn new<E>(config: &Config, event_loop: &EventLoop<E>) -> Result<Display, Error> {
+    pub fn new<E>(
+        config: &Config,
+        event_loop: &EventLoopWindowTarget<E>,
+        rasterizers: &Rasterizers,
+        #[cfg(all(feature = "wayland", not(any(target_os = "macos", windows))))]
+        wayland_event_queue: Option<&EventQueue>,
+    ) -> Result<Display, Error> {
         // Guess DPR based on first monitor.
         let estimated_dpr =
             event_loop.available_monitors().next().map(|m| m.scale_factor()).unwrap_or(1.);
 
         // Guess the target window dimensions.
-        let metrics = GlyphCache::static_metrics(config.ui_config.font.clone(), estimated_dpr)?;
+        let font = &config.ui_config.font;
+        let rasterizer = rasterizers.get(estimated_dpr, font.use_thin_strokes)?;
+        let metrics = GlyphCache::static_metrics(font.clone(), &rasterizer)?;
         let (cell_width, cell_height) = compute_cell_size(config, &metrics);
 
         // Guess the target window size if the user has specified the number of lines/columns.
@@ -187,23 +200,13 @@ impl Display {
         debug!("Estimated window size: {:?}", estimated_size);
         debug!("Estimated cell size: {} x {}", cell_width, cell_height);
 
-        #[cfg(all(feature = "wayland", not(any(target_os = "macos", windows))))]
-        let mut wayland_event_queue = None;
-
-        // Initialize Wayland event queue, to handle Wayland callbacks.
-        #[cfg(all(feature = "wayland", not(any(target_os = "macos", windows))))]
-        if let Some(display) = event_loop.wayland_display() {
-            let display = unsafe { WaylandDisplay::from_external_display(display as _) };
-            wayland_event_queue = Some(display.create_event_queue());
-        }
-
         // Spawn the Alacritty window.
         let mut window = Window::new(
             event_loop,
             &config,
             estimated_size,
             #[cfg(all(feature = "wayland", not(any(target_os = "macos", windows))))]
-            wayland_event_queue.as_ref(),
+            wayland_event_queue,
         )?;
 
         info!("Device pixel ratio: {}", window.dpr);
@@ -212,7 +215,7 @@ impl Display {
         let mut renderer = QuadRenderer::new()?;
 
         let (glyph_cache, cell_width, cell_height) =
-            Self::new_glyph_cache(window.dpr, &mut renderer, config)?;
+            Self::new_glyph_cache(window.dpr, rasterizers, &mut renderer, config)?;
 
         if let Some(dimensions) = dimensions {
             if (estimated_dpr - window.dpr).abs() < f64::EPSILON {
@@ -228,7 +231,7 @@ impl Display {
         let viewport_size = window.inner_size();
 
         // Create new size with at least one column and row.
-        let size_info = SizeInfo::new(
+        let mut size_info = SizeInfo::new(
             viewport_size.width as f32,
             viewport_size.height as f32,
             cell_width,
@@ -237,6 +240,7 @@ impl Display {
             padding.1,
             config.ui_config.window.dynamic_padding && dimensions.is_none(),
         );
+        size_info.reserve_cols(config.ui_config.timestamps.gutter_width());
 
         info!("Cell size: {} x {}", cell_width, cell_height);
         info!("Padding: {} x {}", size_info.padding_x(), size_info.padding_y());
@@ -298,25 +302,26 @@ impl Display {
             window,
             renderer,
             glyph_cache,
+            rasterizers: rasterizers.clone(),
             meter: Meter::new(),
             size_info,
             urls: Urls::new(),
             highlighted_url: None,
             #[cfg(not(any(target_os = "macos", windows)))]
             is_x11,
-            #[cfg(all(feature = "wayland", not(any(target_os = "macos", windows))))]
-            wayland_event_queue,
             cursor_hidden: false,
+            hint_state: HintState::new(config.ui_config.hints.alphabet()),
         })
     }
 
     fn new_glyph_cache(
         dpr: f64,
+        rasterizers: &Rasterizers,
         renderer: &mut QuadRenderer,
         config: &Config,
     ) -> Result<(GlyphCache, f32, f32), Error> {
         let font = config.ui_config.font.clone();
-        let rasterizer = Rasterizer::new(dpr as f32, config.ui_config.font.use_thin_strokes)?;
+        let rasterizer = rasterizers.get(dpr, font.use_thin_strokes)?;
 
         // Initialize glyph cache.
         let glyph_cache = {
@@ -346,10 +351,11 @@ impl Display {
     /// This will return a tuple of the cell width and height.
     fn update_glyph_cache(&mut self, config: &Config, font: &Font) -> (f32, f32) {
         let cache = &mut self.glyph_cache;
+        let rasterizers = &self.rasterizers;
         let dpr = self.window.dpr;
 
         self.renderer.with_loader(|mut api| {
-            let _ = cache.update_font_size(font, dpr, &mut api);
+            let _ = cache.update_font_size(font, dpr, rasterizers, &mut api);
         });
 
         // Compute new cell sizes.
@@ -376,6 +382,8 @@ impl Display {
     ) where
         T: EventListener,
     {
+        self.window.make_current();
+
         let (mut cell_width, mut cell_height) =
             (self.size_info.cell_width(), self.size_info.cell_height());
 
@@ -413,6 +421,7 @@ impl Display {
             message_buffer.message().map(|m| m.text(&self.size_info).len()).unwrap_or(0);
         let search_lines = if search_active { 1 } else { 0 };
         self.size_info.reserve_lines(message_bar_lines + search_lines);
+        self.size_info.reserve_cols(config.ui_config.timestamps.gutter_width());
 
         // Resize PTY.
         pty_resize_handle.on_resize(&self.size_info);
@@ -437,7 +446,7 @@ impl Display {
     /// This call may block if vsync is enabled.
     pub fn draw<T>(
         &mut self,
-        terminal: MutexGuard<'_, Term<T>>,
+        mut terminal: MutexGuard<'_, Term<T>>,
         message_buffer: &MessageBuffer,
         config: &Config,
         mouse: &Mouse,
@@ -451,6 +460,9 @@ impl Display {
             .and_then(|focused_match| terminal.grid().clamp_buffer_range_to_visible(focused_match));
         let cursor_hidden = self.cursor_hidden || search_state.regex().is_some();
 
+        // Collect graphics which have been added or removed since the last frame.
+        let graphics_queues = terminal.graphics_take_queues();
+
         // Collect renderable content before the terminal is dropped.
         let mut content = terminal.renderable_content(config, !cursor_hidden);
         let mut grid_cells = Vec::new();
@@ -473,14 +485,61 @@ impl Display {
 
         let vi_mode = terminal.mode().contains(TermMode::VI);
         let vi_mode_cursor = if vi_mode { Some(terminal.vi_mode_cursor) } else { None };
+        let vi_mode_url_point = vi_mode_cursor.map(|cursor| terminal.viewport_point(cursor.point));
+
+        // Collect the viewport position of all hint labels.
+        self.hint_state.update_matches(&terminal);
+        let hint_labels: Vec<_> = self
+            .hint_state
+            .matches()
+            .iter()
+            .zip(self.hint_state.labels())
+            .filter(|(_, label)| !label.is_empty())
+            .filter_map(|(hint_match, label)| {
+                let start = *terminal.grid().clamp_buffer_range_to_visible(hint_match)?.start();
+                let start = terminal.viewport_point(start);
+
+                // Truncate labels which would extend beyond the last column.
+                let mut label = label.clone();
+                label.truncate(terminal.cols().0 - start.col.0);
+
+                Some((start, label))
+            })
+            .collect();
+
+        // Collect the arrival time of all visible lines.
+        let timestamps: Vec<_> = if config.ui_config.timestamps.gutter {
+            (0..size_info.screen_lines().0)
+                .filter_map(|line| {
+                    let point = terminal.visible_to_buffer(Point::new(Line(line), Column(0)));
+                    let timestamp = terminal.grid()[point.line].timestamp()?;
+                    Some((Line(line), config.ui_config.timestamps.format(timestamp)))
+                })
+                .collect()
+        } else {
+            Vec::new()
+        };
 
         // Drop terminal as early as possible to free lock.
         drop(terminal);
 
+        self.window.make_current();
+
+        if let Some(graphics_queues) = graphics_queues {
+            self.renderer.update_graphics(graphics_queues);
+        }
+
         self.renderer.with_api(&config.ui_config, &size_info, |api| {
             api.clear(background_color);
         });
 
+        // Draw graphics placed below the text before any of the cells.
+        let (graphics_below, graphics): (Vec<_>, Vec<_>) = grid_cells
+            .iter()
+            .filter_map(RenderGraphic::from_cell)
+            .partition(|graphic| graphic.z_index < 0);
+        self.renderer.draw_graphics(&size_info, graphics_below);
+
         let mut lines = RenderLines::new();
         let mut urls = Urls::new();
 
@@ -514,9 +573,29 @@ impl Display {
                     // Draw the cell.
                     api.render_cell(cell, glyph_cache);
                 }
+
+                // Draw hint labels on top of their matches.
+                let colors = config.colors.hints;
+                for (point, label) in hint_labels {
+                    let (first, rest) = label.split_at(1);
+                    let first: String = first.iter().collect();
+                    let rest: String = rest.iter().collect();
+
+                    let fg = colors.start.foreground;
+                    let bg = colors.start.background;
+                    api.render_string(glyph_cache, point, fg, bg, &first);
+
+                    let point = Point::new(point.line, point.col + 1);
+                    let fg = colors.end.foreground;
+                    let bg = colors.end.background;
+                    api.render_string(glyph_cache, point, fg, bg, &rest);
+                }
             });
         }
 
+        // Draw the remaining graphics on top of the text.
+        self.renderer.draw_graphics(&size_info, graphics);
+
         let mut rects = lines.rects(&metrics, &size_info);
 
         // Update visible URLs.
@@ -540,7 +619,7 @@ impl Display {
         if let Some(vi_mode_cursor) = vi_mode_cursor {
             // Highlight URLs at the vi mode cursor position.
             let vi_mode_point = vi_mode_cursor.point;
-            if let Some(url) = self.urls.find_at(vi_mode_point) {
+            if let Some(url) = vi_mode_url_point.and_then(|point| self.urls.find_at(point)) {
                 rects.append(&mut url.rects(&metrics, &size_info));
             }
 
@@ -552,6 +631,10 @@ impl Display {
             self.draw_line_indicator(config, &size_info, total_lines, None, display_offset);
         }
 
+        if !timestamps.is_empty() {
+            self.draw_timestamps(config, &size_info, timestamps, background_color);
+        }
+
         // Push the cursor rects for rendering.
         if let Some(cursor) = cursor {
             for rect in cursor.rects(&size_info, config.cursor.thickness()) {
@@ -614,6 +697,7 @@ impl Display {
         let ime_position = match search_state.regex() {
             Some(regex) => {
                 let search_label = match search_state.direction() {
+                    _ if search_state.time_jump() => TIME_JUMP_LABEL,
                     Direction::Right => FORWARD_SEARCH_LABEL,
                     Direction::Left => BACKWARD_SEARCH_LABEL,
                 };
@@ -740,6 +824,25 @@ impl Display {
         }
     }
 
+    /// Draw the arrival time of lines in the gutter to the right of the terminal.
+    fn draw_timestamps(
+        &mut self,
+        config: &Config,
+        size_info: &SizeInfo,
+        timestamps: Vec<(Line, String)>,
+        bg: Rgb,
+    ) {
+        let fg = config.colors.bright.black;
+        let column = size_info.cols() + 1;
+
+        let glyph_cache = &mut self.glyph_cache;
+        self.renderer.with_api(&config.ui_config, size_info, |mut api| {
+            for (line, text) in timestamps {
+                api.render_string(glyph_cache, Point::new(line, column), fg, bg, &text);
+            }
+        });
+    }
+
     /// Requst a new frame for a window on Wayland.
     #[inline]
     #[cfg(all(feature = "wayland", not(any(target_os = "macos", windows))))]
@@ -761,6 +864,14 @@ impl Display {
     }
 }
 
+impl Drop for Display {
+    fn drop(&mut self) {
+        // Switch OpenGL context before dropping, otherwise objects (like programs) from other
+        // contexts might be deleted.
+        self.window.make_current();
+    }
+}
+
 /// Calculate the cell dimensions based on font metrics.
 ///
 /// This will return a tuple of the cell width and height.
@@ -784,7 +895,8 @@ fn window_size(
 ) -> PhysicalSize<u32> {
     let padding = config.ui_config.window.padding(dpr);
 
-    let grid_width = cell_width * dimensions.columns.0.max(MIN_COLS) as f32;
+    let columns = dimensions.columns.0.max(MIN_COLS) + config.ui_config.timestamps.gutter_width();
+    let grid_width = cell_width * columns as f32;
     let grid_height = cell_height * dimensions.lines.0.max(MIN_SCREEN_LINES) as f32;
 
     let width = (padding.0).mul_add(2., grid_width).floor();
@@ -2,56 +2,71 @@
 
 use std::borrow::Cow;
 use std::cmp::{max, min};
-use std::collections::VecDeque;
+use std::collections::{HashMap, VecDeque};
 use std::env;
+use std::error::Error;
 use std::f32;
 use std::fmt::Debug;
-#[cfg(not(any(target_os = "macos", windows)))]
-use std::fs;
-use std::fs::File;
-use std::io::Write;
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufReader, Write};
 use std::mem;
 use std::ops::RangeInclusive;
-use std::path::PathBuf;
-#[cfg(not(any(target_os = "macos", windows)))]
-use std::sync::atomic::Ordering;
-use std::sync::Arc;
-use std::time::{Duration, Instant};
+#[cfg(unix)]
+use std::os::unix::fs::OpenOptionsExt;
+#[cfg(not(windows))]
+use std::os::unix::io::RawFd;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
 use glutin::dpi::PhysicalSize;
-use glutin::event::{ElementState, Event as GlutinEvent, ModifiersState, MouseButton, WindowEvent};
+use glutin::event::{
+    ElementState, Event as GlutinEvent, ModifiersState, MouseButton, VirtualKeyCode, WindowEvent,
+};
 use glutin::event_loop::{ControlFlow, EventLoop, EventLoopProxy, EventLoopWindowTarget};
 use glutin::platform::run_return::EventLoopExtRunReturn;
 #[cfg(all(feature = "wayland", not(any(target_os = "macos", windows))))]
 use glutin::platform::unix::EventLoopWindowTargetExtUnix;
-use log::info;
-use serde_json as json;
+use glutin::window::WindowId;
+use log::{debug, error, info};
+#[cfg(unix)]
+use serde_yaml::Value;
+#[cfg(all(feature = "wayland", not(any(target_os = "macos", windows))))]
+use wayland_client::{Display as WaylandDisplay, EventQueue};
 
 use crossfont::{self, Size};
 
-use alacritty_terminal::config::LOG_TARGET_CONFIG;
-use alacritty_terminal::event::{Event as TerminalEvent, EventListener, Notify, OnResize};
+use alacritty_terminal::asciicast::Recording;
+use alacritty_terminal::config::{Capability, LOG_TARGET_CONFIG};
+use alacritty_terminal::event::{Event as TerminalEvent, EventListener, Notify};
 use alacritty_terminal::grid::{Dimensions, Scroll};
 use alacritty_terminal::index::{Boundary, Column, Direction, Line, Point, Side};
 use alacritty_terminal::selection::{Selection, SelectionType};
-use alacritty_terminal::sync::FairMutex;
+use alacritty_terminal::term::session::Session;
 use alacritty_terminal::term::{ClipboardType, SizeInfo, Term, TermMode};
-#[cfg(not(windows))]
-use alacritty_terminal::tty;
 
+#[cfg(unix)]
+use crate::cli;
 use crate::cli::Options as CLIOptions;
 use crate::clipboard::Clipboard;
 use crate::config;
+#[cfg(unix)]
+use crate::config::serde_utils;
 use crate::config::Config;
 use crate::daemon::start_daemon;
-use crate::display::{Display, DisplayUpdate};
+use crate::display::DisplayUpdate;
+use crate::hint::HintState;
 use crate::input::{self, ActionContext as _, FONT_SIZE_STEP};
+#[cfg(unix)]
+use crate::ipc::{SocketMessage, SocketReply};
 #[cfg(target_os = "macos")]
 use crate::macos;
-use crate::message_bar::{Message, MessageBuffer};
+use crate::message_bar::{Message, MessageBuffer, MessageType};
+use crate::renderer::Rasterizers;
 use crate::scheduler::{Scheduler, TimerId};
+use crate::session;
 use crate::url::{Url, Urls};
 use crate::window::Window;
+use crate::window_context::WindowContext;
 
 /// Duration after the last user input until an unlimited search is performed.
 pub const TYPING_SEARCH_DELAY: Duration = Duration::from_millis(500);
@@ -59,30 +74,72 @@ pub const TYPING_SEARCH_DELAY: Duration = Duration::from_millis(500);
 /// Maximum number of lines for the blocking search while still typing the search regex.
 const MAX_SEARCH_WHILE_TYPING: Option<usize> = Some(1000);
 
+/// Maximum number of archived scrollback segments loaded by a single search.
+const MAX_SEARCH_PAGE_INS: usize = 10;
+
 /// Maximum number of search terms stored in the history.
 const MAX_HISTORY_SIZE: usize = 255;
 
+/// Minimum time between two desktop notifications of a window.
+const MIN_NOTIFICATION_INTERVAL: Duration = Duration::from_millis(1000);
+
+/// Maximum number of pending escape sequence permission requests.
+const MAX_PERMISSION_REQUESTS: usize = 16;
+
+/// Message bar target of escape sequence permission prompts.
+const PERMISSION_PROMPT_TARGET: &str = "permission_prompt";
+
+/// Maximum number of recently closed windows kept for restoring them on the next launch.
+const MAX_CLOSED_SESSIONS: usize = 8;
+
 /// Events dispatched through the UI event loop.
 #[derive(Debug, Clone)]
-pub enum Event {
+pub struct Event {
+    /// Window receiving the event, events without a window are sent to all windows.
+    window_id: Option<WindowId>,
+
+    /// Event payload.
+    payload: EventType,
+}
+
+impl Event {
+    pub fn new<I: Into<Option<WindowId>>>(payload: EventType, window_id: I) -> Self {
+        Self { window_id: window_id.into(), payload }
+    }
+}
+
+impl From<EventType> for Event {
+    fn from(payload: EventType) -> Self {
+        Self::new(payload, None)
+    }
+}
+
+impl From<Event> for GlutinEvent<'_, Event> {
+    fn from(event: Event) -> Self {
+        GlutinEvent::UserEvent(event)
+    }
+}
+
+/// Alacritty event payloads.
+#[derive(Debug, Clone)]
+pub enum EventType {
     TerminalEvent(TerminalEvent),
     DPRChanged(f64, (u32, u32)),
     Scroll(Scroll),
     ConfigReload(PathBuf),
     Message(Message),
+    #[cfg(unix)]
+    Ipc(SocketMessage, SocketReply),
+    CreateWindow(Option<PathBuf>),
+    SaveSession,
+    ToggleRecording,
     BlinkCursor,
     SearchNext,
 }
 
-impl From<Event> for GlutinEvent<'_, Event> {
-    fn from(event: Event) -> Self {
-        GlutinEvent::UserEvent(event)
-    }
-}
-
-impl From<TerminalEvent> for Event {
+impl From<TerminalEvent> for EventType {
     fn from(event: TerminalEvent) -> Self {
-        Event::TerminalEvent(event)
+        EventType::TerminalEvent(event)
     }
 }
 
@@ -109,10 +166,13 @@ pub struct SearchState {
 
     /// Current position in the search history.
     history_index: Option<usize>,
+
+    /// Whether the search bar is used to enter a time to jump to.
+    time_jump: bool,
 }
 
 impl SearchState {
-    fn new() -> Self {
+    pub fn new() -> Self {
         Self::default()
     }
 
@@ -126,6 +186,11 @@ impl SearchState {
         self.direction
     }
 
+    /// Whether the search bar is used to enter a time to jump to.
+    pub fn time_jump(&self) -> bool {
+        self.time_jump
+    }
+
     /// Focused match during vi-less search.
     pub fn focused_match(&self) -> Option<&RangeInclusive<Point<usize>>> {
         self.focused_match.as_ref()
@@ -146,6 +211,7 @@ impl Default for SearchState {
             history_index: Default::default(),
             history: Default::default(),
             origin: Default::default(),
+            time_jump: Default::default(),
         }
     }
 }
@@ -158,18 +224,27 @@ pub struct ActionContext<'a, N, T> {
     pub mouse: &'a mut Mouse,
     pub received_count: &'a mut usize,
     pub suppress_chars: &'a mut bool,
+    pub pressed_key: &'a mut Option<VirtualKeyCode>,
     pub modifiers: &'a mut ModifiersState,
     pub window: &'a mut Window,
     pub message_buffer: &'a mut MessageBuffer,
+    pub permission_requests: &'a mut VecDeque<(Capability, TerminalEvent)>,
+    pub last_notification: &'a mut Option<Instant>,
     pub display_update_pending: &'a mut DisplayUpdate,
-    pub config: &'a mut Config,
+    pub config: &'a Config,
+    #[cfg(target_os = "macos")]
     pub event_loop: &'a EventLoopWindowTarget<Event>,
+    pub event_proxy: &'a EventProxy,
     pub urls: &'a Urls,
     pub scheduler: &'a mut Scheduler,
     pub search_state: &'a mut SearchState,
-    cursor_hidden: &'a mut bool,
-    cli_options: &'a CLIOptions,
-    font_size: &'a mut Size,
+    pub hint_state: &'a mut HintState,
+    pub cursor_hidden: &'a mut bool,
+    pub font_size: &'a mut Size,
+    #[cfg(not(windows))]
+    pub master_fd: RawFd,
+    #[cfg(not(windows))]
+    pub shell_pid: u32,
 }
 
 impl<'a, N: Notify + 'a, T: EventListener> input::ActionContext<T> for ActionContext<'a, N, T> {
@@ -201,19 +276,37 @@ impl<'a, N: Notify + 'a, T: EventListener> input::ActionContext<T> for ActionCon
             || self.mouse().right_button_state == ElementState::Pressed
         {
             let point = self.size_info().pixels_to_coords(self.mouse().x, self.mouse().y);
+            let point = self.terminal.grid_point(point);
             let cell_side = self.mouse().cell_side;
             self.update_selection(Point { line: point.line, col: point.col }, cell_side);
         }
     }
 
     fn copy_selection(&mut self, ty: ClipboardType) {
-        if let Some(selected) = self.terminal.selection_to_string() {
+        // Prefix lines with their arrival time while the copy modifiers are held.
+        let timestamps = &self.config.ui_config.timestamps;
+        let selected = if self.modifiers.contains(timestamps.copy_mods()) {
+            self.terminal
+                .selection_to_string_with_timestamps(|time| format!("{} ", timestamps.format(time)))
+        } else {
+            self.terminal.selection_to_string()
+        };
+
+        if let Some(selected) = selected {
             if !selected.is_empty() {
                 self.clipboard.store(ty, selected);
             }
         }
     }
 
+    fn copy_selection_as_html(&mut self) {
+        if let Some(selected) = self.terminal.selection_to_html(self.config) {
+            if !self.selection_is_empty() {
+                self.clipboard.store(ClipboardType::Clipboard, selected);
+            }
+        }
+    }
+
     fn selection_is_empty(&self) -> bool {
         self.terminal.selection.as_ref().map(Selection::is_empty).unwrap_or(true)
     }
@@ -302,6 +395,11 @@ impl<'a, N: Notify + 'a, T: EventListener> input::ActionContext<T> for ActionCon
         &mut self.suppress_chars
     }
 
+    #[inline]
+    fn pressed_key(&mut self) -> &mut Option<VirtualKeyCode> {
+        &mut self.pressed_key
+    }
+
     #[inline]
     fn modifiers(&mut self) -> &mut ModifiersState {
         &mut self.modifiers
@@ -331,29 +429,11 @@ impl<'a, N: Notify + 'a, T: EventListener> input::ActionContext<T> for ActionCon
         let mut env_args = env::args();
         let alacritty = env_args.next().unwrap();
 
-        #[cfg(unix)]
-        let mut args = {
-            // Use working directory of controlling process, or fallback to initial shell.
-            let mut pid = unsafe { libc::tcgetpgrp(tty::master_fd()) };
-            if pid < 0 {
-                pid = tty::child_pid();
-            }
-
-            #[cfg(not(any(target_os = "macos", target_os = "freebsd")))]
-            let link_path = format!("/proc/{}/cwd", pid);
-            #[cfg(target_os = "freebsd")]
-            let link_path = format!("/compat/linux/proc/{}/cwd", pid);
-            #[cfg(not(target_os = "macos"))]
-            let cwd = fs::read_link(link_path);
-            #[cfg(target_os = "macos")]
-            let cwd = macos::proc::cwd(pid);
-
-            // Add the current working directory as parameter.
-            cwd.map(|path| vec!["--working-directory".into(), path]).unwrap_or_default()
-        };
-
-        #[cfg(not(unix))]
-        let mut args: Vec<PathBuf> = Vec::new();
+        // Add the current working directory as parameter.
+        let mut args: Vec<PathBuf> = self
+            .working_directory()
+            .map(|path| vec!["--working-directory".into(), path])
+            .unwrap_or_default();
 
         let working_directory_set = !args.is_empty();
 
@@ -371,7 +451,54 @@ impl<'a, N: Notify + 'a, T: EventListener> input::ActionContext<T> for ActionCon
         start_daemon(&alacritty, &args);
     }
 
-    /// Spawn URL launcher when clicking on URLs.
+    fn create_new_window(&mut self) {
+        let working_directory = self.working_directory();
+        self.event_proxy.send_event(EventType::CreateWindow(working_directory));
+    }
+
+    fn save_session(&mut self) {
+        self.event_proxy.send_event(EventType::SaveSession);
+    }
+
+    fn toggle_recording(&mut self) {
+        self.event_proxy.send_event(EventType::ToggleRecording);
+    }
+
+    fn export_scrollback(&mut self) {
+        // Skip empty lines below the last content.
+        let terminal = &self.terminal;
+        let end_line = (0..terminal.total_lines()).find(|&line| !terminal.grid()[line].is_clear());
+        let end_line = match end_line {
+            Some(end_line) => end_line,
+            None => return,
+        };
+
+        let start = Point::new(terminal.total_lines() - 1, Column(0));
+        let end = Point::new(end_line, terminal.cols() - 1);
+        let html = terminal.bounds_to_html(self.config, start, end);
+        let document = format!(
+            "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>Alacritty</title>\n\
+             </head>\n<body style=\"margin: 0;\">\n{}\n</body>\n</html>\n",
+            html
+        );
+
+        let file_name = time::strftime("alacritty-%Y%m%d-%H%M%S.html", &time::now())
+            .unwrap_or_else(|_| String::from("alacritty.html"));
+        let path = dirs::home_dir().unwrap_or_default().join(file_name);
+
+        // Never replace existing files and keep the exported content private.
+        let mut options = OpenOptions::new();
+        options.write(true).create_new(true);
+        #[cfg(unix)]
+        options.mode(0o600);
+
+        match options.open(&path).and_then(|mut file| file.write_all(document.as_bytes())) {
+            Ok(()) => info!("Exported scrollback to {:?}", path),
+            Err(err) => error!("Unable to export scrollback to {:?}: {}", path, err),
+        }
+    }
+
+    /// Spawn URL launcher when clicking on URLs or hyperlinks.
     fn launch_url(&self, url: Url) {
         if self.mouse.block_url_launcher {
             return;
@@ -379,9 +506,16 @@ impl<'a, N: Notify + 'a, T: EventListener> input::ActionContext<T> for ActionCon
 
         if let Some(ref launcher) = self.config.ui_config.mouse.url.launcher {
             let mut args = launcher.args().to_vec();
-            let start = self.terminal.visible_to_buffer(url.start());
-            let end = self.terminal.visible_to_buffer(url.end());
-            args.push(self.terminal.bounds_to_string(start, end));
+            match url.hyperlink() {
+                Some(hyperlink) => args.push(hyperlink.uri.clone()),
+                None => {
+                    let start = self.terminal.grid_point(url.start());
+                    let end = self.terminal.grid_point(url.end());
+                    let start = self.terminal.visible_to_buffer(start);
+                    let end = self.terminal.visible_to_buffer(end);
+                    args.push(self.terminal.bounds_to_string(start, end));
+                },
+            }
 
             start_daemon(launcher.program(), &args);
         }
@@ -402,12 +536,46 @@ impl<'a, N: Notify + 'a, T: EventListener> input::ActionContext<T> for ActionCon
 
     #[inline]
     fn pop_message(&mut self) {
-        if !self.message_buffer.is_empty() {
+        // Closing a permission prompt denies the request.
+        if self.permission_prompt_active() {
+            self.answer_permission_prompt(false);
+        } else if !self.message_buffer.is_empty() {
             self.display_update_pending.dirty = true;
             self.message_buffer.pop();
         }
     }
 
+    #[inline]
+    fn permission_prompt_active(&self) -> bool {
+        let target = self.message_buffer.message().and_then(|message| message.target());
+        target.map(String::as_str) == Some(PERMISSION_PROMPT_TARGET)
+    }
+
+    fn answer_permission_prompt(&mut self, allow: bool) {
+        self.message_buffer.remove_target(PERMISSION_PROMPT_TARGET);
+        self.display_update_pending.dirty = true;
+        self.terminal.dirty = true;
+
+        match self.permission_requests.pop_front() {
+            Some((_, TerminalEvent::ClipboardLoad(clipboard_type, format))) if allow => {
+                let text = format(self.clipboard.load(clipboard_type).as_str());
+                self.write_to_pty(text.into_bytes());
+            },
+            Some((_, TerminalEvent::ClipboardStore(clipboard_type, content))) if allow => {
+                self.clipboard.store(clipboard_type, content);
+            },
+            Some((_, TerminalEvent::PtyWrite(text))) if allow => {
+                self.write_to_pty(text.into_bytes())
+            },
+            Some((capability, _)) if !allow => {
+                info!(target: LOG_TARGET_CONFIG, "Denied escape sequence permission to {}", capability);
+            },
+            _ => (),
+        }
+
+        self.prompt_next_permission();
+    }
+
     #[inline]
     fn start_search(&mut self, direction: Direction) {
         let num_lines = self.terminal.screen_lines();
@@ -440,6 +608,12 @@ impl<'a, N: Notify + 'a, T: EventListener> input::ActionContext<T> for ActionCon
         self.terminal.dirty = true;
     }
 
+    #[inline]
+    fn start_time_jump(&mut self) {
+        self.start_search(Direction::Left);
+        self.search_state.time_jump = true;
+    }
+
     #[inline]
     fn confirm_search(&mut self) {
         // Just cancel search when not in vi mode.
@@ -518,6 +692,11 @@ impl<'a, N: Notify + 'a, T: EventListener> input::ActionContext<T> for ActionCon
     /// Go to the previous regex in the search history.
     #[inline]
     fn search_history_previous(&mut self) {
+        // Entered times have no history.
+        if self.search_state.time_jump {
+            return;
+        }
+
         let index = match &mut self.search_state.history_index {
             None => return,
             Some(index) if *index + 1 >= self.search_state.history.len() => return,
@@ -531,6 +710,11 @@ impl<'a, N: Notify + 'a, T: EventListener> input::ActionContext<T> for ActionCon
     /// Go to the previous regex in the search history.
     #[inline]
     fn search_history_next(&mut self) {
+        // Entered times have no history.
+        if self.search_state.time_jump {
+            return;
+        }
+
         let index = match &mut self.search_state.history_index {
             Some(0) | None => return,
             Some(index) => index,
@@ -613,6 +797,10 @@ impl<'a, N: Notify + 'a, T: EventListener> input::ActionContext<T> for ActionCon
         }
     }
 
+    fn hint_state(&mut self) -> &mut HintState {
+        self.hint_state
+    }
+
     #[inline]
     fn search_direction(&self) -> Direction {
         self.search_state.direction
@@ -631,6 +819,7 @@ impl<'a, N: Notify + 'a, T: EventListener> input::ActionContext<T> for ActionCon
         self.config
     }
 
+    #[cfg(target_os = "macos")]
     fn event_loop(&self) -> &EventLoopWindowTarget<Event> {
         self.event_loop
     }
@@ -649,6 +838,72 @@ impl<'a, N: Notify + 'a, T: EventListener> input::ActionContext<T> for ActionCon
 }
 
 impl<'a, N: Notify + 'a, T: EventListener> ActionContext<'a, N, T> {
+    /// Get the working directory of the terminal.
+    fn working_directory(&self) -> Option<PathBuf> {
+        working_directory(
+            self.terminal,
+            #[cfg(not(windows))]
+            self.master_fd,
+            #[cfg(not(windows))]
+            self.shell_pid,
+        )
+    }
+
+    /// Show a desktop notification requested by the application.
+    fn notify(&mut self, title: Option<String>, body: String) {
+        let ui_config = &self.config.ui_config;
+        let notification = &ui_config.notification;
+        let command = match notification.command.as_ref() {
+            Some(command) if !notification.only_when_unfocused || !self.terminal.is_focused => {
+                command
+            },
+            _ => return,
+        };
+
+        // Drop notifications sent in quick succession, to avoid flooding the desktop.
+        let now = Instant::now();
+        if matches!(*self.last_notification, Some(last) if now - last < MIN_NOTIFICATION_INTERVAL) {
+            debug!("Dropping notification sent within {:?}", MIN_NOTIFICATION_INTERVAL);
+            return;
+        }
+        *self.last_notification = Some(now);
+
+        // Prevent the title and body from being parsed as options.
+        let title = title.unwrap_or_else(|| ui_config.window.title.clone());
+        let mut args = command.args().to_vec();
+        args.extend_from_slice(&[String::from("--"), title, body]);
+        start_daemon(command.program(), &args);
+    }
+
+    /// Queue a request for an escape sequence capability.
+    fn request_permission(&mut self, capability: Capability, event: TerminalEvent) {
+        // Deny requests outright instead of queueing up an endless stream of prompts.
+        if self.permission_requests.len() >= MAX_PERMISSION_REQUESTS {
+            info!(target: LOG_TARGET_CONFIG, "Too many pending requests, denied permission to {}", capability);
+            return;
+        }
+
+        self.permission_requests.push_back((capability, event));
+
+        // Only the oldest request is prompted for, the others follow once it is answered.
+        if self.permission_requests.len() == 1 {
+            self.prompt_next_permission();
+        }
+    }
+
+    /// Show the message bar prompt for the oldest pending permission request.
+    fn prompt_next_permission(&mut self) {
+        if let Some((capability, _)) = self.permission_requests.front() {
+            let text = format!("Allow the application to {}? [y/N]", capability);
+            let mut message = Message::new(text, MessageType::Warning);
+            message.set_target(PERMISSION_PROMPT_TARGET.into());
+            self.message_buffer.push(message);
+
+            self.display_update_pending.dirty = true;
+            self.terminal.dirty = true;
+        }
+    }
+
     fn update_search(&mut self) {
         let regex = match self.search_state.regex() {
             Some(regex) => regex,
@@ -660,7 +915,11 @@ impl<'a, N: Notify + 'a, T: EventListener> ActionContext<'a, N, T> {
             self.window.set_mouse_visible(false);
         }
 
-        if regex.is_empty() {
+        if self.search_state.time_jump {
+            // Jump to the entered time instead of searching for it.
+            let time = parse_time_of_day(regex);
+            self.goto_time(time);
+        } else if regex.is_empty() {
             // Stop search if there's nothing to search for.
             self.search_reset_state();
             self.terminal.cancel_search();
@@ -701,6 +960,22 @@ impl<'a, N: Notify + 'a, T: EventListener> ActionContext<'a, N, T> {
         self.terminal.vi_mode_cursor.point = origin;
     }
 
+    /// Jump to the first line which arrived at or after `time`.
+    fn goto_time(&mut self, time: Option<SystemTime>) {
+        match time.and_then(|time| self.terminal.timestamp_search(time)) {
+            Some(line) => {
+                let old_offset = self.terminal.grid().display_offset() as isize;
+
+                self.terminal.vi_goto_point(Point::new(line, Column(0)));
+
+                // Store number of lines the viewport had to be moved.
+                let display_offset = self.terminal.grid().display_offset();
+                self.search_state.display_offset_delta += old_offset - display_offset as isize;
+            },
+            None => self.search_reset_state(),
+        }
+    }
+
     /// Jump to the first regex match from the search origin.
     fn goto_match(&mut self, mut limit: Option<usize>) {
         if self.search_state.history_index.is_none() {
@@ -712,7 +987,24 @@ impl<'a, N: Notify + 'a, T: EventListener> ActionContext<'a, N, T> {
 
         // Jump to the next match.
         let direction = self.search_state.direction;
-        match self.terminal.search_next(self.absolute_origin(), direction, Side::Left, limit) {
+        let mut next_match =
+            self.terminal.search_next(self.absolute_origin(), direction, Side::Left, limit);
+
+        // Search archived scrollback once the entire history has been searched without a match.
+        //
+        // Only a limited number of segments is loaded, repeating the search continues from there.
+        let mut page_ins = 0;
+        while next_match.is_none()
+            && limit.is_none()
+            && page_ins < MAX_SEARCH_PAGE_INS
+            && self.terminal.page_in_history()
+        {
+            page_ins += 1;
+            next_match =
+                self.terminal.search_next(self.absolute_origin(), direction, Side::Left, limit);
+        }
+
+        match next_match {
             Some(regex_match) => {
                 let old_offset = self.terminal.grid().display_offset() as isize;
 
@@ -740,7 +1032,7 @@ impl<'a, N: Notify + 'a, T: EventListener> ActionContext<'a, N, T> {
                 // Schedule delayed search if we ran into our search limit.
                 if !self.scheduler.scheduled(TimerId::DelayedSearch) {
                     self.scheduler.schedule(
-                        Event::SearchNext.into(),
+                        Event::from(EventType::SearchNext).into(),
                         TYPING_SEARCH_DELAY,
                         false,
                         TimerId::DelayedSearch,
@@ -757,6 +1049,9 @@ impl<'a, N: Notify + 'a, T: EventListener> ActionContext<'a, N, T> {
 
     /// Cleanup the search state.
     fn exit_search(&mut self) {
+        // Return archived lines loaded by the search, except those still in the viewport.
+        self.terminal.page_out_history();
+
         // Move vi cursor down if resize will pull content from history.
         if self.terminal.history_size() != 0
             && self.terminal.grid().display_offset() == 0
@@ -765,6 +1060,11 @@ impl<'a, N: Notify + 'a, T: EventListener> ActionContext<'a, N, T> {
             self.terminal.vi_mode_cursor.point.line += 1;
         }
 
+        // Entered times are not part of the search history.
+        if mem::replace(&mut self.search_state.time_jump, false) {
+            self.search_state.history.pop_front();
+        }
+
         self.display_update_pending.dirty = true;
         self.search_state.history_index = None;
         self.terminal.dirty = true;
@@ -803,7 +1103,7 @@ impl<'a, N: Notify + 'a, T: EventListener> ActionContext<'a, N, T> {
         self.scheduler.unschedule(TimerId::BlinkCursor);
         if blinking && self.terminal.is_focused {
             self.scheduler.schedule(
-                GlutinEvent::UserEvent(Event::BlinkCursor),
+                Event::from(EventType::BlinkCursor).into(),
                 Duration::from_millis(self.config.cursor.blink_interval()),
                 true,
                 TimerId::BlinkCursor,
@@ -869,93 +1169,167 @@ impl Default for Mouse {
 ///
 /// Stores some state from received events and dispatches actions when they are
 /// triggered.
-pub struct Processor<N> {
-    notifier: N,
-    mouse: Mouse,
-    received_count: usize,
-    suppress_chars: bool,
+pub struct Processor {
+    #[cfg(all(feature = "wayland", not(any(target_os = "macos", windows))))]
+    wayland_event_queue: Option<EventQueue>,
+    windows: HashMap<WindowId, WindowContext>,
+    closed_sessions: VecDeque<Session>,
+    rasterizers: Rasterizers,
     clipboard: Clipboard,
-    modifiers: ModifiersState,
-    config: Config,
-    message_buffer: MessageBuffer,
-    display: Display,
-    font_size: Size,
-    event_queue: Vec<GlutinEvent<'static, Event>>,
-    search_state: SearchState,
     cli_options: CLIOptions,
+    config: Config,
 }
 
-impl<N: Notify + OnResize> Processor<N> {
+impl Processor {
     /// Create a new event processor.
     ///
-    /// Takes a writer which is expected to be hooked up to the write end of a PTY.
-    pub fn new(
-        notifier: N,
-        message_buffer: MessageBuffer,
-        config: Config,
-        display: Display,
-        cli_options: CLIOptions,
-    ) -> Processor<N> {
-        #[cfg(not(any(target_os = "macos", windows)))]
-        let clipboard = Clipboard::new(display.window.wayland_display());
+    /// Windows are added to the processor using [`Processor::create_window`].
+    pub fn new(config: Config, cli_options: CLIOptions, event_loop: &EventLoop<Event>) -> Self {
+        // Initialize Wayland event queue, to handle Wayland callbacks.
+        #[cfg(all(feature = "wayland", not(any(target_os = "macos", windows))))]
+        let wayland_event_queue = event_loop.wayland_display().map(|display| {
+            let display = unsafe { WaylandDisplay::from_external_display(display as _) };
+            display.create_event_queue()
+        });
+
+        #[cfg(all(feature = "wayland", not(any(target_os = "macos", windows))))]
+        let clipboard = Clipboard::new(event_loop.wayland_display());
+        #[cfg(all(not(feature = "wayland"), not(any(target_os = "macos", windows))))]
+        let clipboard = Clipboard::new(None);
         #[cfg(any(target_os = "macos", windows))]
         let clipboard = Clipboard::new();
 
+        // The event loop is only needed for platform specific initialization.
+        #[cfg(any(not(feature = "wayland"), target_os = "macos", windows))]
+        let _ = event_loop;
+
         Processor {
-            notifier,
-            mouse: Default::default(),
-            received_count: 0,
-            suppress_chars: false,
-            modifiers: Default::default(),
-            font_size: config.ui_config.font.size(),
-            config,
-            message_buffer,
-            display,
-            event_queue: Vec::new(),
+            #[cfg(all(feature = "wayland", not(any(target_os = "macos", windows))))]
+            wayland_event_queue,
+            windows: HashMap::new(),
+            closed_sessions: VecDeque::new(),
+            rasterizers: Rasterizers::default(),
             clipboard,
-            search_state: SearchState::new(),
             cli_options,
+            config,
         }
     }
 
-    /// Return `true` if `event_queue` is empty, `false` otherwise.
-    #[inline]
-    #[cfg(all(feature = "wayland", not(any(target_os = "macos", windows))))]
-    fn event_queue_empty(&mut self) -> bool {
-        let wayland_event_queue = match self.display.wayland_event_queue.as_mut() {
-            Some(wayland_event_queue) => wayland_event_queue,
-            // Since frame callbacks do not exist on X11, just check for event queue.
-            None => return self.event_queue.is_empty(),
+    /// Create the windows shown on startup.
+    ///
+    /// When restoring is enabled, one window is created for every saved session.
+    pub fn create_initial_windows(
+        &mut self,
+        event_loop: &EventLoopWindowTarget<Event>,
+        proxy: EventLoopProxy<Event>,
+    ) -> Result<(), Box<dyn Error>> {
+        if let Some(path) = self.cli_options.replay.clone() {
+            return self.create_replay_window(event_loop, proxy, &path);
+        }
+
+        let sessions = if self.config.ui_config.session.persist || self.cli_options.restore {
+            session::load()
+        } else {
+            Vec::new()
         };
 
-        // Check for pending frame callbacks on Wayland.
-        let events_dispatched = wayland_event_queue
-            .dispatch_pending(&mut (), |_, _, _| {})
-            .expect("failed to dispatch event queue");
+        let mut window_ids = Vec::new();
+        if sessions.is_empty() {
+            window_ids.push(self.create_window(event_loop, proxy.clone(), None, None, None)?);
+        }
 
-        self.event_queue.is_empty() && events_dispatched == 0
+        for session in sessions {
+            // Start the new shell where the previous one left off, unless overridden by the CLI.
+            let working_directory = session
+                .working_directory
+                .clone()
+                .filter(|path| self.cli_options.working_directory.is_none() && path.is_dir());
+            let window_id = self.create_window(
+                event_loop,
+                proxy.clone(),
+                working_directory,
+                Some(session),
+                None,
+            )?;
+            window_ids.push(window_id);
+        }
+
+        // Only the first window is recorded.
+        let windows = &mut self.windows;
+        let window_context = window_ids.first().and_then(|id| windows.get_mut(id));
+        if let (Some(path), Some(window_context)) = (&self.cli_options.record, window_context) {
+            window_context.start_recording(path);
+        }
+
+        Ok(())
     }
 
-    /// Return `true` if `event_queue` is empty, `false` otherwise.
-    #[inline]
-    #[cfg(any(not(feature = "wayland"), target_os = "macos", windows))]
-    fn event_queue_empty(&mut self) -> bool {
-        self.event_queue.is_empty()
+    /// Create a window replaying an asciicast recording.
+    fn create_replay_window(
+        &mut self,
+        event_loop: &EventLoopWindowTarget<Event>,
+        proxy: EventLoopProxy<Event>,
+        path: &Path,
+    ) -> Result<(), Box<dyn Error>> {
+        let mut recording = File::open(path)
+            .and_then(|file| Recording::read(BufReader::new(file)))
+            .map_err(|err| format!("Unable to read recording {:?}: {}", path, err))?;
+        recording.set_speed(self.cli_options.replay_speed);
+
+        // Match the initial dimensions of the recorded terminal.
+        let dimensions = &mut self.config.ui_config.window.dimensions;
+        dimensions.columns = Column(recording.header.width);
+        dimensions.lines = Line(recording.header.height);
+
+        self.create_window(event_loop, proxy, None, None, Some(recording))?;
+
+        Ok(())
     }
 
-    /// Run the event loop.
-    pub fn run<T>(&mut self, terminal: Arc<FairMutex<Term<T>>>, mut event_loop: EventLoop<Event>)
-    where
-        T: EventListener,
-    {
-        let mut scheduler = Scheduler::new();
-
-        // Start the initial cursor blinking timer.
-        if self.config.cursor.style().blinking {
-            let event: Event = TerminalEvent::CursorBlinkingChange(true).into();
-            self.event_queue.push(event.into());
+    /// Create a new terminal window.
+    ///
+    /// The shell is started in `working_directory` when it is present, falling back to the
+    /// configured working directory otherwise. If a `session` is passed, its content is restored
+    /// into the new terminal. Passing a `replay` plays back the recording instead of starting a
+    /// shell.
+    pub fn create_window(
+        &mut self,
+        event_loop: &EventLoopWindowTarget<Event>,
+        proxy: EventLoopProxy<Event>,
+        working_directory: Option<PathBuf>,
+        session: Option<Session>,
+        replay: Option<Recording>,
+    ) -> Result<WindowId, Box<dyn Error>> {
+        // Temporarily override the configured working directory for the new PTY.
+        let config_working_directory = working_directory
+            .map(|working_directory| self.config.working_directory.replace(working_directory));
+
+        let window_context = WindowContext::new(
+            &self.config,
+            event_loop,
+            proxy,
+            &self.rasterizers,
+            session,
+            replay,
+            #[cfg(all(feature = "wayland", not(any(target_os = "macos", windows))))]
+            self.wayland_event_queue.as_ref(),
+        );
+
+        if let Some(working_directory) = config_working_directory {
+            self.config.working_directory = working_directory;
         }
 
+        let window_context = window_context?;
+        let window_id = window_context.id();
+        self.windows.insert(window_id, window_context);
+
+        Ok(window_id)
+    }
+
+    /// Run the event loop.
+    pub fn run(&mut self, mut event_loop: EventLoop<Event>) {
+        let proxy = event_loop.create_proxy();
+
         event_loop.run_return(|event, event_loop, control_flow| {
             if self.config.ui_config.debug.print_events {
                 info!("glutin event: {:?}", event);
@@ -967,188 +1341,377 @@ impl<N: Notify + OnResize> Processor<N> {
             }
 
             match event {
-                // Check for shutdown.
-                GlutinEvent::UserEvent(Event::TerminalEvent(TerminalEvent::Exit)) => {
-                    *control_flow = ControlFlow::Exit;
-                    return;
+                // Check for window shutdown.
+                GlutinEvent::UserEvent(Event {
+                    window_id: Some(window_id),
+                    payload: EventType::TerminalEvent(TerminalEvent::Exit),
+                }) => {
+                    // Remove the closed terminal.
+                    let window_context = match self.windows.remove(&window_id) {
+                        Some(window_context) => window_context,
+                        None => return,
+                    };
+
+                    // Write ref tests to disk.
+                    if self.config.ui_config.debug.ref_test {
+                        window_context.write_ref_test_results();
+                    }
+
+                    // Keep the most recent terminals for restoring them on the next launch.
+                    if self.config.ui_config.session.persist {
+                        if self.closed_sessions.len() >= MAX_CLOSED_SESSIONS {
+                            self.closed_sessions.pop_front();
+                        }
+                        self.closed_sessions.push_back(window_context.session());
+                    }
+
+                    // Shutdown if no more terminals are open.
+                    if self.windows.is_empty() {
+                        if self.config.ui_config.session.persist {
+                            let sessions: Vec<_> = self.closed_sessions.drain(..).collect();
+                            session::save(&sessions);
+                        }
+
+                        *control_flow = ControlFlow::Exit;
+                    }
+
+                    window_context.close();
                 },
                 // Process events.
                 GlutinEvent::RedrawEventsCleared => {
-                    *control_flow = match scheduler.update(&mut self.event_queue) {
-                        Some(instant) => ControlFlow::WaitUntil(instant),
-                        None => ControlFlow::Wait,
-                    };
-
-                    if self.event_queue_empty() {
-                        return;
+                    *control_flow = ControlFlow::Wait;
+
+                    let frame_callbacks = self.dispatch_wayland_events();
+
+                    for window_context in self.windows.values_mut() {
+                        window_context.process_events(
+                            event_loop,
+                            &mut self.clipboard,
+                            &self.config,
+                            control_flow,
+                            frame_callbacks,
+                        );
                     }
                 },
                 // Remap DPR change event to remove lifetime.
                 GlutinEvent::WindowEvent {
                     event: WindowEvent::ScaleFactorChanged { scale_factor, new_inner_size },
-                    ..
+                    window_id,
                 } => {
                     *control_flow = ControlFlow::Poll;
-                    let size = (new_inner_size.width, new_inner_size.height);
-                    self.event_queue.push(Event::DPRChanged(scale_factor, size).into());
-                    return;
+                    if let Some(window_context) = self.windows.get_mut(&window_id) {
+                        let size = (new_inner_size.width, new_inner_size.height);
+                        let event =
+                            Event::new(EventType::DPRChanged(scale_factor, size), window_id);
+                        window_context.handle_event(event.into());
+                    }
                 },
-                // Transmute to extend lifetime, which exists only for `ScaleFactorChanged` event.
-                // Since we remap that event to remove the lifetime, this is safe.
-                event => unsafe {
+                // Process config update.
+                GlutinEvent::UserEvent(Event {
+                    payload: EventType::ConfigReload(path), ..
+                }) => {
+                    self.reload_config(Some(&path));
+                },
+                // Process IPC messages.
+                #[cfg(unix)]
+                GlutinEvent::UserEvent(Event {
+                    payload: EventType::Ipc(message, reply), ..
+                }) => {
                     *control_flow = ControlFlow::Poll;
-                    self.event_queue.push(mem::transmute(event));
-                    return;
+                    self.ipc_message(message, reply, event_loop, &proxy);
                 },
+                // Open a new terminal window.
+                GlutinEvent::UserEvent(Event {
+                    payload: EventType::CreateWindow(working_directory),
+                    ..
+                }) => {
+                    *control_flow = ControlFlow::Poll;
+                    if let Err(err) =
+                        self.create_window(event_loop, proxy.clone(), working_directory, None, None)
+                    {
+                        error!("Could not open window: {}", err);
+                    }
+                },
+                // Save all windows for the next launch.
+                GlutinEvent::UserEvent(Event { payload: EventType::SaveSession, .. }) => {
+                    let sessions: Vec<_> =
+                        self.windows.values().map(WindowContext::session).collect();
+                    session::save(&sessions);
+                },
+                // Start or stop recording a window.
+                GlutinEvent::UserEvent(Event {
+                    window_id: Some(window_id),
+                    payload: EventType::ToggleRecording,
+                }) => {
+                    if let Some(window_context) = self.windows.get_mut(&window_id) {
+                        window_context.toggle_recording();
+                    }
+                },
+                GlutinEvent::UserEvent(Event {
+                    window_id: Some(window_id),
+                    payload: EventType::TerminalEvent(TerminalEvent::RecordingStopped),
+                }) => {
+                    if let Some(window_context) = self.windows.get_mut(&window_id) {
+                        window_context.recording_stopped();
+                    }
+                },
+                // Process events affecting all windows.
+                GlutinEvent::UserEvent(event @ Event { window_id: None, .. }) => {
+                    *control_flow = ControlFlow::Poll;
+                    for window_context in self.windows.values_mut() {
+                        window_context.handle_event(event.clone().into());
+                    }
+                },
+                // Process window-specific events.
+                GlutinEvent::WindowEvent { window_id, .. }
+                | GlutinEvent::UserEvent(Event { window_id: Some(window_id), .. })
+                | GlutinEvent::RedrawRequested(window_id) => {
+                    *control_flow = ControlFlow::Poll;
+                    // Only the `ScaleFactorChanged` event has a lifetime, which is remapped above.
+                    let window_context = self.windows.get_mut(&window_id);
+                    if let (Some(window_context), Some(event)) = (window_context, event.to_static())
+                    {
+                        window_context.handle_event(event);
+                    }
+                },
+                _ => (),
             }
+        });
+    }
 
-            let mut terminal = terminal.lock();
-
-            let mut display_update_pending = DisplayUpdate::default();
-            let old_is_searching = self.search_state.history_index.is_some();
-
-            let context = ActionContext {
-                terminal: &mut terminal,
-                notifier: &mut self.notifier,
-                mouse: &mut self.mouse,
-                clipboard: &mut self.clipboard,
-                size_info: &mut self.display.size_info,
-                received_count: &mut self.received_count,
-                suppress_chars: &mut self.suppress_chars,
-                modifiers: &mut self.modifiers,
-                message_buffer: &mut self.message_buffer,
-                display_update_pending: &mut display_update_pending,
-                window: &mut self.display.window,
-                font_size: &mut self.font_size,
-                config: &mut self.config,
-                urls: &self.display.urls,
-                scheduler: &mut scheduler,
-                search_state: &mut self.search_state,
-                cli_options: &self.cli_options,
-                cursor_hidden: &mut self.display.cursor_hidden,
-                event_loop,
-            };
-            let mut processor = input::Processor::new(context, &self.display.highlighted_url);
+    /// Dispatch pending Wayland events.
+    ///
+    /// Returns `true` if any events were dispatched, which might include frame callbacks.
+    #[inline]
+    #[cfg(all(feature = "wayland", not(any(target_os = "macos", windows))))]
+    fn dispatch_wayland_events(&mut self) -> bool {
+        // Since frame callbacks do not exist on X11, there is nothing to dispatch.
+        let wayland_event_queue = match self.wayland_event_queue.as_mut() {
+            Some(wayland_event_queue) => wayland_event_queue,
+            None => return false,
+        };
 
-            for event in self.event_queue.drain(..) {
-                Processor::handle_event(event, &mut processor);
-            }
+        let events_dispatched = wayland_event_queue
+            .dispatch_pending(&mut (), |_, _, _| {})
+            .expect("failed to dispatch event queue");
 
-            // Process DisplayUpdate events.
-            if display_update_pending.dirty {
-                self.submit_display_update(&mut terminal, old_is_searching, display_update_pending);
-            }
+        events_dispatched != 0
+    }
 
-            // Skip rendering on Wayland until we get frame event from compositor.
-            #[cfg(not(any(target_os = "macos", windows)))]
-            if !self.display.is_x11 && !self.display.window.should_draw.load(Ordering::Relaxed) {
-                return;
-            }
+    /// Dispatch pending Wayland events.
+    #[inline]
+    #[cfg(any(not(feature = "wayland"), target_os = "macos", windows))]
+    fn dispatch_wayland_events(&mut self) -> bool {
+        false
+    }
 
-            if terminal.dirty {
-                terminal.dirty = false;
+    /// Check if an event is irrelevant and can be skipped.
+    fn skip_event(event: &GlutinEvent<'_, Event>) -> bool {
+        match event {
+            GlutinEvent::WindowEvent { event, .. } => matches!(
+                event,
+                WindowEvent::KeyboardInput { is_synthetic: true, .. }
+                    | WindowEvent::TouchpadPressure { .. }
+                    | WindowEvent::CursorEntered { .. }
+                    | WindowEvent::AxisMotion { .. }
+                    | WindowEvent::HoveredFileCancelled
+                    | WindowEvent::Destroyed
+                    | WindowEvent::HoveredFile(_)
+                    | WindowEvent::Touch(_)
+                    | WindowEvent::Moved(_)
+            ),
+            GlutinEvent::Suspended { .. }
+            | GlutinEvent::NewEvents { .. }
+            | GlutinEvent::MainEventsCleared
+            | GlutinEvent::LoopDestroyed => true,
+            _ => false,
+        }
+    }
 
-                // Request immediate re-draw if visual bell animation is not finished yet.
-                if !terminal.visual_bell.completed() {
-                    let event: Event = TerminalEvent::Wakeup.into();
-                    self.event_queue.push(event.into());
+    /// Reload the configuration and apply it to all windows.
+    fn reload_config(&mut self, path: Option<&PathBuf>) {
+        for window_context in self.windows.values_mut() {
+            window_context.clear_config_logs();
+        }
 
-                    *control_flow = ControlFlow::Poll;
+        let config = match config::reload(path, &self.cli_options) {
+            Ok(config) => config,
+            Err(_) => return,
+        };
+
+        for window_context in self.windows.values_mut() {
+            window_context.update_config(&self.config, &config);
+        }
+
+        // Set subpixel anti-aliasing.
+        #[cfg(target_os = "macos")]
+        crossfont::set_font_smoothing(config.ui_config.font.use_thin_strokes);
+
+        self.config = config;
+    }
+
+    /// Handle a message received through the IPC socket.
+    #[cfg(unix)]
+    fn ipc_message(
+        &mut self,
+        message: SocketMessage,
+        reply: SocketReply,
+        event_loop: &EventLoopWindowTarget<Event>,
+        proxy: &EventLoopProxy<Event>,
+    ) {
+        match message {
+            SocketMessage::CreateWindow => {
+                if let Err(err) = self.create_window(event_loop, proxy.clone(), None, None, None) {
+                    error!("Could not open window: {}", err);
+                }
+            },
+            SocketMessage::ReloadConfig => {
+                let path = self.config.ui_config.config_paths.first().cloned();
+                self.reload_config(path.as_ref());
+            },
+            SocketMessage::Config { options } => {
+                // Store overrides with the CLI options, so they persist across reloads.
+                for option in options {
+                    match cli::option_as_value(&option) {
+                        Ok(value) => {
+                            let config_options =
+                                mem::replace(&mut self.cli_options.config_options, Value::Null);
+                            self.cli_options.config_options =
+                                serde_utils::merge(config_options, value);
+                        },
+                        Err(_) => error!("Invalid IPC config option: {:?}", option),
+                    }
                 }
 
-                // Redraw screen.
-                self.display.draw(
-                    terminal,
-                    &self.message_buffer,
-                    &self.config,
-                    &self.mouse,
-                    self.modifiers,
-                    &self.search_state,
-                );
-            }
-        });
+                let path = self.config.ui_config.config_paths.first().cloned();
+                self.reload_config(path.as_ref());
+            },
+            // Run actions in the focused window.
+            message @ SocketMessage::Action { .. } => {
+                if let Some(window_id) = self.focused_window().map(WindowContext::id) {
+                    let event = Event::new(EventType::Ipc(message, reply), window_id);
+                    self.windows.get_mut(&window_id).unwrap().handle_event(event.into());
+                }
+            },
+            SocketMessage::GetWorkingDirectory => {
+                let working_directory =
+                    self.focused_window().and_then(WindowContext::working_directory);
+                if let Some(working_directory) = working_directory {
+                    let _ = reply.send(working_directory.display().to_string());
+                }
+            },
+        }
+    }
+
+    /// Get the focused window, or any window if none of them is focused.
+    #[cfg(unix)]
+    fn focused_window(&self) -> Option<&WindowContext> {
+        let mut windows = self.windows.values();
+        windows.clone().find(|window| window.focused()).or_else(|| windows.next())
+    }
+}
 
-        // Write ref tests to disk.
-        if self.config.ui_config.debug.ref_test {
-            self.write_ref_test_results(&terminal.lock());
+impl Drop for Processor {
+    fn drop(&mut self) {
+        // Close all remaining windows, shutting down their PTYs.
+        for (_, window_context) in self.windows.drain() {
+            window_context.close();
         }
     }
+}
 
+impl<'a, N: Notify + 'a, T: EventListener> input::Processor<'a, T, ActionContext<'a, N, T>> {
     /// Handle events from glutin.
-    ///
-    /// Doesn't take self mutably due to borrow checking.
-    fn handle_event<T>(
-        event: GlutinEvent<'_, Event>,
-        processor: &mut input::Processor<'_, T, ActionContext<'_, N, T>>,
-    ) where
-        T: EventListener,
-    {
+    pub fn handle_event(&mut self, event: GlutinEvent<'_, Event>) {
         match event {
-            GlutinEvent::UserEvent(event) => match event {
-                Event::DPRChanged(scale_factor, (width, height)) => {
-                    let display_update_pending = &mut processor.ctx.display_update_pending;
+            GlutinEvent::UserEvent(Event { payload, .. }) => match payload {
+                EventType::DPRChanged(scale_factor, (width, height)) => {
+                    let display_update_pending = &mut self.ctx.display_update_pending;
 
                     // Push current font to update its DPR.
-                    let font = processor.ctx.config.ui_config.font.clone();
-                    display_update_pending.set_font(font.with_size(*processor.ctx.font_size));
+                    let font = self.ctx.config.ui_config.font.clone();
+                    display_update_pending.set_font(font.with_size(*self.ctx.font_size));
 
                     // Resize to event's dimensions, since no resize event is emitted on Wayland.
                     display_update_pending.set_dimensions(PhysicalSize::new(width, height));
 
-                    processor.ctx.window.dpr = scale_factor;
-                    processor.ctx.terminal.dirty = true;
+                    self.ctx.window.dpr = scale_factor;
+                    self.ctx.terminal.dirty = true;
+                },
+                EventType::Message(message) => {
+                    self.ctx.message_buffer.push(message);
+                    self.ctx.display_update_pending.dirty = true;
+                    self.ctx.terminal.dirty = true;
                 },
-                Event::Message(message) => {
-                    processor.ctx.message_buffer.push(message);
-                    processor.ctx.display_update_pending.dirty = true;
-                    processor.ctx.terminal.dirty = true;
+                EventType::SearchNext => self.ctx.goto_match(None),
+                #[cfg(unix)]
+                EventType::Ipc(SocketMessage::Action { action }, _) => {
+                    match SocketMessage::parse_action(&action) {
+                        Some(action) => self.execute_action(&action),
+                        None => error!("Invalid IPC action: {:?}", action),
+                    }
                 },
-                Event::SearchNext => processor.ctx.goto_match(None),
-                Event::ConfigReload(path) => Self::reload_config(&path, processor),
-                Event::Scroll(scroll) => processor.ctx.scroll(scroll),
-                Event::BlinkCursor => {
-                    *processor.ctx.cursor_hidden ^= true;
-                    processor.ctx.terminal.dirty = true;
+                EventType::Scroll(scroll) => self.ctx.scroll(scroll),
+                EventType::BlinkCursor => {
+                    *self.ctx.cursor_hidden ^= true;
+                    self.ctx.terminal.dirty = true;
                 },
-                Event::TerminalEvent(event) => match event {
+                EventType::TerminalEvent(event) => match event {
                     TerminalEvent::Title(title) => {
-                        let ui_config = &processor.ctx.config.ui_config;
+                        let ui_config = &self.ctx.config.ui_config;
                         if ui_config.window.dynamic_title {
-                            processor.ctx.window.set_title(&title);
+                            self.ctx.window.set_title(&title);
                         }
                     },
                     TerminalEvent::ResetTitle => {
-                        let ui_config = &processor.ctx.config.ui_config;
+                        let ui_config = &self.ctx.config.ui_config;
                         if ui_config.window.dynamic_title {
-                            processor.ctx.window.set_title(&ui_config.window.title);
+                            self.ctx.window.set_title(&ui_config.window.title);
                         }
                     },
-                    TerminalEvent::Wakeup => processor.ctx.terminal.dirty = true,
+                    TerminalEvent::Wakeup => self.ctx.terminal.dirty = true,
                     TerminalEvent::Bell => {
-                        let bell_command = processor.ctx.config.bell().command.as_ref();
+                        let bell_command = self.ctx.config.bell().command.as_ref();
                         let _ = bell_command.map(|cmd| start_daemon(cmd.program(), cmd.args()));
-                        if processor.ctx.terminal.mode().contains(TermMode::URGENCY_HINTS) {
-                            processor.ctx.window.set_urgent(!processor.ctx.terminal.is_focused);
+                        if self.ctx.terminal.mode().contains(TermMode::URGENCY_HINTS) {
+                            self.ctx.window.set_urgent(!self.ctx.terminal.is_focused);
                         }
                     },
                     TerminalEvent::ClipboardStore(clipboard_type, content) => {
-                        processor.ctx.clipboard.store(clipboard_type, content);
+                        self.ctx.clipboard.store(clipboard_type, content);
                     },
                     TerminalEvent::ClipboardLoad(clipboard_type, format) => {
-                        let text = format(processor.ctx.clipboard.load(clipboard_type).as_str());
-                        processor.ctx.write_to_pty(text.into_bytes());
+                        let text = format(self.ctx.clipboard.load(clipboard_type).as_str());
+                        self.ctx.write_to_pty(text.into_bytes());
                     },
-                    TerminalEvent::MouseCursorDirty => processor.reset_mouse_cursor(),
-                    TerminalEvent::Exit => (),
+                    TerminalEvent::Notify(title, body) => self.ctx.notify(title, body),
+                    TerminalEvent::PtyWrite(text) => self.ctx.write_to_pty(text.into_bytes()),
+                    TerminalEvent::PermissionRequest(capability, event) => {
+                        self.ctx.request_permission(capability, *event);
+                    },
+                    TerminalEvent::ResizeWindow(size) => {
+                        let size = PhysicalSize::new(size.width() as u32, size.height() as u32);
+                        self.ctx.window.set_inner_size(size);
+                        self.ctx.display_update_pending.set_dimensions(size);
+                    },
+                    TerminalEvent::MouseCursorDirty => self.reset_mouse_cursor(),
+                    TerminalEvent::RecordingStopped | TerminalEvent::Exit => (),
                     TerminalEvent::CursorBlinkingChange(_) => {
-                        processor.ctx.update_cursor_blinking();
+                        self.ctx.update_cursor_blinking();
                     },
                 },
+                // Global events are handled by the event processor.
+                #[cfg(unix)]
+                EventType::Ipc(..) => (),
+                EventType::ConfigReload(_)
+                | EventType::CreateWindow(_)
+                | EventType::SaveSession
+                | EventType::ToggleRecording => (),
             },
-            GlutinEvent::RedrawRequested(_) => processor.ctx.terminal.dirty = true,
+            GlutinEvent::RedrawRequested(_) => self.ctx.terminal.dirty = true,
             GlutinEvent::WindowEvent { event, window_id, .. } => {
                 match event {
-                    WindowEvent::CloseRequested => processor.ctx.terminal.exit(),
+                    WindowEvent::CloseRequested => self.ctx.terminal.exit(),
                     WindowEvent::Resized(size) => {
                         // Minimizing the window sends a Resize event with zero width and
                         // height. But there's no need to ever actually resize to this.
@@ -1158,53 +1721,54 @@ impl<N: Notify + OnResize> Processor<N> {
                             return;
                         }
 
-                        processor.ctx.display_update_pending.set_dimensions(size);
-                        processor.ctx.terminal.dirty = true;
+                        self.ctx.display_update_pending.set_dimensions(size);
+                        self.ctx.terminal.dirty = true;
                     },
                     WindowEvent::KeyboardInput { input, is_synthetic: false, .. } => {
-                        processor.key_input(input);
+                        self.key_input(input);
                     },
-                    WindowEvent::ReceivedCharacter(c) => processor.received_char(c),
+                    WindowEvent::ReceivedCharacter(c) => self.received_char(c),
                     WindowEvent::MouseInput { state, button, .. } => {
-                        processor.ctx.window.set_mouse_visible(true);
-                        processor.mouse_input(state, button);
-                        processor.ctx.terminal.dirty = true;
-                    },
-                    WindowEvent::ModifiersChanged(modifiers) => {
-                        processor.modifiers_input(modifiers)
+                        self.ctx.window.set_mouse_visible(true);
+                        self.mouse_input(state, button);
+                        self.ctx.terminal.dirty = true;
                     },
+                    WindowEvent::ModifiersChanged(modifiers) => self.modifiers_input(modifiers),
                     WindowEvent::CursorMoved { position, .. } => {
-                        processor.ctx.window.set_mouse_visible(true);
-                        processor.mouse_moved(position);
+                        self.ctx.window.set_mouse_visible(true);
+                        self.mouse_moved(position);
                     },
                     WindowEvent::MouseWheel { delta, phase, .. } => {
-                        processor.ctx.window.set_mouse_visible(true);
-                        processor.mouse_wheel_input(delta, phase);
+                        self.ctx.window.set_mouse_visible(true);
+                        self.mouse_wheel_input(delta, phase);
                     },
                     WindowEvent::Focused(is_focused) => {
-                        if window_id == processor.ctx.window.window_id() {
-                            processor.ctx.terminal.is_focused = is_focused;
-                            processor.ctx.terminal.dirty = true;
+                        if window_id == self.ctx.window.window_id() {
+                            self.ctx.terminal.is_focused = is_focused;
+                            self.ctx.terminal.dirty = true;
 
                             if is_focused {
-                                processor.ctx.window.set_urgent(false);
+                                self.ctx.window.set_urgent(false);
                             } else {
-                                processor.ctx.window.set_mouse_visible(true);
+                                self.ctx.window.set_mouse_visible(true);
                             }
 
-                            processor.ctx.update_cursor_blinking();
-                            processor.on_focus_change(is_focused);
+                            // Releases are not received while the window is unfocused.
+                            *self.ctx.pressed_key = None;
+
+                            self.ctx.update_cursor_blinking();
+                            self.on_focus_change(is_focused);
                         }
                     },
                     WindowEvent::DroppedFile(path) => {
                         let path: String = path.to_string_lossy().into();
-                        processor.ctx.write_to_pty((path + " ").into_bytes());
+                        self.ctx.write_to_pty((path + " ").into_bytes());
                     },
                     WindowEvent::CursorLeft { .. } => {
-                        processor.ctx.mouse.inside_text_area = false;
+                        self.ctx.mouse.inside_text_area = false;
 
-                        if processor.highlighted_url.is_some() {
-                            processor.ctx.terminal.dirty = true;
+                        if self.highlighted_url.is_some() {
+                            self.ctx.terminal.dirty = true;
                         }
                     },
                     WindowEvent::KeyboardInput { is_synthetic: true, .. }
@@ -1229,182 +1793,96 @@ impl<N: Notify + OnResize> Processor<N> {
             | GlutinEvent::LoopDestroyed => (),
         }
     }
+}
 
-    /// Check if an event is irrelevant and can be skipped.
-    fn skip_event(event: &GlutinEvent<'_, Event>) -> bool {
-        match event {
-            GlutinEvent::WindowEvent { event, .. } => matches!(
-                event,
-                WindowEvent::KeyboardInput { is_synthetic: true, .. }
-                    | WindowEvent::TouchpadPressure { .. }
-                    | WindowEvent::CursorEntered { .. }
-                    | WindowEvent::AxisMotion { .. }
-                    | WindowEvent::HoveredFileCancelled
-                    | WindowEvent::Destroyed
-                    | WindowEvent::HoveredFile(_)
-                    | WindowEvent::Touch(_)
-                    | WindowEvent::Moved(_)
-            ),
-            GlutinEvent::Suspended { .. }
-            | GlutinEvent::NewEvents { .. }
-            | GlutinEvent::MainEventsCleared
-            | GlutinEvent::LoopDestroyed => true,
-            _ => false,
-        }
+/// Get the current working directory of a terminal.
+///
+/// Directories reported by the shell through `OSC 7` are preferred, since they are accurate even
+/// inside of nested shells. Otherwise the working directory of the foreground process is used.
+pub fn working_directory<T>(
+    terminal: &Term<T>,
+    #[cfg(not(windows))] master_fd: RawFd,
+    #[cfg(not(windows))] shell_pid: u32,
+) -> Option<PathBuf> {
+    // Ignore directories which do not exist locally, like ones reported over SSH.
+    match terminal.working_directory().filter(|path| path.is_dir()) {
+        Some(path) => Some(path.to_path_buf()),
+        #[cfg(not(windows))]
+        None => foreground_process_path(master_fd, shell_pid).ok(),
+        #[cfg(windows)]
+        None => None,
     }
+}
 
-    fn reload_config<T>(
-        path: &PathBuf,
-        processor: &mut input::Processor<'_, T, ActionContext<'_, N, T>>,
-    ) where
-        T: EventListener,
-    {
-        if !processor.ctx.message_buffer.is_empty() {
-            processor.ctx.message_buffer.remove_target(LOG_TARGET_CONFIG);
-            processor.ctx.display_update_pending.dirty = true;
-        }
-
-        let config = match config::reload(&path, &processor.ctx.cli_options) {
-            Ok(config) => config,
-            Err(_) => return,
-        };
-
-        processor.ctx.terminal.update_config(&config);
-
-        // Reload cursor if its thickness has changed.
-        if (processor.ctx.config.cursor.thickness() - config.cursor.thickness()).abs()
-            > f32::EPSILON
-        {
-            processor.ctx.display_update_pending.set_cursor_dirty();
-        }
-
-        if processor.ctx.config.ui_config.font != config.ui_config.font {
-            // Do not update font size if it has been changed at runtime.
-            if *processor.ctx.font_size == processor.ctx.config.ui_config.font.size() {
-                *processor.ctx.font_size = config.ui_config.font.size();
-            }
-
-            let font = config.ui_config.font.clone().with_size(*processor.ctx.font_size);
-            processor.ctx.display_update_pending.set_font(font);
-        }
-
-        // Update display if padding options were changed.
-        let window_config = &processor.ctx.config.ui_config.window;
-        if window_config.padding(1.) != config.ui_config.window.padding(1.)
-            || window_config.dynamic_padding != config.ui_config.window.dynamic_padding
-        {
-            processor.ctx.display_update_pending.dirty = true;
-        }
-
-        // Live title reload.
-        if !config.ui_config.window.dynamic_title
-            || processor.ctx.config.ui_config.window.title != config.ui_config.window.title
-        {
-            processor.ctx.window.set_title(&config.ui_config.window.title);
-        }
-
-        #[cfg(all(feature = "wayland", not(any(target_os = "macos", windows))))]
-        if processor.ctx.event_loop.is_wayland() {
-            processor.ctx.window.set_wayland_theme(&config.colors);
-        }
-
-        // Set subpixel anti-aliasing.
-        #[cfg(target_os = "macos")]
-        crossfont::set_font_smoothing(config.ui_config.font.use_thin_strokes);
-
-        // Disable shadows for transparent windows on macOS.
-        #[cfg(target_os = "macos")]
-        processor.ctx.window.set_has_shadow(config.ui_config.background_opacity() >= 1.0);
-
-        *processor.ctx.config = config;
+/// Get the current working directory of the terminal's foreground process.
+///
+/// This falls back to the working directory of the shell if there is no foreground process.
+#[cfg(not(windows))]
+fn foreground_process_path(master_fd: RawFd, shell_pid: u32) -> Result<PathBuf, Box<dyn Error>> {
+    let mut pid = unsafe { libc::tcgetpgrp(master_fd) };
+    if pid < 0 {
+        pid = shell_pid as libc::pid_t;
+    }
+
+    #[cfg(not(any(target_os = "macos", target_os = "freebsd")))]
+    let link_path = format!("/proc/{}/cwd", pid);
+    #[cfg(target_os = "freebsd")]
+    let link_path = format!("/compat/linux/proc/{}/cwd", pid);
+    #[cfg(not(target_os = "macos"))]
+    let cwd = fs::read_link(link_path)?;
+    #[cfg(target_os = "macos")]
+    let cwd = macos::proc::cwd(pid)?;
+
+    Ok(cwd)
+}
 
-        // Update cursor blinking.
-        processor.ctx.update_cursor_blinking();
+/// Parse an `HH:MM[:SS]` local time of day.
+///
+/// Times later than the current time of day are assumed to refer to the previous day.
+fn parse_time_of_day(text: &str) -> Option<SystemTime> {
+    let mut fields = text.trim().split(':').map(|field| field.parse::<u8>().ok());
+    let hour = fields.next()??;
+    let min = fields.next()??;
+    let sec = fields.next().unwrap_or(Some(0))?;
 
-        processor.ctx.terminal.dirty = true;
+    if fields.next().is_some() || hour > 23 || min > 59 || sec > 59 {
+        return None;
     }
 
-    /// Submit the pending changes to the `Display`.
-    fn submit_display_update<T>(
-        &mut self,
-        terminal: &mut Term<T>,
-        old_is_searching: bool,
-        display_update_pending: DisplayUpdate,
-    ) where
-        T: EventListener,
-    {
-        // Compute cursor positions before resize.
-        let num_lines = terminal.screen_lines();
-        let cursor_at_bottom = terminal.grid().cursor.point.line + 1 == num_lines;
-        let origin_at_bottom = if terminal.mode().contains(TermMode::VI) {
-            terminal.vi_mode_cursor.point.line == num_lines - 1
-        } else {
-            self.search_state.direction == Direction::Left
-        };
-
-        self.display.handle_update(
-            terminal,
-            &mut self.notifier,
-            &self.message_buffer,
-            self.search_state.history_index.is_some(),
-            &self.config,
-            display_update_pending,
-        );
+    let mut tm = time::now();
+    tm.tm_hour = i32::from(hour);
+    tm.tm_min = i32::from(min);
+    tm.tm_sec = i32::from(sec);
+    tm.tm_nsec = 0;
 
-        // Scroll to make sure search origin is visible and content moves as little as possible.
-        if !old_is_searching && self.search_state.history_index.is_some() {
-            let display_offset = terminal.grid().display_offset();
-            if display_offset == 0 && cursor_at_bottom && !origin_at_bottom {
-                terminal.scroll_display(Scroll::Delta(1));
-            } else if display_offset != 0 && origin_at_bottom {
-                terminal.scroll_display(Scroll::Delta(-1));
-            }
-        }
+    let mut secs = tm.to_timespec().sec;
+    if secs > time::get_time().sec {
+        secs -= 24 * 60 * 60;
     }
 
-    /// Write the ref test results to the disk.
-    fn write_ref_test_results<T>(&self, terminal: &Term<T>) {
-        // Dump grid state.
-        let mut grid = terminal.grid().clone();
-        grid.initialize_all();
-        grid.truncate();
-
-        let serialized_grid = json::to_string(&grid).expect("serialize grid");
-
-        let serialized_size = json::to_string(&self.display.size_info).expect("serialize size");
-
-        let serialized_config = format!("{{\"history_size\":{}}}", grid.history_size());
-
-        File::create("./grid.json")
-            .and_then(|mut f| f.write_all(serialized_grid.as_bytes()))
-            .expect("write grid.json");
-
-        File::create("./size.json")
-            .and_then(|mut f| f.write_all(serialized_size.as_bytes()))
-            .expect("write size.json");
-
-        File::create("./config.json")
-            .and_then(|mut f| f.write_all(serialized_config.as_bytes()))
-            .expect("write config.json");
-    }
+    Some(UNIX_EPOCH + Duration::from_secs(max(secs, 0) as u64))
 }
 
+/// Event proxy for a single window's terminal.
 #[derive(Debug, Clone)]
-pub struct EventProxy(EventLoopProxy<Event>);
+pub struct EventProxy {
+    proxy: EventLoopProxy<Event>,
+    window_id: WindowId,
+}
 
 impl EventProxy {
-    pub fn new(proxy: EventLoopProxy<Event>) -> Self {
-        EventProxy(proxy)
+    pub fn new(proxy: EventLoopProxy<Event>, window_id: WindowId) -> Self {
+        Self { proxy, window_id }
     }
 
     /// Send an event to the event loop.
-    pub fn send_event(&self, event: Event) {
-        let _ = self.0.send_event(event);
+    pub fn send_event(&self, event: EventType) {
+        let _ = self.proxy.send_event(Event::new(event, self.window_id));
     }
 }
 
 impl EventListener for EventProxy {
     fn send_event(&self, event: TerminalEvent) {
-        let _ = self.0.send_event(Event::TerminalEvent(event));
+        let _ = self.proxy.send_event(Event::new(event.into(), self.window_id));
     }
 }
@@ -0,0 +1,546 @@
+//! Scripted terminal sessions without a window.
+//!
+//! Headless mode runs the shell in a terminal which is never displayed, driving it with a script
+//! read from a file or STDIN. Every line of the script is a single command:
+//!
+//! - `send TEXT` writes text to the shell, supporting `\n`, `\r`, `\t`, `\a`, `\e`, `\\` and `\xHH`
+//! - `key NAME...` writes the escape sequences of named keys like `Enter`, `Up` or `Ctrl+C`
+//! - `wait REGEX` waits until the regex matches the visible terminal content
+//! - `timeout SECONDS` changes the time `wait` gives up after
+//! - `sleep SECONDS` pauses the script
+//! - `resize COLUMNSxLINES` changes the terminal dimensions
+//! - `dump text|ansi|json [PATH]` writes the visible terminal content to STDOUT or a file
+//!
+//! Empty lines and lines starting with `#` are ignored.
+
+use std::error::Error;
+use std::fmt::{self, Display, Formatter};
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufWriter, Read, Write};
+#[cfg(unix)]
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::PathBuf;
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use log::{debug, info};
+use serde::Serialize;
+
+use alacritty_terminal::asciicast::Recorder;
+use alacritty_terminal::event::{Event, EventListener, Notify, OnResize};
+use alacritty_terminal::event_loop::{EventLoop as PtyEventLoop, Msg, Notifier};
+use alacritty_terminal::grid::Dimensions;
+use alacritty_terminal::index::{Column, Point};
+use alacritty_terminal::sync::FairMutex;
+use alacritty_terminal::term::search::RegexSearch;
+use alacritty_terminal::term::{SizeInfo, Term, TermMode};
+use alacritty_terminal::tty;
+
+use crate::cli::Options;
+use crate::config::Config;
+
+/// Terminal dimensions used when none are configured.
+const DEFAULT_DIMENSIONS: (usize, usize) = (80, 24);
+
+/// Default time after which `wait` commands fail.
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Maximum duration accepted by `timeout` and `sleep` commands, in seconds.
+const MAX_DURATION: f64 = 24. * 60. * 60.;
+
+/// Failure of a single script command.
+#[derive(Debug)]
+pub struct ScriptError {
+    /// Line of the script, starting at 1.
+    line: usize,
+    message: String,
+}
+
+impl ScriptError {
+    fn new<M: Display>(line: usize, message: M) -> Self {
+        Self { line, message: message.to_string() }
+    }
+}
+
+impl Error for ScriptError {}
+
+impl Display for ScriptError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "Script line {}: {}", self.line, self.message)
+    }
+}
+
+/// Format of a terminal content dump.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum DumpFormat {
+    Text,
+    Ansi,
+    Json,
+}
+
+/// Single script command.
+#[derive(Debug)]
+enum Command {
+    Send(Vec<u8>),
+    Key(Vec<String>),
+    Wait(Box<RegexSearch>, String),
+    Timeout(Duration),
+    Sleep(Duration),
+    Resize(usize, usize),
+    Dump(DumpFormat, Option<PathBuf>),
+}
+
+impl Command {
+    fn parse(line: &str) -> Result<Self, String> {
+        let (name, argument) = match line.find(' ') {
+            Some(index) => (&line[..index], &line[index + 1..]),
+            None => (line, ""),
+        };
+
+        match name {
+            "send" => Ok(Command::Send(unescape(argument)?)),
+            "key" => {
+                let keys: Vec<String> = argument.split_whitespace().map(String::from).collect();
+                for key in &keys {
+                    key_bytes(key, false)?;
+                }
+                Ok(Command::Key(keys))
+            },
+            "wait" => match RegexSearch::new(argument) {
+                Ok(regex) => Ok(Command::Wait(Box::new(regex), argument.to_owned())),
+                Err(err) => Err(format!("invalid regex: {}", err)),
+            },
+            "timeout" => parse_duration(argument).map(Command::Timeout),
+            "sleep" => parse_duration(argument).map(Command::Sleep),
+            "resize" => {
+                let mut dimensions = argument.splitn(2, 'x').map(|value| value.trim().parse());
+                match (dimensions.next(), dimensions.next()) {
+                    (Some(Ok(columns)), Some(Ok(lines))) if columns > 0 && lines > 0 => {
+                        Ok(Command::Resize(columns, lines))
+                    },
+                    _ => Err(format!("invalid dimensions: {:?}", argument)),
+                }
+            },
+            "dump" => {
+                let mut args = argument.splitn(2, ' ');
+                let format = match args.next().unwrap_or_default() {
+                    "text" => DumpFormat::Text,
+                    "ansi" => DumpFormat::Ansi,
+                    "json" => DumpFormat::Json,
+                    format => return Err(format!("unknown dump format: {:?}", format)),
+                };
+                let path = args.next().map(str::trim).filter(|path| !path.is_empty());
+                Ok(Command::Dump(format, path.map(PathBuf::from)))
+            },
+            _ => Err(format!("unknown command: {:?}", name)),
+        }
+    }
+}
+
+/// Terminal content written by `dump json`.
+#[derive(Serialize)]
+struct JsonDump<'a> {
+    columns: usize,
+    lines: usize,
+    cursor: JsonCursor,
+    title: Option<&'a str>,
+    text: Vec<String>,
+}
+
+#[derive(Serialize)]
+struct JsonCursor {
+    line: usize,
+    column: usize,
+}
+
+/// Terminal event listener forwarding all events to the script runner.
+#[derive(Clone)]
+struct EventProxy(Sender<Event>);
+
+impl EventListener for EventProxy {
+    fn send_event(&self, event: Event) {
+        let _ = self.0.send(event);
+    }
+}
+
+/// State of a running script.
+struct Runner {
+    terminal: Arc<FairMutex<Term<EventProxy>>>,
+    notifier: Notifier,
+    events: Receiver<Event>,
+    timeout: Duration,
+    title: Option<String>,
+    clipboard: String,
+    exited: bool,
+}
+
+impl Runner {
+    /// Run a single script command.
+    fn run(&mut self, command: Command) -> Result<(), String> {
+        match command {
+            Command::Send(bytes) => self.notifier.notify(bytes),
+            Command::Key(keys) => {
+                // Cursor key sequences depend on the terminal mode at the time they are sent.
+                let app_cursor = self.terminal.lock().mode().contains(TermMode::APP_CURSOR);
+
+                let mut bytes = Vec::new();
+                for key in keys {
+                    bytes.extend(key_bytes(&key, app_cursor)?);
+                }
+                self.notifier.notify(bytes);
+            },
+            Command::Wait(regex, source) => self.wait(&regex, &source)?,
+            Command::Timeout(timeout) => self.timeout = timeout,
+            Command::Sleep(duration) => {
+                let deadline = deadline_after(duration)?;
+                while self.next_event(deadline) {}
+            },
+            Command::Resize(columns, lines) => {
+                let size_info = SizeInfo::new(columns as f32, lines as f32, 1., 1., 0., 0., false);
+                self.notifier.on_resize(&size_info);
+                self.terminal.lock().resize(size_info);
+            },
+            Command::Dump(format, path) => {
+                let dump = self.dump(format);
+                let result = match path {
+                    Some(path) => fs::write(&path, dump),
+                    None => io::stdout().write_all(dump.as_bytes()),
+                };
+                result.map_err(|err| format!("unable to write dump: {}", err))?;
+            },
+        }
+
+        Ok(())
+    }
+
+    /// Wait for the regex to match the visible terminal content.
+    fn wait(&mut self, regex: &RegexSearch, source: &str) -> Result<(), String> {
+        let deadline = deadline_after(self.timeout)?;
+
+        loop {
+            if self.terminal.lock().visible_regex_matches(regex).next().is_some() {
+                return Ok(());
+            }
+
+            if self.exited {
+                return Err(format!("shell exited before {:?} appeared", source));
+            }
+
+            if !self.next_event(deadline) {
+                return Err(format!("timed out waiting for {:?}", source));
+            }
+        }
+    }
+
+    /// Handle the next terminal event.
+    ///
+    /// Returns `false` once the deadline has passed without an event.
+    fn next_event(&mut self, deadline: Instant) -> bool {
+        let timeout = deadline.saturating_duration_since(Instant::now());
+        let event = match self.events.recv_timeout(timeout) {
+            Ok(event) => event,
+            Err(RecvTimeoutError::Timeout) | Err(RecvTimeoutError::Disconnected) => return false,
+        };
+
+        match event {
+            Event::PtyWrite(text) => self.notifier.notify(text.into_bytes()),
+            Event::Title(title) => self.title = Some(title),
+            Event::ResetTitle => self.title = None,
+            Event::ClipboardStore(_, text) => self.clipboard = text,
+            Event::ClipboardLoad(_, format) => {
+                self.notifier.notify(format(&self.clipboard).into_bytes());
+            },
+            Event::Exit => {
+                info!("Shell exited");
+                self.exited = true;
+            },
+            event => debug!("Ignoring headless event: {:?}", event),
+        }
+
+        true
+    }
+
+    /// Format the visible terminal content.
+    fn dump(&self, format: DumpFormat) -> String {
+        let terminal = self.terminal.lock();
+
+        let lines = terminal.screen_lines().0;
+        let columns = terminal.cols();
+        let rows = (0..lines).rev().map(|line| {
+            let start = Point::new(line, Column(0));
+            let end = Point::new(line, columns - 1);
+            match format {
+                DumpFormat::Ansi => terminal.bounds_to_ansi(start, end),
+                _ => terminal.bounds_to_string(start, end),
+            }
+        });
+
+        // Print every row on its own line, even if it is wrapped.
+        let rows: Vec<String> = rows.map(|row| row.trim_end_matches('\n').to_owned()).collect();
+
+        if format != DumpFormat::Json {
+            let mut text = rows.join("\n");
+            text.push('\n');
+            return text;
+        }
+
+        let cursor = terminal.grid().cursor.point;
+        let dump = JsonDump {
+            columns: columns.0,
+            lines,
+            cursor: JsonCursor { line: cursor.line.0, column: cursor.col.0 },
+            title: self.title.as_deref(),
+            text: rows,
+        };
+
+        let mut json = serde_json::to_string(&dump).unwrap_or_default();
+        json.push('\n');
+        json
+    }
+}
+
+/// Run the shell without a window, driven by a script.
+pub fn run(config: Config, options: Options) -> Result<(), Box<dyn Error>> {
+    let mut script = String::new();
+    match &options.script {
+        Some(path) => File::open(path)?.read_to_string(&mut script)?,
+        None => io::stdin().read_to_string(&mut script)?,
+    };
+
+    // Parse the entire script before starting the shell, to report errors early.
+    let mut commands = Vec::new();
+    for (index, line) in script.lines().enumerate() {
+        let line = line.trim_start();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let command = Command::parse(line).map_err(|err| ScriptError::new(index + 1, err))?;
+        commands.push((index + 1, command));
+    }
+
+    let (columns, lines) = match config.ui_config.window.dimensions() {
+        Some(dimensions) => (dimensions.columns.0, dimensions.lines.0),
+        None => DEFAULT_DIMENSIONS,
+    };
+    let size_info = SizeInfo::new(columns as f32, lines as f32, 1., 1., 0., 0., false);
+
+    let (event_tx, event_rx) = mpsc::channel();
+    let event_proxy = EventProxy(event_tx);
+
+    let terminal = Term::new(&config, size_info, event_proxy.clone());
+    let terminal = Arc::new(FairMutex::new(terminal));
+
+    let pty = tty::new(&config, &size_info, None);
+    let event_loop = PtyEventLoop::new(
+        Arc::clone(&terminal),
+        event_proxy,
+        pty,
+        config.hold,
+        config.ui_config.debug.ref_test,
+    );
+    let loop_tx = event_loop.channel();
+    let io_thread = event_loop.spawn();
+
+    if let Some(path) = &options.record {
+        // Recordings contain everything typed into the terminal, so keep them private.
+        let mut file_options = OpenOptions::new();
+        file_options.write(true).create_new(true);
+        #[cfg(unix)]
+        file_options.mode(0o600);
+
+        let recorder = file_options
+            .open(path)
+            .and_then(|file| Recorder::new(BufWriter::new(file), &size_info))?;
+        let _ = loop_tx.send(Msg::Record(Some(recorder)));
+    }
+
+    let mut runner = Runner {
+        terminal,
+        notifier: Notifier(loop_tx.clone()),
+        events: event_rx,
+        timeout: DEFAULT_TIMEOUT,
+        title: None,
+        clipboard: String::new(),
+        exited: false,
+    };
+
+    let mut result = Ok(());
+    for (line, command) in commands {
+        if let Err(err) = runner.run(command) {
+            result = Err(ScriptError::new(line, err));
+            break;
+        }
+    }
+
+    // Shut down the shell.
+    let _ = loop_tx.send(Msg::Shutdown);
+    let _ = io_thread.join();
+
+    result.map_err(Into::into)
+}
+
+/// Replace escape sequences in the text of a `send` command.
+fn unescape(text: &str) -> Result<Vec<u8>, String> {
+    let mut bytes = Vec::with_capacity(text.len());
+
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            let mut buf = [0; 4];
+            bytes.extend_from_slice(c.encode_utf8(&mut buf).as_bytes());
+            continue;
+        }
+
+        match chars.next() {
+            Some('n') => bytes.push(b'\n'),
+            Some('r') => bytes.push(b'\r'),
+            Some('t') => bytes.push(b'\t'),
+            Some('a') => bytes.push(0x07),
+            Some('e') => bytes.push(0x1b),
+            Some('\\') => bytes.push(b'\\'),
+            Some('x') => {
+                let hex: String = chars.by_ref().take(2).collect();
+                match u8::from_str_radix(&hex, 16) {
+                    Ok(byte) if hex.len() == 2 => bytes.push(byte),
+                    _ => return Err(format!("invalid escape \\x{}", hex)),
+                }
+            },
+            Some(c) => return Err(format!("invalid escape \\{}", c)),
+            None => return Err(String::from("trailing backslash")),
+        }
+    }
+
+    Ok(bytes)
+}
+
+/// Escape sequence of a named key.
+///
+/// Keys can be combined with a `Ctrl+` or `Alt+` modifier prefix.
+fn key_bytes(key: &str, app_cursor: bool) -> Result<Vec<u8>, String> {
+    let mut parts = key.splitn(2, '+');
+    match (parts.next(), parts.next()) {
+        (Some("Alt"), Some(key)) => {
+            let mut bytes = vec![0x1b];
+            bytes.extend(key_bytes(key, app_cursor)?);
+            return Ok(bytes);
+        },
+        (Some("Ctrl"), Some(key)) => {
+            let mut chars = key.chars();
+            return match (chars.next(), chars.next()) {
+                (Some(c), None) if c.is_ascii_alphabetic() || "@[\\]^_".contains(c) => {
+                    Ok(vec![c.to_ascii_uppercase() as u8 & 0x1f])
+                },
+                (Some(' '), None) | (Some('2'), None) => Ok(vec![0]),
+                _ => Err(format!("unsupported key: Ctrl+{}", key)),
+            };
+        },
+        _ => (),
+    }
+
+    let cursor = |c: char| {
+        if app_cursor {
+            format!("\x1bO{}", c)
+        } else {
+            format!("\x1b[{}", c)
+        }
+    };
+
+    let sequence = match key {
+        "Enter" => String::from("\r"),
+        "Tab" => String::from("\t"),
+        "Backspace" => String::from("\x7f"),
+        "Escape" => String::from("\x1b"),
+        "Space" => String::from(" "),
+        "Up" => cursor('A'),
+        "Down" => cursor('B'),
+        "Right" => cursor('C'),
+        "Left" => cursor('D'),
+        "Home" => cursor('H'),
+        "End" => cursor('F'),
+        "Insert" => String::from("\x1b[2~"),
+        "Delete" => String::from("\x1b[3~"),
+        "PageUp" => String::from("\x1b[5~"),
+        "PageDown" => String::from("\x1b[6~"),
+        "F1" => String::from("\x1bOP"),
+        "F2" => String::from("\x1bOQ"),
+        "F3" => String::from("\x1bOR"),
+        "F4" => String::from("\x1bOS"),
+        "F5" => String::from("\x1b[15~"),
+        "F6" => String::from("\x1b[17~"),
+        "F7" => String::from("\x1b[18~"),
+        "F8" => String::from("\x1b[19~"),
+        "F9" => String::from("\x1b[20~"),
+        "F10" => String::from("\x1b[21~"),
+        "F11" => String::from("\x1b[23~"),
+        "F12" => String::from("\x1b[24~"),
+        _ if key.chars().count() == 1 => key.to_owned(),
+        _ => return Err(format!("unsupported key: {}", key)),
+    };
+
+    Ok(sequence.into_bytes())
+}
+
+/// Parse a duration in seconds.
+fn parse_duration(text: &str) -> Result<Duration, String> {
+    match text.trim().parse::<f64>() {
+        Ok(seconds) if (0. ..=MAX_DURATION).contains(&seconds) => {
+            Ok(Duration::from_secs_f64(seconds))
+        },
+        _ => Err(format!("invalid duration: {:?}", text)),
+    }
+}
+
+/// Point in time once `duration` has passed.
+fn deadline_after(duration: Duration) -> Result<Instant, String> {
+    Instant::now().checked_add(duration).ok_or_else(|| format!("invalid duration: {:?}", duration))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unescape_send() {
+        assert_eq!(unescape("ls\\n").unwrap(), b"ls\n");
+        assert_eq!(unescape("\\e[A\\x03\\\\\u{e4}").unwrap(), "\x1b[A\x03\\\u{e4}".as_bytes());
+        assert!(unescape("\\x3").is_err());
+        assert!(unescape("\\q").is_err());
+        assert!(unescape("trailing\\").is_err());
+    }
+
+    #[test]
+    fn named_keys() {
+        assert_eq!(key_bytes("Up", false).unwrap(), b"\x1b[A");
+        assert_eq!(key_bytes("Up", true).unwrap(), b"\x1bOA");
+        assert_eq!(key_bytes("Ctrl+c", false).unwrap(), b"\x03");
+        assert_eq!(key_bytes("Alt+x", false).unwrap(), b"\x1bx");
+        assert_eq!(key_bytes("Alt+Ctrl+[", false).unwrap(), b"\x1b\x1b");
+        assert!(key_bytes("Ctrl+Up", false).is_err());
+        assert!(key_bytes("Hyper", false).is_err());
+    }
+
+    #[test]
+    fn parse_commands() {
+        assert!(matches!(Command::parse("resize 100x30"), Ok(Command::Resize(100, 30))));
+        assert!(Command::parse("resize 0x30").is_err());
+        assert!(matches!(
+            Command::parse("dump json out.json"),
+            Ok(Command::Dump(DumpFormat::Json, Some(_)))
+        ));
+        assert!(matches!(Command::parse("dump text"), Ok(Command::Dump(_, None))));
+        assert!(Command::parse("dump html").is_err());
+        assert!(Command::parse("wait (").is_err());
+        assert!(Command::parse("launch").is_err());
+    }
+
+    #[test]
+    fn durations() {
+        assert_eq!(parse_duration(" 1.5").unwrap(), Duration::from_millis(1500));
+        assert!(parse_duration("-1").is_err());
+        assert!(parse_duration("NaN").is_err());
+        assert!(parse_duration("1e19").is_err());
+        assert!(parse_duration("1e20").is_err());
+    }
+}
@@ -8,6 +8,7 @@
 use std::borrow::Cow;
 use std::cmp::{max, min, Ordering};
 use std::marker::PhantomData;
+use std::mem;
 use std::time::{Duration, Instant};
 
 use log::trace;
@@ -15,7 +16,9 @@ use log::trace;
 use glutin::dpi::PhysicalPosition;
 use glutin::event::{
     ElementState, KeyboardInput, ModifiersState, MouseButton, MouseScrollDelta, TouchPhase,
+    VirtualKeyCode,
 };
+#[cfg(target_os = "macos")]
 use glutin::event_loop::EventLoopWindowTarget;
 #[cfg(target_os = "macos")]
 use glutin::platform::macos::EventLoopWindowTargetExtMacOS;
@@ -30,9 +33,13 @@ use alacritty_terminal::term::{ClipboardType, SizeInfo, Term, TermMode};
 use alacritty_terminal::vi_mode::ViMotion;
 
 use crate::clipboard::Clipboard;
-use crate::config::{Action, Binding, BindingMode, Config, Key, SearchAction, ViAction};
+use crate::config::ui_config::{HintAction, HintInternalAction};
+use crate::config::{
+    default_key_bindings, Action, Binding, BindingMode, Config, Key, SearchAction, ViAction,
+};
 use crate::daemon::start_daemon;
-use crate::event::{ClickState, Event, Mouse, TYPING_SEARCH_DELAY};
+use crate::event::{ClickState, Event, EventType, Mouse, TYPING_SEARCH_DELAY};
+use crate::hint::HintState;
 use crate::message_bar::{self, Message};
 use crate::scheduler::{Scheduler, TimerId};
 use crate::url::{Url, Urls};
@@ -64,6 +71,7 @@ pub trait ActionContext<T: EventListener> {
     fn write_to_pty<B: Into<Cow<'static, [u8]>>>(&mut self, data: B);
     fn size_info(&self) -> SizeInfo;
     fn copy_selection(&mut self, ty: ClipboardType);
+    fn copy_selection_as_html(&mut self);
     fn start_selection(&mut self, ty: SelectionType, point: Point, side: Side);
     fn toggle_selection(&mut self, ty: SelectionType, point: Point, side: Side);
     fn update_selection(&mut self, point: Point, side: Side);
@@ -74,6 +82,7 @@ pub trait ActionContext<T: EventListener> {
     fn mouse_coords(&self) -> Option<Point>;
     fn received_count(&mut self) -> &mut usize;
     fn suppress_chars(&mut self) -> &mut bool;
+    fn pressed_key(&mut self) -> &mut Option<VirtualKeyCode>;
     fn modifiers(&mut self) -> &mut ModifiersState;
     fn scroll(&mut self, scroll: Scroll);
     fn window(&self) -> &Window;
@@ -81,11 +90,18 @@ pub trait ActionContext<T: EventListener> {
     fn terminal(&self) -> &Term<T>;
     fn terminal_mut(&mut self) -> &mut Term<T>;
     fn spawn_new_instance(&mut self);
+    fn create_new_window(&mut self);
+    fn save_session(&mut self);
+    fn toggle_recording(&mut self);
+    fn export_scrollback(&mut self);
     fn change_font_size(&mut self, delta: f32);
     fn reset_font_size(&mut self);
     fn pop_message(&mut self);
     fn message(&self) -> Option<&Message>;
+    fn permission_prompt_active(&self) -> bool;
+    fn answer_permission_prompt(&mut self, allow: bool);
     fn config(&self) -> &Config;
+    #[cfg(target_os = "macos")]
     fn event_loop(&self) -> &EventLoopWindowTarget<Event>;
     fn urls(&self) -> &Urls;
     fn launch_url(&self, url: Url);
@@ -93,6 +109,7 @@ pub trait ActionContext<T: EventListener> {
     fn clipboard_mut(&mut self) -> &mut Clipboard;
     fn scheduler_mut(&mut self) -> &mut Scheduler;
     fn start_search(&mut self, direction: Direction);
+    fn start_time_jump(&mut self);
     fn confirm_search(&mut self);
     fn cancel_search(&mut self);
     fn search_input(&mut self, c: char);
@@ -103,6 +120,7 @@ pub trait ActionContext<T: EventListener> {
     fn search_direction(&self) -> Direction;
     fn search_active(&self) -> bool;
     fn on_typing_start(&mut self);
+    fn hint_state(&mut self) -> &mut HintState;
 }
 
 trait Execute<T: EventListener> {
@@ -131,6 +149,31 @@ impl Action {
             selection.include_all();
         }
     }
+
+    fn scroll_to_prompt<T, A>(ctx: &mut A, direction: Direction)
+    where
+        T: EventListener,
+        A: ActionContext<T>,
+    {
+        // Move the vi mode cursor instead of the viewport.
+        if ctx.terminal().mode().contains(TermMode::VI) {
+            let motion = match direction {
+                Direction::Left => ViMotion::PreviousPrompt,
+                Direction::Right => ViMotion::NextPrompt,
+            };
+            ctx.terminal_mut().vi_motion(motion);
+            return;
+        }
+
+        // Put the closest prompt at the top of the viewport.
+        let term = ctx.terminal();
+        let top_line = term.grid().display_offset() + term.screen_lines().0 - 1;
+        match term.prompt_search(top_line, direction) {
+            Some(line) => ctx.scroll(Scroll::Delta(line as isize - top_line as isize)),
+            None if direction == Direction::Right => ctx.scroll(Scroll::Bottom),
+            None => (),
+        }
+    }
 }
 
 impl<T: EventListener> Execute<T> for Action {
@@ -151,6 +194,10 @@ impl<T: EventListener> Execute<T> for Action {
 
                 start_daemon(program, args);
             },
+            Action::Hint(ref hint) => {
+                ctx.hint_state().start(hint.clone());
+                ctx.terminal_mut().dirty = true;
+            },
             Action::ToggleViMode => ctx.terminal_mut().toggle_vi_mode(),
             Action::ViMotion(motion) => {
                 ctx.on_typing_start();
@@ -170,10 +217,12 @@ impl<T: EventListener> Execute<T> for Action {
             },
             Action::ViAction(ViAction::Open) => {
                 ctx.mouse_mut().block_url_launcher = false;
-                if let Some(url) = ctx.urls().find_at(ctx.terminal().vi_mode_cursor.point) {
+                let point = ctx.terminal().viewport_point(ctx.terminal().vi_mode_cursor.point);
+                if let Some(url) = ctx.urls().find_at(point) {
                     ctx.launch_url(url);
                 }
             },
+            Action::ViAction(ViAction::JumpToTime) => ctx.start_time_jump(),
             Action::ViAction(ViAction::SearchNext) => {
                 let terminal = ctx.terminal();
                 let direction = ctx.search_direction();
@@ -246,6 +295,7 @@ impl<T: EventListener> Execute<T> for Action {
             Action::SearchForward => ctx.start_search(Direction::Right),
             Action::SearchBackward => ctx.start_search(Direction::Left),
             Action::Copy => ctx.copy_selection(ClipboardType::Clipboard),
+            Action::CopySelectionAsHtml => ctx.copy_selection_as_html(),
             #[cfg(not(any(target_os = "macos", windows)))]
             Action::CopySelection => ctx.copy_selection(ClipboardType::Selection),
             Action::ClearSelection => ctx.clear_selection(),
@@ -340,9 +390,22 @@ impl<T: EventListener> Execute<T> for Action {
                 term.vi_motion(ViMotion::FirstOccupied);
                 term.vi_motion(ViMotion::FirstOccupied);
             },
+            Action::ScrollToPreviousPrompt => Self::scroll_to_prompt(ctx, Direction::Left),
+            Action::ScrollToNextPrompt => Self::scroll_to_prompt(ctx, Direction::Right),
+            Action::SelectLastCommandOutput => {
+                if let Some(selection) = ctx.terminal().last_command_output() {
+                    ctx.terminal_mut().selection = Some(selection);
+                    ctx.terminal_mut().dirty = true;
+                    ctx.copy_selection(ClipboardType::Clipboard);
+                }
+            },
             Action::ClearHistory => ctx.terminal_mut().clear_screen(ClearMode::Saved),
             Action::ClearLogNotice => ctx.pop_message(),
             Action::SpawnNewInstance => ctx.spawn_new_instance(),
+            Action::CreateNewWindow => ctx.create_new_window(),
+            Action::SaveSession => ctx.save_session(),
+            Action::ToggleRecording => ctx.toggle_recording(),
+            Action::ExportScrollback => ctx.export_scrollback(),
             Action::ReceiveChar | Action::None => (),
         }
     }
@@ -440,6 +503,7 @@ impl<'a, T: EventListener, A: ActionContext<T>> Processor<'a, T, A> {
 
         if (lmb_pressed || rmb_pressed) && (self.ctx.modifiers().shift() || !self.ctx.mouse_mode())
         {
+            let point = self.ctx.terminal().grid_point(point);
             self.ctx.update_selection(point, cell_side);
         } else if cell_changed
             && point.line < self.ctx.terminal().screen_lines()
@@ -479,8 +543,19 @@ impl<'a, T: EventListener, A: ActionContext<T>> Processor<'a, T, A> {
         }
     }
 
+    /// Grid cell below the mouse cursor, used for mouse reports.
+    fn mouse_report_point(&self) -> Point {
+        let mouse = self.ctx.mouse();
+        let point = Point::new(mouse.line, mouse.column);
+        if point.line < self.ctx.terminal().screen_lines() {
+            self.ctx.terminal().grid_point(point)
+        } else {
+            point
+        }
+    }
+
     fn normal_mouse_report(&mut self, button: u8) {
-        let (line, column) = (self.ctx.mouse().line, self.ctx.mouse().column);
+        let Point { line, col: column } = self.mouse_report_point();
         let utf8 = self.ctx.terminal().mode().contains(TermMode::UTF8_MOUSE);
 
         let max_point = if utf8 { 2015 } else { 223 };
@@ -514,7 +589,7 @@ impl<'a, T: EventListener, A: ActionContext<T>> Processor<'a, T, A> {
     }
 
     fn sgr_mouse_report(&mut self, button: u8, state: ElementState) {
-        let (line, column) = (self.ctx.mouse().line, self.ctx.mouse().column);
+        let Point { line, col: column } = self.mouse_report_point();
         let c = match state {
             ElementState::Pressed => 'M',
             ElementState::Released => 'm',
@@ -589,6 +664,7 @@ impl<'a, T: EventListener, A: ActionContext<T>> Processor<'a, T, A> {
             let mouse = self.ctx.mouse();
             let mut point = self.ctx.size_info().pixels_to_coords(mouse.x, mouse.y);
             point.line = min(point.line, self.ctx.terminal().screen_lines() - 1);
+            let point = self.ctx.terminal().grid_point(point);
 
             match button {
                 MouseButton::Left => self.on_left_click(point),
@@ -826,6 +902,11 @@ impl<'a, T: EventListener, A: ActionContext<T>> Processor<'a, T, A> {
         }
     }
 
+    /// Execute a binding action outside of any input event.
+    pub fn execute_action(&mut self, action: &Action) {
+        action.execute(&mut self.ctx);
+    }
+
     /// Process key input.
     pub fn key_input(&mut self, input: KeyboardInput) {
         // Reset search delay when the user is still typing.
@@ -838,10 +919,76 @@ impl<'a, T: EventListener, A: ActionContext<T>> Processor<'a, T, A> {
         match input.state {
             ElementState::Pressed => {
                 *self.ctx.received_count() = 0;
-                self.process_key_bindings(input);
+
+                // Only the last pressed key is repeated while it is held down.
+                let key = input.virtual_keycode;
+                let repeat = key.is_some() && mem::replace(self.ctx.pressed_key(), key) == key;
+
+                // Answer pending permission prompts before anything else.
+                if self.ctx.permission_prompt_active() {
+                    self.permission_prompt_input(input);
+                    return;
+                }
+
+                // Don't process bindings while hint selection is active.
+                if !self.ctx.hint_state().active() {
+                    self.process_key_bindings(input, repeat);
+                }
             },
-            ElementState::Released => *self.ctx.suppress_chars() = false,
+            ElementState::Released => {
+                *self.ctx.suppress_chars() = false;
+
+                if *self.ctx.pressed_key() == input.virtual_keycode {
+                    *self.ctx.pressed_key() = None;
+                }
+
+                if self.kitty_keyboard_active() && !self.ctx.hint_state().active() {
+                    let mode = *self.ctx.terminal().mode();
+                    let mods = *self.ctx.modifiers();
+                    let sequence = input
+                        .virtual_keycode
+                        .and_then(|key| kitty_key_sequence(mode, key, mods, KeyEventType::Release));
+
+                    if let Some(sequence) = sequence {
+                        self.ctx.write_to_pty(sequence);
+                    }
+                }
+            },
+        }
+    }
+
+    /// Answer a permission prompt, any key other than `y` denies the request.
+    fn permission_prompt_input(&mut self, input: KeyboardInput) {
+        let key = match input.virtual_keycode {
+            Some(key) => key,
+            None => return,
+        };
+
+        // Allow using modifiers without answering the prompt.
+        if matches!(
+            key,
+            VirtualKeyCode::LShift
+                | VirtualKeyCode::RShift
+                | VirtualKeyCode::LControl
+                | VirtualKeyCode::RControl
+                | VirtualKeyCode::LAlt
+                | VirtualKeyCode::RAlt
+                | VirtualKeyCode::LWin
+                | VirtualKeyCode::RWin
+        ) {
+            return;
         }
+
+        self.ctx.answer_permission_prompt(key == VirtualKeyCode::Y);
+        *self.ctx.suppress_chars() = true;
+    }
+
+    /// Check if key events should be encoded using the kitty keyboard protocol.
+    fn kitty_keyboard_active(&self) -> bool {
+        let mode = self.ctx.terminal().mode();
+        mode.intersects(TermMode::KITTY_KEYBOARD_PROTOCOL)
+            && !mode.contains(TermMode::VI)
+            && !self.ctx.search_active()
     }
 
     /// Modifier state change.
@@ -865,8 +1012,15 @@ impl<'a, T: EventListener, A: ActionContext<T>> Processor<'a, T, A> {
     pub fn received_char(&mut self, c: char) {
         let suppress_chars = *self.ctx.suppress_chars();
         let search_active = self.ctx.search_active();
-        if suppress_chars || self.ctx.terminal().mode().contains(TermMode::VI) || search_active {
-            if search_active && !suppress_chars {
+        let hint_active = self.ctx.hint_state().active();
+        if suppress_chars
+            || self.ctx.terminal().mode().contains(TermMode::VI)
+            || search_active
+            || hint_active
+        {
+            if hint_active && !suppress_chars {
+                self.hint_input(c);
+            } else if search_active && !suppress_chars {
                 self.ctx.search_input(c);
             }
 
@@ -898,13 +1052,42 @@ impl<'a, T: EventListener, A: ActionContext<T>> Processor<'a, T, A> {
         *self.ctx.received_count() += 1;
     }
 
+    /// Process character input during hint selection.
+    fn hint_input(&mut self, c: char) {
+        let hint_match = self.ctx.hint_state().keyboard_input(c);
+        self.ctx.terminal_mut().dirty = true;
+
+        let hint_match = match hint_match {
+            Some(hint_match) => hint_match,
+            None => return,
+        };
+
+        let start = *hint_match.bounds.start();
+        let end = *hint_match.bounds.end();
+        let text = self.ctx.terminal().bounds_to_string(start, end);
+
+        match hint_match.action {
+            HintAction::Action(HintInternalAction::Copy) => {
+                self.ctx.clipboard_mut().store(ClipboardType::Clipboard, text);
+            },
+            HintAction::Action(HintInternalAction::Paste) => paste(&mut self.ctx, &text),
+            HintAction::Command(ref command) => {
+                let mut args = command.args().to_vec();
+                args.push(text);
+                start_daemon(command.program(), &args);
+            },
+        }
+    }
+
     /// Attempt to find a binding and execute its action.
     ///
     /// The provided mode, mods, and key must match what is allowed by a binding
     /// for its action to be executed.
-    fn process_key_bindings(&mut self, input: KeyboardInput) {
+    fn process_key_bindings(&mut self, input: KeyboardInput, repeat: bool) {
         let mode = BindingMode::new(self.ctx.terminal().mode(), self.ctx.search_active());
         let mods = *self.ctx.modifiers();
+        let kitty_keyboard = self.kitty_keyboard_active();
+        let mut default_bindings = None;
         let mut suppress_chars = None;
 
         for i in 0..self.ctx.config().ui_config.key_bindings().len() {
@@ -917,6 +1100,14 @@ impl<'a, T: EventListener, A: ActionContext<T>> Processor<'a, T, A> {
             };
 
             if binding.is_triggered_by(mode, mods, &key) {
+                // Default escape sequences are generated by the kitty keyboard protocol instead.
+                if kitty_keyboard
+                    && matches!(binding.action, Action::Esc(_))
+                    && default_bindings.get_or_insert_with(default_key_bindings).contains(binding)
+                {
+                    continue;
+                }
+
                 // Binding was triggered; run the action.
                 let binding = binding.clone();
                 binding.execute(&mut self.ctx);
@@ -926,6 +1117,25 @@ impl<'a, T: EventListener, A: ActionContext<T>> Processor<'a, T, A> {
             }
         }
 
+        // Encode keys without bindings using the kitty keyboard protocol.
+        if kitty_keyboard && suppress_chars.is_none() {
+            let mode = *self.ctx.terminal().mode();
+            let event_type = if repeat { KeyEventType::Repeat } else { KeyEventType::Press };
+            let sequence = input
+                .virtual_keycode
+                .and_then(|key| kitty_key_sequence(mode, key, mods, event_type));
+
+            if let Some(sequence) = sequence {
+                self.ctx.on_typing_start();
+                self.ctx.scroll(Scroll::Bottom);
+                self.ctx.clear_selection();
+                self.ctx.write_to_pty(sequence);
+
+                // Drop the text of the key, since it's already part of the escape sequence.
+                suppress_chars = Some(true);
+            }
+        }
+
         // Don't suppress char if no bindings were triggered.
         *self.ctx.suppress_chars() = suppress_chars.unwrap_or(false);
     }
@@ -1053,7 +1263,7 @@ impl<'a, T: EventListener, A: ActionContext<T>> Processor<'a, T, A> {
 
         // Scale number of lines scrolled based on distance to boundary.
         let delta = delta as isize / step as isize;
-        let event = Event::Scroll(Scroll::Delta(delta));
+        let event: Event = EventType::Scroll(Scroll::Delta(delta)).into();
 
         // Schedule event.
         match scheduler.get_mut(TimerId::SelectionScrolling) {
@@ -1070,6 +1280,184 @@ impl<'a, T: EventListener, A: ActionContext<T>> Processor<'a, T, A> {
     }
 }
 
+/// Key encoding categories of the kitty keyboard protocol.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum KittyKey {
+    /// Key generating text, identified by its unshifted codepoint.
+    Text(u32),
+    /// Key with a legacy encoding which is only ambiguous with modifiers.
+    Legacy(u32),
+    /// Key which always has its own escape sequence, using `CSI number ; modifiers terminator`.
+    Functional(u32, char),
+    /// Key which is only reported when all keys are reported as escape sequences.
+    Modifier(u32),
+}
+
+impl KittyKey {
+    fn from_keycode(key: VirtualKeyCode) -> Option<Self> {
+        // Keys with consecutive codes are mapped based on their offset to the first key.
+        let offset = |first: VirtualKeyCode, last: VirtualKeyCode| {
+            let code = key as u32;
+            if code >= first as u32 && code <= last as u32 {
+                Some(code - first as u32)
+            } else {
+                None
+            }
+        };
+
+        if let Some(offset) = offset(VirtualKeyCode::A, VirtualKeyCode::Z) {
+            return Some(Self::Text(u32::from('a') + offset));
+        } else if let Some(offset) = offset(VirtualKeyCode::Key1, VirtualKeyCode::Key9) {
+            return Some(Self::Text(u32::from('1') + offset));
+        } else if let Some(offset) = offset(VirtualKeyCode::F13, VirtualKeyCode::F24) {
+            return Some(Self::Functional(57376 + offset, 'u'));
+        } else if let Some(offset) = offset(VirtualKeyCode::Numpad0, VirtualKeyCode::Numpad9) {
+            return Some(Self::Functional(57399 + offset, 'u'));
+        }
+
+        let kitty_key = match key {
+            VirtualKeyCode::Key0 => Self::Text(u32::from('0')),
+            VirtualKeyCode::Space => Self::Text(u32::from(' ')),
+            VirtualKeyCode::Apostrophe => Self::Text(u32::from('\'')),
+            VirtualKeyCode::Asterisk => Self::Text(u32::from('*')),
+            VirtualKeyCode::At => Self::Text(u32::from('@')),
+            VirtualKeyCode::Backslash => Self::Text(u32::from('\\')),
+            VirtualKeyCode::Caret => Self::Text(u32::from('^')),
+            VirtualKeyCode::Colon => Self::Text(u32::from(':')),
+            VirtualKeyCode::Comma => Self::Text(u32::from(',')),
+            VirtualKeyCode::Equals => Self::Text(u32::from('=')),
+            VirtualKeyCode::Grave => Self::Text(u32::from('`')),
+            VirtualKeyCode::LBracket => Self::Text(u32::from('[')),
+            VirtualKeyCode::Minus => Self::Text(u32::from('-')),
+            VirtualKeyCode::Period => Self::Text(u32::from('.')),
+            VirtualKeyCode::Plus => Self::Text(u32::from('+')),
+            VirtualKeyCode::RBracket => Self::Text(u32::from(']')),
+            VirtualKeyCode::Semicolon => Self::Text(u32::from(';')),
+            VirtualKeyCode::Slash => Self::Text(u32::from('/')),
+            VirtualKeyCode::Underline => Self::Text(u32::from('_')),
+            VirtualKeyCode::Return => Self::Legacy(13),
+            VirtualKeyCode::Tab => Self::Legacy(9),
+            VirtualKeyCode::Back => Self::Legacy(127),
+            VirtualKeyCode::Escape => Self::Functional(27, 'u'),
+            VirtualKeyCode::Insert => Self::Functional(2, '~'),
+            VirtualKeyCode::Delete => Self::Functional(3, '~'),
+            VirtualKeyCode::PageUp => Self::Functional(5, '~'),
+            VirtualKeyCode::PageDown => Self::Functional(6, '~'),
+            VirtualKeyCode::Up => Self::Functional(1, 'A'),
+            VirtualKeyCode::Down => Self::Functional(1, 'B'),
+            VirtualKeyCode::Right => Self::Functional(1, 'C'),
+            VirtualKeyCode::Left => Self::Functional(1, 'D'),
+            VirtualKeyCode::End => Self::Functional(1, 'F'),
+            VirtualKeyCode::Home => Self::Functional(1, 'H'),
+            VirtualKeyCode::F1 => Self::Functional(1, 'P'),
+            VirtualKeyCode::F2 => Self::Functional(1, 'Q'),
+            VirtualKeyCode::F3 => Self::Functional(13, '~'),
+            VirtualKeyCode::F4 => Self::Functional(1, 'S'),
+            VirtualKeyCode::F5 => Self::Functional(15, '~'),
+            VirtualKeyCode::F6 => Self::Functional(17, '~'),
+            VirtualKeyCode::F7 => Self::Functional(18, '~'),
+            VirtualKeyCode::F8 => Self::Functional(19, '~'),
+            VirtualKeyCode::F9 => Self::Functional(20, '~'),
+            VirtualKeyCode::F10 => Self::Functional(21, '~'),
+            VirtualKeyCode::F11 => Self::Functional(23, '~'),
+            VirtualKeyCode::F12 => Self::Functional(24, '~'),
+            VirtualKeyCode::Snapshot => Self::Functional(57361, 'u'),
+            VirtualKeyCode::Pause => Self::Functional(57362, 'u'),
+            VirtualKeyCode::Apps => Self::Functional(57363, 'u'),
+            VirtualKeyCode::NumpadDecimal => Self::Functional(57409, 'u'),
+            VirtualKeyCode::NumpadDivide => Self::Functional(57410, 'u'),
+            VirtualKeyCode::NumpadMultiply => Self::Functional(57411, 'u'),
+            VirtualKeyCode::NumpadSubtract => Self::Functional(57412, 'u'),
+            VirtualKeyCode::NumpadAdd => Self::Functional(57413, 'u'),
+            VirtualKeyCode::NumpadEnter => Self::Functional(57414, 'u'),
+            VirtualKeyCode::NumpadEquals => Self::Functional(57415, 'u'),
+            VirtualKeyCode::PlayPause => Self::Functional(57430, 'u'),
+            VirtualKeyCode::MediaStop => Self::Functional(57432, 'u'),
+            VirtualKeyCode::NextTrack => Self::Functional(57435, 'u'),
+            VirtualKeyCode::PrevTrack => Self::Functional(57436, 'u'),
+            VirtualKeyCode::VolumeDown => Self::Functional(57438, 'u'),
+            VirtualKeyCode::VolumeUp => Self::Functional(57439, 'u'),
+            VirtualKeyCode::Mute => Self::Functional(57440, 'u'),
+            VirtualKeyCode::Capital => Self::Modifier(57358),
+            VirtualKeyCode::Scroll => Self::Modifier(57359),
+            VirtualKeyCode::Numlock => Self::Modifier(57360),
+            VirtualKeyCode::LShift => Self::Modifier(57441),
+            VirtualKeyCode::LControl => Self::Modifier(57442),
+            VirtualKeyCode::LAlt => Self::Modifier(57443),
+            VirtualKeyCode::LWin => Self::Modifier(57444),
+            VirtualKeyCode::RShift => Self::Modifier(57447),
+            VirtualKeyCode::RControl => Self::Modifier(57448),
+            VirtualKeyCode::RAlt => Self::Modifier(57449),
+            VirtualKeyCode::RWin => Self::Modifier(57450),
+            _ => return None,
+        };
+
+        Some(kitty_key)
+    }
+}
+
+/// Key event types distinguished by the kitty keyboard protocol.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum KeyEventType {
+    Press,
+    Repeat,
+    Release,
+}
+
+/// Build the kitty keyboard protocol escape sequence for a key event.
+///
+/// Returns `None` if the key should use its legacy encoding instead.
+fn kitty_key_sequence(
+    mode: TermMode,
+    key: VirtualKeyCode,
+    mods: ModifiersState,
+    event_type: KeyEventType,
+) -> Option<Vec<u8>> {
+    let all_keys_as_esc = mode.contains(TermMode::REPORT_ALL_KEYS_AS_ESC);
+
+    // Without event types, repeats are reported as presses and releases are not reported.
+    let event_type = match event_type {
+        _ if mode.contains(TermMode::REPORT_EVENT_TYPES) => event_type,
+        KeyEventType::Release => return None,
+        _ => KeyEventType::Press,
+    };
+
+    let modifiers = 1
+        + mods.shift() as u8
+        + ((mods.alt() as u8) << 1)
+        + ((mods.ctrl() as u8) << 2)
+        + ((mods.logo() as u8) << 3);
+    let text_mods = mods.alt() || mods.ctrl() || mods.logo();
+
+    let (number, terminator) = match KittyKey::from_keycode(key)? {
+        // Text is only ambiguous when modifiers other than shift are held.
+        KittyKey::Text(codepoint) if all_keys_as_esc || text_mods => (codepoint, 'u'),
+        KittyKey::Legacy(codepoint) if all_keys_as_esc || modifiers != 1 => (codepoint, 'u'),
+        KittyKey::Modifier(codepoint) if all_keys_as_esc => (codepoint, 'u'),
+        KittyKey::Functional(number, terminator) => (number, terminator),
+        _ => return None,
+    };
+
+    // Modifiers are mandatory when the event type is reported.
+    let mut parameters = String::new();
+    if modifiers != 1 || event_type != KeyEventType::Press {
+        parameters = format!(";{}", modifiers);
+    }
+    match event_type {
+        KeyEventType::Press => (),
+        KeyEventType::Repeat => parameters.push_str(":2"),
+        KeyEventType::Release => parameters.push_str(":3"),
+    }
+
+    let sequence = if number == 1 && parameters.is_empty() && terminator != 'u' {
+        format!("\x1b[{}", terminator)
+    } else {
+        format!("\x1b[{}{}{}", number, parameters, terminator)
+    };
+
+    Some(sequence.into_bytes())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -1098,6 +1486,7 @@ mod tests {
         pub message_buffer: &'a mut MessageBuffer,
         pub received_count: usize,
         pub suppress_chars: bool,
+        pub pressed_key: Option<VirtualKeyCode>,
         pub modifiers: ModifiersState,
         config: &'a Config,
     }
@@ -1113,16 +1502,28 @@ mod tests {
 
         fn copy_selection(&mut self, _: ClipboardType) {}
 
+        fn copy_selection_as_html(&mut self) {}
+
         fn clear_selection(&mut self) {}
 
         fn spawn_new_instance(&mut self) {}
 
+        fn create_new_window(&mut self) {}
+
+        fn save_session(&mut self) {}
+
+        fn toggle_recording(&mut self) {}
+
+        fn export_scrollback(&mut self) {}
+
         fn change_font_size(&mut self, _delta: f32) {}
 
         fn reset_font_size(&mut self) {}
 
         fn start_search(&mut self, _direction: Direction) {}
 
+        fn start_time_jump(&mut self) {}
+
         fn confirm_search(&mut self) {}
 
         fn cancel_search(&mut self) {}
@@ -1198,6 +1599,10 @@ mod tests {
             &mut self.suppress_chars
         }
 
+        fn pressed_key(&mut self) -> &mut Option<VirtualKeyCode> {
+            &mut self.pressed_key
+        }
+
         fn modifiers(&mut self) -> &mut ModifiersState {
             &mut self.modifiers
         }
@@ -1218,6 +1623,12 @@ mod tests {
             self.message_buffer.message()
         }
 
+        fn permission_prompt_active(&self) -> bool {
+            false
+        }
+
+        fn answer_permission_prompt(&mut self, _allow: bool) {}
+
         fn config(&self) -> &Config {
             self.config
         }
@@ -1226,6 +1637,7 @@ mod tests {
             self.clipboard
         }
 
+        #[cfg(target_os = "macos")]
         fn event_loop(&self) -> &EventLoopWindowTarget<Event> {
             unimplemented!();
         }
@@ -1245,6 +1657,10 @@ mod tests {
         fn on_typing_start(&mut self) {
             unimplemented!();
         }
+
+        fn hint_state(&mut self) -> &mut HintState {
+            unimplemented!();
+        }
     }
 
     macro_rules! test_clickstate {
@@ -1288,6 +1704,7 @@ mod tests {
                     clipboard: &mut clipboard,
                     received_count: 0,
                     suppress_chars: false,
+                    pressed_key: None,
                     modifiers: Default::default(),
                     message_buffer: &mut message_buffer,
                     config: &cfg,
@@ -1491,4 +1908,50 @@ mod tests {
         mode: BindingMode::empty(),
         mods: ModifiersState::ALT | ModifiersState::LOGO,
     }
+
+    #[test]
+    fn kitty_key_encoding() {
+        let disambiguate = TermMode::DISAMBIGUATE_ESC_CODES;
+        let pressed = KeyEventType::Press;
+        let repeated = KeyEventType::Repeat;
+        let released = KeyEventType::Release;
+        let encode = |mode, key, mods| {
+            kitty_key_sequence(mode, key, mods, pressed).map(|s| String::from_utf8(s).unwrap())
+        };
+
+        // Text and unmodified legacy keys keep their legacy encoding.
+        assert_eq!(encode(disambiguate, VirtualKeyCode::I, ModifiersState::SHIFT), None);
+        assert_eq!(encode(disambiguate, VirtualKeyCode::Tab, ModifiersState::empty()), None);
+
+        let ctrl_i = encode(disambiguate, VirtualKeyCode::I, ModifiersState::CTRL);
+        assert_eq!(ctrl_i.as_deref(), Some("\x1b[105;5u"));
+        let alt_tab = encode(disambiguate, VirtualKeyCode::Tab, ModifiersState::ALT);
+        assert_eq!(alt_tab.as_deref(), Some("\x1b[9;3u"));
+        let escape = encode(disambiguate, VirtualKeyCode::Escape, ModifiersState::empty());
+        assert_eq!(escape.as_deref(), Some("\x1b[27u"));
+        let up = encode(disambiguate, VirtualKeyCode::Up, ModifiersState::empty());
+        assert_eq!(up.as_deref(), Some("\x1b[A"));
+        let shift_f5 = encode(disambiguate, VirtualKeyCode::F5, ModifiersState::SHIFT);
+        assert_eq!(shift_f5.as_deref(), Some("\x1b[15;2~"));
+
+        let all_keys = disambiguate | TermMode::REPORT_ALL_KEYS_AS_ESC;
+        let a = encode(all_keys, VirtualKeyCode::A, ModifiersState::empty());
+        assert_eq!(a.as_deref(), Some("\x1b[97u"));
+        let shift = encode(all_keys, VirtualKeyCode::LShift, ModifiersState::SHIFT);
+        assert_eq!(shift.as_deref(), Some("\x1b[57441;2u"));
+
+        // Releases are only reported with event types enabled.
+        let up = VirtualKeyCode::Up;
+        let mods = ModifiersState::empty();
+        assert_eq!(kitty_key_sequence(disambiguate, up, mods, released), None);
+        let mode = disambiguate | TermMode::REPORT_EVENT_TYPES;
+        let release = kitty_key_sequence(mode, up, mods, released);
+        assert_eq!(release, Some(b"\x1b[1;1:3A".to_vec()));
+
+        // Repeats are only distinguished from presses with event types enabled.
+        let repeat = kitty_key_sequence(disambiguate, up, mods, repeated);
+        assert_eq!(repeat, Some(b"\x1b[A".to_vec()));
+        let repeat = kitty_key_sequence(mode, up, mods, repeated);
+        assert_eq!(repeat, Some(b"\x1b[1;1:2A".to_vec()));
+    }
 }
@@ -0,0 +1,213 @@
+//! Alacritty socket IPC.
+//!
+//! Every instance listens on a unix socket for newline-separated JSON messages, which can be
+//! sent using `alacritty msg` or any other tool capable of writing to unix sockets:
+//!
+//! ```text
+//! {"type":"create_window"}
+//! {"type":"reload_config"}
+//! {"type":"config","options":["cursor.style=Beam"]}
+//! {"type":"action","action":"ScrollToTop"}
+//! {"type":"get_working_directory"}
+//! ```
+//!
+//! Responses are written back to the socket as a single line, once the message was processed.
+
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::Shutdown;
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Sender};
+use std::time::Duration;
+use std::{env, fs, process};
+
+use glutin::event_loop::EventLoopProxy;
+use log::{error, warn};
+use serde::{Deserialize, Serialize};
+use serde_yaml::Value as SerdeValue;
+
+use alacritty_terminal::thread;
+use alacritty_terminal::tty::ALACRITTY_SOCKET_ENV;
+use alacritty_terminal::vi_mode::ViMotion;
+
+use crate::config::{Action, SearchAction, ViAction};
+use crate::event::{Event, EventType};
+
+/// Maximum time to wait for a message to be processed before dropping its response.
+const REPLY_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Maximum time a client may take to send a message before its connection is closed.
+const READ_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Channel for responding to an IPC message.
+pub type SocketReply = Sender<String>;
+
+/// Messages which can be sent to a running Alacritty instance.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SocketMessage {
+    /// Create a new terminal window.
+    CreateWindow,
+
+    /// Reload the configuration file.
+    ReloadConfig,
+
+    /// Override configuration options, using the same format as `--option`.
+    Config { options: Vec<String> },
+
+    /// Execute a key binding action.
+    Action { action: String },
+
+    /// Print the working directory of the focused window.
+    GetWorkingDirectory,
+}
+
+impl SocketMessage {
+    /// Parse the action name of an `Action` message.
+    pub fn parse_action(name: &str) -> Option<Action> {
+        let value = SerdeValue::String(name.to_owned());
+
+        if let Ok(vi_action) = ViAction::deserialize(value.clone()) {
+            Some(vi_action.into())
+        } else if let Ok(vi_motion) = ViMotion::deserialize(value.clone()) {
+            Some(vi_motion.into())
+        } else if let Ok(search_action) = SearchAction::deserialize(value.clone()) {
+            Some(search_action.into())
+        } else {
+            Action::deserialize(value).ok()
+        }
+    }
+}
+
+/// Create an IPC socket and listen for messages on it.
+///
+/// The socket path should be advertised to child processes through the `ALACRITTY_SOCKET`
+/// environment variable, so this should be called before the shell is spawned.
+pub fn spawn_ipc_socket(event_proxy: EventLoopProxy<Event>) -> Option<PathBuf> {
+    let socket_path = socket_path();
+
+    let listener = match UnixListener::bind(&socket_path) {
+        Ok(listener) => listener,
+        Err(err) => {
+            warn!("Unable to create socket {:?}: {}", socket_path, err);
+            return None;
+        },
+    };
+
+    thread::spawn_named("socket listener", move || {
+        for stream in listener.incoming().filter_map(Result::ok) {
+            // Handle every client separately, so a single one cannot block all others.
+            let event_proxy = event_proxy.clone();
+            thread::spawn_named("socket connection", move || {
+                handle_connection(stream, event_proxy)
+            });
+        }
+    });
+
+    Some(socket_path)
+}
+
+/// Process all messages sent by a single client.
+fn handle_connection(stream: UnixStream, event_proxy: EventLoopProxy<Event>) {
+    // Close connections of clients which stop sending messages.
+    if stream.set_read_timeout(Some(READ_TIMEOUT)).is_err() {
+        return;
+    }
+
+    let mut writer = match stream.try_clone() {
+        Ok(writer) => writer,
+        Err(_) => return,
+    };
+
+    for line in BufReader::new(stream).lines() {
+        let line = match line {
+            Ok(line) => line,
+            Err(_) => break,
+        };
+
+        match serde_json::from_str::<SocketMessage>(&line) {
+            Ok(message) => {
+                let (reply, response) = mpsc::channel();
+                let event = EventType::Ipc(message, reply);
+                let _ = event_proxy.send_event(Event::from(event));
+
+                // Wait for the response, the channel is closed if there is none.
+                if let Ok(response) = response.recv_timeout(REPLY_TIMEOUT) {
+                    let _ = writeln!(writer, "{}", response);
+                }
+            },
+            Err(err) => error!("Invalid IPC message {:?}: {}", line, err),
+        }
+    }
+}
+
+/// Send a message to a running Alacritty instance and return its response.
+///
+/// Without an explicit `socket`, the instance is looked up through `ALACRITTY_SOCKET`.
+pub fn send_message(socket: Option<PathBuf>, message: &SocketMessage) -> io::Result<String> {
+    let socket = match socket.or_else(|| env::var_os(ALACRITTY_SOCKET_ENV).map(PathBuf::from)) {
+        Some(socket) => socket,
+        None => {
+            return Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("no socket specified and ${} is not set", ALACRITTY_SOCKET_ENV),
+            ))
+        },
+    };
+
+    let mut stream = UnixStream::connect(&socket)?;
+
+    let mut json = serde_json::to_string(message)?;
+    json.push('\n');
+    stream.write_all(json.as_bytes())?;
+    stream.shutdown(Shutdown::Write)?;
+
+    let mut response = String::new();
+    stream.read_to_string(&mut response)?;
+
+    Ok(response)
+}
+
+/// Remove the socket file created by `spawn_ipc_socket`.
+pub fn remove_socket(socket_path: &Path) {
+    let _ = fs::remove_file(socket_path);
+}
+
+/// Path for the IPC socket of this instance.
+fn socket_path() -> PathBuf {
+    let display = env::var("WAYLAND_DISPLAY").or_else(|_| env::var("DISPLAY")).unwrap_or_default();
+    let socket_name = format!("Alacritty-{}-{}.sock", display.replace('/', "-"), process::id());
+
+    // Use runtime directory if available, falling back to the temporary directory.
+    xdg::BaseDirectories::with_prefix("alacritty")
+        .ok()
+        .and_then(|xdg| xdg.place_runtime_file(&socket_name).ok())
+        .unwrap_or_else(|| env::temp_dir().join(socket_name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn socket_message_json() {
+        let message = SocketMessage::Config { options: vec![String::from("cursor.style=Beam")] };
+        let json = serde_json::to_string(&message).unwrap();
+
+        assert_eq!(json, r#"{"type":"config","options":["cursor.style=Beam"]}"#);
+        assert_eq!(serde_json::from_str::<SocketMessage>(&json).unwrap(), message);
+
+        let message = serde_json::from_str::<SocketMessage>(r#"{"type":"create_window"}"#);
+        assert_eq!(message.unwrap(), SocketMessage::CreateWindow);
+
+        let message = serde_json::from_str::<SocketMessage>(r#"{"type":"get_working_directory"}"#);
+        assert_eq!(message.unwrap(), SocketMessage::GetWorkingDirectory);
+    }
+
+    #[test]
+    fn parse_socket_action() {
+        assert_eq!(SocketMessage::parse_action("ScrollToTop"), Some(Action::ScrollToTop));
+        assert_eq!(SocketMessage::parse_action("Up"), Some(Action::ViMotion(ViMotion::Up)));
+        assert_eq!(SocketMessage::parse_action("NotAnAction"), None);
+    }
+}
@@ -3,10 +3,12 @@
 //! The main executable is supposed to call `initialize()` exactly once during
 //! startup. All logging messages are written to stdout, given that their
 //! log-level is sufficient for the level configured in `cli::Options`.
+//!
+//! In headless mode STDOUT is reserved for the script output, so STDERR is used instead.
 
 use std::env;
 use std::fs::{File, OpenOptions};
-use std::io::{self, LineWriter, Stdout, Write};
+use std::io::{self, LineWriter, Write};
 use std::path::PathBuf;
 use std::process;
 use std::sync::atomic::{AtomicBool, Ordering};
@@ -16,7 +18,7 @@ use glutin::event_loop::EventLoopProxy;
 use log::{self, Level};
 
 use crate::cli::Options;
-use crate::event::Event;
+use crate::event::{Event, EventType};
 use crate::message_bar::{Message, MessageType};
 
 /// Name for the environment variable containing the log file's path.
@@ -27,7 +29,7 @@ const ALLOWED_TARGETS: [&str; 4] =
 
 pub fn initialize(
     options: &Options,
-    event_proxy: EventLoopProxy<Event>,
+    event_proxy: Option<EventLoopProxy<Event>>,
 ) -> Result<Option<PathBuf>, log::SetLoggerError> {
     log::set_max_level(options.log_level);
 
@@ -40,16 +42,18 @@ pub fn initialize(
 
 pub struct Logger {
     logfile: Mutex<OnDemandLogFile>,
-    stdout: Mutex<LineWriter<Stdout>>,
-    event_proxy: Mutex<EventLoopProxy<Event>>,
+    console: Mutex<LineWriter<Box<dyn Write + Send>>>,
+    event_proxy: Option<Mutex<EventLoopProxy<Event>>>,
 }
 
 impl Logger {
-    fn new(event_proxy: EventLoopProxy<Event>) -> Self {
-        let logfile = Mutex::new(OnDemandLogFile::new());
-        let stdout = Mutex::new(LineWriter::new(io::stdout()));
+    /// Create a new logger, the event proxy is only absent in headless mode.
+    fn new(event_proxy: Option<EventLoopProxy<Event>>) -> Self {
+        let headless = event_proxy.is_none();
+        let logfile = Mutex::new(OnDemandLogFile::new(headless));
+        let console = Mutex::new(LineWriter::new(console(headless)));
 
-        Logger { logfile, stdout, event_proxy: Mutex::new(event_proxy) }
+        Logger { logfile, console, event_proxy: event_proxy.map(Mutex::new) }
     }
 
     fn file_path(&self) -> Option<PathBuf> {
@@ -62,9 +66,9 @@ impl Logger {
 
     /// Log a record to the message bar.
     fn message_bar_log(&self, record: &log::Record<'_>, logfile_path: &str) {
-        let event_proxy = match self.event_proxy.lock() {
-            Ok(event_proxy) => event_proxy,
-            Err(_) => return,
+        let event_proxy = match self.event_proxy.as_ref().map(Mutex::lock) {
+            Some(Ok(event_proxy)) => event_proxy,
+            _ => return,
         };
 
         #[cfg(not(windows))]
@@ -88,7 +92,7 @@ impl Logger {
         let mut message = Message::new(message, message_type);
         message.set_target(record.target().to_owned());
 
-        let _ = event_proxy.send_event(Event::Message(message));
+        let _ = event_proxy.send_event(Event::from(EventType::Message(message)));
     }
 }
 
@@ -120,9 +124,9 @@ impl log::Log for Logger {
             }
         }
 
-        // Write to stdout.
-        if let Ok(mut stdout) = self.stdout.lock() {
-            let _ = stdout.write_all(message.as_ref());
+        // Write to stdout, or stderr in headless mode.
+        if let Ok(mut console) = self.console.lock() {
+            let _ = console.write_all(message.as_ref());
         }
     }
 
@@ -149,21 +153,31 @@ fn create_log_message(record: &log::Record<'_>, target: &str) -> String {
     message
 }
 
+/// Output for log messages outside of the log file.
+fn console(headless: bool) -> Box<dyn Write + Send> {
+    if headless {
+        Box::new(io::stderr())
+    } else {
+        Box::new(io::stdout())
+    }
+}
+
 struct OnDemandLogFile {
     file: Option<LineWriter<File>>,
     created: Arc<AtomicBool>,
     path: PathBuf,
+    headless: bool,
 }
 
 impl OnDemandLogFile {
-    fn new() -> Self {
+    fn new(headless: bool) -> Self {
         let mut path = env::temp_dir();
         path.push(format!("Alacritty-{}.log", process::id()));
 
         // Set log path as an environment variable.
         env::set_var(ALACRITTY_LOG_ENV, path.as_os_str());
 
-        OnDemandLogFile { path, file: None, created: Arc::new(AtomicBool::new(false)) }
+        OnDemandLogFile { path, file: None, created: Arc::new(AtomicBool::new(false)), headless }
     }
 
     fn file(&mut self) -> Result<&mut LineWriter<File>, io::Error> {
@@ -180,11 +194,11 @@ impl OnDemandLogFile {
                 Ok(file) => {
                     self.file = Some(io::LineWriter::new(file));
                     self.created.store(true, Ordering::Relaxed);
-                    let _ =
-                        writeln!(io::stdout(), "Created log file at \"{}\"", self.path.display());
+                    let path = self.path.display();
+                    let _ = writeln!(console(self.headless), "Created log file at \"{}\"", path);
                 },
                 Err(e) => {
-                    let _ = writeln!(io::stdout(), "Unable to create log file: {}", e);
+                    let _ = writeln!(console(self.headless), "Unable to create log file: {}", e);
                     return Err(e);
                 },
             }
@@ -17,16 +17,12 @@ use std::env;
 use std::error::Error;
 use std::fs;
 use std::io::{self, Write};
-use std::sync::Arc;
 
 use glutin::event_loop::EventLoop as GlutinEventLoop;
 use log::{error, info};
 #[cfg(windows)]
 use winapi::um::wincon::{AttachConsole, FreeConsole, ATTACH_PARENT_PROCESS};
 
-use alacritty_terminal::event_loop::{self, EventLoop, Msg};
-use alacritty_terminal::sync::FairMutex;
-use alacritty_terminal::term::Term;
 use alacritty_terminal::tty;
 
 mod cli;
@@ -36,7 +32,11 @@ mod cursor;
 mod daemon;
 mod display;
 mod event;
+mod headless;
+mod hint;
 mod input;
+#[cfg(unix)]
+mod ipc;
 mod logging;
 #[cfg(target_os = "macos")]
 mod macos;
@@ -46,8 +46,10 @@ mod meter;
 mod panic;
 mod renderer;
 mod scheduler;
+mod session;
 mod url;
 mod window;
+mod window_context;
 
 #[cfg(all(feature = "wayland", not(any(target_os = "macos", windows))))]
 mod wayland_theme;
@@ -60,11 +62,9 @@ mod gl {
 use crate::cli::Options;
 use crate::config::monitor;
 use crate::config::Config;
-use crate::display::Display;
-use crate::event::{Event, EventProxy, Processor};
+use crate::event::{Event, Processor};
 #[cfg(target_os = "macos")]
 use crate::macos::locale;
-use crate::message_bar::MessageBuffer;
 
 fn main() {
     #[cfg(windows)]
@@ -81,12 +81,26 @@ fn main() {
     // Load command line options.
     let options = Options::new();
 
-    // Setup glutin event loop.
-    let window_event_loop = GlutinEventLoop::<Event>::with_user_event();
+    // Send the IPC message and exit when the `msg` subcommand was used.
+    #[cfg(unix)]
+    if let Some(msg_options) = &options.msg {
+        match ipc::send_message(msg_options.socket.clone(), &msg_options.message) {
+            Ok(response) => print!("{}", response),
+            Err(err) => {
+                eprintln!("Unable to send message to Alacritty: {}", err);
+                std::process::exit(1);
+            },
+        }
+        return;
+    }
+
+    // Setup glutin event loop, unless no windows will be created.
+    let window_event_loop =
+        if options.headless { None } else { Some(GlutinEventLoop::<Event>::with_user_event()) };
 
     // Initialize the logger as soon as possible as to capture output from other subsystems.
-    let log_file = logging::initialize(&options, window_event_loop.create_proxy())
-        .expect("Unable to initialize logger");
+    let event_proxy = window_event_loop.as_ref().map(GlutinEventLoop::create_proxy);
+    let log_file = logging::initialize(&options, event_proxy).expect("Unable to initialize logger");
 
     // Load configuration file.
     let config = config::load(&options);
@@ -105,7 +119,11 @@ fn main() {
     let persistent_logging = config.ui_config.debug.persistent_logging;
 
     // Run Alacritty.
-    if let Err(err) = run(window_event_loop, config, options) {
+    let result = match window_event_loop {
+        Some(window_event_loop) => run(window_event_loop, config, options),
+        None => run_headless(config, options),
+    };
+    if let Err(err) = result {
         error!("Alacritty encountered an unrecoverable error:\n\n\t{}\n", err);
         std::process::exit(1);
     }
@@ -120,8 +138,8 @@ fn main() {
 
 /// Run Alacritty.
 ///
-/// Creates a window, the terminal state, PTY, I/O event loop, input processor,
-/// config change monitor, and runs the main display loop.
+/// Creates the event processor and its first window, the config change monitor, and runs the
+/// main display loop.
 fn run(
     window_event_loop: GlutinEventLoop<Event>,
     config: Config,
@@ -132,99 +150,50 @@ fn run(
     // Log the configuration paths.
     log_config_path(&config);
 
-    // Set environment variables.
-    tty::setup_env(&config);
-
-    let event_proxy = EventProxy::new(window_event_loop.create_proxy());
-
-    // Create a display.
-    //
-    // The display manages a window and can draw the terminal.
-    let display = Display::new(&config, &window_event_loop)?;
-
-    info!(
-        "PTY dimensions: {:?} x {:?}",
-        display.size_info.screen_lines(),
-        display.size_info.cols()
-    );
-
-    // Create the terminal.
-    //
-    // This object contains all of the state about what's being displayed. It's
-    // wrapped in a clonable mutex since both the I/O loop and display need to
-    // access it.
-    let terminal = Term::new(&config, display.size_info, event_proxy.clone());
-    let terminal = Arc::new(FairMutex::new(terminal));
-
-    // Create the PTY.
+    // Create the IPC socket listener.
     //
-    // The PTY forks a process to run the shell on the slave side of the
-    // pseudoterminal. A file descriptor for the master side is retained for
-    // reading/writing to the shell.
-    let pty = tty::new(&config, &display.size_info, display.window.x11_window_id());
+    // This must happen before the shell is spawned, so its path is part of the environment.
+    #[cfg(unix)]
+    let socket_path = if config.ui_config.ipc_socket {
+        ipc::spawn_ipc_socket(window_event_loop.create_proxy())
+    } else {
+        None
+    };
 
-    // Create the pseudoterminal I/O loop.
-    //
-    // PTY I/O is ran on another thread as to not occupy cycles used by the
-    // renderer and input processing. Note that access to the terminal state is
-    // synchronized since the I/O loop updates the state, and the display
-    // consumes it periodically.
-    let event_loop = EventLoop::new(
-        Arc::clone(&terminal),
-        event_proxy.clone(),
-        pty,
-        config.hold,
-        config.ui_config.debug.ref_test,
-    );
-
-    // The event loop channel allows write requests from the event processor
-    // to be sent to the pty loop and ultimately written to the pty.
-    let loop_tx = event_loop.channel();
+    // Set environment variables.
+    #[cfg(unix)]
+    tty::setup_env(&config, socket_path.as_deref());
+    #[cfg(not(unix))]
+    tty::setup_env(&config, None);
 
     // Create a config monitor when config was loaded from path.
     //
     // The monitor watches the config file for changes and reloads it. Pending
     // config changes are processed in the main loop.
     if config.ui_config.live_config_reload {
-        monitor::watch(config.ui_config.config_paths.clone(), event_proxy);
+        monitor::watch(config.ui_config.config_paths.clone(), window_event_loop.create_proxy());
     }
 
-    // Setup storage for message UI.
-    let message_buffer = MessageBuffer::new();
-
     // Event processor.
-    let mut processor = Processor::new(
-        event_loop::Notifier(loop_tx.clone()),
-        message_buffer,
-        config,
-        display,
-        options,
-    );
+    let mut processor = Processor::new(config, options, &window_event_loop);
 
-    // Kick off the I/O thread.
-    let io_thread = event_loop.spawn();
+    // Create the first Alacritty windows.
+    let proxy = window_event_loop.create_proxy();
+    processor.create_initial_windows(&window_event_loop, proxy)?;
 
     info!("Initialisation complete");
 
     // Start event loop and block until shutdown.
-    processor.run(terminal, window_event_loop);
-
-    // This explicit drop is needed for Windows, ConPTY backend. Otherwise a deadlock can occur.
-    // The cause:
-    //   - Drop for ConPTY will deadlock if the conout pipe has already been dropped.
-    //   - The conout pipe is dropped when the io_thread is joined below (io_thread owns PTY).
-    //   - ConPTY is dropped when the last of processor and io_thread are dropped, because both of
-    //     them own an Arc<ConPTY>.
-    //
-    // The fix is to ensure that processor is dropped first. That way, when io_thread (i.e. PTY)
-    // is dropped, it can ensure ConPTY is dropped before the conout pipe in the PTY drop order.
-    //
-    // FIXME: Change PTY API to enforce the correct drop order with the typesystem.
+    processor.run(window_event_loop);
+
+    // Close all remaining windows, shutting down their PTYs.
     drop(processor);
 
-    // Shutdown PTY parser event loop.
-    loop_tx.send(Msg::Shutdown).expect("Error sending shutdown to PTY event loop");
-    io_thread.join().expect("join io thread");
+    // Clean up the IPC socket file.
+    #[cfg(unix)]
+    if let Some(socket_path) = socket_path {
+        ipc::remove_socket(&socket_path);
+    }
 
     // FIXME patch notify library to have a shutdown method.
     // config_reloader.join().ok();
@@ -240,6 +209,25 @@ fn run(
     Ok(())
 }
 
+/// Run Alacritty without any windows.
+///
+/// Creates a single terminal and drives it using the script from the command line options.
+fn run_headless(config: Config, options: Options) -> Result<(), Box<dyn Error>> {
+    info!("Welcome to Alacritty");
+
+    // Log the configuration paths.
+    log_config_path(&config);
+
+    // Set environment variables.
+    tty::setup_env(&config, None);
+
+    headless::run(config, options)?;
+
+    info!("Goodbye");
+
+    Ok(())
+}
+
 fn log_config_path(config: &Config) {
     let mut msg = String::from("Configuration files loaded from:");
     for path in &config.ui_config.config_paths {
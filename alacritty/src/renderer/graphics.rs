@@ -0,0 +1,304 @@
+use std::collections::HashMap;
+use std::mem;
+use std::ptr;
+
+use alacritty_terminal::graphics::{GraphicData, GraphicId, UpdateQueues};
+use alacritty_terminal::index::{Column, Line};
+use alacritty_terminal::term::render::RenderableCell;
+use alacritty_terminal::term::SizeInfo;
+
+use crate::gl;
+use crate::gl::types::*;
+use crate::renderer;
+
+/// Piece of a graphic displayed in a single cell.
+#[derive(Debug, Copy, Clone)]
+pub struct RenderGraphic {
+    pub id: GraphicId,
+    pub line: Line,
+    pub column: Column,
+    pub offset_x: u16,
+    pub offset_y: u16,
+    pub z_index: i32,
+}
+
+impl RenderGraphic {
+    pub fn from_cell(cell: &RenderableCell) -> Option<Self> {
+        cell.graphic.as_ref().map(|graphic| RenderGraphic {
+            id: graphic.graphic.id(),
+            line: cell.line,
+            column: cell.column,
+            offset_x: graphic.offset_x,
+            offset_y: graphic.offset_y,
+            z_index: graphic.z_index,
+        })
+    }
+}
+
+/// Shader sources for graphics rendering program.
+static GRAPHICS_SHADER_F: &str = include_str!("../../res/graphics.f.glsl");
+static GRAPHICS_SHADER_V: &str = include_str!("../../res/graphics.v.glsl");
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+struct Vertex {
+    // Normalized screen coordinates.
+    x: f32,
+    y: f32,
+
+    // Texture coordinates.
+    u: f32,
+    v: f32,
+}
+
+/// Graphic uploaded to the GPU.
+#[derive(Debug)]
+struct GraphicTexture {
+    id: GLuint,
+    width: f32,
+    height: f32,
+}
+
+#[derive(Debug)]
+pub struct GraphicsRenderer {
+    // GL buffer objects.
+    vao: GLuint,
+    vbo: GLuint,
+
+    program: GraphicsShaderProgram,
+
+    textures: HashMap<GraphicId, GraphicTexture>,
+
+    vertices: Vec<Vertex>,
+}
+
+impl GraphicsRenderer {
+    pub fn new() -> Result<Self, renderer::Error> {
+        let mut vao: GLuint = 0;
+        let mut vbo: GLuint = 0;
+        let program = GraphicsShaderProgram::new()?;
+
+        unsafe {
+            // Allocate buffers.
+            gl::GenVertexArrays(1, &mut vao);
+            gl::GenBuffers(1, &mut vbo);
+
+            gl::BindVertexArray(vao);
+            gl::BindBuffer(gl::ARRAY_BUFFER, vbo);
+
+            // Position.
+            gl::VertexAttribPointer(
+                0,
+                2,
+                gl::FLOAT,
+                gl::FALSE,
+                mem::size_of::<Vertex>() as i32,
+                ptr::null(),
+            );
+            gl::EnableVertexAttribArray(0);
+
+            // Texture coordinates.
+            gl::VertexAttribPointer(
+                1,
+                2,
+                gl::FLOAT,
+                gl::FALSE,
+                mem::size_of::<Vertex>() as i32,
+                (mem::size_of::<f32>() * 2) as *const _,
+            );
+            gl::EnableVertexAttribArray(1);
+
+            // Reset buffer bindings.
+            gl::BindVertexArray(0);
+            gl::BindBuffer(gl::ARRAY_BUFFER, 0);
+        }
+
+        Ok(Self { vao, vbo, program, textures: HashMap::new(), vertices: Vec::new() })
+    }
+
+    /// Upload new graphics and delete the ones which are no longer displayed.
+    pub fn update(&mut self, queues: UpdateQueues) {
+        for (id, graphic) in queues.pending {
+            let texture = Self::upload(&graphic);
+            self.textures.insert(id, texture);
+        }
+
+        for id in queues.remove_queue {
+            if let Some(texture) = self.textures.remove(&id) {
+                unsafe { gl::DeleteTextures(1, &texture.id) };
+            }
+        }
+    }
+
+    pub fn draw(&mut self, size_info: &SizeInfo, mut graphics: Vec<RenderGraphic>) {
+        graphics.sort_unstable_by_key(|graphic| graphic.id);
+
+        unsafe {
+            gl::BindVertexArray(self.vao);
+            gl::BindBuffer(gl::ARRAY_BUFFER, self.vbo);
+            gl::UseProgram(self.program.id);
+            gl::ActiveTexture(gl::TEXTURE0);
+        }
+
+        let half_width = size_info.width() / 2.;
+        let half_height = size_info.height() / 2.;
+
+        // Draw all cells of a graphic with a single draw call.
+        let mut start = 0;
+        while start < graphics.len() {
+            let id = graphics[start].id;
+            let end = graphics[start..]
+                .iter()
+                .position(|graphic| graphic.id != id)
+                .map_or(graphics.len(), |len| start + len);
+            let cells = &graphics[start..end];
+            start = end;
+
+            let texture = match self.textures.get(&id) {
+                Some(texture) => texture,
+                None => continue,
+            };
+
+            self.vertices.clear();
+            for cell in cells {
+                Self::add_cell(
+                    &mut self.vertices,
+                    half_width,
+                    half_height,
+                    size_info,
+                    texture,
+                    cell,
+                );
+            }
+
+            unsafe {
+                gl::BindTexture(gl::TEXTURE_2D, texture.id);
+
+                gl::BufferData(
+                    gl::ARRAY_BUFFER,
+                    (self.vertices.len() * mem::size_of::<Vertex>()) as isize,
+                    self.vertices.as_ptr() as *const _,
+                    gl::STREAM_DRAW,
+                );
+
+                gl::DrawArrays(gl::TRIANGLES, 0, self.vertices.len() as i32);
+            }
+        }
+
+        unsafe {
+            gl::BindTexture(gl::TEXTURE_2D, 0);
+            gl::UseProgram(0);
+            gl::BindBuffer(gl::ARRAY_BUFFER, 0);
+            gl::BindVertexArray(0);
+        }
+    }
+
+    fn upload(graphic: &GraphicData) -> GraphicTexture {
+        let mut id: GLuint = 0;
+
+        unsafe {
+            gl::GenTextures(1, &mut id);
+            gl::BindTexture(gl::TEXTURE_2D, id);
+
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, gl::NEAREST as i32);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::NEAREST as i32);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_S, gl::CLAMP_TO_EDGE as i32);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_T, gl::CLAMP_TO_EDGE as i32);
+
+            gl::TexImage2D(
+                gl::TEXTURE_2D,
+                0,
+                gl::RGBA as i32,
+                graphic.width as i32,
+                graphic.height as i32,
+                0,
+                gl::RGBA,
+                gl::UNSIGNED_BYTE,
+                graphic.pixels.as_ptr() as *const _,
+            );
+
+            gl::BindTexture(gl::TEXTURE_2D, 0);
+        }
+
+        GraphicTexture { id, width: graphic.width as f32, height: graphic.height as f32 }
+    }
+
+    /// Add the vertices for a single cell of a graphic.
+    fn add_cell(
+        vertices: &mut Vec<Vertex>,
+        half_width: f32,
+        half_height: f32,
+        size_info: &SizeInfo,
+        texture: &GraphicTexture,
+        cell: &RenderGraphic,
+    ) {
+        let offset_x = f32::from(cell.offset_x);
+        let offset_y = f32::from(cell.offset_y);
+
+        // Clip the last column and line to the graphic's size.
+        let width = size_info.cell_width().min(texture.width - offset_x);
+        let height = size_info.cell_height().min(texture.height - offset_y);
+        if width <= 0. || height <= 0. {
+            return;
+        }
+
+        let x = size_info.padding_x() + cell.column.0 as f32 * size_info.cell_width();
+        let y = size_info.padding_y() + cell.line.0 as f32 * size_info.cell_height();
+
+        // Calculate vertex positions in normalized device coordinates.
+        let left = x / half_width - 1.;
+        let right = (x + width) / half_width - 1.;
+        let top = -y / half_height + 1.;
+        let bottom = -(y + height) / half_height + 1.;
+
+        let u_left = offset_x / texture.width;
+        let u_right = (offset_x + width) / texture.width;
+        let v_top = offset_y / texture.height;
+        let v_bottom = (offset_y + height) / texture.height;
+
+        let quad = [
+            Vertex { x: left, y: top, u: u_left, v: v_top },
+            Vertex { x: left, y: bottom, u: u_left, v: v_bottom },
+            Vertex { x: right, y: top, u: u_right, v: v_top },
+            Vertex { x: right, y: bottom, u: u_right, v: v_bottom },
+        ];
+
+        // Append the vertices to form two triangles.
+        vertices.push(quad[0]);
+        vertices.push(quad[1]);
+        vertices.push(quad[2]);
+        vertices.push(quad[2]);
+        vertices.push(quad[3]);
+        vertices.push(quad[1]);
+    }
+}
+
+/// Graphics drawing program.
+#[derive(Debug)]
+pub struct GraphicsShaderProgram {
+    /// Program id.
+    id: GLuint,
+}
+
+impl GraphicsShaderProgram {
+    pub fn new() -> Result<Self, renderer::ShaderCreationError> {
+        let vertex_shader = renderer::create_shader(gl::VERTEX_SHADER, GRAPHICS_SHADER_V)?;
+        let fragment_shader = renderer::create_shader(gl::FRAGMENT_SHADER, GRAPHICS_SHADER_F)?;
+        let program = renderer::create_program(vertex_shader, fragment_shader)?;
+
+        unsafe {
+            gl::DeleteShader(fragment_shader);
+            gl::DeleteShader(vertex_shader);
+        }
+
+        Ok(Self { id: program })
+    }
+}
+
+impl Drop for GraphicsShaderProgram {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteProgram(self.id);
+        }
+    }
+}
@@ -1,9 +1,11 @@
+use std::cell::{RefCell, RefMut};
 use std::collections::HashMap;
 use std::fmt::{self, Display, Formatter};
 use std::hash::BuildHasherDefault;
 use std::io;
 use std::mem::size_of;
 use std::ptr;
+use std::rc::{Rc, Weak};
 
 use bitflags::bitflags;
 use crossfont::{
@@ -14,6 +16,8 @@ use fnv::FnvHasher;
 use log::{error, info};
 use unicode_width::UnicodeWidthChar;
 
+use alacritty_terminal::ansi::LineSize;
+use alacritty_terminal::graphics::UpdateQueues;
 use alacritty_terminal::index::Point;
 use alacritty_terminal::term::cell::Flags;
 use alacritty_terminal::term::color::Rgb;
@@ -24,8 +28,10 @@ use crate::config::font::{Font, FontDescription};
 use crate::config::ui_config::{Delta, UIConfig};
 use crate::gl;
 use crate::gl::types::*;
+use crate::renderer::graphics::{GraphicsRenderer, RenderGraphic};
 use crate::renderer::rects::{RectRenderer, RenderRect};
 
+pub mod graphics;
 pub mod rects;
 
 // Shader source.
@@ -106,6 +112,82 @@ pub struct Glyph {
     uv_height: f32,
 }
 
+/// Rasterized glyphs, or the glyph used for rendering missing glyphs on failure.
+type RasterizeResult = Result<RasterizedGlyph, Option<RasterizedGlyph>>;
+
+/// Rasterizer shared between all windows with the same DPR and font rendering settings.
+///
+/// Since every window has its own OpenGL context, the glyph atlas cannot be shared between
+/// windows. Loading fonts and rasterizing glyphs only has to happen once per process however.
+#[derive(Clone)]
+pub struct SharedRasterizer(Rc<RefCell<RasterizerCache>>);
+
+struct RasterizerCache {
+    rasterizer: Rasterizer,
+    glyphs: HashMap<GlyphKey, RasterizeResult, BuildHasherDefault<FnvHasher>>,
+}
+
+impl SharedRasterizer {
+    /// Access the underlying rasterizer for loading fonts.
+    fn rasterizer(&self) -> RefMut<'_, Rasterizer> {
+        RefMut::map(self.0.borrow_mut(), |cache| &mut cache.rasterizer)
+    }
+
+    /// Rasterize a glyph, reusing glyphs rasterized for other windows.
+    fn get_glyph(&self, glyph_key: GlyphKey) -> RasterizeResult {
+        let mut cache = self.0.borrow_mut();
+        let RasterizerCache { rasterizer, glyphs } = &mut *cache;
+        glyphs
+            .entry(glyph_key)
+            .or_insert_with(|| match rasterizer.get_glyph(glyph_key) {
+                Ok(rasterized) => Ok(rasterized),
+                Err(RasterizerError::MissingGlyph(rasterized)) => Err(Some(rasterized)),
+                Err(_) => Err(None),
+            })
+            .clone()
+    }
+
+    /// Remove all rasterized glyphs, so glyphs of fonts which are no longer used are freed.
+    fn clear_glyphs(&self) {
+        self.0.borrow_mut().glyphs = HashMap::default();
+    }
+}
+
+/// Settings a rasterizer is created with, the bits of the DPR and whether thin strokes are used.
+type RasterizerKey = (u64, bool);
+
+/// All rasterizers currently in use, keyed by their settings.
+///
+/// Rasterizers are dropped once no window is using them anymore.
+#[derive(Clone, Default)]
+pub struct Rasterizers(Rc<RefCell<HashMap<RasterizerKey, Weak<RefCell<RasterizerCache>>>>>);
+
+impl Rasterizers {
+    /// Get the rasterizer for a DPR, creating it if no window is using these settings yet.
+    pub fn get(
+        &self,
+        dpr: f64,
+        use_thin_strokes: bool,
+    ) -> Result<SharedRasterizer, crossfont::Error> {
+        let mut rasterizers = self.0.borrow_mut();
+
+        // Remove rasterizers which are no longer used by any window.
+        rasterizers.retain(|_, cache| cache.strong_count() > 0);
+
+        let key = (dpr.to_bits(), use_thin_strokes);
+        if let Some(cache) = rasterizers.get(&key).and_then(Weak::upgrade) {
+            return Ok(SharedRasterizer(cache));
+        }
+
+        let rasterizer = Rasterizer::new(dpr as f32, use_thin_strokes)?;
+        let cache = RasterizerCache { rasterizer, glyphs: HashMap::default() };
+        let cache = Rc::new(RefCell::new(cache));
+        rasterizers.insert(key, Rc::downgrade(&cache));
+
+        Ok(SharedRasterizer(cache))
+    }
+}
+
 /// Naïve glyph cache.
 ///
 /// Currently only keyed by `char`, and thus not possible to hold different
@@ -115,7 +197,7 @@ pub struct GlyphCache {
     cache: HashMap<GlyphKey, Glyph, BuildHasherDefault<FnvHasher>>,
 
     /// Rasterizer for loading new glyphs.
-    rasterizer: Rasterizer,
+    rasterizer: SharedRasterizer,
 
     /// Regular font.
     font_key: FontKey,
@@ -141,21 +223,26 @@ pub struct GlyphCache {
 
 impl GlyphCache {
     pub fn new<L>(
-        mut rasterizer: Rasterizer,
+        rasterizer: SharedRasterizer,
         font: &Font,
         loader: &mut L,
     ) -> Result<GlyphCache, crossfont::Error>
     where
         L: LoadGlyph,
     {
-        let (regular, bold, italic, bold_italic) = Self::compute_font_keys(font, &mut rasterizer)?;
-
-        // Need to load at least one glyph for the face before calling metrics.
-        // The glyph requested here ('m' at the time of writing) has no special
-        // meaning.
-        rasterizer.get_glyph(GlyphKey { font_key: regular, character: 'm', size: font.size() })?;
-
-        let metrics = rasterizer.metrics(regular, font.size())?;
+        let (regular, bold, italic, bold_italic, metrics) = {
+            let mut rasterizer = rasterizer.rasterizer();
+            let (regular, bold, italic, bold_italic) =
+                Self::compute_font_keys(font, &mut rasterizer)?;
+
+            // Need to load at least one glyph for the face before calling metrics.
+            // The glyph requested here ('m' at the time of writing) has no special
+            // meaning.
+            let size = font.size();
+            rasterizer.get_glyph(GlyphKey { font_key: regular, character: 'm', size })?;
+
+            (regular, bold, italic, bold_italic, rasterizer.metrics(regular, size)?)
+        };
 
         let mut cache = Self {
             cache: HashMap::default(),
@@ -270,7 +357,7 @@ impl GlyphCache {
         let glyph = match self.rasterizer.get_glyph(glyph_key) {
             Ok(rasterized) => self.load_glyph(loader, rasterized),
             // Load fallback glyph.
-            Err(RasterizerError::MissingGlyph(rasterized)) if show_missing => {
+            Err(Some(rasterized)) if show_missing => {
                 // Use `\0` as "missing" glyph to cache it only once.
                 let missing_key = GlyphKey { character: '\0', ..glyph_key };
                 if let Some(glyph) = self.cache.get(&missing_key) {
@@ -319,6 +406,7 @@ impl GlyphCache {
     pub fn clear_glyph_cache<L: LoadGlyph>(&mut self, loader: &mut L) {
         loader.clear();
         self.cache = HashMap::default();
+        self.rasterizer.clear_glyphs();
 
         self.load_common_glyphs(loader);
     }
@@ -327,21 +415,19 @@ impl GlyphCache {
         &mut self,
         font: &Font,
         dpr: f64,
+        rasterizers: &Rasterizers,
         loader: &mut L,
     ) -> Result<(), crossfont::Error> {
         // Update dpi scaling.
-        self.rasterizer.update_dpr(dpr as f32);
+        self.rasterizer = rasterizers.get(dpr, font.use_thin_strokes)?;
 
         // Recompute font keys.
-        let (regular, bold, italic, bold_italic) =
-            Self::compute_font_keys(font, &mut self.rasterizer)?;
+        let mut rasterizer = self.rasterizer.rasterizer();
+        let (regular, bold, italic, bold_italic) = Self::compute_font_keys(font, &mut rasterizer)?;
 
-        self.rasterizer.get_glyph(GlyphKey {
-            font_key: regular,
-            character: 'm',
-            size: font.size(),
-        })?;
-        let metrics = self.rasterizer.metrics(regular, font.size())?;
+        rasterizer.get_glyph(GlyphKey { font_key: regular, character: 'm', size: font.size() })?;
+        let metrics = rasterizer.metrics(regular, font.size())?;
+        drop(rasterizer);
 
         info!("Font size changed to {:?} with DPR of {}", font.size(), dpr);
 
@@ -370,8 +456,11 @@ impl GlyphCache {
     }
 
     /// Calculate font metrics without access to a glyph cache.
-    pub fn static_metrics(font: Font, dpr: f64) -> Result<crossfont::Metrics, crossfont::Error> {
-        let mut rasterizer = crossfont::Rasterizer::new(dpr as f32, font.use_thin_strokes)?;
+    pub fn static_metrics(
+        font: Font,
+        rasterizer: &SharedRasterizer,
+    ) -> Result<crossfont::Metrics, crossfont::Error> {
+        let mut rasterizer = rasterizer.rasterizer();
         let regular_desc = GlyphCache::make_desc(&font.normal(), Slant::Normal, Weight::Normal);
         let regular = Self::load_regular_font(&mut rasterizer, &regular_desc, font.size())?;
         rasterizer.get_glyph(GlyphKey { font_key: regular, character: 'm', size: font.size() })?;
@@ -384,8 +473,11 @@ impl GlyphCache {
 bitflags! {
     #[repr(C)]
     struct RenderingGlyphFlags: u8 {
-        const WIDE_CHAR = 0b0000_0001;
-        const COLORED   = 0b0000_0010;
+        const WIDE_CHAR            = 0b0000_0001;
+        const COLORED              = 0b0000_0010;
+        const DOUBLE_WIDTH         = 0b0000_0100;
+        const DOUBLE_HEIGHT_TOP    = 0b0000_1000;
+        const DOUBLE_HEIGHT_BOTTOM = 0b0001_0000;
     }
 }
 
@@ -439,6 +531,7 @@ pub struct QuadRenderer {
     batch: Batch,
 
     rect_renderer: RectRenderer,
+    graphics_renderer: GraphicsRenderer,
 }
 
 #[derive(Debug)]
@@ -478,6 +571,12 @@ impl Batch {
         let mut cell_flags = RenderingGlyphFlags::empty();
         cell_flags.set(RenderingGlyphFlags::COLORED, glyph.multicolor);
         cell_flags.set(RenderingGlyphFlags::WIDE_CHAR, cell.flags.contains(Flags::WIDE_CHAR));
+        cell_flags |= match cell.line_size {
+            LineSize::Normal => RenderingGlyphFlags::empty(),
+            LineSize::DoubleWidth => RenderingGlyphFlags::DOUBLE_WIDTH,
+            LineSize::DoubleHeightTop => RenderingGlyphFlags::DOUBLE_HEIGHT_TOP,
+            LineSize::DoubleHeightBottom => RenderingGlyphFlags::DOUBLE_HEIGHT_BOTTOM,
+        };
 
         self.instances.push(InstanceData {
             col: cell.column.0 as u16,
@@ -638,6 +737,7 @@ impl QuadRenderer {
         let mut renderer = Self {
             program,
             rect_renderer: RectRenderer::new()?,
+            graphics_renderer: GraphicsRenderer::new()?,
             vao,
             ebo,
             vbo_instance,
@@ -682,6 +782,44 @@ impl QuadRenderer {
         }
     }
 
+    /// Upload new graphics and release the ones no longer referenced by the terminal.
+    pub fn update_graphics(&mut self, queues: UpdateQueues) {
+        self.graphics_renderer.update(queues);
+
+        // Uploading graphics binds textures.
+        self.active_tex = 0;
+    }
+
+    /// Draw all visible pieces of inline graphics.
+    pub fn draw_graphics(&mut self, size_info: &SizeInfo, graphics: Vec<RenderGraphic>) {
+        if graphics.is_empty() {
+            return;
+        }
+
+        // Prepare graphics rendering state.
+        unsafe {
+            // Remove padding from viewport.
+            gl::Viewport(0, 0, size_info.width() as i32, size_info.height() as i32);
+            gl::BlendFuncSeparate(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA, gl::SRC_ALPHA, gl::ONE);
+        }
+
+        self.graphics_renderer.draw(size_info, graphics);
+        self.active_tex = 0;
+
+        // Activate regular state again.
+        unsafe {
+            // Reset blending strategy.
+            gl::BlendFunc(gl::SRC1_COLOR, gl::ONE_MINUS_SRC1_COLOR);
+
+            // Restore viewport with padding.
+            let padding_x = size_info.padding_x() as i32;
+            let padding_y = size_info.padding_y() as i32;
+            let width = size_info.width() as i32;
+            let height = size_info.height() as i32;
+            gl::Viewport(padding_x, padding_y, width - 2 * padding_x, height - 2 * padding_y);
+        }
+    }
+
     pub fn with_api<F, T>(&mut self, config: &UIConfig, props: &SizeInfo, func: F) -> T
     where
         F: FnOnce(RenderApi<'_>) -> T,
@@ -837,7 +975,11 @@ impl<'a> RenderApi<'a> {
                 bg_alpha: 1.0,
                 fg,
                 bg,
+                underline: fg,
                 is_match: false,
+                hyperlink: None,
+                graphic: None,
+                line_size: LineSize::Normal,
             })
             .collect::<Vec<_>>();
 
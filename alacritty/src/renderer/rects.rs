@@ -3,6 +3,7 @@ use std::mem;
 
 use crossfont::Metrics;
 
+use alacritty_terminal::ansi::LineSize;
 use alacritty_terminal::index::{Column, Point};
 use alacritty_terminal::term::cell::Flags;
 use alacritty_terminal::term::color::Rgb;
@@ -79,6 +80,39 @@ impl RenderLine {
 
                 (bottom_pos, metrics.underline_thickness)
             },
+            Flags::UNDERCURL => {
+                Self::push_undercurl(rects, metrics, size, start, end, color);
+                return;
+            },
+            Flags::DOTTED_UNDERLINE | Flags::DASHED_UNDERLINE => {
+                let line = Self::create_rect(
+                    size,
+                    metrics.descent,
+                    start,
+                    end,
+                    metrics.underline_position,
+                    metrics.underline_thickness,
+                    color,
+                );
+
+                // Dots are squares, while dashes cover the center half of every cell.
+                let (offset, width, step) = if flag == Flags::DOTTED_UNDERLINE {
+                    (0., line.height, 2. * line.height)
+                } else {
+                    let cell_width = size.cell_width();
+                    (cell_width / 4., (cell_width / 2.).round(), cell_width)
+                };
+
+                let line_end = line.x + line.width;
+                let mut x = line.x + offset;
+                while x < line_end {
+                    let width = width.min(line_end - x);
+                    rects.push(RenderRect::new(x, line.y, width, line.height, color, 1.));
+                    x += step;
+                }
+
+                return;
+            },
             Flags::UNDERLINE => (metrics.underline_position, metrics.underline_thickness),
             Flags::STRIKEOUT => (metrics.strikeout_position, metrics.strikeout_thickness),
             _ => unimplemented!("Invalid flag for cell line drawing specified"),
@@ -95,6 +129,48 @@ impl RenderLine {
         ));
     }
 
+    /// Push the rects approximating a sine wave with one period per cell.
+    fn push_undercurl(
+        rects: &mut Vec<RenderRect>,
+        metrics: &Metrics,
+        size: &SizeInfo,
+        start: Point,
+        end: Point,
+        color: Rgb,
+    ) {
+        let thickness = metrics.underline_thickness.max(1.);
+        let amplitude = (1.5 * thickness).round().max(1.);
+
+        // Bounding box of the wave, moved up when it doesn't fit into the descent.
+        let bounds = Self::create_rect(
+            size,
+            metrics.descent,
+            start,
+            end,
+            metrics.underline_position,
+            thickness + 2. * amplitude,
+            color,
+        );
+
+        let cell_width = size.cell_width();
+        let wave_y = |x: f32| {
+            let phase = (x - size.padding_x()) / cell_width * 2. * std::f32::consts::PI;
+            (amplitude * (1. + phase.sin())).round()
+        };
+
+        // Draw one pixel wide columns covering the distance to the next column's offset.
+        let mut x = bounds.x;
+        let line_end = bounds.x + bounds.width;
+        while x < line_end {
+            let y = wave_y(x);
+            let next_y = wave_y(x + 1.);
+            let top = y.min(next_y);
+            let height = (y - next_y).abs() + thickness;
+            rects.push(RenderRect::new(x, bounds.y + top, 1., height, color, 1.));
+            x += 1.;
+        }
+    }
+
     /// Create a line's rect at a position relative to the baseline.
     fn create_rect(
         size: &SizeInfo,
@@ -132,7 +208,7 @@ impl RenderLine {
     }
 }
 
-/// Lines for underline and strikeout.
+/// Lines for underlines and strikeout.
 #[derive(Default)]
 pub struct RenderLines {
     inner: HashMap<Flags, Vec<RenderLine>>,
@@ -157,9 +233,12 @@ impl RenderLines {
     /// Update the stored lines with the next cell info.
     #[inline]
     pub fn update(&mut self, cell: &RenderableCell) {
-        self.update_flag(&cell, Flags::UNDERLINE);
-        self.update_flag(&cell, Flags::DOUBLE_UNDERLINE);
-        self.update_flag(&cell, Flags::STRIKEOUT);
+        self.update_flag(cell, Flags::UNDERLINE);
+        self.update_flag(cell, Flags::DOUBLE_UNDERLINE);
+        self.update_flag(cell, Flags::UNDERCURL);
+        self.update_flag(cell, Flags::DOTTED_UNDERLINE);
+        self.update_flag(cell, Flags::DASHED_UNDERLINE);
+        self.update_flag(cell, Flags::STRIKEOUT);
     }
 
     /// Update the lines for a specific flag.
@@ -169,16 +248,22 @@ impl RenderLines {
         }
 
         // Include wide char spacer if the current cell is a wide char.
-        let mut end: Point = cell.into();
-        if cell.flags.contains(Flags::WIDE_CHAR) {
-            end.col += 1;
+        let mut width = if cell.flags.contains(Flags::WIDE_CHAR) { 2 } else { 1 };
+
+        // Cells in double-width lines cover twice as many columns.
+        if cell.line_size != LineSize::Normal {
+            width *= 2;
         }
 
+        let mut end: Point = cell.into();
+        end.col += width - 1;
+
+        // Underlines can have a color separate from the foreground.
+        let color = if flag == Flags::STRIKEOUT { cell.fg } else { cell.underline };
+
         // Check if there's an active line.
         if let Some(line) = self.inner.get_mut(&flag).and_then(|lines| lines.last_mut()) {
-            if cell.fg == line.color
-                && cell.column == line.end.col + 1
-                && cell.line == line.end.line
+            if color == line.color && cell.column == line.end.col + 1 && cell.line == line.end.line
             {
                 // Update the length of the line.
                 line.end = end;
@@ -187,7 +272,7 @@ impl RenderLines {
         }
 
         // Start new line if there currently is none.
-        let line = RenderLine { start: cell.into(), end, color: cell.fg };
+        let line = RenderLine { start: cell.into(), end, color };
         match self.inner.get_mut(&flag) {
             Some(lines) => lines.push(line),
             None => {
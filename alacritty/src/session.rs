@@ -0,0 +1,108 @@
+//! Storage of terminal sessions across launches.
+
+#[cfg(not(windows))]
+use std::env;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufReader, BufWriter, Write};
+#[cfg(unix)]
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::{Path, PathBuf};
+
+use log::{error, info};
+
+use alacritty_terminal::term::session::Session;
+
+/// Name of the file storing the saved sessions.
+const SESSION_FILE: &str = "session.json";
+
+/// Load the sessions saved by a previous instance.
+pub fn load() -> Vec<Session> {
+    let path = match session_path() {
+        Some(path) => path,
+        None => return Vec::new(),
+    };
+
+    let file = match File::open(&path) {
+        Ok(file) => file,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Vec::new(),
+        Err(err) => {
+            error!("Unable to read session {:?}: {}", path, err);
+            return Vec::new();
+        },
+    };
+
+    match serde_json::from_reader(BufReader::new(file)) {
+        Ok(sessions) => {
+            info!("Restoring session from {:?}", path);
+            sessions
+        },
+        Err(err) => {
+            error!("Unable to parse session {:?}: {}", path, err);
+            Vec::new()
+        },
+    }
+}
+
+/// Save sessions, replacing any previously saved ones.
+pub fn save(sessions: &[Session]) {
+    let path = match session_path() {
+        Some(path) => path,
+        None => {
+            error!("Unable to locate directory for saving the session");
+            return;
+        },
+    };
+
+    // Write to a temporary file first, so a failed write cannot destroy the previous session.
+    let temp_path = path.with_extension("json.tmp");
+    let result = path
+        .parent()
+        .map_or(Ok(()), fs::create_dir_all)
+        .and_then(|_| create_private(&temp_path))
+        .and_then(|file| {
+            let mut writer = BufWriter::new(file);
+            serde_json::to_writer(&mut writer, sessions)?;
+            writer.flush()
+        })
+        .and_then(|_| fs::rename(&temp_path, &path));
+
+    match result {
+        Ok(()) => info!("Session saved to {:?}", path),
+        Err(err) => {
+            let _ = fs::remove_file(&temp_path);
+            error!("Unable to save session {:?}: {}", path, err);
+        },
+    }
+}
+
+/// Create a new file which is only accessible by the current user.
+fn create_private(path: &Path) -> io::Result<File> {
+    // Remove leftovers of previous attempts, since their permissions are unknown.
+    match fs::remove_file(path) {
+        Err(err) if err.kind() != io::ErrorKind::NotFound => return Err(err),
+        _ => (),
+    }
+
+    let mut options = OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    options.mode(0o600);
+    options.open(path)
+}
+
+/// Location of the session file.
+#[cfg(not(windows))]
+fn session_path() -> Option<PathBuf> {
+    // Fall back to the default state directory, since the `xdg` crate does not support it yet.
+    env::var_os("XDG_STATE_HOME")
+        .map(PathBuf::from)
+        .filter(|path| path.is_absolute())
+        .or_else(|| dirs::home_dir().map(|home| home.join(".local").join("state")))
+        .map(|path| path.join("alacritty").join(SESSION_FILE))
+}
+
+/// Location of the session file.
+#[cfg(windows)]
+fn session_path() -> Option<PathBuf> {
+    dirs::data_local_dir().map(|path| path.join("alacritty").join(SESSION_FILE))
+}
@@ -1,10 +1,13 @@
 use std::cmp::min;
+use std::collections::HashMap;
 use std::mem;
+use std::sync::Arc;
 
 use crossfont::Metrics;
 use glutin::event::{ElementState, ModifiersState};
 use urlocator::{UrlLocation, UrlLocator};
 
+use alacritty_terminal::ansi::{Hyperlink, LineSize};
 use alacritty_terminal::index::{Column, Point};
 use alacritty_terminal::term::cell::Flags;
 use alacritty_terminal::term::color::Rgb;
@@ -20,6 +23,7 @@ pub struct Url {
     lines: Vec<RenderLine>,
     end_offset: u16,
     num_cols: Column,
+    hyperlink: Option<Arc<Hyperlink>>,
 }
 
 impl Url {
@@ -44,11 +48,28 @@ impl Url {
     pub fn end(&self) -> Point {
         self.lines[self.lines.len() - 1].end.sub(self.num_cols, self.end_offset as usize)
     }
+
+    /// OSC 8 hyperlink this URL was created from.
+    pub fn hyperlink(&self) -> Option<&Hyperlink> {
+        self.hyperlink.as_deref()
+    }
+
+    /// Check if a point is part of the URL.
+    fn contains(&self, point: Point) -> bool {
+        match self.hyperlink {
+            // Hyperlinks with an ID are not necessarily contiguous.
+            Some(_) => self.lines.iter().any(|line| (line.start..=line.end).contains(&point)),
+            None => (self.start()..=self.end()).contains(&point),
+        }
+    }
 }
 
 pub struct Urls {
     locator: UrlLocator,
     urls: Vec<Url>,
+    hyperlinks: Vec<Url>,
+    /// Index of the latest URL in `hyperlinks` for every hyperlink.
+    hyperlink_indices: HashMap<Arc<Hyperlink>, usize>,
     scheme_buffer: Vec<(Point, Rgb)>,
     last_point: Option<Point>,
     state: UrlLocation,
@@ -60,6 +81,8 @@ impl Default for Urls {
             locator: UrlLocator::new(),
             scheme_buffer: Vec::new(),
             urls: Vec::new(),
+            hyperlinks: Vec::new(),
+            hyperlink_indices: HashMap::new(),
             state: UrlLocation::Reset,
             last_point: None,
         }
@@ -76,16 +99,25 @@ impl Urls {
         let point: Point = cell.into();
         let mut end = point;
 
+        // Include the second column of cells in double-width lines.
+        let width = if cell.line_size == LineSize::Normal { 1 } else { 2 };
+        end.col += width - 1;
+
         // Include the following wide char spacer.
         if cell.flags.contains(Flags::WIDE_CHAR) {
-            end.col += 1;
+            end.col += width;
         }
 
         // Reset URL when empty cells have been skipped.
-        if point != Point::default() && Some(point.sub(num_cols, 1)) != self.last_point {
+        let adjacent = point == Point::default() || Some(point.sub(num_cols, 1)) == self.last_point;
+        if !adjacent {
             self.reset();
         }
 
+        if let Some(hyperlink) = &cell.hyperlink {
+            self.update_hyperlink(num_cols, hyperlink, point, end, cell.fg, adjacent);
+        }
+
         self.last_point = Some(end);
 
         // Extend current state if a leading wide char spacer is encountered.
@@ -105,8 +137,10 @@ impl Urls {
         let last_state = mem::replace(&mut self.state, self.locator.advance(cell.character));
         match (self.state, last_state) {
             (UrlLocation::Url(_length, end_offset), UrlLocation::Scheme) => {
+                let end_offset = end_offset * width as u16;
+
                 // Create empty URL.
-                self.urls.push(Url { lines: Vec::new(), end_offset, num_cols });
+                self.urls.push(Url { lines: Vec::new(), end_offset, num_cols, hyperlink: None });
 
                 // Push schemes into URL.
                 for (scheme_point, scheme_fg) in self.scheme_buffer.split_off(0) {
@@ -117,6 +151,7 @@ impl Urls {
                 self.extend_url(point, end, cell.fg, end_offset);
             },
             (UrlLocation::Url(_length, end_offset), UrlLocation::Url(..)) => {
+                let end_offset = end_offset * width as u16;
                 self.extend_url(point, end, cell.fg, end_offset);
             },
             (UrlLocation::Scheme, _) => self.scheme_buffer.push((cell.into(), cell.fg)),
@@ -125,7 +160,7 @@ impl Urls {
         }
 
         // Reset at un-wrapped linebreak.
-        if cell.column + 1 == num_cols && !cell.flags.contains(Flags::WRAPLINE) {
+        if cell.column + width >= num_cols && !cell.flags.contains(Flags::WRAPLINE) {
             self.reset();
         }
     }
@@ -145,6 +180,41 @@ impl Urls {
         url.end_offset = end_offset;
     }
 
+    /// Add a cell to the hyperlink it is part of.
+    fn update_hyperlink(
+        &mut self,
+        num_cols: Column,
+        hyperlink: &Arc<Hyperlink>,
+        start: Point,
+        end: Point,
+        color: Rgb,
+        adjacent: bool,
+    ) {
+        let previous = self.last_point.filter(|_| adjacent);
+
+        // Hyperlinks without an ID are only grouped while they are contiguous.
+        let hyperlinks = &self.hyperlinks;
+        let index = self.hyperlink_indices.get(hyperlink).copied().filter(|&index| {
+            hyperlink.id.is_some()
+                || hyperlinks[index].lines.last().map(|line| line.end) == previous
+        });
+
+        let url = match index {
+            Some(index) => &mut self.hyperlinks[index],
+            None => {
+                self.hyperlink_indices.insert(hyperlink.clone(), self.hyperlinks.len());
+                let hyperlink = Some(hyperlink.clone());
+                self.hyperlinks.push(Url { lines: Vec::new(), end_offset: 0, num_cols, hyperlink });
+                self.hyperlinks.last_mut().unwrap()
+            },
+        };
+
+        match url.lines.last_mut() {
+            Some(line) if Some(line.end) == previous && line.color == color => line.end = end,
+            _ => url.lines.push(RenderLine { color, start, end }),
+        }
+    }
+
     /// Find URL below the mouse cursor.
     pub fn highlighted(
         &self,
@@ -175,12 +245,8 @@ impl Urls {
 
     /// Find URL at location.
     pub fn find_at(&self, point: Point) -> Option<Url> {
-        for url in &self.urls {
-            if (url.start()..=url.end()).contains(&point) {
-                return Some(url.clone());
-            }
-        }
-        None
+        // Explicit hyperlinks take precedence over detected URLs.
+        self.hyperlinks.iter().chain(&self.urls).find(|url| url.contains(point)).cloned()
     }
 
     fn reset(&mut self) {
@@ -194,7 +260,7 @@ impl Urls {
 mod tests {
     use super::*;
 
-    use alacritty_terminal::index::{Column, Line};
+    use alacritty_terminal::index::Line;
 
     fn text_to_cells(text: &str) -> Vec<RenderableCell> {
         text.chars()
@@ -206,9 +272,13 @@ mod tests {
                 column: Column(i),
                 fg: Default::default(),
                 bg: Default::default(),
+                underline: Default::default(),
                 bg_alpha: 0.,
                 flags: Flags::empty(),
                 is_match: false,
+                hyperlink: None,
+                graphic: None,
+                line_size: LineSize::Normal,
             })
             .collect()
     }
@@ -254,6 +324,26 @@ mod tests {
         assert_eq!(urls.urls[2].end().col, Column(21));
     }
 
+    #[test]
+    fn double_width_urls() {
+        let mut input = text_to_cells("test git:a ing");
+        for cell in &mut input {
+            cell.column = Column(cell.column.0 * 2);
+            cell.line_size = LineSize::DoubleWidth;
+        }
+        let num_cols = input.len() * 2;
+
+        let mut urls = Urls::new();
+
+        for cell in input {
+            urls.update(Column(num_cols), &cell);
+        }
+
+        assert_eq!(urls.urls.len(), 1);
+        assert_eq!(urls.urls[0].start().col, Column(10));
+        assert_eq!(urls.urls[0].end().col, Column(19));
+    }
+
     #[test]
     fn wide_urls() {
         let input = text_to_cells("test https://こんにちは (http:여보세요) ing");
@@ -273,4 +363,37 @@ mod tests {
         assert_eq!(urls.urls[1].start().col, Column(20));
         assert_eq!(urls.urls[1].end().col, Column(28));
     }
+
+    #[test]
+    fn hyperlinks() {
+        let mut input = text_to_cells("ab cd ef gh");
+        let num_cols = input.len();
+
+        let anonymous = Arc::new(Hyperlink { id: None, uri: String::from("https://example.org") });
+        let with_id =
+            Arc::new(Hyperlink { id: Some(String::from("1")), uri: anonymous.uri.clone() });
+        for &i in &[0, 1, 3, 4] {
+            input[i].hyperlink = Some(anonymous.clone());
+        }
+        for &i in &[6, 7, 9, 10] {
+            input[i].hyperlink = Some(with_id.clone());
+        }
+
+        let mut urls = Urls::new();
+
+        for cell in input {
+            urls.update(Column(num_cols), &cell);
+        }
+
+        assert_eq!(urls.hyperlinks.len(), 3);
+
+        assert_eq!(urls.find_at(Point::new(Line(0), Column(1))), Some(urls.hyperlinks[0].clone()));
+        assert_eq!(urls.find_at(Point::new(Line(0), Column(3))), Some(urls.hyperlinks[1].clone()));
+        assert_eq!(urls.find_at(Point::new(Line(0), Column(2))), None);
+
+        let url = urls.find_at(Point::new(Line(0), Column(10))).unwrap();
+        assert_eq!(url.hyperlink(), Some(&*with_id));
+        assert_eq!(url.lines.len(), 2);
+        assert_eq!(urls.find_at(Point::new(Line(0), Column(8))), None);
+    }
 }
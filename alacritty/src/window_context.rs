@@ -0,0 +1,551 @@
+//! Terminal window context.
+
+use std::collections::VecDeque;
+use std::error::Error;
+use std::fs::{File, OpenOptions};
+use std::io::{BufWriter, Write};
+use std::mem;
+#[cfg(unix)]
+use std::os::unix::fs::OpenOptionsExt;
+#[cfg(not(windows))]
+use std::os::unix::io::{AsRawFd, RawFd};
+use std::path::{Path, PathBuf};
+#[cfg(not(any(target_os = "macos", windows)))]
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::thread::JoinHandle;
+use std::time::Instant;
+
+use crossfont::Size;
+use glutin::event::{Event as GlutinEvent, ModifiersState, VirtualKeyCode};
+use glutin::event_loop::{ControlFlow, EventLoopProxy, EventLoopWindowTarget};
+use glutin::window::WindowId;
+use log::{error, info};
+use serde_json as json;
+#[cfg(all(feature = "wayland", not(any(target_os = "macos", windows))))]
+use wayland_client::EventQueue;
+
+use alacritty_terminal::asciicast::{Player, Recorder, Recording};
+use alacritty_terminal::config::{Capability, LOG_TARGET_CONFIG};
+use alacritty_terminal::event::Event as TerminalEvent;
+use alacritty_terminal::event_loop::{EventLoop as PtyEventLoop, Msg, Notifier, State};
+use alacritty_terminal::grid::{Dimensions, Scroll};
+use alacritty_terminal::index::Direction;
+use alacritty_terminal::sync::FairMutex;
+use alacritty_terminal::term::session::Session;
+use alacritty_terminal::term::{Term, TermMode};
+use alacritty_terminal::tty;
+
+use crate::clipboard::Clipboard;
+use crate::config::Config;
+use crate::display::{Display, DisplayUpdate};
+use crate::event::{self, ActionContext, Event, EventProxy, EventType, Mouse, SearchState};
+use crate::input;
+use crate::message_bar::MessageBuffer;
+use crate::renderer::Rasterizers;
+use crate::scheduler::Scheduler;
+
+/// Event context for one individual Alacritty window.
+pub struct WindowContext {
+    display: Display,
+    terminal: Arc<FairMutex<Term<EventProxy>>>,
+    event_proxy: EventProxy,
+    notifier: Notifier,
+    io_thread: Option<JoinHandle<(PtyEventLoop<tty::Pty, EventProxy>, State)>>,
+    scheduler: Scheduler,
+    event_queue: Vec<GlutinEvent<'static, Event>>,
+    display_update_pending: DisplayUpdate,
+    message_buffer: MessageBuffer,
+    permission_requests: VecDeque<(Capability, TerminalEvent)>,
+    last_notification: Option<Instant>,
+    search_state: SearchState,
+    modifiers: ModifiersState,
+    received_count: usize,
+    suppress_chars: bool,
+    pressed_key: Option<VirtualKeyCode>,
+    font_size: Size,
+    mouse: Mouse,
+    dirty: bool,
+    recording: bool,
+    #[cfg(not(windows))]
+    master_fd: RawFd,
+    #[cfg(not(windows))]
+    shell_pid: u32,
+}
+
+impl WindowContext {
+    /// Create a new terminal window context.
+    pub fn new(
+        config: &Config,
+        window_event_loop: &EventLoopWindowTarget<Event>,
+        proxy: EventLoopProxy<Event>,
+        rasterizers: &Rasterizers,
+        session: Option<Session>,
+        replay: Option<Recording>,
+        #[cfg(all(feature = "wayland", not(any(target_os = "macos", windows))))]
+        wayland_event_queue: Option<&EventQueue>,
+    ) -> Result<Self, Box<dyn Error>> {
+        // Create a display.
+        //
+        // The display manages a window and can draw the terminal.
+        let display = Display::new(
+            config,
+            window_event_loop,
+            rasterizers,
+            #[cfg(all(feature = "wayland", not(any(target_os = "macos", windows))))]
+            wayland_event_queue,
+        )?;
+
+        info!(
+            "PTY dimensions: {:?} x {:?}",
+            display.size_info.screen_lines(),
+            display.size_info.cols()
+        );
+
+        let event_proxy = EventProxy::new(proxy, display.window.window_id());
+
+        // Create the terminal.
+        //
+        // This object contains all of the state about what's being displayed. It's
+        // wrapped in a clonable mutex since both the I/O loop and display need to
+        // access it.
+        let mut terminal = Term::new(config, display.size_info, event_proxy.clone());
+
+        // Restore the previous session before the shell can write to the terminal.
+        if let Some(session) = session {
+            terminal.restore_session(session);
+        }
+
+        let terminal = Arc::new(FairMutex::new(terminal));
+
+        // Play back recordings without starting a shell.
+        #[cfg(not(windows))]
+        let (master_fd, shell_pid);
+        let (loop_tx, io_thread) = match replay {
+            Some(recording) => {
+                let event_proxy = event_proxy.clone();
+                let size = display.size_info;
+                let player = Player::new(Arc::clone(&terminal), event_proxy, recording, size);
+                let loop_tx = player.channel();
+                player.spawn();
+
+                // There is no process which could be queried for its working directory.
+                #[cfg(not(windows))]
+                {
+                    master_fd = -1;
+                    shell_pid = 0;
+                }
+
+                (loop_tx, None)
+            },
+            None => {
+                // Create the PTY.
+                //
+                // The PTY forks a process to run the shell on the slave side of the
+                // pseudoterminal. A file descriptor for the master side is retained for
+                // reading/writing to the shell.
+                let pty = tty::new(config, &display.size_info, display.window.x11_window_id());
+
+                #[cfg(not(windows))]
+                {
+                    master_fd = pty.file().as_raw_fd();
+                    shell_pid = pty.child().id();
+                }
+
+                // Create the pseudoterminal I/O loop.
+                //
+                // PTY I/O is ran on another thread as to not occupy cycles used by the
+                // renderer and input processing. Note that access to the terminal state is
+                // synchronized since the I/O loop updates the state, and the display
+                // consumes it periodically.
+                let event_loop = PtyEventLoop::new(
+                    Arc::clone(&terminal),
+                    event_proxy.clone(),
+                    pty,
+                    config.hold,
+                    config.ui_config.debug.ref_test,
+                );
+
+                // The event loop channel allows write requests from the event processor
+                // to be sent to the pty loop and ultimately written to the pty.
+                let loop_tx = event_loop.channel();
+
+                // Kick off the I/O thread.
+                let io_thread = event_loop.spawn();
+
+                (loop_tx, Some(io_thread))
+            },
+        };
+
+        // Start cursor blinking, in case `Focused` isn't sent on startup.
+        let mut event_queue = Vec::new();
+        if config.cursor.style().blinking {
+            let event =
+                Event::from(EventType::TerminalEvent(TerminalEvent::CursorBlinkingChange(true)));
+            event_queue.push(event.into());
+        }
+
+        Ok(WindowContext {
+            font_size: config.ui_config.font.size(),
+            notifier: Notifier(loop_tx),
+            io_thread,
+            scheduler: Scheduler::new(),
+            display_update_pending: Default::default(),
+            message_buffer: MessageBuffer::new(),
+            permission_requests: VecDeque::new(),
+            last_notification: None,
+            search_state: SearchState::new(),
+            modifiers: Default::default(),
+            received_count: Default::default(),
+            suppress_chars: Default::default(),
+            pressed_key: Default::default(),
+            mouse: Default::default(),
+            dirty: Default::default(),
+            recording: Default::default(),
+            #[cfg(not(windows))]
+            master_fd,
+            #[cfg(not(windows))]
+            shell_pid,
+            event_queue,
+            event_proxy,
+            terminal,
+            display,
+        })
+    }
+
+    /// ID of this terminal context.
+    pub fn id(&self) -> WindowId {
+        self.display.window.window_id()
+    }
+
+    /// Close the window and wait for its PTY to shut down.
+    pub fn close(mut self) {
+        let io_thread = self.io_thread.take();
+
+        // This explicit drop is needed for Windows, ConPTY backend. Otherwise a deadlock can occur.
+        // The cause:
+        //   - Drop for ConPTY will deadlock if the conout pipe has already been dropped.
+        //   - The conout pipe is dropped when the io_thread is joined below (io_thread owns PTY).
+        //   - ConPTY is dropped when the last of the window context and io_thread are dropped,
+        //     because both of them own an Arc<ConPTY>.
+        //
+        // The fix is to ensure that the window context is dropped first. That way, when io_thread
+        // (i.e. PTY) is dropped, it can ensure ConPTY is dropped before the conout pipe in the PTY
+        // drop order.
+        //
+        // FIXME: Change PTY API to enforce the correct drop order with the typesystem.
+        drop(self);
+
+        // Shutdown PTY parser event loop.
+        if let Some(io_thread) = io_thread {
+            io_thread.join().expect("join io thread");
+        }
+    }
+
+    /// Check if the terminal window has keyboard focus.
+    pub fn focused(&self) -> bool {
+        self.terminal.lock().is_focused
+    }
+
+    /// Get the working directory of the terminal.
+    pub fn working_directory(&self) -> Option<PathBuf> {
+        event::working_directory(
+            &self.terminal.lock(),
+            #[cfg(not(windows))]
+            self.master_fd,
+            #[cfg(not(windows))]
+            self.shell_pid,
+        )
+    }
+
+    /// Snapshot of the terminal for restoring it on the next launch.
+    pub fn session(&self) -> Session {
+        let mut session = self.terminal.lock().session();
+        session.working_directory = self.working_directory();
+        session
+    }
+
+    /// Start recording the terminal's output to `path`.
+    pub fn start_recording(&mut self, path: &Path) {
+        // Recordings contain everything typed into the terminal, so keep them private.
+        let mut options = OpenOptions::new();
+        options.write(true).create_new(true);
+        #[cfg(unix)]
+        options.mode(0o600);
+
+        let recorder = options
+            .open(path)
+            .and_then(|file| Recorder::new(BufWriter::new(file), &self.display.size_info));
+
+        match recorder {
+            Ok(recorder) => {
+                info!("Recording terminal to {:?}", path);
+                let _ = self.notifier.0.send(Msg::Record(Some(recorder)));
+                self.recording = true;
+            },
+            Err(err) => error!("Unable to create recording {:?}: {}", path, err),
+        }
+    }
+
+    /// Start recording to a new file in the home directory, or stop the active recording.
+    pub fn toggle_recording(&mut self) {
+        if mem::replace(&mut self.recording, false) {
+            info!("Stopped recording terminal");
+            let _ = self.notifier.0.send(Msg::Record(None));
+            return;
+        }
+
+        let file_name = time::strftime("alacritty-%Y%m%d-%H%M%S.cast", &time::now())
+            .unwrap_or_else(|_| String::from("alacritty.cast"));
+        let directory = dirs::home_dir().unwrap_or_default();
+        self.start_recording(&directory.join(file_name));
+    }
+
+    /// Clear the recording state after the recording was stopped by a write error.
+    pub fn recording_stopped(&mut self) {
+        self.recording = false;
+    }
+
+    /// Queue an event for processing with the next batch of events.
+    pub fn handle_event(&mut self, event: GlutinEvent<'static, Event>) {
+        self.event_queue.push(event);
+    }
+
+    /// Process all queued events and redraw the window if necessary.
+    ///
+    /// The `control_flow` is updated to wake up for the next scheduled timer of this window.
+    pub fn process_events(
+        &mut self,
+        #[cfg_attr(not(target_os = "macos"), allow(unused_variables))]
+        event_loop: &EventLoopWindowTarget<Event>,
+        clipboard: &mut Clipboard,
+        config: &Config,
+        control_flow: &mut ControlFlow,
+        frame_callbacks: bool,
+    ) {
+        // Queue all due timers and wake up for the next pending one.
+        let deadline = self.scheduler.update(&mut self.event_queue);
+        *control_flow = match (*control_flow, deadline) {
+            (ControlFlow::Wait, Some(deadline)) => ControlFlow::WaitUntil(deadline),
+            (ControlFlow::WaitUntil(instant), Some(deadline)) if deadline < instant => {
+                ControlFlow::WaitUntil(deadline)
+            },
+            (control_flow, _) => control_flow,
+        };
+
+        if self.event_queue.is_empty() && !self.dirty && !frame_callbacks {
+            return;
+        }
+        self.dirty = false;
+
+        let terminal = Arc::clone(&self.terminal);
+        let mut terminal = terminal.lock();
+
+        let old_is_searching = self.search_state.regex().is_some();
+
+        let context = ActionContext {
+            terminal: &mut terminal,
+            notifier: &mut self.notifier,
+            mouse: &mut self.mouse,
+            clipboard,
+            size_info: &mut self.display.size_info,
+            received_count: &mut self.received_count,
+            suppress_chars: &mut self.suppress_chars,
+            pressed_key: &mut self.pressed_key,
+            modifiers: &mut self.modifiers,
+            message_buffer: &mut self.message_buffer,
+            permission_requests: &mut self.permission_requests,
+            last_notification: &mut self.last_notification,
+            display_update_pending: &mut self.display_update_pending,
+            window: &mut self.display.window,
+            font_size: &mut self.font_size,
+            config,
+            urls: &self.display.urls,
+            scheduler: &mut self.scheduler,
+            search_state: &mut self.search_state,
+            hint_state: &mut self.display.hint_state,
+            cursor_hidden: &mut self.display.cursor_hidden,
+            event_proxy: &self.event_proxy,
+            #[cfg(not(windows))]
+            master_fd: self.master_fd,
+            #[cfg(not(windows))]
+            shell_pid: self.shell_pid,
+            #[cfg(target_os = "macos")]
+            event_loop,
+        };
+        let mut processor = input::Processor::new(context, &self.display.highlighted_url);
+
+        for event in self.event_queue.drain(..) {
+            processor.handle_event(event);
+        }
+
+        // Process DisplayUpdate events.
+        if self.display_update_pending.dirty {
+            self.submit_display_update(&mut terminal, config, old_is_searching);
+        }
+
+        // Skip rendering on Wayland until we get frame event from compositor.
+        #[cfg(not(any(target_os = "macos", windows)))]
+        if !self.display.is_x11 && !self.display.window.should_draw.load(Ordering::Relaxed) {
+            return;
+        }
+
+        if terminal.dirty {
+            terminal.dirty = false;
+
+            // Request immediate re-draw if visual bell animation is not finished yet.
+            if !terminal.visual_bell.completed() {
+                let event = Event::from(EventType::TerminalEvent(TerminalEvent::Wakeup));
+                self.event_queue.push(event.into());
+
+                *control_flow = ControlFlow::Poll;
+            }
+
+            // Redraw screen.
+            self.display.draw(
+                terminal,
+                &self.message_buffer,
+                config,
+                &self.mouse,
+                self.modifiers,
+                &self.search_state,
+            );
+        }
+    }
+
+    /// Remove all configuration errors from the message bar.
+    pub fn clear_config_logs(&mut self) {
+        if !self.message_buffer.is_empty() {
+            self.message_buffer.remove_target(LOG_TARGET_CONFIG);
+            self.display_update_pending.dirty = true;
+            self.dirty = true;
+        }
+    }
+
+    /// Update the terminal window after the configuration has been reloaded.
+    pub fn update_config(&mut self, old_config: &Config, config: &Config) {
+        let mut terminal = self.terminal.lock();
+        terminal.update_config(config);
+
+        // Reload cursor if its thickness has changed.
+        if (old_config.cursor.thickness() - config.cursor.thickness()).abs() > f32::EPSILON {
+            self.display_update_pending.set_cursor_dirty();
+        }
+
+        if old_config.ui_config.font != config.ui_config.font {
+            // Do not update font size if it has been changed at runtime.
+            if self.font_size == old_config.ui_config.font.size() {
+                self.font_size = config.ui_config.font.size();
+            }
+
+            let font = config.ui_config.font.clone().with_size(self.font_size);
+            self.display_update_pending.set_font(font);
+        }
+
+        // Update display if padding options or the timestamp gutter were changed.
+        let window_config = &old_config.ui_config.window;
+        if window_config.padding(1.) != config.ui_config.window.padding(1.)
+            || window_config.dynamic_padding != config.ui_config.window.dynamic_padding
+            || old_config.ui_config.timestamps.gutter_width()
+                != config.ui_config.timestamps.gutter_width()
+        {
+            self.display_update_pending.dirty = true;
+        }
+
+        // Live title reload.
+        if !config.ui_config.window.dynamic_title
+            || old_config.ui_config.window.title != config.ui_config.window.title
+        {
+            self.display.window.set_title(&config.ui_config.window.title);
+        }
+
+        #[cfg(all(feature = "wayland", not(any(target_os = "macos", windows))))]
+        if !self.display.is_x11 {
+            self.display.window.set_wayland_theme(&config.colors);
+        }
+
+        // Disable shadows for transparent windows on macOS.
+        #[cfg(target_os = "macos")]
+        self.display.window.set_has_shadow(config.ui_config.background_opacity() >= 1.0);
+
+        // Update hint keys.
+        self.display.hint_state.update_alphabet(config.ui_config.hints.alphabet());
+
+        // Update cursor blinking.
+        let blinking = terminal.cursor_style().blinking;
+        let event = TerminalEvent::CursorBlinkingChange(blinking);
+        self.event_queue.push(Event::from(EventType::TerminalEvent(event)).into());
+
+        terminal.dirty = true;
+    }
+
+    /// Write the ref test results to the disk.
+    pub fn write_ref_test_results(&self) {
+        // Dump grid state.
+        let mut grid = self.terminal.lock().grid().clone();
+        grid.initialize_all();
+        grid.truncate();
+
+        let serialized_grid = json::to_string(&grid).expect("serialize grid");
+
+        let serialized_size = json::to_string(&self.display.size_info).expect("serialize size");
+
+        let serialized_config = format!("{{\"history_size\":{}}}", grid.history_size());
+
+        File::create("./grid.json")
+            .and_then(|mut f| f.write_all(serialized_grid.as_bytes()))
+            .expect("write grid.json");
+
+        File::create("./size.json")
+            .and_then(|mut f| f.write_all(serialized_size.as_bytes()))
+            .expect("write size.json");
+
+        File::create("./config.json")
+            .and_then(|mut f| f.write_all(serialized_config.as_bytes()))
+            .expect("write config.json");
+    }
+
+    /// Submit the pending changes to the `Display`.
+    fn submit_display_update(
+        &mut self,
+        terminal: &mut Term<EventProxy>,
+        config: &Config,
+        old_is_searching: bool,
+    ) {
+        // Compute cursor positions before resize.
+        let num_lines = terminal.screen_lines();
+        let cursor_at_bottom = terminal.grid().cursor.point.line + 1 == num_lines;
+        let origin_at_bottom = if terminal.mode().contains(TermMode::VI) {
+            terminal.vi_mode_cursor.point.line == num_lines - 1
+        } else {
+            self.search_state.direction() == Direction::Left
+        };
+
+        let search_active = self.search_state.regex().is_some();
+        self.display.handle_update(
+            terminal,
+            &mut self.notifier,
+            &self.message_buffer,
+            search_active,
+            config,
+            mem::take(&mut self.display_update_pending),
+        );
+
+        // Scroll to make sure search origin is visible and content moves as little as possible.
+        if !old_is_searching && search_active {
+            let display_offset = terminal.grid().display_offset();
+            if display_offset == 0 && cursor_at_bottom && !origin_at_bottom {
+                terminal.scroll_display(Scroll::Delta(1));
+            } else if display_offset != 0 && origin_at_bottom {
+                terminal.scroll_display(Scroll::Delta(-1));
+            }
+        }
+    }
+}
+
+impl Drop for WindowContext {
+    fn drop(&mut self) {
+        // Shutdown the terminal's PTY.
+        let _ = self.notifier.0.send(Msg::Shutdown);
+    }
+}
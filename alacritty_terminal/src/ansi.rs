@@ -1,16 +1,20 @@
 //! ANSI Terminal Stream Parsing.
 
 use std::convert::TryFrom;
-use std::{io, iter, str};
+use std::path::PathBuf;
+use std::{io, iter, mem, str};
 
+use bitflags::bitflags;
 use log::{debug, trace};
 use serde::{Deserialize, Serialize};
 use vte::{Params, ParamsIter};
 
 use alacritty_config_derive::ConfigDeserialize;
 
+use crate::graphics::{kitty, sixel, GraphicData};
 use crate::index::{Column, Line};
 use crate::term::color::Rgb;
+use crate::tty;
 
 /// Parse colors in XParseColor format.
 fn xparse_color(color: &[u8]) -> Option<Rgb> {
@@ -62,6 +66,25 @@ fn parse_legacy_color(color: &[u8]) -> Option<Rgb> {
     })
 }
 
+/// Number of xterm's special colors.
+const SPECIAL_COLOR_COUNT: u8 = 5;
+
+/// Palette index of one of xterm's special colors, as used by `OSC 5` and `OSC 105`.
+///
+/// The special colors for bold, underlined, blinking, reverse and italic text are numbered from
+/// 0 to 4. Only bold text has a separate color, the others are always drawn using the regular
+/// foreground color.
+fn special_color_index(number: u8) -> Option<usize> {
+    match number {
+        0 => Some(NamedColor::BrightForeground as usize),
+        1..=4 => {
+            debug!("Special color {} is not supported", number);
+            None
+        },
+        _ => None,
+    }
+}
+
 fn parse_number(input: &[u8]) -> Option<u8> {
     if input.is_empty() {
         return None;
@@ -81,15 +104,106 @@ fn parse_number(input: &[u8]) -> Option<u8> {
     Some(num)
 }
 
+/// Decode a string of hexadecimal byte values, like the capability names of XTGETTCAP.
+fn parse_hex_string(input: &[u8]) -> Option<String> {
+    let chunks = input.chunks_exact(2);
+    if input.is_empty() || !chunks.remainder().is_empty() {
+        return None;
+    }
+
+    let bytes = chunks
+        .map(|byte| u8::from_str_radix(str::from_utf8(byte).ok()?, 16).ok())
+        .collect::<Option<Vec<u8>>>()?;
+
+    String::from_utf8(bytes).ok()
+}
+
+/// Parse the local path of a `file://host/path` URI.
+///
+/// Paths on any host other than the local machine, identified by `hostname`, are discarded.
+fn parse_file_uri(uri: &[u8], hostname: Option<&str>) -> Option<PathBuf> {
+    if !uri.starts_with(b"file://") {
+        return None;
+    }
+
+    let uri = &uri[7..];
+    let path_start = uri.iter().position(|&b| b == b'/')?;
+    let (host, path) = uri.split_at(path_start);
+
+    let is_local = match host {
+        b"" | b"localhost" => true,
+        host => matches!(hostname, Some(name) if name.as_bytes().eq_ignore_ascii_case(host)),
+    };
+    if !is_local {
+        return None;
+    }
+
+    // Decode percent-encoded bytes.
+    let mut bytes = Vec::with_capacity(path.len());
+    let mut iter = path.iter();
+    while let Some(&byte) = iter.next() {
+        if byte != b'%' {
+            bytes.push(byte);
+            continue;
+        }
+
+        let hex = iter.as_slice().get(..2).and_then(|hex| str::from_utf8(hex).ok())?;
+        bytes.push(u8::from_str_radix(hex, 16).ok()?);
+        iter.nth(1);
+    }
+
+    String::from_utf8(bytes).ok().map(PathBuf::from)
+}
+
 /// The processor wraps a `vte::Parser` to ultimately call methods on a Handler.
 pub struct Processor {
     state: ProcessorState,
     parser: vte::Parser,
 }
 
+/// Maximum length of an APC string.
+const MAX_APC_LENGTH: usize = 1024 * 1024;
+
+/// Maximum length of the data in a DECRQSS or XTGETTCAP request.
+const MAX_REQUEST_LENGTH: usize = 1024;
+
 /// Internal state for VTE processor.
 struct ProcessorState {
     preceding_char: Option<char>,
+
+    /// DCS sequence which is currently receiving data.
+    dcs: Option<Dcs>,
+
+    /// APC string which is currently receiving data.
+    apc: Apc,
+
+    /// Name of the local machine, used to recognize local working directory reports.
+    hostname: Option<String>,
+}
+
+/// APC string recognizer.
+///
+/// APC strings are consumed without dispatching them by `vte`, so they are tracked separately
+/// from the bytes passed to the parser.
+#[derive(Default)]
+struct Apc {
+    /// Previous byte was an escape.
+    escape: bool,
+
+    /// Content of the current APC string.
+    data: Option<Vec<u8>>,
+}
+
+/// DCS sequences with a data string.
+enum Dcs {
+    /// Sixel graphic (DCS P1 ; P2 ; P3 q <data> ST).
+    Sixel(Box<sixel::Parser>),
+
+    /// Request status string, DECRQSS (DCS $ q <setting> ST).
+    RequestSetting(Vec<u8>),
+
+    /// Request terminfo capabilities, XTGETTCAP (DCS + q <hex names> ST).
+    RequestCapabilities(Vec<u8>),
 }
 
 /// Helper type that implements `vte::Perform`.
@@ -112,11 +226,60 @@ impl<'a, H: Handler + 'a, W: io::Write> Performer<'a, H, W> {
     ) -> Performer<'b, H, W> {
         Performer { state, handler, writer }
     }
+
+    /// Collect the content of APC strings.
+    #[inline]
+    fn advance_apc(&mut self, byte: u8) {
+        let apc = &mut self.state.apc;
+        let escape = mem::replace(&mut apc.escape, byte == C0::ESC);
+
+        // Every escape ends the current string, with `ESC \` being the string terminator.
+        if escape {
+            let data = apc.data.take();
+            if byte == b'_' {
+                apc.data = Some(Vec::new());
+            } else if let (Some(data), b'\\') = (data, byte) {
+                self.apc_dispatch(&data);
+            }
+            return;
+        }
+
+        match &mut apc.data {
+            Some(_) if byte == C0::CAN || byte == C0::SUB => apc.data = None,
+            Some(data) if byte != C0::ESC => {
+                if data.len() < MAX_APC_LENGTH {
+                    data.push(byte);
+                } else {
+                    debug!("Ignoring APC string exceeding {} bytes", MAX_APC_LENGTH);
+                    apc.data = None;
+                }
+            },
+            _ => (),
+        }
+    }
+
+    /// Dispatch a complete APC string.
+    #[inline]
+    fn apc_dispatch(&mut self, data: &[u8]) {
+        match data {
+            [b'G', command @ ..] => match kitty::Command::parse(command) {
+                Some(command) => self.handler.kitty_graphics(self.writer, command),
+                None => debug!("Invalid kitty graphics command: {:?}", str::from_utf8(command)),
+            },
+            _ => debug!("[unhandled apc] data={:?}", str::from_utf8(data)),
+        }
+    }
 }
 
 impl Default for Processor {
     fn default() -> Processor {
-        Processor { state: ProcessorState { preceding_char: None }, parser: vte::Parser::new() }
+        let state = ProcessorState {
+            preceding_char: None,
+            dcs: None,
+            apc: Apc::default(),
+            hostname: tty::hostname(),
+        };
+        Processor { state, parser: vte::Parser::new() }
     }
 }
 
@@ -133,6 +296,7 @@ impl Processor {
     {
         let mut performer = Performer::new(&mut self.state, handler, writer);
         self.parser.advance(&mut performer, byte);
+        performer.advance_apc(byte);
     }
 }
 
@@ -284,6 +448,14 @@ pub trait Handler {
     /// DECSTBM - Set the terminal scrolling region.
     fn set_scrolling_region(&mut self, _top: usize, _bottom: Option<usize>) {}
 
+    /// DECSLRM - Set the left and right margins.
+    ///
+    /// This shares its escape with saving the cursor position, so it should only take effect
+    /// while DECLRMM is set.
+    fn set_left_right_margins(&mut self, _left: usize, _right: Option<usize>) {
+        self.save_cursor_position();
+    }
+
     /// DECKPAM - Set keypad to applications mode (ESCape instead of digits).
     fn set_keypad_application_mode(&mut self) {}
 
@@ -296,6 +468,11 @@ pub trait Handler {
     /// shift out and locking shift depending on the set being activated.
     fn set_active_charset(&mut self, _: CharsetIndex) {}
 
+    /// Invoke G2 or G3 in the GL area for the next character only.
+    ///
+    /// Also referred to as single shift, the active charset is unaffected.
+    fn set_single_shift(&mut self, _: CharsetIndex) {}
+
     /// Assign a graphic character set to G0, G1, G2 or G3.
     ///
     /// 'Designate' a graphic character set as one of G0 to G3, so that it can
@@ -305,8 +482,10 @@ pub trait Handler {
     /// Set an indexed color value.
     fn set_color(&mut self, _: usize, _: Rgb) {}
 
-    /// Write a foreground/background color escape sequence with the current color.
-    fn dynamic_color_sequence<W: io::Write>(&mut self, _: &mut W, _: u8, _: usize, _: &str) {}
+    /// Write a color escape sequence with the current color.
+    ///
+    /// The prefix contains the OSC parameters identifying the color, like `10` or `4;1`.
+    fn dynamic_color_sequence<W: io::Write>(&mut self, _: &mut W, _: &str, _: usize, _: &str) {}
 
     /// Reset an indexed color to original value.
     fn reset_color(&mut self, _: usize) {}
@@ -320,6 +499,9 @@ pub trait Handler {
     /// Run the decaln routine.
     fn decaln(&mut self) {}
 
+    /// Set the size of the characters in the cursor line, DECSWL, DECDWL and DECDHL.
+    fn set_line_size(&mut self, _size: LineSize) {}
+
     /// Push a title onto the stack.
     fn push_title(&mut self) {}
 
@@ -331,6 +513,74 @@ pub trait Handler {
 
     /// Report text area size in characters.
     fn text_area_size_chars<W: io::Write>(&mut self, _: &mut W) {}
+
+    /// Report the window title.
+    fn report_title<W: io::Write>(&mut self, _: &mut W) {}
+
+    /// Show a desktop notification.
+    fn desktop_notification(&mut self, _title: Option<String>, _body: String) {}
+
+    /// Set hyperlink for all following cells, `None` terminates the active hyperlink.
+    fn set_hyperlink(&mut self, _: Option<Hyperlink>) {}
+
+    /// Mark the cursor's line with a shell integration mark.
+    fn set_prompt_mark(&mut self, _: PromptMark) {}
+
+    /// OSC 7 to report the shell's working directory.
+    fn set_working_directory(&mut self, _: PathBuf) {}
+
+    /// Push keyboard modes onto the kitty keyboard protocol stack.
+    fn push_keyboard_mode(&mut self, _: KeyboardModes) {}
+
+    /// Pop the specified number of entries from the kitty keyboard protocol stack.
+    fn pop_keyboard_modes(&mut self, _: u16) {}
+
+    /// Modify the active kitty keyboard protocol modes.
+    fn set_keyboard_mode(&mut self, _: KeyboardModes, _: KeyboardModesApplyBehavior) {}
+
+    /// Report the active kitty keyboard protocol modes.
+    fn report_keyboard_mode<W: io::Write>(&mut self, _: &mut W) {}
+
+    /// Insert a graphic at the cursor position.
+    fn insert_graphic(&mut self, _: GraphicData) {}
+
+    /// Handle a kitty graphics protocol command.
+    fn kitty_graphics<W: io::Write>(&mut self, _: &mut W, _: kitty::Command) {}
+
+    /// Report whether a mode is set, DECRQM.
+    fn report_mode<W: io::Write>(&mut self, _: &mut W, _: Mode) {}
+
+    /// Report the current value of a setting, DECRQSS.
+    fn report_setting<W: io::Write>(&mut self, _: &mut W, _: Setting) {}
+
+    /// Report the value of a terminfo capability, XTGETTCAP.
+    fn report_capability<W: io::Write>(&mut self, _: &mut W, _name: &str) {}
+
+    /// Report the terminal name and version, XTVERSION.
+    fn report_version<W: io::Write>(&mut self, _: &mut W) {}
+
+    /// Reply to a query which is not supported.
+    fn report_unsupported<W: io::Write>(&mut self, _: &mut W, _report: String) {}
+
+    /// Copy a rectangular area to a new position, DECCRA.
+    fn copy_rectangle(&mut self, _source: Rectangle, _top: usize, _left: usize) {}
+
+    /// Fill a rectangular area with a character, DECFRA.
+    fn fill_rectangle(&mut self, _c: char, _rect: Rectangle) {}
+
+    /// Erase a rectangular area, DECERA and DECSERA.
+    ///
+    /// Selective erase only clears the characters and leaves their attributes untouched.
+    fn erase_rectangle(&mut self, _rect: Rectangle, _selective: bool) {}
+
+    /// Change the attributes in a rectangular area, DECCARA.
+    fn set_rectangle_attributes(&mut self, _rect: Rectangle, _attrs: &[Attr]) {}
+
+    /// Reverse the attributes in a rectangular area, DECRARA.
+    fn reverse_rectangle_attributes(&mut self, _rect: Rectangle, _attrs: &[Attr]) {}
+
+    /// Report the checksum of a rectangular area, DECRQCRA.
+    fn report_rectangle_checksum<W: io::Write>(&mut self, _: &mut W, _id: u16, _: Rectangle) {}
 }
 
 /// Terminal cursor configuration.
@@ -397,6 +647,10 @@ pub enum Mode {
     LineWrap = 7,
     /// ?12
     BlinkingCursor = 12,
+    /// ?69
+    ///
+    /// DECLRMM; enables setting left and right margins with DECSLRM.
+    LeftRightMargin = 69,
     /// 20
     ///
     /// NB This is actually a private mode. We should consider adding a second
@@ -424,6 +678,10 @@ pub enum Mode {
     SwapScreenAndSetRestoreCursor = 1049,
     /// ?2004
     BracketedPaste = 2004,
+    /// ?2026
+    ///
+    /// Synchronized updates; rendering is held back until the mode is reset.
+    SyncUpdate = 2026,
 }
 
 impl Mode {
@@ -445,6 +703,7 @@ impl Mode {
                 7 => Mode::LineWrap,
                 12 => Mode::BlinkingCursor,
                 25 => Mode::ShowCursor,
+                69 => Mode::LeftRightMargin,
                 1000 => Mode::ReportMouseClicks,
                 1002 => Mode::ReportCellMouseMotion,
                 1003 => Mode::ReportAllMouseMotion,
@@ -455,6 +714,7 @@ impl Mode {
                 1042 => Mode::UrgencyHints,
                 1049 => Mode::SwapScreenAndSetRestoreCursor,
                 2004 => Mode::BracketedPaste,
+                2026 => Mode::SyncUpdate,
                 _ => {
                     trace!("[unimplemented] primitive mode: {}", num);
                     return None;
@@ -470,6 +730,25 @@ impl Mode {
     }
 }
 
+/// Size of the characters in a line.
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq, Eq)]
+pub enum LineSize {
+    /// Single width and height, DECSWL.
+    Normal,
+    /// Double width, DECDWL.
+    DoubleWidth,
+    /// Top half of double width and height characters, DECDHL.
+    DoubleHeightTop,
+    /// Bottom half of double width and height characters, DECDHL.
+    DoubleHeightBottom,
+}
+
+impl Default for LineSize {
+    fn default() -> LineSize {
+        LineSize::Normal
+    }
+}
+
 /// Mode for clearing line.
 ///
 /// Relative to cursor.
@@ -571,6 +850,10 @@ pub enum NamedColor {
     BrightForeground,
     /// Dim foreground.
     DimForeground,
+    /// Foreground of selected text.
+    SelectionForeground,
+    /// Background of selected text.
+    SelectionBackground,
 }
 
 impl NamedColor {
@@ -630,8 +913,33 @@ pub enum Color {
     Indexed(u8),
 }
 
+/// Rectangular area used by the VT420 rectangle operations.
+///
+/// Coordinates are inclusive and one-based, like the escape parameters. Omitted bottom and right
+/// edges default to the last line and column.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Rectangle {
+    pub top: usize,
+    pub left: usize,
+    pub bottom: Option<usize>,
+    pub right: Option<usize>,
+}
+
+/// Settings which can be requested using DECRQSS.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Setting {
+    /// Character attributes, SGR (`m`).
+    Attributes,
+    /// Top and bottom margins, DECSTBM (`r`).
+    ScrollingRegion,
+    /// Left and right margins, DECSLRM (`s`).
+    LeftRightMargins,
+    /// Cursor style, DECSCUSR (` q`).
+    CursorStyle,
+}
+
 /// Terminal character attributes.
-#[derive(Debug, Eq, PartialEq)]
+#[derive(Debug, Clone, Eq, PartialEq)]
 pub enum Attr {
     /// Clear all special abilities.
     Reset,
@@ -645,6 +953,12 @@ pub enum Attr {
     Underline,
     /// Underlined twice.
     DoubleUnderline,
+    /// Wavy underline.
+    Undercurl,
+    /// Dotted underline.
+    DottedUnderline,
+    /// Dashed underline.
+    DashedUnderline,
     /// Blink cursor slowly.
     BlinkSlow,
     /// Blink cursor fast.
@@ -675,6 +989,59 @@ pub enum Attr {
     Foreground(Color),
     /// Set indexed background color.
     Background(Color),
+    /// Set underline color, `None` uses the foreground color.
+    UnderlineColor(Option<Color>),
+}
+
+/// OSC 8 hyperlink.
+#[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq, Hash)]
+pub struct Hyperlink {
+    /// Identifier used to group cells which are not adjacent to each other.
+    pub id: Option<String>,
+
+    /// Target of the hyperlink.
+    pub uri: String,
+}
+
+/// OSC 133 shell integration mark.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum PromptMark {
+    /// Start of the shell prompt.
+    PromptStart,
+    /// End of the prompt and start of the command typed by the user.
+    CommandStart,
+    /// End of the command and start of its output.
+    OutputStart,
+    /// End of the command's output.
+    CommandEnd,
+}
+
+bitflags! {
+    /// Progressive enhancement flags of the kitty keyboard protocol.
+    pub struct KeyboardModes: u8 {
+        const NO_MODE                 = 0b0000_0000;
+        /// Report keys which are ambiguous in the legacy encoding using `CSI u`.
+        const DISAMBIGUATE_ESC_CODES  = 0b0000_0001;
+        /// Report key repeat and release events.
+        const REPORT_EVENT_TYPES      = 0b0000_0010;
+        /// Report shifted and base layout variants of keys.
+        const REPORT_ALTERNATE_KEYS   = 0b0000_0100;
+        /// Report all keys as escape sequences, including the ones generating text.
+        const REPORT_ALL_KEYS_AS_ESC  = 0b0000_1000;
+        /// Report the text generated by a key together with its escape sequence.
+        const REPORT_ASSOCIATED_TEXT  = 0b0001_0000;
+    }
+}
+
+/// How `CSI = flags ; mode u` applies its flags to the active keyboard modes.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum KeyboardModesApplyBehavior {
+    /// Replace all active modes.
+    Replace,
+    /// Enable the specified modes.
+    Union,
+    /// Disable the specified modes.
+    Difference,
 }
 
 /// Identifiers which can be assigned to a graphic character set.
@@ -698,6 +1065,20 @@ impl Default for CharsetIndex {
 pub enum StandardCharset {
     Ascii,
     SpecialCharacterAndLineDrawing,
+    DecSupplementalGraphics,
+    DecTechnical,
+    British,
+    Dutch,
+    Finnish,
+    French,
+    FrenchCanadian,
+    German,
+    Italian,
+    NorwegianDanish,
+    Portuguese,
+    Spanish,
+    Swedish,
+    Swiss,
 }
 
 impl Default for StandardCharset {
@@ -747,6 +1128,241 @@ impl StandardCharset {
                 '~' => '·',
                 _ => c,
             },
+            StandardCharset::DecSupplementalGraphics => match c {
+                '(' => '¤',
+                'W' => 'Œ',
+                ']' => 'Ÿ',
+                'w' => 'œ',
+                '}' => 'ÿ',
+                // Everything else matches the upper half of ISO Latin-1.
+                '!'..='~' => char::from(c as u8 + 0x80),
+                _ => c,
+            },
+            StandardCharset::DecTechnical => match c {
+                '!' => '⎷',
+                '"' => '┌',
+                '#' => '─',
+                '$' => '⌠',
+                '%' => '⌡',
+                '&' => '│',
+                '\'' => '⎡',
+                '(' => '⎣',
+                ')' => '⎤',
+                '*' => '⎦',
+                '+' => '⎧',
+                ',' => '⎩',
+                '-' => '⎫',
+                '.' => '⎭',
+                '/' => '⎨',
+                '0' => '⎬',
+                '1' => '⎲',
+                '2' => '⎳',
+                '3' => '╲',
+                '4' => '╱',
+                '5' => '⌝',
+                '6' => '⌟',
+                '<' => '≤',
+                '=' => '≠',
+                '>' => '≥',
+                '?' => '∫',
+                '@' => '∴',
+                'A' => '∝',
+                'B' => '∞',
+                'C' => '÷',
+                'D' => 'Δ',
+                'E' => '∇',
+                'F' => 'Φ',
+                'G' => 'Γ',
+                'H' => '∼',
+                'I' => '≃',
+                'J' => 'Θ',
+                'K' => '×',
+                'L' => 'Λ',
+                'M' => '⇔',
+                'N' => '⇒',
+                'O' => '≡',
+                'P' => 'Π',
+                'Q' => 'Ψ',
+                'S' => 'Σ',
+                'V' => '√',
+                'W' => 'Ω',
+                'X' => 'Ξ',
+                'Y' => 'Υ',
+                'Z' => '⊂',
+                '[' => '⊃',
+                '\\' => '∩',
+                ']' => '∪',
+                '^' => '∧',
+                '_' => '∨',
+                '`' => '¬',
+                'a' => 'α',
+                'b' => 'β',
+                'c' => 'χ',
+                'd' => 'δ',
+                'e' => 'ε',
+                'f' => 'φ',
+                'g' => 'γ',
+                'h' => 'η',
+                'i' => 'ι',
+                'j' => 'θ',
+                'k' => 'κ',
+                'l' => 'λ',
+                'n' => 'ν',
+                'o' => '∂',
+                'p' => 'π',
+                'q' => 'ψ',
+                'r' => 'ρ',
+                's' => 'σ',
+                't' => 'τ',
+                'v' => 'ƒ',
+                'w' => 'ω',
+                'x' => 'ξ',
+                'y' => 'υ',
+                'z' => 'ζ',
+                '{' => '←',
+                '|' => '↑',
+                '}' => '→',
+                '~' => '↓',
+                _ => c,
+            },
+            StandardCharset::British => match c {
+                '#' => '£',
+                _ => c,
+            },
+            StandardCharset::Dutch => match c {
+                '#' => '£',
+                '@' => '¾',
+                '[' => 'ĳ',
+                '\\' => '½',
+                ']' => '|',
+                '{' => '¨',
+                '|' => 'ƒ',
+                '}' => '¼',
+                '~' => '´',
+                _ => c,
+            },
+            StandardCharset::Finnish => match c {
+                '[' => 'Ä',
+                '\\' => 'Ö',
+                ']' => 'Å',
+                '^' => 'Ü',
+                '`' => 'é',
+                '{' => 'ä',
+                '|' => 'ö',
+                '}' => 'å',
+                '~' => 'ü',
+                _ => c,
+            },
+            StandardCharset::French => match c {
+                '#' => '£',
+                '@' => 'à',
+                '[' => '°',
+                '\\' => 'ç',
+                ']' => '§',
+                '{' => 'é',
+                '|' => 'ù',
+                '}' => 'è',
+                '~' => '¨',
+                _ => c,
+            },
+            StandardCharset::FrenchCanadian => match c {
+                '@' => 'à',
+                '[' => 'â',
+                '\\' => 'ç',
+                ']' => 'ê',
+                '^' => 'î',
+                '`' => 'ô',
+                '{' => 'é',
+                '|' => 'ù',
+                '}' => 'è',
+                '~' => 'û',
+                _ => c,
+            },
+            StandardCharset::German => match c {
+                '@' => '§',
+                '[' => 'Ä',
+                '\\' => 'Ö',
+                ']' => 'Ü',
+                '{' => 'ä',
+                '|' => 'ö',
+                '}' => 'ü',
+                '~' => 'ß',
+                _ => c,
+            },
+            StandardCharset::Italian => match c {
+                '#' => '£',
+                '@' => '§',
+                '[' => '°',
+                '\\' => 'ç',
+                ']' => 'é',
+                '`' => 'ù',
+                '{' => 'à',
+                '|' => 'ò',
+                '}' => 'è',
+                '~' => 'ì',
+                _ => c,
+            },
+            StandardCharset::NorwegianDanish => match c {
+                '@' => 'Ä',
+                '[' => 'Æ',
+                '\\' => 'Ø',
+                ']' => 'Å',
+                '^' => 'Ü',
+                '`' => 'ä',
+                '{' => 'æ',
+                '|' => 'ø',
+                '}' => 'å',
+                '~' => 'ü',
+                _ => c,
+            },
+            StandardCharset::Portuguese => match c {
+                '[' => 'Ã',
+                '\\' => 'Ç',
+                ']' => 'Õ',
+                '{' => 'ã',
+                '|' => 'ç',
+                '}' => 'õ',
+                _ => c,
+            },
+            StandardCharset::Spanish => match c {
+                '#' => '£',
+                '@' => '§',
+                '[' => '¡',
+                '\\' => 'Ñ',
+                ']' => '¿',
+                '{' => '°',
+                '|' => 'ñ',
+                '}' => 'ç',
+                _ => c,
+            },
+            StandardCharset::Swedish => match c {
+                '@' => 'É',
+                '[' => 'Ä',
+                '\\' => 'Ö',
+                ']' => 'Å',
+                '^' => 'Ü',
+                '`' => 'é',
+                '{' => 'ä',
+                '|' => 'ö',
+                '}' => 'å',
+                '~' => 'ü',
+                _ => c,
+            },
+            StandardCharset::Swiss => match c {
+                '#' => 'ù',
+                '@' => 'à',
+                '[' => 'é',
+                '\\' => 'ç',
+                ']' => 'ê',
+                '^' => 'î',
+                '_' => 'è',
+                '`' => 'ô',
+                '{' => 'ä',
+                '|' => 'ö',
+                '}' => 'ü',
+                '~' => 'û',
+                _ => c,
+            },
         }
     }
 }
@@ -778,21 +1394,85 @@ where
     }
 
     #[inline]
-    fn hook(&mut self, params: &Params, intermediates: &[u8], ignore: bool, _c: char) {
-        debug!(
-            "[unhandled hook] params={:?}, ints: {:?}, ignore: {:?}",
-            params, intermediates, ignore
-        );
+    fn hook(&mut self, params: &Params, intermediates: &[u8], ignore: bool, action: char) {
+        match (action, intermediates) {
+            // Begin/end synchronized update (DCS = 1 s ST / DCS = 2 s ST).
+            ('s', [b'=']) if !ignore => match params.iter().next() {
+                Some([1]) => self.handler.set_mode(Mode::SyncUpdate),
+                Some([2]) => self.handler.unset_mode(Mode::SyncUpdate),
+                _ => debug!("[unhandled hook] params={:?}, ints: {:?}", params, intermediates),
+            },
+            // Begin sixel graphic, with a P2 of 1 making unset pixels transparent.
+            ('q', []) if !ignore => {
+                let transparent_background = matches!(params.iter().nth(1), Some([1]));
+                let parser = sixel::Parser::new(transparent_background);
+                self.state.dcs = Some(Dcs::Sixel(Box::new(parser)));
+            },
+            ('q', [b'$']) if !ignore => self.state.dcs = Some(Dcs::RequestSetting(Vec::new())),
+            ('q', [b'+']) if !ignore => {
+                self.state.dcs = Some(Dcs::RequestCapabilities(Vec::new()));
+            },
+            _ => debug!(
+                "[unhandled hook] params={:?}, ints: {:?}, ignore: {:?}, action: {:?}",
+                params, intermediates, ignore, action
+            ),
+        }
     }
 
     #[inline]
     fn put(&mut self, byte: u8) {
-        debug!("[unhandled put] byte={:?}", byte);
+        match &mut self.state.dcs {
+            Some(Dcs::Sixel(parser)) => parser.put(byte),
+            Some(Dcs::RequestSetting(data)) | Some(Dcs::RequestCapabilities(data)) => {
+                if data.len() < MAX_REQUEST_LENGTH {
+                    data.push(byte);
+                }
+            },
+            None => debug!("[unhandled put] byte={:?}", byte),
+        }
     }
 
     #[inline]
     fn unhook(&mut self) {
-        debug!("[unhandled unhook]");
+        match self.state.dcs.take() {
+            Some(Dcs::Sixel(parser)) => match parser.finish() {
+                Some(graphic) => self.handler.insert_graphic(graphic),
+                None => debug!("Ignoring empty sixel graphic"),
+            },
+            Some(Dcs::RequestSetting(data)) => {
+                let setting = match data.as_slice() {
+                    b"m" => Setting::Attributes,
+                    b"r" => Setting::ScrollingRegion,
+                    b"s" => Setting::LeftRightMargins,
+                    b" q" => Setting::CursorStyle,
+                    _ => {
+                        debug!("Unsupported DECRQSS setting: {:?}", str::from_utf8(&data));
+                        let report = String::from("\x1bP0$r\x1b\\");
+                        self.handler.report_unsupported(self.writer, report);
+                        return;
+                    },
+                };
+
+                self.handler.report_setting(self.writer, setting);
+            },
+            Some(Dcs::RequestCapabilities(data)) => {
+                for hex_name in data.split(|&b| b == b';') {
+                    match parse_hex_string(hex_name) {
+                        Some(name) => self.handler.report_capability(self.writer, &name),
+                        // Invalid names are never echoed, since they could inject input.
+                        None => {
+                            debug!(
+                                "Invalid XTGETTCAP name: {:?}",
+                                String::from_utf8_lossy(hex_name)
+                            );
+                            let report = String::from("\x1bP0+r\x1b\\");
+                            self.handler.report_unsupported(self.writer, report);
+                        },
+                    }
+                }
+            },
+            None => debug!("[unhandled unhook]"),
+        }
     }
 
     // TODO replace OSC parsing with parser combinators.
@@ -834,45 +1514,77 @@ where
                 unhandled(params);
             },
 
-            // Set color index.
-            b"4" => {
-                if params.len() > 1 && params.len() % 2 != 0 {
-                    for chunk in params[1..].chunks(2) {
-                        let index = parse_number(chunk[0]);
-                        let color = xparse_color(chunk[1]);
-                        if let (Some(i), Some(c)) = (index, color) {
-                            self.handler.set_color(i as usize, c);
-                            return;
-                        }
+            // Get/set color index or special color.
+            b"4" | b"5" => {
+                if params.len() < 3 || params.len() % 2 == 0 {
+                    return unhandled(params);
+                }
+
+                for chunk in params[1..].chunks(2) {
+                    let number = parse_number(chunk[0]);
+                    let index = match params[0] {
+                        b"4" => number.map(usize::from),
+                        _ => number.and_then(special_color_index),
+                    };
+                    let (number, index) = match (number, index) {
+                        (Some(number), Some(index)) => (number, index),
+                        _ => {
+                            unhandled(params);
+                            continue;
+                        },
+                    };
+
+                    if let Some(color) = xparse_color(chunk[1]) {
+                        self.handler.set_color(index, color);
+                    } else if chunk[1] == b"?" {
+                        let prefix = format!("{};{}", params[0][0] as char, number);
+                        self.handler.dynamic_color_sequence(writer, &prefix, index, terminator);
+                    } else {
+                        unhandled(params);
                     }
                 }
-                unhandled(params);
             },
 
-            // Get/set Foreground, Background, Cursor colors.
-            b"10" | b"11" | b"12" => {
+            // Report working directory.
+            b"7" => {
+                // Semicolons are not escaped inside the URI, so it has to be reassembled.
+                let uri = params[1..].join(&b';');
+                match parse_file_uri(&uri, self.state.hostname.as_deref()) {
+                    Some(path) => self.handler.set_working_directory(path),
+                    None => unhandled(params),
+                }
+            },
+
+            // Get/set Foreground, Background, Cursor and Selection colors.
+            b"10" | b"11" | b"12" | b"13" | b"14" | b"15" | b"16" | b"17" | b"18" | b"19" => {
                 if params.len() >= 2 {
                     if let Some(mut dynamic_code) = parse_number(params[0]) {
                         for param in &params[1..] {
-                            // 10 is the first dynamic color, also the foreground.
-                            let offset = dynamic_code as usize - 10;
-                            let index = NamedColor::Foreground as usize + offset;
-
-                            // End of setting dynamic colors.
-                            if index > NamedColor::Cursor as usize {
-                                unhandled(params);
-                                break;
-                            }
+                            // Each additional parameter applies to the next dynamic color.
+                            let index = match dynamic_code {
+                                10 => NamedColor::Foreground,
+                                11 => NamedColor::Background,
+                                12 => NamedColor::Cursor,
+                                17 => NamedColor::SelectionBackground,
+                                19 => NamedColor::SelectionForeground,
+                                // End of setting dynamic colors.
+                                20..=255 => {
+                                    unhandled(params);
+                                    break;
+                                },
+                                // Mouse and Tektronix colors are not supported.
+                                _ => {
+                                    dynamic_code += 1;
+                                    continue;
+                                },
+                            } as usize;
 
                             if let Some(color) = xparse_color(param) {
                                 self.handler.set_color(index, color);
                             } else if param == b"?" {
-                                self.handler.dynamic_color_sequence(
-                                    writer,
-                                    dynamic_code,
-                                    index,
-                                    terminator,
-                                );
+                                let prefix = dynamic_code.to_string();
+                                self.handler
+                                    .dynamic_color_sequence(writer, &prefix, index, terminator);
                             } else {
                                 unhandled(params);
                             }
@@ -884,6 +1596,72 @@ where
                 unhandled(params);
             },
 
+            // Set or terminate hyperlink.
+            b"8" => {
+                if params.len() < 3 {
+                    return unhandled(params);
+                }
+
+                // Semicolons are not escaped inside the URI, so it has to be reassembled.
+                let uri = match params[2..]
+                    .iter()
+                    .map(|x| str::from_utf8(x))
+                    .collect::<Result<Vec<&str>, _>>()
+                {
+                    Ok(segments) => segments.join(";"),
+                    Err(_) => return unhandled(params),
+                };
+
+                // An empty URI terminates the active hyperlink.
+                if uri.is_empty() {
+                    self.handler.set_hyperlink(None);
+                    return;
+                }
+
+                // Parameters are `key=value` pairs separated by colons, only `id` is defined.
+                let id = params[1]
+                    .split(|&b| b == b':')
+                    .find(|param| param.starts_with(b"id=") && param.len() > 3)
+                    .and_then(|param| str::from_utf8(&param[3..]).ok())
+                    .map(String::from);
+
+                self.handler.set_hyperlink(Some(Hyperlink { id, uri }));
+            },
+
+            // Desktop notification.
+            b"9" => {
+                // ConEmu uses numeric subcommands for progress and other extensions.
+                if params.len() < 2 || (params.len() > 2 && parse_number(params[1]).is_some()) {
+                    return unhandled(params);
+                }
+
+                let body = join_params(&params[1..]);
+                self.handler.desktop_notification(None, body);
+            },
+
+            // Shell integration prompt marks.
+            b"133" => {
+                let mark = match params.get(1).and_then(|param| param.first()) {
+                    Some(b'A') => PromptMark::PromptStart,
+                    Some(b'B') => PromptMark::CommandStart,
+                    Some(b'C') => PromptMark::OutputStart,
+                    Some(b'D') => PromptMark::CommandEnd,
+                    _ => return unhandled(params),
+                };
+                self.handler.set_prompt_mark(mark);
+            },
+
+            // Desktop notification with title.
+            b"777" => {
+                if params.len() < 3 || params[1] != b"notify" {
+                    return unhandled(params);
+                }
+
+                let title = String::from_utf8_lossy(params[2]).into_owned();
+                let body = join_params(&params[3..]);
+                self.handler.desktop_notification(Some(title), body);
+            },
+
             // Set cursor style.
             b"50" => {
                 if params.len() >= 2
@@ -940,9 +1718,34 @@ where
             // Reset background color.
             b"111" => self.handler.reset_color(NamedColor::Background as usize),
 
+            // Reset special colors.
+            b"105" => {
+                // Reset all special colors when no parameters are given.
+                if params.len() == 1 {
+                    for index in (0..SPECIAL_COLOR_COUNT).filter_map(special_color_index) {
+                        self.handler.reset_color(index);
+                    }
+                    return;
+                }
+
+                // Reset special colors given as parameters.
+                for param in &params[1..] {
+                    match parse_number(param).and_then(special_color_index) {
+                        Some(index) => self.handler.reset_color(index),
+                        None => unhandled(params),
+                    }
+                }
+            },
+
             // Reset text cursor color.
             b"112" => self.handler.reset_color(NamedColor::Cursor as usize),
 
+            // Reset selection background color.
+            b"117" => self.handler.reset_color(NamedColor::SelectionBackground as usize),
+
+            // Reset selection foreground color.
+            b"119" => self.handler.reset_color(NamedColor::SelectionForeground as usize),
+
             _ => unhandled(params),
         }
     }
@@ -965,7 +1768,8 @@ where
             }};
         }
 
-        if has_ignored_intermediates || intermediates.len() > 1 {
+        // DECRQM for private modes is the only sequence with multiple intermediates.
+        if has_ignored_intermediates || (intermediates.len() > 1 && intermediates != b"?$") {
             unhandled!();
             return;
         }
@@ -1078,6 +1882,18 @@ where
                 }
             },
             ('n', None) => handler.device_status(writer, next_param_or(0) as usize),
+            ('p', Some(b'$')) | ('p', Some(b'?')) if intermediates.ends_with(b"$") => {
+                let private = intermediates[0] == b'?';
+                let num = next_param_or(0);
+                match Mode::from_primitive(intermediates.first().filter(|_| private), num) {
+                    Some(mode) => handler.report_mode(writer, mode),
+                    None => {
+                        let prefix = if private { "?" } else { "" };
+                        let report = format!("\x1b[{}{};0$y", prefix, num);
+                        handler.report_unsupported(writer, report);
+                    },
+                }
+            },
             ('P', None) => handler.delete_chars(Column(next_param_or(1) as usize)),
             ('q', Some(b' ')) => {
                 // DECSCUSR (CSI Ps SP q) -- Set Cursor Style.
@@ -1097,6 +1913,7 @@ where
 
                 handler.set_cursor_style(cursor_style);
             },
+            ('q', Some(b'>')) if next_param_or(0) == 0 => handler.report_version(writer),
             ('r', None) => {
                 let top = next_param_or(1) as usize;
                 let bottom =
@@ -1104,19 +1921,85 @@ where
 
                 handler.set_scrolling_region(top, bottom);
             },
+            ('r', Some(b'$')) => {
+                let rect = parse_rectangle(&mut next_param_or);
+                handler.set_rectangle_attributes(rect, &rectangle_attributes(&mut params_iter));
+            },
             ('S', None) => handler.scroll_up(Line(next_param_or(1) as usize)),
-            ('s', None) => handler.save_cursor_position(),
+            ('s', None) => {
+                let left = next_param_or(1) as usize;
+                let right =
+                    params_iter.next().map(|param| param[0] as usize).filter(|&param| param != 0);
+
+                handler.set_left_right_margins(left, right);
+            },
             ('T', None) => handler.scroll_down(Line(next_param_or(1) as usize)),
             ('t', None) => match next_param_or(1) as usize {
                 14 => handler.text_area_size_pixels(writer),
                 18 => handler.text_area_size_chars(writer),
+                21 => handler.report_title(writer),
                 22 => handler.push_title(),
                 23 => handler.pop_title(),
                 _ => unhandled!(),
             },
+            ('t', Some(b'$')) => {
+                let rect = parse_rectangle(&mut next_param_or);
+                handler.reverse_rectangle_attributes(rect, &rectangle_attributes(&mut params_iter));
+            },
             ('u', None) => handler.restore_cursor_position(),
+            ('u', Some(b'?')) => handler.report_keyboard_mode(writer),
+            ('u', Some(b'>')) => {
+                let mode = KeyboardModes::from_bits_truncate(next_param_or(0) as u8);
+                handler.push_keyboard_mode(mode);
+            },
+            ('u', Some(b'<')) => handler.pop_keyboard_modes(next_param_or(1)),
+            ('u', Some(b'=')) => {
+                let mode = KeyboardModes::from_bits_truncate(next_param_or(0) as u8);
+                let behavior = match next_param_or(1) {
+                    1 => KeyboardModesApplyBehavior::Replace,
+                    2 => KeyboardModesApplyBehavior::Union,
+                    3 => KeyboardModesApplyBehavior::Difference,
+                    _ => {
+                        unhandled!();
+                        return;
+                    },
+                };
+
+                handler.set_keyboard_mode(mode, behavior);
+            },
+            ('v', Some(b'$')) => {
+                let source = parse_rectangle(&mut next_param_or);
+                let _source_page = next_param_or(1);
+                let top = next_param_or(1) as usize;
+                let left = next_param_or(1) as usize;
+
+                handler.copy_rectangle(source, top, left);
+            },
             ('X', None) => handler.erase_chars(Column(next_param_or(1) as usize)),
+            ('x', Some(b'$')) => {
+                let c = next_param_or(0);
+                let rect = parse_rectangle(&mut next_param_or);
+
+                // Only printable characters from the GL and GR sets can be used.
+                match char::from_u32(c.into()) {
+                    Some(c @ ' '..='~') | Some(c @ '\u{a0}'..='\u{ff}') => {
+                        handler.fill_rectangle(c, rect)
+                    },
+                    _ => unhandled!(),
+                }
+            },
+            ('y', Some(b'*')) => {
+                let id = next_param_or(0);
+                let _page = next_param_or(1);
+                let rect = parse_rectangle(&mut next_param_or);
+
+                handler.report_rectangle_checksum(writer, id, rect);
+            },
             ('Z', None) => handler.move_backward_tabs(next_param_or(1)),
+            ('z', Some(b'$')) => {
+                handler.erase_rectangle(parse_rectangle(&mut next_param_or), false)
+            },
+            ('{', Some(b'$')) => handler.erase_rectangle(parse_rectangle(&mut next_param_or), true),
             _ => unhandled!(),
         }
     }
@@ -1149,24 +2032,59 @@ where
         }
 
         match (byte, intermediates) {
+            (b'A', [_]) => configure_charset!(StandardCharset::British, intermediates),
             (b'B', intermediates) => configure_charset!(StandardCharset::Ascii, intermediates),
+            (b'C', [_]) => configure_charset!(StandardCharset::Finnish, intermediates),
             (b'D', []) => self.handler.linefeed(),
             (b'E', []) => {
                 self.handler.linefeed();
                 self.handler.carriage_return();
             },
+            (b'E', [_]) | (b'`', [_]) => {
+                configure_charset!(StandardCharset::NorwegianDanish, intermediates)
+            },
             (b'H', []) => self.handler.set_horizontal_tabstop(),
+            (b'H', [_]) => configure_charset!(StandardCharset::Swedish, intermediates),
+            (b'K', [_]) => configure_charset!(StandardCharset::German, intermediates),
             (b'M', []) => self.handler.reverse_index(),
+            (b'N', []) => self.handler.set_single_shift(CharsetIndex::G2),
+            (b'O', []) => self.handler.set_single_shift(CharsetIndex::G3),
+            (b'Q', [_]) => {
+                configure_charset!(StandardCharset::FrenchCanadian, intermediates)
+            },
+            (b'R', [_]) | (b'f', [_]) => configure_charset!(StandardCharset::French, intermediates),
+            (b'Y', [_]) => configure_charset!(StandardCharset::Italian, intermediates),
             (b'Z', []) => self.handler.identify_terminal(self.writer, None),
+            (b'Z', [_]) => configure_charset!(StandardCharset::Spanish, intermediates),
             (b'c', []) => self.handler.reset_state(),
+            (b'n', []) => self.handler.set_active_charset(CharsetIndex::G2),
+            (b'o', []) => self.handler.set_active_charset(CharsetIndex::G3),
             (b'0', intermediates) => {
                 configure_charset!(StandardCharset::SpecialCharacterAndLineDrawing, intermediates)
             },
+            (b'3', [b'#']) => self.handler.set_line_size(LineSize::DoubleHeightTop),
+            (b'4', [b'#']) => self.handler.set_line_size(LineSize::DoubleHeightBottom),
+            (b'4', [_]) => configure_charset!(StandardCharset::Dutch, intermediates),
+            (b'5', [b'#']) => self.handler.set_line_size(LineSize::Normal),
+            (b'5', [index, b'%']) => {
+                configure_charset!(StandardCharset::DecSupplementalGraphics, [*index])
+            },
+            (b'5', [_]) => configure_charset!(StandardCharset::Finnish, intermediates),
+            (b'6', [b'#']) => self.handler.set_line_size(LineSize::DoubleWidth),
+            (b'6', [index, b'%']) => configure_charset!(StandardCharset::Portuguese, [*index]),
+            (b'6', [_]) => configure_charset!(StandardCharset::NorwegianDanish, intermediates),
             (b'7', []) => self.handler.save_cursor_position(),
+            (b'7', [_]) => configure_charset!(StandardCharset::Swedish, intermediates),
             (b'8', [b'#']) => self.handler.decaln(),
             (b'8', []) => self.handler.restore_cursor_position(),
+            (b'9', [_]) => configure_charset!(StandardCharset::FrenchCanadian, intermediates),
+            (b'<', [_]) => {
+                configure_charset!(StandardCharset::DecSupplementalGraphics, intermediates)
+            },
             (b'=', []) => self.handler.set_keypad_application_mode(),
+            (b'=', [_]) => configure_charset!(StandardCharset::Swiss, intermediates),
             (b'>', []) => self.handler.unset_keypad_application_mode(),
+            (b'>', [_]) => configure_charset!(StandardCharset::DecTechnical, intermediates),
             // String terminator, do nothing (parser handles as string terminator).
             (b'\\', []) => (),
             _ => unhandled!(),
@@ -1174,6 +2092,46 @@ where
     }
 }
 
+/// Join OSC parameters which were split at unescaped semicolons.
+fn join_params(params: &[&[u8]]) -> String {
+    String::from_utf8_lossy(&params.join(&b';')).into_owned()
+}
+
+/// Parse the top, left, bottom and right edges of a rectangular area.
+fn parse_rectangle<F: FnMut(u16) -> u16>(next_param_or: &mut F) -> Rectangle {
+    let top = next_param_or(1) as usize;
+    let left = next_param_or(1) as usize;
+    let bottom = Some(next_param_or(0) as usize).filter(|&bottom| bottom != 0);
+    let right = Some(next_param_or(0) as usize).filter(|&right| right != 0);
+    Rectangle { top, left, bottom, right }
+}
+
+/// Parse the attributes which can be changed in a rectangular area.
+///
+/// Only the VT420 subset of SGR is allowed, without any parameters meaning `0`.
+fn rectangle_attributes(params: &mut ParamsIter<'_>) -> Vec<Attr> {
+    let mut attrs: Vec<Attr> = params
+        .filter_map(|param| match param[0] {
+            0 => Some(Attr::Reset),
+            1 => Some(Attr::Bold),
+            4 => Some(Attr::Underline),
+            5 => Some(Attr::BlinkSlow),
+            7 => Some(Attr::Reverse),
+            22 => Some(Attr::CancelBold),
+            24 => Some(Attr::CancelUnderline),
+            25 => Some(Attr::CancelBlink),
+            27 => Some(Attr::CancelReverse),
+            _ => None,
+        })
+        .collect();
+
+    if attrs.is_empty() {
+        attrs.push(Attr::Reset);
+    }
+
+    attrs
+}
+
 fn attrs_from_sgr_parameters(params: &mut ParamsIter<'_>) -> Vec<Option<Attr>> {
     let mut attrs = Vec::with_capacity(params.size_hint().0);
 
@@ -1185,6 +2143,9 @@ fn attrs_from_sgr_parameters(params: &mut ParamsIter<'_>) -> Vec<Option<Attr>> {
             [3] => Some(Attr::Italic),
             [4, 0] => Some(Attr::CancelUnderline),
             [4, 2] => Some(Attr::DoubleUnderline),
+            [4, 3] => Some(Attr::Undercurl),
+            [4, 4] => Some(Attr::DottedUnderline),
+            [4, 5] => Some(Attr::DashedUnderline),
             [4, ..] => Some(Attr::Underline),
             [5] => Some(Attr::BlinkSlow),
             [6] => Some(Attr::BlinkFast),
@@ -1239,6 +2200,18 @@ fn attrs_from_sgr_parameters(params: &mut ParamsIter<'_>) -> Vec<Option<Attr>> {
                 parse_sgr_color(&mut iter).map(Attr::Background)
             },
             [49] => Some(Attr::Background(Color::Named(NamedColor::Background))),
+            [58] => {
+                let mut iter = params.map(|param| param[0]);
+                parse_sgr_color(&mut iter).map(|color| Attr::UnderlineColor(Some(color)))
+            },
+            [58, params @ ..] => {
+                let rgb_start = if params.len() > 4 { 2 } else { 1 };
+                let rgb_iter = params[rgb_start..].iter().copied();
+                let mut iter = iter::once(params[0]).chain(rgb_iter);
+
+                parse_sgr_color(&mut iter).map(|color| Attr::UnderlineColor(Some(color)))
+            },
+            [59] => Some(Attr::UnderlineColor(None)),
             [90] => Some(Attr::Foreground(Color::Named(NamedColor::BrightBlack))),
             [91] => Some(Attr::Foreground(Color::Named(NamedColor::BrightRed))),
             [92] => Some(Attr::Foreground(Color::Named(NamedColor::BrightGreen))),
@@ -1353,16 +2326,38 @@ pub mod C0 {
 #[cfg(test)]
 mod tests {
     use super::{
-        parse_number, xparse_color, Attr, CharsetIndex, Color, Handler, Processor, StandardCharset,
+        parse_file_uri, parse_number, xparse_color, Attr, CharsetIndex, Color, Handler, Hyperlink,
+        KeyboardModes, KeyboardModesApplyBehavior, LineSize, Mode, NamedColor, Processor,
+        PromptMark, Rectangle, Setting, StandardCharset,
     };
+    use crate::graphics::kitty;
     use crate::term::color::Rgb;
     use std::io;
+    use std::path::PathBuf;
 
     struct MockHandler {
         index: CharsetIndex,
         charset: StandardCharset,
         attr: Option<Attr>,
         identity_reported: bool,
+        hyperlink: Option<Hyperlink>,
+        sync_update: bool,
+        prompt_marks: Vec<PromptMark>,
+        working_directory: Option<PathBuf>,
+        keyboard_modes: Vec<KeyboardModes>,
+        kitty_graphics: Vec<kitty::Command>,
+        reported_modes: Vec<Mode>,
+        reported_settings: Vec<Setting>,
+        reported_capabilities: Vec<String>,
+        unsupported_reports: Vec<String>,
+        filled_rectangles: Vec<(char, Rectangle)>,
+        rectangle_attributes: Vec<Attr>,
+        line_sizes: Vec<LineSize>,
+        single_shift: Option<CharsetIndex>,
+        notifications: Vec<(Option<String>, String)>,
+        colors: Vec<(usize, Rgb)>,
+        color_queries: Vec<(String, usize)>,
+        reset_colors: Vec<usize>,
     }
 
     impl Handler for MockHandler {
@@ -1379,6 +2374,32 @@ mod tests {
             self.index = index;
         }
 
+        fn set_single_shift(&mut self, index: CharsetIndex) {
+            self.single_shift = Some(index);
+        }
+
+        fn desktop_notification(&mut self, title: Option<String>, body: String) {
+            self.notifications.push((title, body));
+        }
+
+        fn set_color(&mut self, index: usize, color: Rgb) {
+            self.colors.push((index, color));
+        }
+
+        fn dynamic_color_sequence<W: io::Write>(
+            &mut self,
+            _: &mut W,
+            prefix: &str,
+            index: usize,
+            _: &str,
+        ) {
+            self.color_queries.push((prefix.to_owned(), index));
+        }
+
+        fn reset_color(&mut self, index: usize) {
+            self.reset_colors.push(index);
+        }
+
         fn identify_terminal<W: io::Write>(&mut self, _: &mut W, _intermediate: Option<char>) {
             self.identity_reported = true;
         }
@@ -1386,6 +2407,79 @@ mod tests {
         fn reset_state(&mut self) {
             *self = Self::default();
         }
+
+        fn set_hyperlink(&mut self, hyperlink: Option<Hyperlink>) {
+            self.hyperlink = hyperlink;
+        }
+
+        fn push_keyboard_mode(&mut self, mode: KeyboardModes) {
+            self.keyboard_modes.push(mode);
+        }
+
+        fn pop_keyboard_modes(&mut self, count: u16) {
+            let len = self.keyboard_modes.len().saturating_sub(count as usize);
+            self.keyboard_modes.truncate(len);
+        }
+
+        fn set_keyboard_mode(&mut self, mode: KeyboardModes, behavior: KeyboardModesApplyBehavior) {
+            if behavior == KeyboardModesApplyBehavior::Union {
+                if let Some(active_mode) = self.keyboard_modes.last_mut() {
+                    active_mode.insert(mode);
+                }
+            }
+        }
+
+        fn kitty_graphics<W: io::Write>(&mut self, _: &mut W, command: kitty::Command) {
+            self.kitty_graphics.push(command);
+        }
+
+        fn report_mode<W: io::Write>(&mut self, _: &mut W, mode: Mode) {
+            self.reported_modes.push(mode);
+        }
+
+        fn report_setting<W: io::Write>(&mut self, _: &mut W, setting: Setting) {
+            self.reported_settings.push(setting);
+        }
+
+        fn report_capability<W: io::Write>(&mut self, _: &mut W, name: &str) {
+            self.reported_capabilities.push(name.into());
+        }
+
+        fn report_unsupported<W: io::Write>(&mut self, _: &mut W, report: String) {
+            self.unsupported_reports.push(report);
+        }
+
+        fn set_line_size(&mut self, size: LineSize) {
+            self.line_sizes.push(size);
+        }
+
+        fn fill_rectangle(&mut self, c: char, rect: Rectangle) {
+            self.filled_rectangles.push((c, rect));
+        }
+
+        fn set_rectangle_attributes(&mut self, _: Rectangle, attrs: &[Attr]) {
+            self.rectangle_attributes.extend_from_slice(attrs);
+        }
+
+        fn set_mode(&mut self, mode: Mode) {
+            if mode == Mode::SyncUpdate {
+                self.sync_update = true;
+            }
+        }
+
+        fn unset_mode(&mut self, mode: Mode) {
+            if mode == Mode::SyncUpdate {
+                self.sync_update = false;
+            }
+        }
+
+        fn set_prompt_mark(&mut self, mark: PromptMark) {
+            self.prompt_marks.push(mark);
+        }
+
+        fn set_working_directory(&mut self, working_directory: PathBuf) {
+            self.working_directory = Some(working_directory);
+        }
     }
 
     impl Default for MockHandler {
@@ -1395,6 +2489,24 @@ mod tests {
                 charset: StandardCharset::Ascii,
                 attr: None,
                 identity_reported: false,
+                hyperlink: None,
+                sync_update: false,
+                prompt_marks: Vec::new(),
+                working_directory: None,
+                keyboard_modes: Vec::new(),
+                kitty_graphics: Vec::new(),
+                reported_modes: Vec::new(),
+                reported_settings: Vec::new(),
+                reported_capabilities: Vec::new(),
+                unsupported_reports: Vec::new(),
+                filled_rectangles: Vec::new(),
+                rectangle_attributes: Vec::new(),
+                line_sizes: Vec::new(),
+                single_shift: None,
+                notifications: Vec::new(),
+                colors: Vec::new(),
+                color_queries: Vec::new(),
+                reset_colors: Vec::new(),
             }
         }
     }
@@ -1491,6 +2603,183 @@ mod tests {
         assert_eq!(handler.attr, Some(Attr::Foreground(Color::Spec(spec))));
     }
 
+    #[test]
+    fn parse_underline_attrs() {
+        let mut parser = Processor::new();
+        let mut handler = MockHandler::default();
+
+        for byte in b"\x1b[4:3m" {
+            parser.advance(&mut handler, *byte, &mut io::sink());
+        }
+        assert_eq!(handler.attr, Some(Attr::Undercurl));
+
+        for byte in b"\x1b[58:2::1:2:3m" {
+            parser.advance(&mut handler, *byte, &mut io::sink());
+        }
+        let spec = Rgb { r: 1, g: 2, b: 3 };
+        assert_eq!(handler.attr, Some(Attr::UnderlineColor(Some(Color::Spec(spec)))));
+
+        for byte in b"\x1b[58;5;42m" {
+            parser.advance(&mut handler, *byte, &mut io::sink());
+        }
+        assert_eq!(handler.attr, Some(Attr::UnderlineColor(Some(Color::Indexed(42)))));
+
+        for byte in b"\x1b[59m" {
+            parser.advance(&mut handler, *byte, &mut io::sink());
+        }
+        assert_eq!(handler.attr, Some(Attr::UnderlineColor(None)));
+    }
+
+    #[test]
+    fn parse_keyboard_modes() {
+        let mut parser = Processor::new();
+        let mut handler = MockHandler::default();
+
+        for byte in b"\x1b[>1u\x1b[>3u\x1b[=8;2u" {
+            parser.advance(&mut handler, *byte, &mut io::sink());
+        }
+
+        let disambiguate = KeyboardModes::DISAMBIGUATE_ESC_CODES;
+        let all_keys = KeyboardModes::REPORT_ALL_KEYS_AS_ESC;
+        let events = KeyboardModes::REPORT_EVENT_TYPES;
+        assert_eq!(handler.keyboard_modes, vec![disambiguate, disambiguate | events | all_keys]);
+
+        for byte in b"\x1b[<u" {
+            parser.advance(&mut handler, *byte, &mut io::sink());
+        }
+        assert_eq!(handler.keyboard_modes, vec![disambiguate]);
+
+        // Cursor restore must not be affected.
+        for byte in b"\x1b[u" {
+            parser.advance(&mut handler, *byte, &mut io::sink());
+        }
+        assert_eq!(handler.keyboard_modes, vec![disambiguate]);
+    }
+
+    #[test]
+    fn parse_mode_requests() {
+        let mut parser = Processor::new();
+        let mut handler = MockHandler::default();
+        let mut writer = Vec::new();
+
+        for byte in b"\x1b[?2004$p\x1b[4$p\x1b[?9999$p" {
+            parser.advance(&mut handler, *byte, &mut writer);
+        }
+
+        assert_eq!(handler.reported_modes, vec![Mode::BracketedPaste, Mode::Insert]);
+        assert_eq!(handler.unsupported_reports, vec![String::from("\x1b[?9999;0$y")]);
+    }
+
+    #[test]
+    fn parse_setting_requests() {
+        let mut parser = Processor::new();
+        let mut handler = MockHandler::default();
+        let mut writer = Vec::new();
+
+        for byte in b"\x1bP$qm\x1b\\\x1bP$q q\x1b\\\x1bP$qx\x1b\\" {
+            parser.advance(&mut handler, *byte, &mut writer);
+        }
+
+        assert_eq!(handler.reported_settings, vec![Setting::Attributes, Setting::CursorStyle]);
+        assert_eq!(handler.unsupported_reports, vec![String::from("\x1bP0$r\x1b\\")]);
+    }
+
+    #[test]
+    fn parse_capability_requests() {
+        let mut parser = Processor::new();
+        let mut handler = MockHandler::default();
+        let mut writer = Vec::new();
+
+        // Request `TN` and `Co`, followed by an invalid name.
+        for byte in b"\x1bP+q544E;436f;XY\x1b\\" {
+            parser.advance(&mut handler, *byte, &mut writer);
+        }
+
+        assert_eq!(handler.reported_capabilities, vec![String::from("TN"), String::from("Co")]);
+        assert_eq!(handler.unsupported_reports, vec![String::from("\x1bP0+r\x1b\\")]);
+    }
+
+    #[test]
+    fn parse_capability_request_with_control_bytes() {
+        let mut parser = Processor::new();
+        let mut handler = MockHandler::default();
+        let mut writer = Vec::new();
+
+        for byte in b"\x1bP+q\x15touch /tmp/pwned\r\x1b\\" {
+            parser.advance(&mut handler, *byte, &mut writer);
+        }
+
+        assert!(handler.reported_capabilities.is_empty());
+        assert_eq!(handler.unsupported_reports, vec![String::from("\x1bP0+r\x1b\\")]);
+    }
+
+    #[test]
+    fn parse_line_size() {
+        let mut parser = Processor::new();
+        let mut handler = MockHandler::default();
+
+        for byte in b"\x1b#3\x1b#4\x1b#5\x1b#6\x1b#8" {
+            parser.advance(&mut handler, *byte, &mut io::sink());
+        }
+
+        assert_eq!(
+            handler.line_sizes,
+            vec![
+                LineSize::DoubleHeightTop,
+                LineSize::DoubleHeightBottom,
+                LineSize::Normal,
+                LineSize::DoubleWidth
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_rectangle_operations() {
+        let mut parser = Processor::new();
+        let mut handler = MockHandler::default();
+
+        // Fill with `x`, then with a control character which is ignored.
+        for byte in b"\x1b[120;2;3;4;5$x\x1b[10;1;1$x" {
+            parser.advance(&mut handler, *byte, &mut io::sink());
+        }
+
+        let rect = Rectangle { top: 2, left: 3, bottom: Some(4), right: Some(5) };
+        assert_eq!(handler.filled_rectangles, vec![('x', rect)]);
+
+        // Unsupported attributes are dropped, no attributes resets them.
+        for byte in b"\x1b[1;1;2;2;1;3;27$r\x1b[;;;$r" {
+            parser.advance(&mut handler, *byte, &mut io::sink());
+        }
+
+        assert_eq!(
+            handler.rectangle_attributes,
+            vec![Attr::Bold, Attr::CancelReverse, Attr::Reset]
+        );
+    }
+
+    #[test]
+    fn parse_kitty_graphics() {
+        let mut parser = Processor::new();
+        let mut handler = MockHandler::default();
+
+        // Aborted and unknown APC strings are ignored.
+        for byte in b"\x1b_Ga=T\x18\x1b_Xdata\x1b\\\x1b_Gi=1,a=T;AQID\x1b\\" {
+            parser.advance(&mut handler, *byte, &mut io::sink());
+        }
+
+        assert_eq!(handler.kitty_graphics.len(), 1);
+        let command = &handler.kitty_graphics[0];
+        assert_eq!(command.image_id, 1);
+        assert_eq!(command.action, kitty::Action::TransmitAndDisplay);
+        assert_eq!(command.payload, vec![1, 2, 3]);
+
+        // APC strings must not interfere with regular escapes.
+        for byte in b"\x1b_Gi=2\x1bc" {
+            parser.advance(&mut handler, *byte, &mut io::sink());
+        }
+        assert!(handler.kitty_graphics.is_empty());
+    }
+
     /// No exactly a test; useful for debugging.
     #[test]
     fn parse_zsh_startup() {
@@ -1551,6 +2840,220 @@ mod tests {
         assert_eq!(handler.index, CharsetIndex::G1);
     }
 
+    #[test]
+    fn parse_national_and_supplemental_charsets() {
+        let mut parser = Processor::new();
+        let mut handler = MockHandler::default();
+
+        let mut designate = |bytes: &[u8]| {
+            for byte in bytes {
+                parser.advance(&mut handler, *byte, &mut io::sink());
+            }
+            (handler.index, handler.charset)
+        };
+
+        assert_eq!(designate(b"\x1b*K"), (CharsetIndex::G2, StandardCharset::German));
+        assert_eq!(designate(b"\x1b)7"), (CharsetIndex::G1, StandardCharset::Swedish));
+        assert_eq!(designate(b"\x1b+>"), (CharsetIndex::G3, StandardCharset::DecTechnical));
+        assert_eq!(
+            designate(b"\x1b(%5"),
+            (CharsetIndex::G0, StandardCharset::DecSupplementalGraphics)
+        );
+        assert_eq!(designate(b"\x1b+%6"), (CharsetIndex::G3, StandardCharset::Portuguese));
+        assert_eq!(designate(b"\x1bn").0, CharsetIndex::G2);
+        assert_eq!(designate(b"\x1bo").0, CharsetIndex::G3);
+
+        // Line size and keypad sequences share their final bytes with charsets.
+        assert_eq!(designate(b"\x1b#5\x1b=").1, StandardCharset::Portuguese);
+
+        for byte in b"\x1bN" {
+            parser.advance(&mut handler, *byte, &mut io::sink());
+        }
+        assert_eq!(handler.single_shift, Some(CharsetIndex::G2));
+    }
+
+    #[test]
+    fn parse_desktop_notifications() {
+        let mut parser = Processor::new();
+        let mut handler = MockHandler::default();
+
+        let bytes: &[u8] = b"\x1b]9;Build finished; 0 errors\x07\x1b]9;4;1;50\x07\
+            \x1b]777;notify;Title;Body\x1b\\\x1b]777;other;x\x07";
+        for byte in bytes {
+            parser.advance(&mut handler, *byte, &mut io::sink());
+        }
+
+        assert_eq!(
+            handler.notifications,
+            vec![
+                (None, String::from("Build finished; 0 errors")),
+                (Some(String::from("Title")), String::from("Body")),
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_dynamic_colors() {
+        let mut parser = Processor::new();
+        let mut handler = MockHandler::default();
+
+        let bytes: &[u8] = b"\x1b]4;1;#ff0000;2;?\x07\x1b]5;0;?;1;?;13;?\x07\
+            \x1b]17;#00ff00;?;?\x07\x1b]105;0;4\x07\x1b]105\x07\x1b]119\x07";
+        for byte in bytes {
+            parser.advance(&mut handler, *byte, &mut io::sink());
+        }
+
+        let selection_background = NamedColor::SelectionBackground as usize;
+        assert_eq!(
+            handler.colors,
+            vec![
+                (1, Rgb { r: 0xff, g: 0, b: 0 }),
+                (selection_background, Rgb { r: 0, g: 0xff, b: 0 }),
+            ]
+        );
+        assert_eq!(
+            handler.color_queries,
+            vec![
+                (String::from("4;2"), 2),
+                (String::from("5;0"), NamedColor::BrightForeground as usize),
+                (String::from("19"), NamedColor::SelectionForeground as usize),
+            ]
+        );
+        assert_eq!(
+            handler.reset_colors,
+            vec![
+                NamedColor::BrightForeground as usize,
+                NamedColor::BrightForeground as usize,
+                NamedColor::SelectionForeground as usize,
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_unsupported_special_colors() {
+        let mut parser = Processor::new();
+        let mut handler = MockHandler::default();
+
+        // Only bold text has a special color, underlined, blinking, reverse and italic don't.
+        let bytes: &[u8] = b"\x1b]5;1;#ff0000;2;#ff0000;3;?;4;?\x07\x1b]105;1;2;3;4\x07\
+            \x1b]5;5;#ff0000\x07\x1b]105;5\x07";
+        for byte in bytes {
+            parser.advance(&mut handler, *byte, &mut io::sink());
+        }
+
+        assert!(handler.colors.is_empty());
+        assert!(handler.color_queries.is_empty());
+        assert!(handler.reset_colors.is_empty());
+    }
+
+    #[test]
+    fn parse_sync_update() {
+        let mut parser = Processor::new();
+        let mut handler = MockHandler::default();
+
+        for byte in b"\x1bP=1s\x1b\\" {
+            parser.advance(&mut handler, *byte, &mut io::sink());
+        }
+        assert!(handler.sync_update);
+
+        for byte in b"\x1bP=2s\x1b\\" {
+            parser.advance(&mut handler, *byte, &mut io::sink());
+        }
+        assert!(!handler.sync_update);
+
+        for byte in b"\x1b[?2026h" {
+            parser.advance(&mut handler, *byte, &mut io::sink());
+        }
+        assert!(handler.sync_update);
+
+        for byte in b"\x1b[?2026l" {
+            parser.advance(&mut handler, *byte, &mut io::sink());
+        }
+        assert!(!handler.sync_update);
+    }
+
+    #[test]
+    fn parse_hyperlink() {
+        let mut parser = Processor::new();
+        let mut handler = MockHandler::default();
+
+        for byte in b"\x1b]8;id=1:foo=bar;https://example.org/a;b\x1b\\" {
+            parser.advance(&mut handler, *byte, &mut io::sink());
+        }
+
+        let expected =
+            Hyperlink { id: Some(String::from("1")), uri: String::from("https://example.org/a;b") };
+        assert_eq!(handler.hyperlink, Some(expected));
+
+        for byte in b"\x1b]8;;\x07" {
+            parser.advance(&mut handler, *byte, &mut io::sink());
+        }
+
+        assert_eq!(handler.hyperlink, None);
+
+        for byte in b"\x1b]8;;file:///tmp\x07" {
+            parser.advance(&mut handler, *byte, &mut io::sink());
+        }
+
+        let expected = Hyperlink { id: None, uri: String::from("file:///tmp") };
+        assert_eq!(handler.hyperlink, Some(expected.clone()));
+
+        // URIs with invalid UTF-8 are rejected instead of dropping parts of them.
+        for byte in b"\x1b]8;;https://example.org/a;\xffb\x07" {
+            parser.advance(&mut handler, *byte, &mut io::sink());
+        }
+
+        assert_eq!(handler.hyperlink, Some(expected));
+    }
+
+    #[test]
+    fn parse_prompt_marks() {
+        let mut parser = Processor::new();
+        let mut handler = MockHandler::default();
+
+        for byte in b"\x1b]133;A\x07$ \x1b]133;B\x07ls\r\n\x1b]133;C\x07\x1b]133;D;0\x1b\\" {
+            parser.advance(&mut handler, *byte, &mut io::sink());
+        }
+
+        let expected = vec![
+            PromptMark::PromptStart,
+            PromptMark::CommandStart,
+            PromptMark::OutputStart,
+            PromptMark::CommandEnd,
+        ];
+        assert_eq!(handler.prompt_marks, expected);
+    }
+
+    #[test]
+    fn parse_working_directory() {
+        let mut parser = Processor::new();
+        let mut handler = MockHandler::default();
+
+        for byte in b"\x1b]7;file://localhost/tmp/a%20b;c\x07" {
+            parser.advance(&mut handler, *byte, &mut io::sink());
+        }
+
+        assert_eq!(handler.working_directory, Some(PathBuf::from("/tmp/a b;c")));
+    }
+
+    #[test]
+    fn parse_invalid_file_uri() {
+        let hostname = Some("alacritty.test");
+        assert_eq!(parse_file_uri(b"file://localhost", hostname), None);
+        assert_eq!(parse_file_uri(b"file://remote.invalid/tmp", hostname), None);
+        assert_eq!(parse_file_uri(b"file://alacritty.test/tmp", None), None);
+        assert_eq!(
+            parse_file_uri(b"file://Alacritty.Test/tmp", hostname),
+            Some(PathBuf::from("/tmp"))
+        );
+        assert_eq!(parse_file_uri(b"https://host/tmp", hostname), None);
+        assert_eq!(parse_file_uri(b"file:///tmp%2", hostname), None);
+        assert_eq!(
+            parse_file_uri(b"file:///tmp/%e4%bd%a0", hostname),
+            Some(PathBuf::from("/tmp/你"))
+        );
+    }
+
     #[test]
     fn parse_valid_rgb_colors() {
         assert_eq!(xparse_color(b"rgb:f/e/d"), Some(Rgb { r: 0xff, g: 0xee, b: 0xdd }));
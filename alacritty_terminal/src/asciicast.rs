@@ -0,0 +1,450 @@
+//! Recording and replaying terminal sessions in the asciicast v2 format.
+//!
+//! See <https://github.com/asciinema/asciinema/blob/develop/doc/asciicast-v2.md> for the format
+//! specification.
+
+use std::io::{self, BufRead, ErrorKind, Write};
+use std::str;
+use std::sync::Arc;
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use log::error;
+use mio::{self, Events, PollOpt, Ready};
+use mio_extras::channel::{self, Receiver, Sender};
+use serde::{Deserialize, Serialize};
+
+use crate::ansi;
+use crate::event::{Event, EventListener};
+use crate::event_loop::Msg;
+use crate::index::{Column, Line};
+use crate::sync::FairMutex;
+use crate::term::{SizeInfo, Term};
+use crate::thread;
+
+/// Supported asciicast format version.
+const VERSION: u8 = 2;
+
+/// Maximum time of a frame in seconds.
+const MAX_FRAME_TIME: f64 = 365. * 24. * 60. * 60.;
+
+/// First line of an asciicast file.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Header {
+    pub version: u8,
+
+    /// Initial terminal width in columns.
+    pub width: usize,
+
+    /// Initial terminal height in lines.
+    pub height: usize,
+
+    /// Unix timestamp of the beginning of the recording.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timestamp: Option<u64>,
+
+    /// Maximum time between two frames in seconds.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub idle_time_limit: Option<f64>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+}
+
+/// Type of data stored in a frame.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum FrameKind {
+    /// Data written by the application.
+    Output,
+
+    /// Data sent by the user.
+    Input,
+
+    /// New terminal dimensions as `COLSxLINES`.
+    Resize,
+
+    /// Marker with an optional label.
+    Marker,
+}
+
+impl FrameKind {
+    fn code(self) -> &'static str {
+        match self {
+            FrameKind::Output => "o",
+            FrameKind::Input => "i",
+            FrameKind::Resize => "r",
+            FrameKind::Marker => "m",
+        }
+    }
+
+    fn from_code(code: &str) -> Option<Self> {
+        match code {
+            "o" => Some(FrameKind::Output),
+            "i" => Some(FrameKind::Input),
+            "r" => Some(FrameKind::Resize),
+            "m" => Some(FrameKind::Marker),
+            _ => None,
+        }
+    }
+}
+
+/// Single event of a recording.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Frame {
+    /// Seconds since the beginning of the recording.
+    pub time: f64,
+
+    pub kind: FrameKind,
+
+    pub data: String,
+}
+
+/// Parsed asciicast file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Recording {
+    pub header: Header,
+    pub frames: Vec<Frame>,
+}
+
+impl Recording {
+    /// Parse an asciicast v2 file.
+    ///
+    /// Frames with unknown types are ignored.
+    pub fn read<R: BufRead>(reader: R) -> io::Result<Self> {
+        let mut lines = reader.lines();
+
+        let header = match lines.next() {
+            Some(line) => serde_json::from_str::<Header>(&line?)?,
+            None => return Err(io::Error::new(ErrorKind::InvalidData, "missing asciicast header")),
+        };
+
+        if header.version != VERSION {
+            let msg = format!("unsupported asciicast version {}", header.version);
+            return Err(io::Error::new(ErrorKind::InvalidData, msg));
+        }
+
+        match header.idle_time_limit {
+            Some(limit) if limit.is_nan() || limit < 0. => {
+                let msg = format!("invalid idle time limit {}", limit);
+                return Err(io::Error::new(ErrorKind::InvalidData, msg));
+            },
+            _ => (),
+        }
+
+        let mut frames = Vec::new();
+        for line in lines {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            let (time, code, data): (f64, String, String) = serde_json::from_str(&line)?;
+            if !(0. ..=MAX_FRAME_TIME).contains(&time) {
+                let msg = format!("invalid frame time {}", time);
+                return Err(io::Error::new(ErrorKind::InvalidData, msg));
+            }
+
+            if let Some(kind) = FrameKind::from_code(&code) {
+                frames.push(Frame { time, kind, data });
+            }
+        }
+
+        Ok(Self { header, frames })
+    }
+
+    /// Change the playback speed by scaling the time of all frames.
+    pub fn set_speed(&mut self, speed: f64) {
+        for frame in &mut self.frames {
+            frame.time /= speed;
+        }
+
+        if let Some(idle_time_limit) = &mut self.header.idle_time_limit {
+            *idle_time_limit /= speed;
+        }
+    }
+}
+
+/// Writer for asciicast recordings.
+#[derive(Debug)]
+pub struct Recorder<W: Write> {
+    writer: W,
+    start: Instant,
+
+    /// Trailing bytes of an incomplete UTF-8 sequence.
+    pending: Vec<u8>,
+}
+
+impl<W: Write> Recorder<W> {
+    /// Start a new recording with the initial terminal dimensions.
+    pub fn new(mut writer: W, size: &SizeInfo) -> io::Result<Self> {
+        let timestamp = SystemTime::now().duration_since(UNIX_EPOCH).ok().map(|d| d.as_secs());
+        let header = Header {
+            version: VERSION,
+            width: size.cols().0,
+            height: size.screen_lines().0,
+            timestamp,
+            idle_time_limit: None,
+            title: None,
+        };
+
+        writeln!(writer, "{}", serde_json::to_string(&header)?)?;
+
+        Ok(Self { writer, start: Instant::now(), pending: Vec::new() })
+    }
+
+    /// Record data written by the application.
+    pub fn output(&mut self, bytes: &[u8]) -> io::Result<()> {
+        self.pending.extend_from_slice(bytes);
+
+        // Hold back incomplete UTF-8 sequences until the rest of them arrives.
+        let len = match str::from_utf8(&self.pending) {
+            Err(err) if err.error_len().is_none() => err.valid_up_to(),
+            _ => self.pending.len(),
+        };
+
+        if len == 0 {
+            return Ok(());
+        }
+
+        let data = String::from_utf8_lossy(&self.pending[..len]).into_owned();
+        self.pending.drain(..len);
+
+        self.write_frame(FrameKind::Output, &data)
+    }
+
+    /// Record a change of the terminal dimensions.
+    pub fn resize(&mut self, size: &SizeInfo) -> io::Result<()> {
+        let data = format!("{}x{}", size.cols(), size.screen_lines());
+        self.write_frame(FrameKind::Resize, &data)
+    }
+
+    /// Write all buffered frames.
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+
+    fn write_frame(&mut self, kind: FrameKind, data: &str) -> io::Result<()> {
+        let time = self.start.elapsed().as_secs_f64();
+        let frame = serde_json::to_string(&(time, kind.code(), data))?;
+        writeln!(self.writer, "{}", frame)
+    }
+}
+
+/// Replay of a recording into a terminal.
+///
+/// This takes the place of the PTY event loop, accepting the same messages.
+pub struct Player<U: EventListener> {
+    poll: mio::Poll,
+    rx: Receiver<Msg>,
+    tx: Sender<Msg>,
+    terminal: Arc<FairMutex<Term<U>>>,
+    event_proxy: U,
+    recording: Recording,
+
+    /// Current dimensions of the terminal.
+    size: SizeInfo,
+}
+
+impl<U> Player<U>
+where
+    U: EventListener + Send + 'static,
+{
+    pub fn new(
+        terminal: Arc<FairMutex<Term<U>>>,
+        event_proxy: U,
+        recording: Recording,
+        size: SizeInfo,
+    ) -> Self {
+        let (tx, rx) = channel::channel();
+        Self {
+            poll: mio::Poll::new().expect("create mio Poll"),
+            rx,
+            tx,
+            terminal,
+            event_proxy,
+            recording,
+            size,
+        }
+    }
+
+    pub fn channel(&self) -> Sender<Msg> {
+        self.tx.clone()
+    }
+
+    /// Play the recording on a new thread.
+    ///
+    /// Input messages are ignored, the thread exits once the recording is finished or a shutdown
+    /// message was received.
+    pub fn spawn(mut self) -> JoinHandle<()> {
+        thread::spawn_named("asciicast player", move || {
+            let token = mio::Token(0);
+            self.poll.register(&self.rx, token, Ready::readable(), PollOpt::edge()).unwrap();
+
+            let mut events = Events::with_capacity(16);
+            let mut parser = ansi::Processor::new();
+            let idle_time_limit = self.recording.header.idle_time_limit.unwrap_or(MAX_FRAME_TIME);
+            let idle_time_limit = idle_time_limit.min(MAX_FRAME_TIME);
+
+            let start = Instant::now();
+            let mut elapsed = 0.;
+            let mut last_time = 0.;
+
+            for frame in &self.recording.frames {
+                // Skip over long pauses, if the recording requests it.
+                let delay = if frame.time > last_time {
+                    (frame.time - last_time).min(idle_time_limit)
+                } else {
+                    0.
+                };
+                last_time = frame.time;
+                elapsed += delay;
+
+                // Wait for the frame, while still responding to shutdown.
+                let deadline = start + Duration::from_secs_f64(elapsed);
+                loop {
+                    let now = Instant::now();
+                    if now >= deadline {
+                        break;
+                    }
+
+                    if let Err(err) = self.poll.poll(&mut events, Some(deadline - now)) {
+                        if err.kind() != ErrorKind::Interrupted {
+                            error!("Asciicast player polling error: {}", err);
+                            return;
+                        }
+                    }
+
+                    while let Ok(msg) = self.rx.try_recv() {
+                        match msg {
+                            Msg::Shutdown => return,
+                            Msg::Resize(size) => self.size = size,
+                            _ => (),
+                        }
+                    }
+                }
+
+                match frame.kind {
+                    FrameKind::Output => {
+                        // Responses to the application are discarded, since there is none.
+                        let mut terminal = self.terminal.lock();
+                        for byte in frame.data.bytes() {
+                            parser.advance(&mut *terminal, byte, &mut io::sink());
+                        }
+                        drop(terminal);
+
+                        self.event_proxy.send_event(Event::Wakeup);
+                    },
+                    FrameKind::Resize => match parse_dimensions(&frame.data) {
+                        Some((columns, lines)) => {
+                            // Resize the terminal right away, so it matches the following output.
+                            self.size = resized(&self.size, columns, lines);
+                            self.terminal.lock().resize(self.size);
+
+                            self.event_proxy.send_event(Event::ResizeWindow(self.size));
+                        },
+                        None => error!("Invalid asciicast resize frame: {:?}", frame.data),
+                    },
+                    FrameKind::Input | FrameKind::Marker => (),
+                }
+            }
+        })
+    }
+}
+
+/// Parse the `COLSxLINES` dimensions of a resize frame.
+fn parse_dimensions(data: &str) -> Option<(Column, Line)> {
+    let mut dimensions = data.splitn(2, 'x');
+    let columns = dimensions.next()?.trim().parse().ok()?;
+    let lines = dimensions.next()?.trim().parse().ok()?;
+    Some((Column(columns), Line(lines)))
+}
+
+/// Change the number of columns and lines, keeping cell size and padding.
+fn resized(size: &SizeInfo, columns: Column, lines: Line) -> SizeInfo {
+    let width = size.cell_width().mul_add(columns.0 as f32, 2. * size.padding_x());
+    let height = size.cell_height().mul_add(lines.0 as f32, 2. * size.padding_y());
+    SizeInfo::new(
+        width,
+        height,
+        size.cell_width(),
+        size.cell_height(),
+        size.padding_x(),
+        size.padding_y(),
+        false,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn record_and_read() {
+        let size = SizeInfo::new(10., 5., 1., 1., 0., 0., false);
+        let mut recorder = Recorder::new(Vec::new(), &size).unwrap();
+
+        // Split a multi-byte character across two reads.
+        let text = "a\u{e4}\x1b[1m";
+        recorder.output(&text.as_bytes()[..2]).unwrap();
+        recorder.output(&text.as_bytes()[2..]).unwrap();
+        recorder.resize(&SizeInfo::new(20., 3., 1., 1., 0., 0., false)).unwrap();
+
+        let recording = Recording::read(&recorder.writer[..]).unwrap();
+        assert_eq!(recording.header.width, 10);
+        assert_eq!(recording.header.height, 5);
+
+        let frames: Vec<_> =
+            recording.frames.iter().map(|frame| (frame.kind, frame.data.as_str())).collect();
+        assert_eq!(
+            frames,
+            vec![
+                (FrameKind::Output, "a"),
+                (FrameKind::Output, "\u{e4}\x1b[1m"),
+                (FrameKind::Resize, "20x3"),
+            ]
+        );
+    }
+
+    #[test]
+    fn read_asciicast() {
+        let cast = "{\"version\": 2, \"width\": 80, \"height\": 24, \"idle_time_limit\": 2.0}\n\
+                    [0.5, \"o\", \"hello\"]\n\
+                    [1.0, \"x\", \"unknown\"]\n\
+                    \n\
+                    [2.0, \"i\", \"q\"]\n";
+
+        let mut recording = Recording::read(cast.as_bytes()).unwrap();
+        recording.set_speed(2.);
+
+        assert_eq!(recording.header.idle_time_limit, Some(1.));
+        assert_eq!(
+            recording.frames,
+            vec![
+                Frame { time: 0.25, kind: FrameKind::Output, data: "hello".into() },
+                Frame { time: 1., kind: FrameKind::Input, data: "q".into() },
+            ]
+        );
+
+        let cast = "{\"version\": 1, \"width\": 80, \"height\": 24}\n";
+        assert!(Recording::read(cast.as_bytes()).is_err());
+
+        let cast = "{\"version\": 2, \"width\": 80, \"height\": 24}\n[1e20, \"o\", \"x\"]\n";
+        assert!(Recording::read(cast.as_bytes()).is_err());
+
+        let cast = "{\"version\": 2, \"width\": 80, \"height\": 24, \"idle_time_limit\": -1}\n";
+        assert!(Recording::read(cast.as_bytes()).is_err());
+    }
+
+    #[test]
+    fn resize_frames() {
+        assert_eq!(parse_dimensions("100x30"), Some((Column(100), Line(30))));
+        assert_eq!(parse_dimensions("100"), None);
+        assert_eq!(parse_dimensions("ax30"), None);
+
+        let size = SizeInfo::new(100., 70., 10., 20., 5., 5., false);
+        let size = resized(&size, Column(20), Line(4));
+        assert_eq!(size.cols(), Column(20));
+        assert_eq!(size.screen_lines(), Line(4));
+        assert_eq!(size.width(), 210.);
+    }
+}
@@ -17,6 +17,7 @@ pub struct Colors {
     pub indexed_colors: Vec<IndexedColor>,
     pub search: SearchColors,
     pub line_indicator: LineIndicatorColors,
+    pub hints: HintColors,
 }
 
 impl Colors {
@@ -29,6 +30,42 @@ impl Colors {
     }
 }
 
+#[derive(ConfigDeserialize, Copy, Clone, Default, Debug, PartialEq, Eq)]
+pub struct HintColors {
+    pub start: HintStartColors,
+    pub end: HintEndColors,
+}
+
+#[derive(ConfigDeserialize, Copy, Clone, Debug, PartialEq, Eq)]
+pub struct HintStartColors {
+    pub foreground: Rgb,
+    pub background: Rgb,
+}
+
+impl Default for HintStartColors {
+    fn default() -> Self {
+        Self {
+            foreground: Rgb { r: 0x1d, g: 0x1f, b: 0x21 },
+            background: Rgb { r: 0xe9, g: 0xff, b: 0x5e },
+        }
+    }
+}
+
+#[derive(ConfigDeserialize, Copy, Clone, Debug, PartialEq, Eq)]
+pub struct HintEndColors {
+    pub foreground: Rgb,
+    pub background: Rgb,
+}
+
+impl Default for HintEndColors {
+    fn default() -> Self {
+        Self {
+            foreground: Rgb { r: 0xe9, g: 0xff, b: 0x5e },
+            background: Rgb { r: 0x1d, g: 0x1f, b: 0x21 },
+        }
+    }
+}
+
 #[derive(ConfigDeserialize, Copy, Clone, Default, Debug, PartialEq, Eq)]
 pub struct LineIndicatorColors {
     pub foreground: Option<Rgb>,
@@ -9,12 +9,14 @@ use alacritty_config_derive::ConfigDeserialize;
 mod bell;
 mod colors;
 mod scrolling;
+mod security;
 
 use crate::ansi::{CursorShape, CursorStyle};
 
 pub use crate::config::bell::{BellAnimation, BellConfig};
 pub use crate::config::colors::Colors;
 pub use crate::config::scrolling::Scrolling;
+pub use crate::config::security::{Capability, Permission, Security};
 
 pub const LOG_TARGET_CONFIG: &str = "alacritty_config_derive";
 const MIN_BLINK_INTERVAL: u64 = 10;
@@ -46,6 +48,9 @@ pub struct Config<T> {
     /// Shell startup directory.
     pub working_directory: Option<PathBuf>,
 
+    /// Permissions for escape sequences exposing data outside of the terminal.
+    pub security: Security,
+
     /// Additional configuration options not directly required by the terminal.
     #[config(flatten)]
     pub ui_config: T,
@@ -54,6 +59,10 @@ pub struct Config<T> {
     #[config(skip)]
     pub hold: bool,
 
+    /// Application version reported to terminal queries.
+    #[config(skip)]
+    pub version: String,
+
     /// Bell configuration.
     bell: BellConfig,
 
@@ -11,12 +11,15 @@ const MAX_SCROLLBACK_LINES: u32 = 100_000;
 pub struct Scrolling {
     pub multiplier: u8,
 
+    /// Archive lines exceeding the history, instead of discarding them.
+    pub archive: bool,
+
     history: ScrollingHistory,
 }
 
 impl Default for Scrolling {
     fn default() -> Self {
-        Self { multiplier: 3, history: Default::default() }
+        Self { multiplier: 3, archive: false, history: Default::default() }
     }
 }
 
@@ -0,0 +1,85 @@
+use std::fmt::{self, Display, Formatter};
+
+use alacritty_config_derive::ConfigDeserialize;
+
+/// Permissions for escape sequences which expose data or state outside of the terminal.
+#[derive(ConfigDeserialize, Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Security {
+    /// Reading the clipboard with OSC 52.
+    pub clipboard_read: Permission,
+
+    /// Writing to the clipboard with OSC 52.
+    pub clipboard_write: Permission,
+
+    /// Querying colors with OSC 4, 10, 11 and 12.
+    pub color_queries: Permission,
+
+    /// Reporting the window title with `CSI 21 t`.
+    pub title_reporting: Permission,
+
+    /// Mode, setting, terminfo, version, checksum, size and status queries.
+    pub queries: Permission,
+
+    /// Reading image files with the kitty graphics protocol.
+    pub file_reads: Permission,
+}
+
+impl Default for Security {
+    fn default() -> Self {
+        Self {
+            clipboard_read: Permission::Prompt,
+            clipboard_write: Permission::Allow,
+            color_queries: Permission::Allow,
+            title_reporting: Permission::Deny,
+            queries: Permission::Allow,
+            file_reads: Permission::Allow,
+        }
+    }
+}
+
+impl Security {
+    /// Permission configured for a capability.
+    pub fn permission(&self, capability: Capability) -> Permission {
+        match capability {
+            Capability::ClipboardRead => self.clipboard_read,
+            Capability::ClipboardWrite => self.clipboard_write,
+            Capability::ColorQuery => self.color_queries,
+            Capability::TitleReport => self.title_reporting,
+            Capability::Query => self.queries,
+            Capability::FileRead => self.file_reads,
+        }
+    }
+}
+
+/// Action taken when an escape sequence requires a capability.
+#[derive(ConfigDeserialize, Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Permission {
+    Allow,
+    Deny,
+    Prompt,
+}
+
+/// Capabilities escape sequences can require.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Capability {
+    ClipboardRead,
+    ClipboardWrite,
+    ColorQuery,
+    TitleReport,
+    Query,
+    FileRead,
+}
+
+impl Display for Capability {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let description = match self {
+            Capability::ClipboardRead => "read the clipboard",
+            Capability::ClipboardWrite => "write to the clipboard",
+            Capability::ColorQuery => "query terminal colors",
+            Capability::TitleReport => "read the window title",
+            Capability::Query => "query terminal state",
+            Capability::FileRead => "read local files",
+        };
+        f.write_str(description)
+    }
+}
@@ -2,6 +2,7 @@ use std::borrow::Cow;
 use std::fmt::{self, Debug, Formatter};
 use std::sync::Arc;
 
+use crate::config::Capability;
 use crate::term::{ClipboardType, SizeInfo};
 
 #[derive(Clone)]
@@ -11,7 +12,12 @@ pub enum Event {
     ResetTitle,
     ClipboardStore(ClipboardType, String),
     ClipboardLoad(ClipboardType, Arc<dyn Fn(&str) -> String + Sync + Send + 'static>),
+    PtyWrite(String),
+    PermissionRequest(Capability, Box<Event>),
+    Notify(Option<String>, String),
     CursorBlinkingChange(bool),
+    ResizeWindow(SizeInfo),
+    RecordingStopped,
     Wakeup,
     Bell,
     Exit,
@@ -25,6 +31,13 @@ impl Debug for Event {
             Event::ResetTitle => write!(f, "ResetTitle"),
             Event::ClipboardStore(ty, text) => write!(f, "ClipboardStore({:?}, {})", ty, text),
             Event::ClipboardLoad(ty, _) => write!(f, "ClipboardLoad({:?})", ty),
+            Event::PtyWrite(text) => write!(f, "PtyWrite({})", text),
+            Event::PermissionRequest(capability, event) => {
+                write!(f, "PermissionRequest({:?}, {:?})", capability, event)
+            },
+            Event::Notify(title, body) => write!(f, "Notify({:?}, {})", title, body),
+            Event::ResizeWindow(size) => write!(f, "ResizeWindow({:?})", size),
+            Event::RecordingStopped => write!(f, "RecordingStopped"),
             Event::Wakeup => write!(f, "Wakeup"),
             Event::Bell => write!(f, "Bell"),
             Event::Exit => write!(f, "Exit"),
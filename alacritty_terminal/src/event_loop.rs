@@ -3,10 +3,11 @@
 use std::borrow::Cow;
 use std::collections::VecDeque;
 use std::fs::File;
-use std::io::{self, ErrorKind, Read, Write};
+use std::io::{self, BufWriter, ErrorKind, Read, Write};
 use std::marker::Send;
 use std::sync::Arc;
 use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
 
 use log::error;
 #[cfg(not(windows))]
@@ -15,15 +16,19 @@ use mio::{self, Events, PollOpt, Ready};
 use mio_extras::channel::{self, Receiver, Sender};
 
 use crate::ansi;
+use crate::asciicast::Recorder;
 use crate::event::{self, Event, EventListener};
 use crate::sync::FairMutex;
-use crate::term::{SizeInfo, Term};
+use crate::term::{SizeInfo, Term, TermMode};
 use crate::thread;
 use crate::tty;
 
 /// Max bytes to read from the PTY.
 const MAX_READ: usize = u16::max_value() as usize;
 
+/// Maximum time a synchronized update can hold back rendering.
+const SYNC_UPDATE_TIMEOUT: Duration = Duration::from_millis(150);
+
 /// Messages that may be sent to the `EventLoop`.
 #[derive(Debug)]
 pub enum Msg {
@@ -35,6 +40,9 @@ pub enum Msg {
 
     /// Instruction to resize the PTY.
     Resize(SizeInfo),
+
+    /// Start recording the PTY output, or stop it with `None`.
+    Record(Option<Recorder<BufWriter<File>>>),
 }
 
 /// The main event!.. loop.
@@ -50,6 +58,7 @@ pub struct EventLoop<T: tty::EventedPty, U: EventListener> {
     event_proxy: U,
     hold: bool,
     ref_test: bool,
+    recorder: Option<Recorder<BufWriter<File>>>,
 }
 
 /// Helper type which tracks how much of a buffer has been written.
@@ -66,6 +75,9 @@ pub struct State {
     write_list: VecDeque<Cow<'static, [u8]>>,
     writing: Option<Writing>,
     parser: ansi::Processor,
+
+    /// Deadline after which a pending synchronized update is rendered anyway.
+    sync_deadline: Option<Instant>,
 }
 
 pub struct Notifier(pub Sender<Msg>);
@@ -81,19 +93,25 @@ impl event::Notify for Notifier {
             return;
         }
 
-        self.0.send(Msg::Input(bytes)).expect("send event loop msg");
+        // Ignore errors, since the PTY might have shut down before its window was closed.
+        let _ = self.0.send(Msg::Input(bytes));
     }
 }
 
 impl event::OnResize for Notifier {
     fn on_resize(&mut self, size: &SizeInfo) {
-        self.0.send(Msg::Resize(*size)).expect("expected send event loop msg");
+        let _ = self.0.send(Msg::Resize(*size));
     }
 }
 
 impl Default for State {
     fn default() -> State {
-        State { write_list: VecDeque::new(), parser: ansi::Processor::new(), writing: None }
+        State {
+            write_list: VecDeque::new(),
+            parser: ansi::Processor::new(),
+            writing: None,
+            sync_deadline: None,
+        }
     }
 }
 
@@ -124,6 +142,12 @@ impl State {
     fn set_current(&mut self, new: Option<Writing>) {
         self.writing = new;
     }
+
+    /// Time left until the pending synchronized update times out.
+    #[inline]
+    fn sync_timeout(&self) -> Option<Duration> {
+        self.sync_deadline.map(|deadline| deadline.saturating_duration_since(Instant::now()))
+    }
 }
 
 impl Writing {
@@ -171,6 +195,7 @@ where
             event_proxy,
             hold,
             ref_test,
+            recorder: None,
         }
     }
 
@@ -186,7 +211,16 @@ where
             match msg {
                 Msg::Input(input) => state.write_list.push_back(input),
                 Msg::Shutdown => return false,
-                Msg::Resize(size) => self.pty.on_resize(&size),
+                Msg::Resize(size) => {
+                    self.pty.on_resize(&size);
+                    record(&mut self.recorder, &self.event_proxy, |recorder| {
+                        recorder.resize(&size)
+                    });
+                },
+                Msg::Record(recorder) => {
+                    record(&mut self.recorder, &self.event_proxy, Recorder::flush);
+                    self.recorder = recorder;
+                },
             }
         }
 
@@ -234,6 +268,11 @@ where
                         w
                     });
 
+                    // Add bytes to the asciicast recording.
+                    record(&mut self.recorder, &self.event_proxy, |recorder| {
+                        recorder.output(&buf[..got])
+                    });
+
                     // Get reference to terminal. Lock is acquired on initial
                     // iteration and held until there's no bytes left to parse
                     // or we've reached `MAX_READ`.
@@ -261,9 +300,18 @@ where
             }
         }
 
-        if processed > 0 {
-            // Queue terminal redraw.
-            self.event_proxy.send_event(Event::Wakeup);
+        if let Some(terminal) = terminal {
+            let sync_update = terminal.mode().contains(TermMode::SYNC_UPDATE);
+            drop(terminal);
+
+            if !sync_update {
+                // Queue terminal redraw.
+                state.sync_deadline = None;
+                self.event_proxy.send_event(Event::Wakeup);
+            } else if state.sync_deadline.is_none() {
+                // Hold back redraws until the update is finished or timed out.
+                state.sync_deadline = Some(Instant::now() + SYNC_UPDATE_TIMEOUT);
+            }
         }
 
         Ok(())
@@ -325,13 +373,19 @@ where
             };
 
             'event_loop: loop {
-                if let Err(err) = self.poll.poll(&mut events, None) {
+                if let Err(err) = self.poll.poll(&mut events, state.sync_timeout()) {
                     match err.kind() {
                         ErrorKind::Interrupted => continue,
                         _ => panic!("EventLoop polling error: {:?}", err),
                     }
                 }
 
+                // Render the synchronized update once it has timed out.
+                if state.sync_timeout() == Some(Duration::from_secs(0)) {
+                    state.sync_deadline = None;
+                    self.event_proxy.send_event(Event::Wakeup);
+                }
+
                 for event in events.iter() {
                     match event.token() {
                         token if token == channel_token => {
@@ -389,7 +443,7 @@ where
                                     break 'event_loop;
                                 }
                             }
-                        }
+                        },
                         _ => (),
                     }
                 }
@@ -407,7 +461,23 @@ where
             let _ = self.poll.deregister(&self.rx);
             let _ = self.pty.deregister(&self.poll);
 
+            // Write the remainder of an active recording.
+            record(&mut self.recorder, &self.event_proxy, Recorder::flush);
+
             (self, state)
         })
     }
 }
+
+/// Write to the active recording, stopping it on failure.
+fn record<U, F>(recorder: &mut Option<Recorder<BufWriter<File>>>, event_proxy: &U, f: F)
+where
+    U: EventListener,
+    F: FnOnce(&mut Recorder<BufWriter<File>>) -> io::Result<()>,
+{
+    if let Some(Err(err)) = recorder.as_mut().map(f) {
+        error!("Stopped recording after write error: {}", err);
+        *recorder = None;
+        event_proxy.send_event(Event::RecordingStopped);
+    }
+}
@@ -0,0 +1,595 @@
+//! Kitty graphics protocol.
+//!
+//! Commands are sent as `APC G <control data> ; <payload> ST`, with the control data being a
+//! comma separated list of `key=value` pairs and the payload being base64 encoded. Large images
+//! are split into multiple chunks, with every chunk but the last one setting `m=1`.
+
+use std::cmp::min;
+use std::fmt::{self, Display, Formatter};
+use std::fs::{self, File};
+use std::io::{self, Cursor, Read, Seek, SeekFrom};
+use std::path::Path;
+use std::{env, str};
+
+use log::debug;
+use miniz_oxide::inflate::core::{decompress, inflate_flags, DecompressorOxide};
+use miniz_oxide::inflate::TINFLStatus;
+
+use crate::graphics::{GraphicData, MAX_GRAPHIC_DIMENSIONS};
+
+/// Maximum size of the raw data of a single image.
+const MAX_DATA_SIZE: usize = MAX_GRAPHIC_DIMENSIONS.0 * MAX_GRAPHIC_DIMENSIONS.1 * 4;
+
+/// Files sent with the temporary file medium must contain this string in their name.
+const TEMP_FILE_MARKER: &str = "tty-graphics-protocol";
+
+/// Operation requested by a command (`a` key).
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Action {
+    /// Transmit image data (`a=t`).
+    Transmit,
+    /// Transmit image data and display it at the cursor (`a=T`).
+    TransmitAndDisplay,
+    /// Check if image data could be loaded, without storing it (`a=q`).
+    Query,
+    /// Display a previously transmitted image at the cursor (`a=p`).
+    Place,
+    /// Delete placements and images (`a=d`).
+    Delete,
+}
+
+/// How the image data is transmitted (`t` key).
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Medium {
+    /// Data is part of the payload (`t=d`).
+    Direct,
+    /// Payload is the path of a file containing the data (`t=f`).
+    File,
+    /// Payload is the path of a temporary file, deleted after reading it (`t=t`).
+    TempFile,
+    /// Payload is the name of a shared memory object (`t=s`).
+    SharedMemory,
+}
+
+/// Pixel format of the image data (`f` key).
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Format {
+    /// 24-bit RGB pixels (`f=24`).
+    Rgb,
+    /// 32-bit RGBA pixels (`f=32`).
+    Rgba,
+    /// PNG image (`f=100`).
+    Png,
+}
+
+/// Placements removed by a delete command (`d` key).
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum DeleteTarget {
+    /// All placements visible on the screen (`d=a`).
+    All,
+    /// Placements of the image with the specified ID (`d=i`).
+    Image,
+    /// Placements intersecting with the cursor (`d=c`).
+    Cursor,
+    /// Placements with the specified z-index (`d=z`).
+    ZIndex,
+}
+
+/// Parsed graphics command.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Command {
+    pub action: Action,
+
+    /// Suppress `OK` responses with `1` and all responses with `2`.
+    pub quiet: u8,
+
+    /// Client assigned image ID, `0` if absent.
+    pub image_id: u32,
+
+    /// Client assigned placement ID, `0` if absent.
+    pub placement_id: u32,
+
+    pub format: Format,
+    pub medium: Medium,
+
+    /// Data is compressed with zlib (`o=z`).
+    pub compressed: bool,
+
+    /// Image width in pixels, required for raw pixel formats.
+    pub width: usize,
+
+    /// Image height in pixels, required for raw pixel formats.
+    pub height: usize,
+
+    /// Number of bytes to read from a file, `0` to read until the end.
+    pub data_size: usize,
+
+    /// Offset of the data in a file.
+    pub data_offset: u64,
+
+    /// More chunks of the payload will follow (`m=1`).
+    pub more_chunks: bool,
+
+    /// Stacking order relative to text, negative values are drawn below it.
+    pub z_index: i32,
+
+    /// Move the cursor after the displayed image (`C=0`).
+    pub move_cursor: bool,
+
+    /// Placements removed by a delete command.
+    pub delete: Option<DeleteTarget>,
+
+    /// Free the image data of deleted placements (uppercase `d` value).
+    pub delete_data: bool,
+
+    /// Base64 decoded payload.
+    pub payload: Vec<u8>,
+}
+
+impl Default for Command {
+    fn default() -> Self {
+        Self {
+            action: Action::Transmit,
+            quiet: 0,
+            image_id: 0,
+            placement_id: 0,
+            format: Format::Rgba,
+            medium: Medium::Direct,
+            compressed: false,
+            width: 0,
+            height: 0,
+            data_size: 0,
+            data_offset: 0,
+            more_chunks: false,
+            z_index: 0,
+            move_cursor: true,
+            delete: Some(DeleteTarget::All),
+            delete_data: false,
+            payload: Vec::new(),
+        }
+    }
+}
+
+impl Command {
+    /// Parse the content of an APC string, without the leading `G`.
+    pub fn parse(data: &[u8]) -> Option<Self> {
+        let mut parts = data.splitn(2, |&b| b == b';');
+        let control = parts.next()?;
+        let payload = parts.next().unwrap_or_default();
+
+        let mut command = Command::default();
+        for pair in control.split(|&b| b == b',').filter(|pair| !pair.is_empty()) {
+            let (key, value) = match pair {
+                [key, b'=', value @ ..] if !value.is_empty() => (*key, value),
+                _ => {
+                    debug!("Invalid kitty graphics control data: {:?}", str::from_utf8(pair));
+                    return None;
+                },
+            };
+
+            match (key, value) {
+                (b'a', [action]) => {
+                    command.action = match action {
+                        b't' => Action::Transmit,
+                        b'T' => Action::TransmitAndDisplay,
+                        b'q' => Action::Query,
+                        b'p' => Action::Place,
+                        b'd' => Action::Delete,
+                        _ => return None,
+                    }
+                },
+                (b't', [medium]) => {
+                    command.medium = match medium {
+                        b'd' => Medium::Direct,
+                        b'f' => Medium::File,
+                        b't' => Medium::TempFile,
+                        b's' => Medium::SharedMemory,
+                        _ => return None,
+                    }
+                },
+                (b'o', [compression]) => command.compressed = *compression == b'z',
+                (b'd', [target]) => {
+                    command.delete = match target.to_ascii_lowercase() {
+                        b'a' => Some(DeleteTarget::All),
+                        b'i' => Some(DeleteTarget::Image),
+                        b'c' => Some(DeleteTarget::Cursor),
+                        b'z' => Some(DeleteTarget::ZIndex),
+                        _ => None,
+                    };
+                    command.delete_data = target.is_ascii_uppercase();
+                },
+                (b'f', value) => {
+                    command.format = match parse_number::<u32>(value)? {
+                        24 => Format::Rgb,
+                        32 => Format::Rgba,
+                        100 => Format::Png,
+                        _ => return None,
+                    }
+                },
+                (b'q', value) => command.quiet = parse_number(value)?,
+                (b'i', value) => command.image_id = parse_number(value)?,
+                (b'p', value) => command.placement_id = parse_number(value)?,
+                (b's', value) => command.width = parse_number(value)?,
+                (b'v', value) => command.height = parse_number(value)?,
+                (b'S', value) => command.data_size = parse_number(value)?,
+                (b'O', value) => command.data_offset = parse_number(value)?,
+                (b'm', value) => command.more_chunks = parse_number::<u8>(value)? == 1,
+                (b'z', value) => command.z_index = parse_number(value)?,
+                (b'C', value) => command.move_cursor = parse_number::<u8>(value)? == 0,
+                _ => debug!("Ignoring kitty graphics key {:?}", key as char),
+            }
+        }
+
+        command.payload = base64::decode(payload).ok()?;
+
+        Some(command)
+    }
+
+    /// Add the payload of the next chunk.
+    ///
+    /// Only the `m` key is used from chunks following the first one.
+    pub fn append_chunk(&mut self, chunk: Command) {
+        // Oversized payloads are truncated, causing the image to be rejected once it is loaded.
+        if self.payload.len() + chunk.payload.len() <= MAX_DATA_SIZE {
+            self.payload.extend_from_slice(&chunk.payload);
+        }
+        self.more_chunks = chunk.more_chunks;
+    }
+
+    /// Check if loading the image data requires reading a file.
+    pub fn reads_file(&self) -> bool {
+        matches!(self.medium, Medium::File | Medium::TempFile)
+    }
+
+    /// Load the image data described by this command.
+    pub fn load(&self) -> Result<GraphicData, Error> {
+        let data = match self.medium {
+            Medium::Direct => self.payload.clone(),
+            Medium::File | Medium::TempFile => self.read_file()?,
+            Medium::SharedMemory => return Err(Error::UnsupportedMedium),
+        };
+
+        let data = if self.compressed {
+            // Raw pixels are limited to their expected size, to fail early on invalid data.
+            let limit = match self.format {
+                Format::Png => MAX_DATA_SIZE,
+                Format::Rgb => self.width.saturating_mul(self.height).saturating_mul(3),
+                Format::Rgba => self.width.saturating_mul(self.height).saturating_mul(4),
+            };
+            inflate(&data, min(limit, MAX_DATA_SIZE))?
+        } else {
+            data
+        };
+
+        match self.format {
+            Format::Png => decode_png(&data),
+            Format::Rgb | Format::Rgba => self.decode_raw(data),
+        }
+    }
+
+    /// Response for the result of this command.
+    ///
+    /// Returns `None` if the response is suppressed or the client did not specify an image ID.
+    pub fn response(&self, result: &Result<(), Error>) -> Option<String> {
+        let message = match result {
+            Ok(()) if self.quiet == 0 => String::from("OK"),
+            Err(err) if self.quiet < 2 => err.to_string(),
+            _ => return None,
+        };
+
+        if self.image_id == 0 {
+            return None;
+        }
+
+        let placement = match self.placement_id {
+            0 => String::new(),
+            id => format!(",p={}", id),
+        };
+
+        Some(format!("\x1b_Gi={}{};{}\x1b\\", self.image_id, placement, message))
+    }
+
+    /// Read the image data from the file specified in the payload.
+    fn read_file(&self) -> Result<Vec<u8>, Error> {
+        let path = str::from_utf8(&self.payload).map_err(|_| Error::InvalidData)?;
+        let path = fs::canonicalize(path)?;
+
+        // Reading from devices or FIFOs could block the terminal forever.
+        if !fs::metadata(&path)?.is_file() {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "not a regular file").into());
+        }
+
+        // Only remove files which were clearly created for this protocol.
+        let remove = self.medium == Medium::TempFile && is_temp_file(&path);
+
+        let mut file = File::open(&path)?;
+        file.seek(SeekFrom::Start(self.data_offset))?;
+
+        let limit = match self.data_size {
+            0 => MAX_DATA_SIZE,
+            size => size.min(MAX_DATA_SIZE),
+        };
+
+        let mut data = Vec::new();
+        file.take(limit as u64).read_to_end(&mut data)?;
+
+        if remove {
+            let _ = fs::remove_file(path);
+        }
+
+        Ok(data)
+    }
+
+    /// Convert raw RGB or RGBA pixels to a graphic.
+    fn decode_raw(&self, data: Vec<u8>) -> Result<GraphicData, Error> {
+        let (width, height) = (self.width, self.height);
+        check_dimensions(width, height)?;
+
+        let pixels = match self.format {
+            Format::Rgb if data.len() == width * height * 3 => to_rgba(&data, 3),
+            Format::Rgba if data.len() == width * height * 4 => data,
+            _ => return Err(Error::InvalidData),
+        };
+
+        Ok(GraphicData { width, height, pixels })
+    }
+}
+
+/// Errors reported back to the client.
+#[derive(Debug)]
+pub enum Error {
+    /// Image data does not match its format or size.
+    InvalidData,
+
+    /// Image is larger than `MAX_GRAPHIC_DIMENSIONS`.
+    TooLarge,
+
+    /// No image with the requested ID exists.
+    NotFound(u32),
+
+    /// Shared memory transmission is not supported.
+    UnsupportedMedium,
+
+    /// Reading files is not permitted by the security policy.
+    PermissionDenied,
+
+    /// Reading the image file failed.
+    Io(io::Error),
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::InvalidData => write!(f, "EINVAL:invalid image data"),
+            Error::TooLarge => write!(f, "EFBIG:image is too large"),
+            Error::NotFound(id) => write!(f, "ENOENT:image {} not found", id),
+            Error::UnsupportedMedium => write!(f, "EINVAL:unsupported transmission medium"),
+            Error::PermissionDenied => write!(f, "EPERM:reading files is not permitted"),
+            Error::Io(_) => write!(f, "EBADF:cannot read file"),
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Self {
+        Error::Io(err)
+    }
+}
+
+/// Check if a canonical path is a file created for the temporary file medium.
+///
+/// Temporary files must be placed directly inside the temporary directory.
+fn is_temp_file(path: &Path) -> bool {
+    let temp_dir = match fs::canonicalize(env::temp_dir()) {
+        Ok(temp_dir) => temp_dir,
+        Err(_) => return false,
+    };
+
+    path.parent() == Some(&temp_dir)
+        && matches!(path.file_name(), Some(name) if name.to_string_lossy().contains(TEMP_FILE_MARKER))
+}
+
+/// Decompress zlib data, failing once it exceeds `limit` bytes.
+fn inflate(data: &[u8], limit: usize) -> Result<Vec<u8>, Error> {
+    let flags = inflate_flags::TINFL_FLAG_PARSE_ZLIB_HEADER
+        | inflate_flags::TINFL_FLAG_USING_NON_WRAPPING_OUTPUT_BUF;
+    let mut decompressor = Box::<DecompressorOxide>::default();
+    let mut output = vec![0; min(data.len() * 2, limit)];
+
+    let (mut in_pos, mut out_pos) = (0, 0);
+    loop {
+        // The whole output is passed, since matches can refer to all previous data.
+        let mut cursor = Cursor::new(output.as_mut_slice());
+        cursor.set_position(out_pos as u64);
+        let (status, in_consumed, out_consumed) =
+            decompress(&mut decompressor, &data[in_pos..], &mut cursor, flags);
+        in_pos += in_consumed;
+        out_pos += out_consumed;
+
+        match status {
+            TINFLStatus::Done => {
+                output.truncate(out_pos);
+                return Ok(output);
+            },
+            TINFLStatus::HasMoreOutput if output.len() < limit => {
+                let len = min(output.len().max(1) * 2, limit);
+                output.resize(len, 0);
+            },
+            TINFLStatus::HasMoreOutput => return Err(Error::TooLarge),
+            _ => return Err(Error::InvalidData),
+        }
+    }
+}
+
+fn parse_number<T: str::FromStr>(value: &[u8]) -> Option<T> {
+    str::from_utf8(value).ok()?.parse().ok()
+}
+
+fn check_dimensions(width: usize, height: usize) -> Result<(), Error> {
+    if width == 0 || height == 0 {
+        Err(Error::InvalidData)
+    } else if width > MAX_GRAPHIC_DIMENSIONS.0 || height > MAX_GRAPHIC_DIMENSIONS.1 {
+        Err(Error::TooLarge)
+    } else {
+        Ok(())
+    }
+}
+
+/// Decode a PNG image to RGBA pixels.
+fn decode_png(data: &[u8]) -> Result<GraphicData, Error> {
+    let mut decoder = png::Decoder::new(data);
+    decoder.set_transformations(png::Transformations::EXPAND | png::Transformations::STRIP_16);
+
+    let (info, mut reader) = decoder.read_info().map_err(|_| Error::InvalidData)?;
+    let (width, height) = (info.width as usize, info.height as usize);
+    check_dimensions(width, height)?;
+
+    let mut buffer = vec![0; info.buffer_size()];
+    reader.next_frame(&mut buffer).map_err(|_| Error::InvalidData)?;
+
+    let pixels = match info.color_type {
+        png::ColorType::RGBA => buffer,
+        png::ColorType::RGB => to_rgba(&buffer, 3),
+        png::ColorType::GrayscaleAlpha => to_rgba(&buffer, 2),
+        png::ColorType::Grayscale => to_rgba(&buffer, 1),
+        png::ColorType::Indexed => return Err(Error::InvalidData),
+    };
+
+    Ok(GraphicData { width, height, pixels })
+}
+
+/// Convert pixels with one to three channels to RGBA.
+fn to_rgba(data: &[u8], channels: usize) -> Vec<u8> {
+    let mut pixels = Vec::with_capacity(data.len() / channels * 4);
+    for pixel in data.chunks_exact(channels) {
+        match *pixel {
+            [gray] => pixels.extend_from_slice(&[gray, gray, gray, 255]),
+            [gray, alpha] => pixels.extend_from_slice(&[gray, gray, gray, alpha]),
+            [r, g, b] => pixels.extend_from_slice(&[r, g, b, 255]),
+            _ => pixels.extend_from_slice(pixel),
+        }
+    }
+    pixels
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_command() {
+        let command = Command::parse(b"a=T,f=24,s=1,v=2,i=7,p=3,z=-1,C=1,q=1;AQIDBAUG").unwrap();
+
+        assert_eq!(command.action, Action::TransmitAndDisplay);
+        assert_eq!(command.format, Format::Rgb);
+        assert_eq!((command.width, command.height), (1, 2));
+        assert_eq!((command.image_id, command.placement_id), (7, 3));
+        assert_eq!(command.z_index, -1);
+        assert!(!command.move_cursor);
+        assert_eq!(command.quiet, 1);
+        assert_eq!(command.payload, vec![1, 2, 3, 4, 5, 6]);
+
+        let graphic = command.load().unwrap();
+        assert_eq!(graphic.pixels, vec![1, 2, 3, 255, 4, 5, 6, 255]);
+
+        assert_eq!(Command::parse(b"a=x"), None);
+        assert_eq!(Command::parse(b"i=x"), None);
+        assert_eq!(Command::parse(b"a=t;%%%%"), None);
+    }
+
+    #[test]
+    fn parse_delete() {
+        let command = Command::parse(b"a=d,d=I,i=1").unwrap();
+        assert_eq!(command.delete, Some(DeleteTarget::Image));
+        assert!(command.delete_data);
+
+        let command = Command::parse(b"a=d").unwrap();
+        assert_eq!(command.delete, Some(DeleteTarget::All));
+        assert!(!command.delete_data);
+    }
+
+    #[test]
+    fn load_invalid_size() {
+        let command = Command::parse(b"f=32,s=2,v=1;AAAAAA==").unwrap();
+        assert!(matches!(command.load(), Err(Error::InvalidData)));
+
+        let command = Command::parse(b"f=32,s=5000,v=1;AAAAAA==").unwrap();
+        assert!(matches!(command.load(), Err(Error::TooLarge)));
+    }
+
+    #[test]
+    fn load_png() {
+        let mut png = Vec::new();
+        {
+            let mut encoder = png::Encoder::new(&mut png, 2, 1);
+            encoder.set_color(png::ColorType::GrayscaleAlpha);
+            encoder.set_depth(png::BitDepth::Eight);
+            let mut writer = encoder.write_header().unwrap();
+            writer.write_image_data(&[10, 255, 20, 128]).unwrap();
+        }
+
+        let mut command = Command::parse(b"f=100,o=z").unwrap();
+        command.payload = miniz_oxide::deflate::compress_to_vec_zlib(&png, 6);
+
+        let graphic = command.load().unwrap();
+        assert_eq!((graphic.width, graphic.height), (2, 1));
+        assert_eq!(graphic.pixels, vec![10, 10, 10, 255, 20, 20, 20, 128]);
+    }
+
+    #[test]
+    fn inflate_limit() {
+        let data = miniz_oxide::deflate::compress_to_vec_zlib(&[0; 64], 6);
+        assert_eq!(inflate(&data, 64).unwrap(), vec![0; 64]);
+        assert!(matches!(inflate(&data, 63), Err(Error::TooLarge)));
+
+        let command = Command::parse(b"f=32,s=1,v=1,o=z").unwrap();
+        let command = Command { payload: data, ..command };
+        assert!(matches!(command.load(), Err(Error::TooLarge)));
+    }
+
+    #[test]
+    fn read_temp_files() {
+        let temp_dir = env::temp_dir();
+        let nested_dir = temp_dir.join("alacritty-tty-graphics-protocol-test");
+        fs::create_dir_all(&nested_dir).unwrap();
+
+        // Only files directly inside the temporary directory are removed.
+        for dir in &[&temp_dir, &nested_dir] {
+            let path = dir.join(format!("{}-{}", TEMP_FILE_MARKER, std::process::id()));
+            fs::write(&path, [1, 2, 3, 4]).unwrap();
+
+            let command = Command::parse(b"t=t").unwrap();
+            let command =
+                Command { payload: path.to_string_lossy().as_bytes().to_vec(), ..command };
+            assert_eq!(command.read_file().unwrap(), vec![1, 2, 3, 4]);
+            assert_eq!(path.exists(), *dir == &nested_dir);
+        }
+
+        fs::remove_dir_all(&nested_dir).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn reject_special_files() {
+        let command = Command::parse(b"t=f").unwrap();
+        let command = Command { payload: b"/dev/zero".to_vec(), ..command };
+        let error = command.read_file().unwrap_err();
+        assert_eq!(error.to_string(), "EBADF:cannot read file");
+    }
+
+    #[test]
+    fn responses() {
+        let command = Command::parse(b"i=3;").unwrap();
+        assert_eq!(command.response(&Ok(())).unwrap(), "\x1b_Gi=3;OK\x1b\\");
+        let error = command.response(&Err(Error::NotFound(3))).unwrap();
+        assert_eq!(error, "\x1b_Gi=3;ENOENT:image 3 not found\x1b\\");
+
+        let command = Command::parse(b"i=3,q=1;").unwrap();
+        assert_eq!(command.response(&Ok(())), None);
+        assert!(command.response(&Err(Error::InvalidData)).is_some());
+
+        // Images without client ID never get a response.
+        let command = Command::parse(b"a=T;").unwrap();
+        assert_eq!(command.response(&Err(Error::InvalidData)), None);
+    }
+}
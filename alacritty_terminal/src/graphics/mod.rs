@@ -0,0 +1,289 @@
+//! Inline graphics attached to grid cells.
+//!
+//! Graphics are decoded into RGBA pixels and split into cell-sized pieces, with every cell
+//! covered by a graphic holding a reference to it. This way graphics scroll and get cleared
+//! together with the text around them, and their pixels are released once the last cell
+//! referencing them is gone.
+
+use std::collections::{HashMap, HashSet};
+use std::mem;
+use std::sync::{Arc, Weak};
+
+use parking_lot::Mutex;
+
+pub mod kitty;
+pub mod sixel;
+
+/// Maximum width and height of a graphic, in pixels.
+pub const MAX_GRAPHIC_DIMENSIONS: (usize, usize) = (4096, 4096);
+
+/// Maximum size of all stored kitty images, in bytes.
+///
+/// Once exceeded, the oldest images are removed first.
+const MAX_KITTY_IMAGES_SIZE: usize = 320 * 1024 * 1024;
+
+/// Unique identifier for every graphic added to a terminal.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct GraphicId(pub u64);
+
+/// Decoded graphic.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GraphicData {
+    /// Width in pixels.
+    pub width: usize,
+
+    /// Height in pixels.
+    pub height: usize,
+
+    /// RGBA pixels, starting at the top left corner.
+    pub pixels: Vec<u8>,
+}
+
+/// Reference to a graphic, shared by all cells displaying it.
+///
+/// Dropping the last reference queues the graphic for removal from the renderer.
+#[derive(Debug)]
+pub struct GraphicRef {
+    id: GraphicId,
+    remove_queue: Weak<Mutex<Vec<GraphicId>>>,
+}
+
+impl GraphicRef {
+    /// ID of the referenced graphic.
+    #[inline]
+    pub fn id(&self) -> GraphicId {
+        self.id
+    }
+}
+
+impl Drop for GraphicRef {
+    fn drop(&mut self) {
+        if let Some(remove_queue) = self.remove_queue.upgrade() {
+            remove_queue.lock().push(self.id);
+        }
+    }
+}
+
+/// Piece of a graphic displayed in a single cell.
+#[derive(Debug, Clone)]
+pub struct GraphicCell {
+    /// Graphic displayed in this cell.
+    pub graphic: Arc<GraphicRef>,
+
+    /// Horizontal offset of the cell inside the graphic, in pixels.
+    pub offset_x: u16,
+
+    /// Vertical offset of the cell inside the graphic, in pixels.
+    pub offset_y: u16,
+
+    /// Client assigned ID of the placement this cell is part of, `0` if absent.
+    pub placement_id: u32,
+
+    /// Stacking order relative to text, negative values are drawn below it.
+    pub z_index: i32,
+}
+
+impl PartialEq for GraphicCell {
+    fn eq(&self, other: &Self) -> bool {
+        self.graphic.id == other.graphic.id
+            && self.offset_x == other.offset_x
+            && self.offset_y == other.offset_y
+            && self.placement_id == other.placement_id
+            && self.z_index == other.z_index
+    }
+}
+
+impl Eq for GraphicCell {}
+
+/// Changes to the graphics of a terminal which have to be applied by the renderer.
+#[derive(Debug, Default)]
+pub struct UpdateQueues {
+    /// Graphics which have to be uploaded.
+    pub pending: Vec<(GraphicId, GraphicData)>,
+
+    /// Graphics which are no longer displayed by any cell.
+    pub remove_queue: Vec<GraphicId>,
+}
+
+/// Image transmitted with the kitty graphics protocol.
+#[derive(Debug, Clone)]
+pub struct KittyImage {
+    /// Reference keeping the image alive while it is not displayed.
+    pub graphic: Arc<GraphicRef>,
+
+    /// Width in pixels.
+    pub width: usize,
+
+    /// Height in pixels.
+    pub height: usize,
+}
+
+impl KittyImage {
+    /// Size of the image's RGBA pixels, in bytes.
+    fn size(&self) -> usize {
+        self.width * self.height * 4
+    }
+}
+
+/// Graphics state of a terminal.
+#[derive(Debug, Default)]
+pub struct Graphics {
+    /// ID for the next graphic.
+    next_id: u64,
+
+    /// Graphics added since the last update.
+    pending: Vec<(GraphicId, GraphicData)>,
+
+    /// Graphics without any references left.
+    remove_queue: Arc<Mutex<Vec<GraphicId>>>,
+
+    /// Kitty images by their client assigned ID.
+    kitty_images: HashMap<u32, KittyImage>,
+
+    /// Total size of all stored kitty images, in bytes.
+    kitty_images_size: usize,
+
+    /// Kitty graphics command which is still receiving chunks.
+    kitty_transmission: Option<kitty::Command>,
+}
+
+impl Graphics {
+    /// Register a new graphic, returning a reference for the cells displaying it.
+    pub fn insert(&mut self, graphic: GraphicData) -> Arc<GraphicRef> {
+        let id = GraphicId(self.next_id);
+        self.next_id += 1;
+
+        self.pending.push((id, graphic));
+
+        Arc::new(GraphicRef { id, remove_queue: Arc::downgrade(&self.remove_queue) })
+    }
+
+    /// Take all changes since the last update.
+    ///
+    /// Returns `None` if the graphics did not change.
+    pub fn take_queues(&mut self) -> Option<UpdateQueues> {
+        let remove_queue = mem::take(&mut *self.remove_queue.lock());
+        if self.pending.is_empty() && remove_queue.is_empty() {
+            return None;
+        }
+
+        Some(UpdateQueues { pending: mem::take(&mut self.pending), remove_queue })
+    }
+
+    /// Register a kitty image, storing it for later placements if it has an ID.
+    pub fn insert_kitty_image(&mut self, id: u32, graphic: GraphicData) -> KittyImage {
+        let (width, height) = (graphic.width, graphic.height);
+        let image = KittyImage { graphic: self.insert(graphic), width, height };
+
+        if id != 0 {
+            self.remove_kitty_image(id);
+            self.kitty_images_size += image.size();
+            self.kitty_images.insert(id, image.clone());
+
+            // Evict the oldest images, which have the lowest graphic IDs.
+            while self.kitty_images_size > MAX_KITTY_IMAGES_SIZE {
+                let oldest = self.kitty_images.iter().min_by_key(|(_, image)| image.graphic.id());
+                match oldest.map(|(id, _)| *id) {
+                    Some(oldest) => self.remove_kitty_image(oldest),
+                    None => break,
+                }
+            }
+        }
+
+        image
+    }
+
+    /// Kitty image with the specified ID.
+    pub fn kitty_image(&self, id: u32) -> Option<&KittyImage> {
+        self.kitty_images.get(&id)
+    }
+
+    /// Remove the stored data of a kitty image.
+    pub fn remove_kitty_image(&mut self, id: u32) {
+        if let Some(image) = self.kitty_images.remove(&id) {
+            self.kitty_images_size -= image.size();
+        }
+    }
+
+    /// Remove the stored data of kitty images displaying one of `graphics`, once none of their
+    /// placements are left.
+    pub fn remove_unplaced_kitty_images(&mut self, graphics: &HashSet<GraphicId>) {
+        let kitty_images_size = &mut self.kitty_images_size;
+        self.kitty_images.retain(|_, image| {
+            // Without placements, the stored image holds the only reference to the graphic.
+            let unplaced = Arc::strong_count(&image.graphic) == 1;
+            let remove = unplaced && graphics.contains(&image.graphic.id());
+            if remove {
+                *kitty_images_size -= image.size();
+            }
+            !remove
+        });
+    }
+
+    /// Remove the stored data of all kitty images.
+    pub fn clear_kitty_images(&mut self) {
+        self.kitty_images.clear();
+        self.kitty_images_size = 0;
+        self.kitty_transmission = None;
+    }
+
+    /// Combine chunked kitty graphics commands.
+    ///
+    /// Returns the complete command once its last chunk has been received.
+    pub fn kitty_chunk(&mut self, command: kitty::Command) -> Option<kitty::Command> {
+        let command = match self.kitty_transmission.take() {
+            Some(mut transmission) => {
+                transmission.append_chunk(command);
+                transmission
+            },
+            None => command,
+        };
+
+        if command.more_chunks {
+            self.kitty_transmission = Some(command);
+            None
+        } else {
+            Some(command)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn remove_unreferenced_graphics() {
+        let mut graphics = Graphics::default();
+        let data = GraphicData { width: 1, height: 1, pixels: vec![0; 4] };
+
+        let graphic = graphics.insert(data.clone());
+        let queues = graphics.take_queues().unwrap();
+        assert_eq!(queues.pending, vec![(graphic.id(), data)]);
+        assert!(queues.remove_queue.is_empty());
+        assert!(graphics.take_queues().is_none());
+
+        let id = graphic.id();
+        drop(graphic);
+        let queues = graphics.take_queues().unwrap();
+        assert!(queues.pending.is_empty());
+        assert_eq!(queues.remove_queue, vec![id]);
+    }
+
+    #[test]
+    fn evict_oldest_kitty_images() {
+        let mut graphics = Graphics::default();
+        let (width, height) = MAX_GRAPHIC_DIMENSIONS;
+        let data = GraphicData { width, height, pixels: Vec::new() };
+
+        let count = MAX_KITTY_IMAGES_SIZE / (width * height * 4);
+        for id in 1..=count as u32 + 1 {
+            graphics.insert_kitty_image(id, data.clone());
+        }
+
+        assert!(graphics.kitty_image(1).is_none());
+        assert!(graphics.kitty_image(2).is_some());
+        assert_eq!(graphics.kitty_images.len(), count);
+        assert_eq!(graphics.kitty_images_size, MAX_KITTY_IMAGES_SIZE);
+    }
+}
@@ -0,0 +1,356 @@
+//! Sixel graphics decoder.
+//!
+//! Sixel data is a stream of bands, six pixels high, in which every data byte describes a column
+//! of six pixels using the current color. The data is sent as `DCS P1 ; P2 ; P3 q <data> ST` and
+//! fed to the [`Parser`] one byte at a time.
+
+use std::cmp::{max, min};
+
+use crate::graphics::{GraphicData, MAX_GRAPHIC_DIMENSIONS};
+use crate::term::color::Rgb;
+
+/// Number of available color registers.
+const MAX_COLOR_REGISTERS: usize = 1024;
+
+/// Maximum number of parameters of a sixel control function.
+const MAX_COMMAND_PARAMS: usize = 5;
+
+/// Default color registers of the VT340, as RGB percentages.
+const VT340_PALETTE: [(u16, u16, u16); 16] = [
+    (0, 0, 0),
+    (20, 20, 80),
+    (80, 13, 13),
+    (20, 80, 20),
+    (80, 20, 80),
+    (20, 80, 80),
+    (80, 80, 20),
+    (53, 53, 53),
+    (26, 26, 26),
+    (33, 33, 60),
+    (60, 26, 26),
+    (33, 60, 33),
+    (60, 33, 60),
+    (33, 60, 60),
+    (60, 60, 33),
+    (80, 80, 80),
+];
+
+/// Control function waiting for its parameters.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Command {
+    None,
+    /// `! Pn`: Repeat the next sixel.
+    Repeat,
+    /// `" Pan ; Pad ; Ph ; Pv`: Set aspect ratio and image size.
+    RasterAttributes,
+    /// `# Pc` or `# Pc ; Pu ; Px ; Py ; Pz`: Select or define a color register.
+    ColorIntroducer,
+}
+
+/// Sixel stream decoder.
+#[derive(Debug)]
+pub struct Parser {
+    /// Command currently collecting parameters.
+    command: Command,
+
+    /// Parameters of the current command.
+    params: [u16; MAX_COMMAND_PARAMS],
+
+    /// Number of parameters of the current command.
+    param_count: usize,
+
+    /// Column of the next sixel.
+    x: usize,
+
+    /// Top row of the current sixel band.
+    y: usize,
+
+    /// Number of times the next sixel is repeated.
+    repeat: usize,
+
+    /// Defined color registers.
+    color_registers: Vec<Rgb>,
+
+    /// Index of the selected color register.
+    color: usize,
+
+    /// Image size requested by the raster attributes.
+    raster_size: (usize, usize),
+
+    /// Unset pixels are transparent instead of using the background color.
+    transparent_background: bool,
+
+    /// Pixel rows, `None` for pixels which were never set.
+    rows: Vec<Vec<Option<Rgb>>>,
+}
+
+impl Parser {
+    /// Create a new decoder.
+    ///
+    /// The `transparent_background` corresponds to a `P2` parameter of `1` in the sixel `DCS`.
+    pub fn new(transparent_background: bool) -> Self {
+        let color_registers =
+            VT340_PALETTE.iter().map(|&(r, g, b)| rgb_to_color(r, g, b)).collect();
+
+        Self {
+            command: Command::None,
+            params: [0; MAX_COMMAND_PARAMS],
+            param_count: 0,
+            x: 0,
+            y: 0,
+            repeat: 1,
+            color_registers,
+            color: 0,
+            raster_size: (0, 0),
+            transparent_background,
+            rows: Vec::new(),
+        }
+    }
+
+    /// Process the next byte of the sixel data.
+    pub fn put(&mut self, byte: u8) {
+        if self.command != Command::None {
+            match byte {
+                b'0'..=b'9' => {
+                    let param = &mut self.params[self.param_count - 1];
+                    *param = param.saturating_mul(10).saturating_add(u16::from(byte - b'0'));
+                    return;
+                },
+                b';' => {
+                    if self.param_count < MAX_COMMAND_PARAMS {
+                        self.params[self.param_count] = 0;
+                        self.param_count += 1;
+                    }
+                    return;
+                },
+                _ => self.finish_command(),
+            }
+        }
+
+        match byte {
+            b'?'..=b'~' => self.draw_sixel(byte - b'?'),
+            b'!' => self.start_command(Command::Repeat),
+            b'"' => self.start_command(Command::RasterAttributes),
+            b'#' => self.start_command(Command::ColorIntroducer),
+            // Graphics carriage return.
+            b'$' => self.x = 0,
+            // Graphics new line.
+            b'-' => {
+                self.x = 0;
+                self.y += 6;
+            },
+            _ => (),
+        }
+    }
+
+    /// Finish decoding and return the graphic.
+    ///
+    /// Returns `None` if the graphic has no pixels.
+    pub fn finish(mut self) -> Option<GraphicData> {
+        self.finish_command();
+
+        let drawn_width = self.rows.iter().map(Vec::len).max().unwrap_or(0);
+        let width = max(self.raster_size.0, drawn_width);
+        let height = max(self.raster_size.1, self.rows.len());
+        if width == 0 || height == 0 {
+            return None;
+        }
+
+        let background = if self.transparent_background {
+            [0, 0, 0, 0]
+        } else {
+            let Rgb { r, g, b } = self.color_registers[0];
+            [r, g, b, 255]
+        };
+
+        let mut pixels = Vec::with_capacity(width * height * 4);
+        for y in 0..height {
+            let row = self.rows.get(y).map(Vec::as_slice).unwrap_or_default();
+            for x in 0..width {
+                match row.get(x).copied().flatten() {
+                    Some(Rgb { r, g, b }) => pixels.extend_from_slice(&[r, g, b, 255]),
+                    None => pixels.extend_from_slice(&background),
+                }
+            }
+        }
+
+        Some(GraphicData { width, height, pixels })
+    }
+
+    fn start_command(&mut self, command: Command) {
+        self.command = command;
+        self.params[0] = 0;
+        self.param_count = 1;
+    }
+
+    fn finish_command(&mut self) {
+        let params = &self.params[..self.param_count];
+
+        match self.command {
+            Command::None => (),
+            Command::Repeat => self.repeat = max(usize::from(params[0]), 1),
+            Command::RasterAttributes => {
+                if let [_, _, width, height] = *params {
+                    let width = min(usize::from(width), MAX_GRAPHIC_DIMENSIONS.0);
+                    let height = min(usize::from(height), MAX_GRAPHIC_DIMENSIONS.1);
+                    self.raster_size = (width, height);
+                }
+            },
+            Command::ColorIntroducer => {
+                let register = usize::from(params[0]);
+                if register >= MAX_COLOR_REGISTERS {
+                    self.command = Command::None;
+                    return;
+                }
+
+                let color = match *params {
+                    [_, 1, hue, lightness, saturation] => {
+                        Some(hls_to_rgb(hue, lightness, saturation))
+                    },
+                    [_, 2, r, g, b] => Some(rgb_to_color(r, g, b)),
+                    _ => None,
+                };
+
+                if register >= self.color_registers.len() {
+                    self.color_registers.resize(register + 1, Rgb::default());
+                }
+
+                if let Some(color) = color {
+                    self.color_registers[register] = color;
+                }
+
+                self.color = register;
+            },
+        }
+
+        self.command = Command::None;
+    }
+
+    /// Draw a column of six pixels, repeated as requested by the last repeat command.
+    fn draw_sixel(&mut self, bits: u8) {
+        let start = self.x;
+        let end = min(start + self.repeat, MAX_GRAPHIC_DIMENSIONS.0);
+        self.x += self.repeat;
+        self.repeat = 1;
+
+        if bits == 0 || start >= end {
+            return;
+        }
+
+        let color = self.color_registers[self.color];
+        for i in (0..6).filter(|i| bits & (1 << i) != 0) {
+            let y = self.y + i;
+            if y >= MAX_GRAPHIC_DIMENSIONS.1 {
+                break;
+            }
+
+            if self.rows.len() <= y {
+                self.rows.resize_with(y + 1, Vec::new);
+            }
+
+            let row = &mut self.rows[y];
+            if row.len() < end {
+                row.resize(end, None);
+            }
+
+            for pixel in &mut row[start..end] {
+                *pixel = Some(color);
+            }
+        }
+    }
+}
+
+/// Convert RGB percentages to a color.
+fn rgb_to_color(r: u16, g: u16, b: u16) -> Rgb {
+    let convert = |percent: u16| ((u32::from(min(percent, 100)) * 255 + 50) / 100) as u8;
+    Rgb { r: convert(r), g: convert(g), b: convert(b) }
+}
+
+/// Convert a color in the HLS color space to RGB.
+///
+/// Sixel uses the DEC variant of HLS, in which a hue of 0° is blue instead of red.
+fn hls_to_rgb(hue: u16, lightness: u16, saturation: u16) -> Rgb {
+    let hue = f32::from((hue % 360 + 240) % 360) / 60.;
+    let lightness = f32::from(min(lightness, 100)) / 100.;
+    let saturation = f32::from(min(saturation, 100)) / 100.;
+
+    let chroma = (1. - (2. * lightness - 1.).abs()) * saturation;
+    let x = chroma * (1. - (hue % 2. - 1.).abs());
+    let (r, g, b) = match hue as u8 {
+        0 => (chroma, x, 0.),
+        1 => (x, chroma, 0.),
+        2 => (0., chroma, x),
+        3 => (0., x, chroma),
+        4 => (x, 0., chroma),
+        _ => (chroma, 0., x),
+    };
+
+    let m = lightness - chroma / 2.;
+    let convert = |value: f32| ((value + m) * 255.).round() as u8;
+    Rgb { r: convert(r), g: convert(g), b: convert(b) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decode(data: &[u8], transparent_background: bool) -> Option<GraphicData> {
+        let mut parser = Parser::new(transparent_background);
+        for byte in data {
+            parser.put(*byte);
+        }
+        parser.finish()
+    }
+
+    fn pixel(graphic: &GraphicData, x: usize, y: usize) -> &[u8] {
+        let offset = (y * graphic.width + x) * 4;
+        &graphic.pixels[offset..offset + 4]
+    }
+
+    #[test]
+    fn decode_bands() {
+        // Red column of six pixels, followed by a blue pixel in the next band.
+        let graphic = decode(b"#1;2;100;0;0#1~-#2;2;0;0;100#2@", true).unwrap();
+
+        assert_eq!((graphic.width, graphic.height), (1, 7));
+        assert_eq!(pixel(&graphic, 0, 0), &[255, 0, 0, 255]);
+        assert_eq!(pixel(&graphic, 0, 5), &[255, 0, 0, 255]);
+        assert_eq!(pixel(&graphic, 0, 6), &[0, 0, 255, 255]);
+    }
+
+    #[test]
+    fn decode_repeat_and_overlay() {
+        // Three pixels in the top row, overlaid with a green one after a carriage return.
+        let graphic = decode(b"#0;2;100;100;100!3@$#1;2;0;100;0?@", true).unwrap();
+
+        assert_eq!((graphic.width, graphic.height), (3, 1));
+        assert_eq!(pixel(&graphic, 0, 0), &[255, 255, 255, 255]);
+        assert_eq!(pixel(&graphic, 1, 0), &[0, 255, 0, 255]);
+        assert_eq!(pixel(&graphic, 2, 0), &[255, 255, 255, 255]);
+    }
+
+    #[test]
+    fn decode_raster_attributes() {
+        let graphic = decode(b"\"1;1;4;2#1;2;0;0;100@", true).unwrap();
+        assert_eq!((graphic.width, graphic.height), (4, 2));
+        assert_eq!(pixel(&graphic, 0, 0), &[0, 0, 255, 255]);
+        assert_eq!(pixel(&graphic, 3, 1), &[0, 0, 0, 0]);
+
+        // Without transparency, the background uses the first color register.
+        let graphic = decode(b"\"1;1;2;1#0;2;0;100;0#1@", false).unwrap();
+        assert_eq!(pixel(&graphic, 1, 0), &[0, 255, 0, 255]);
+    }
+
+    #[test]
+    fn decode_hls_colors() {
+        assert_eq!(hls_to_rgb(0, 50, 100), Rgb { r: 0, g: 0, b: 255 });
+        assert_eq!(hls_to_rgb(120, 50, 100), Rgb { r: 255, g: 0, b: 0 });
+        assert_eq!(hls_to_rgb(240, 50, 100), Rgb { r: 0, g: 255, b: 0 });
+        assert_eq!(hls_to_rgb(0, 100, 0), Rgb { r: 255, g: 255, b: 255 });
+    }
+
+    #[test]
+    fn decode_empty() {
+        assert_eq!(decode(b"#1;2;100;0;0", true), None);
+    }
+}
@@ -0,0 +1,710 @@
+//! Compressed storage for lines pushed out of the scrollback history.
+//!
+//! Archived lines are grouped into segments, which are serialized and compressed. Once the
+//! compressed segments exceed [`MAX_MEMORY_SIZE`], the oldest ones are moved into a temporary
+//! file. Segments are loaded back into the scrollback history in reverse order, so the archive
+//! behaves like a stack with the newest lines at the top.
+
+use std::cmp::max;
+use std::env;
+use std::fs::{File, OpenOptions};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::mem;
+#[cfg(windows)]
+use std::path::PathBuf;
+use std::process;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender, TryRecvError};
+use std::time::{Duration, UNIX_EPOCH};
+
+use log::error;
+
+use crate::ansi::LineSize;
+use crate::grid::{Dimensions, Grid, PromptMarks, Row};
+use crate::index::Column;
+use crate::term::cell::Cell;
+use crate::thread;
+
+/// Number of lines compressed into a single segment.
+const SEGMENT_LINES: usize = 1_000;
+
+/// Maximum size of compressed segments kept in memory before spilling them to disk.
+const MAX_MEMORY_SIZE: usize = 16 * 1024 * 1024;
+
+/// Compression level used for all segments.
+const COMPRESSION_LEVEL: u8 = 6;
+
+/// Counter for unique spill file names within this process.
+static SPILL_FILE_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+/// Lines sent to the compression thread, with the channel receiving the compressed segment.
+type CompressionJob = (Vec<Row<Cell>>, Sender<io::Result<Vec<u8>>>);
+
+/// Archive of lines which exceeded the scrollback history.
+#[derive(Debug, Default)]
+pub struct Archive {
+    /// Compressed segments, ordered from oldest to newest.
+    ///
+    /// All segments stored on disk precede the segments stored in memory, which precede the
+    /// segments still being compressed.
+    segments: Vec<Segment>,
+
+    /// Lines not compressed into a segment yet, ordered from oldest to newest.
+    pending: Vec<Row<Cell>>,
+
+    /// Combined size of all compressed segments kept in memory.
+    memory_size: usize,
+
+    /// Temporary file for segments spilled to disk.
+    spill_file: Option<SpillFile>,
+
+    /// Number of lines loaded into the history beyond its configured size.
+    paged_lines: usize,
+
+    /// Channel to the thread compressing segments, started with the first segment.
+    ///
+    /// Compression happens on a separate thread, so it does not block the terminal.
+    compressor: Option<Sender<CompressionJob>>,
+}
+
+impl Archive {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Number of archived lines.
+    pub fn len(&self) -> usize {
+        self.segments.iter().map(|segment| segment.lines).sum::<usize>() + self.pending.len()
+    }
+
+    /// Check if there are no archived lines.
+    pub fn is_empty(&self) -> bool {
+        self.segments.is_empty() && self.pending.is_empty()
+    }
+
+    /// Archive the lines which will be pushed out of the history by scrolling up `positions`.
+    ///
+    /// This must be called before the grid is scrolled.
+    pub fn scroll_up(&mut self, grid: &mut Grid<Cell>, positions: usize) {
+        // Return lines loaded from the archive once the viewport is back at the bottom.
+        if self.paged_lines > 0 && grid.display_offset() == 0 {
+            self.page_out(grid, 0);
+        }
+
+        let overflow = (grid.history_size() + positions).saturating_sub(grid.max_scroll_limit());
+        let total_lines = grid.total_lines();
+        for index in (total_lines - overflow..total_lines).rev() {
+            self.push(grid[index].clone());
+        }
+    }
+
+    /// Reduce the history to `history_size`, archiving all lines removed from it.
+    pub fn shrink_history(&mut self, grid: &mut Grid<Cell>, history_size: usize) {
+        let excess = grid.history_size().saturating_sub(history_size);
+        let total_lines = grid.total_lines();
+        for index in (total_lines - excess..total_lines).rev() {
+            self.push(grid[index].clone());
+        }
+
+        grid.update_history(history_size);
+        self.paged_lines = 0;
+    }
+
+    /// Load the newest archived lines back into the history.
+    ///
+    /// Returns `false` if there were no lines left to load.
+    pub fn page_in(&mut self, grid: &mut Grid<Cell>) -> bool {
+        let rows = match self.pop() {
+            Some(rows) => rows,
+            None => return false,
+        };
+
+        let max_scroll_limit = grid.max_scroll_limit();
+        grid.prepend_history(rows);
+        self.paged_lines += grid.max_scroll_limit() - max_scroll_limit;
+
+        true
+    }
+
+    /// Return lines loaded by [`Archive::page_in`] to the archive.
+    ///
+    /// The history is never reduced below `min_history` lines, to keep the viewport intact.
+    pub fn page_out(&mut self, grid: &mut Grid<Cell>, min_history: usize) {
+        let configured_size = grid.max_scroll_limit() - self.paged_lines;
+        let history_size = max(configured_size, min_history);
+        self.shrink_history(grid, history_size);
+        self.paged_lines = history_size - configured_size;
+    }
+
+    /// Remove all archived lines and restore the configured history size.
+    pub fn clear(&mut self, grid: &mut Grid<Cell>) {
+        if self.paged_lines > 0 {
+            let history_size = grid.max_scroll_limit() - self.paged_lines;
+            grid.update_history(history_size);
+        }
+
+        *self = Self::default();
+    }
+
+    /// Add a line above all previously archived lines.
+    fn push(&mut self, row: Row<Cell>) {
+        self.pending.push(row);
+
+        if self.pending.len() >= SEGMENT_LINES {
+            let rows = mem::take(&mut self.pending);
+            let lines = rows.len();
+
+            let compressor = self.compressor.get_or_insert_with(spawn_compressor);
+            let (sender, receiver) = mpsc::channel();
+            match compressor.send((rows, sender)) {
+                Ok(()) => self.segments.push(Segment { lines, data: Data::Compressing(receiver) }),
+                Err(_) => {
+                    error!("Unable to archive scrollback: compression thread stopped");
+                    self.compressor = None;
+                },
+            }
+
+            self.collect();
+        }
+    }
+
+    /// Remove the newest archived lines, ordered from oldest to newest.
+    fn pop(&mut self) -> Option<Vec<Row<Cell>>> {
+        if !self.pending.is_empty() {
+            return Some(mem::take(&mut self.pending));
+        }
+
+        // Skip segments which cannot be restored, so a single corrupt segment loses no others.
+        while let Some(segment) = self.segments.pop() {
+            let data = match segment.data {
+                Data::Compressing(receiver) => receive(&receiver, true).unwrap(),
+                Data::Memory(data) => {
+                    self.memory_size -= data.len();
+                    Ok(data)
+                },
+                Data::Disk { offset, size } => match self.spill_file.as_mut() {
+                    Some(spill_file) => spill_file.take(offset, size),
+                    None => Err(io::Error::new(io::ErrorKind::NotFound, "missing archive file")),
+                },
+            };
+
+            match data.and_then(|data| decompress(&data)) {
+                Ok(rows) => return Some(rows),
+                Err(err) => error!("Unable to restore archived scrollback: {}", err),
+            }
+        }
+
+        None
+    }
+
+    /// Store all segments which finished compressing in memory.
+    fn collect(&mut self) {
+        let mut index = self.segments.iter().take_while(|s| !s.data.is_compressing()).count();
+        while let Some(segment) = self.segments.get_mut(index) {
+            let result = match &segment.data {
+                Data::Compressing(receiver) => match receive(receiver, false) {
+                    Some(result) => result,
+                    None => break,
+                },
+                _ => unreachable!("segments are compressed in order"),
+            };
+
+            match result {
+                Ok(data) => {
+                    self.memory_size += data.len();
+                    segment.data = Data::Memory(data);
+                    index += 1;
+                },
+                Err(err) => {
+                    error!("Unable to archive scrollback: {}", err);
+                    self.segments.remove(index);
+                },
+            }
+        }
+
+        self.spill();
+    }
+
+    /// Move the oldest segments in memory to disk, until memory usage is within its limit.
+    fn spill(&mut self) {
+        while self.memory_size > MAX_MEMORY_SIZE {
+            if self.spill_file.is_none() {
+                match SpillFile::new() {
+                    Ok(spill_file) => self.spill_file = Some(spill_file),
+                    Err(err) => {
+                        error!("Unable to create scrollback archive file: {}", err);
+                        return;
+                    },
+                }
+            }
+
+            // Segments in memory always follow all segments on disk.
+            let index = self.segments.iter().take_while(|s| s.data.is_disk()).count();
+            let segment = &mut self.segments[index];
+            let spill_file = self.spill_file.as_mut().unwrap();
+
+            let result = match &segment.data {
+                Data::Memory(data) => spill_file.write(data).map(|offset| (offset, data.len())),
+                _ => return,
+            };
+
+            match result {
+                Ok((offset, size)) => {
+                    self.memory_size -= size;
+                    segment.data = Data::Disk { offset, size };
+                },
+                Err(err) => {
+                    error!("Unable to write scrollback archive file: {}", err);
+                    return;
+                },
+            }
+        }
+    }
+}
+
+/// Start the thread compressing segments.
+///
+/// The thread stops once the archive is dropped.
+fn spawn_compressor() -> Sender<CompressionJob> {
+    let (sender, receiver) = mpsc::channel::<CompressionJob>();
+
+    thread::spawn_named("scrollback archive", move || {
+        for (rows, result) in receiver {
+            let _ = result.send(compress(&rows));
+        }
+    });
+
+    sender
+}
+
+/// Receive the result of a segment's compression.
+///
+/// Returns `None` if the compression is still running and `block` is `false`.
+fn receive(receiver: &Receiver<io::Result<Vec<u8>>>, block: bool) -> Option<io::Result<Vec<u8>>> {
+    let result = if block {
+        receiver.recv().ok()
+    } else {
+        match receiver.try_recv() {
+            Err(TryRecvError::Empty) => return None,
+            result => result.ok(),
+        }
+    };
+
+    let stopped = || io::Error::new(io::ErrorKind::BrokenPipe, "compression thread stopped");
+    Some(result.unwrap_or_else(|| Err(stopped())))
+}
+
+/// Group of archived lines.
+#[derive(Debug)]
+struct Segment {
+    /// Number of lines in this segment.
+    lines: usize,
+
+    /// Compressed lines.
+    data: Data,
+}
+
+/// Storage of a segment's compressed data.
+#[derive(Debug)]
+enum Data {
+    /// Compression is still running.
+    Compressing(Receiver<io::Result<Vec<u8>>>),
+
+    /// Data kept in memory.
+    Memory(Vec<u8>),
+
+    /// Data stored in the spill file.
+    Disk { offset: u64, size: usize },
+}
+
+impl Data {
+    fn is_compressing(&self) -> bool {
+        matches!(self, Data::Compressing(_))
+    }
+
+    fn is_disk(&self) -> bool {
+        matches!(self, Data::Disk { .. })
+    }
+}
+
+/// Encode and compress lines.
+fn compress(rows: &[Row<Cell>]) -> io::Result<Vec<u8>> {
+    let mut encoded = Vec::new();
+    for row in rows {
+        encode_row(row, &mut encoded)?;
+    }
+
+    Ok(miniz_oxide::deflate::compress_to_vec(&encoded, COMPRESSION_LEVEL))
+}
+
+/// Decompress and decode lines.
+fn decompress(data: &[u8]) -> io::Result<Vec<Row<Cell>>> {
+    let encoded = miniz_oxide::inflate::decompress_to_vec(data)
+        .map_err(|status| io::Error::new(io::ErrorKind::InvalidData, format!("{:?}", status)))?;
+
+    let mut reader = Reader(&encoded);
+    let mut rows = Vec::new();
+    while !reader.0.is_empty() {
+        rows.push(decode_row(&mut reader)?);
+    }
+
+    Ok(rows)
+}
+
+/// Append the binary encoding of a line.
+///
+/// Lines are stored as their text, followed by runs of cells sharing the same attributes. Since
+/// attributes rarely change within a line, they only add little to the size of the text.
+fn encode_row(row: &Row<Cell>, out: &mut Vec<u8>) -> io::Result<()> {
+    let cells = &row[..];
+    write_len(out, cells.len());
+    write_len(out, row.occ);
+    out.push(match row.line_size {
+        LineSize::Normal => 0,
+        LineSize::DoubleWidth => 1,
+        LineSize::DoubleHeightTop => 2,
+        LineSize::DoubleHeightBottom => 3,
+    });
+    out.push(row.marks.bits());
+
+    match row.timestamp.and_then(|timestamp| timestamp.duration_since(UNIX_EPOCH).ok()) {
+        Some(duration) => {
+            out.push(1);
+            out.extend_from_slice(&duration.as_secs().to_le_bytes());
+            out.extend_from_slice(&duration.subsec_nanos().to_le_bytes());
+        },
+        None => out.push(0),
+    }
+
+    let text: String = cells.iter().map(|cell| cell.c).collect();
+    write_bytes(out, text.as_bytes());
+
+    let mut start = 0;
+    while start < cells.len() {
+        let template = &cells[start];
+        let len = cells[start..].iter().take_while(|cell| cell.same_attributes(template)).count();
+        write_len(out, len);
+        write_bytes(out, &serde_json::to_vec(template)?);
+        start += len;
+    }
+
+    Ok(())
+}
+
+/// Decode a line written by [`encode_row`].
+fn decode_row(reader: &mut Reader<'_>) -> io::Result<Row<Cell>> {
+    let columns = reader.read_len()?;
+    let occ = reader.read_len()?;
+    let line_size = match reader.read_u8()? {
+        0 => LineSize::Normal,
+        1 => LineSize::DoubleWidth,
+        2 => LineSize::DoubleHeightTop,
+        3 => LineSize::DoubleHeightBottom,
+        _ => return Err(invalid_data()),
+    };
+    let marks = PromptMarks::from_bits_truncate(reader.read_u8()?);
+
+    let timestamp = match reader.read_u8()? {
+        0 => None,
+        _ => {
+            let secs = reader.read_u64()?;
+            let nanos = reader.read_u32()?;
+            UNIX_EPOCH.checked_add(Duration::new(secs, nanos))
+        },
+    };
+
+    let text = std::str::from_utf8(reader.read_bytes()?).map_err(|_| invalid_data())?;
+    if text.chars().count() != columns {
+        return Err(invalid_data());
+    }
+
+    let mut row = Row::new(Column(columns));
+    let mut chars = text.chars();
+    let mut column = 0;
+    while column < columns {
+        let len = reader.read_len()?;
+        let template: Cell = serde_json::from_slice(reader.read_bytes()?)?;
+        if len == 0 || column + len > columns {
+            return Err(invalid_data());
+        }
+
+        for c in chars.by_ref().take(len) {
+            let cell = &mut row[Column(column)];
+            *cell = template.clone();
+            cell.c = c;
+            column += 1;
+        }
+    }
+
+    row.occ = occ.min(columns);
+    row.line_size = line_size;
+    row.marks = marks;
+    row.timestamp = timestamp;
+
+    Ok(row)
+}
+
+/// Append a length to the encoded data.
+fn write_len(out: &mut Vec<u8>, len: usize) {
+    out.extend_from_slice(&(len as u32).to_le_bytes());
+}
+
+/// Append a length-prefixed byte string to the encoded data.
+fn write_bytes(out: &mut Vec<u8>, bytes: &[u8]) {
+    write_len(out, bytes.len());
+    out.extend_from_slice(bytes);
+}
+
+fn invalid_data() -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, "invalid archived line")
+}
+
+/// Cursor over encoded data.
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn read_bytes_exact(&mut self, len: usize) -> io::Result<&'a [u8]> {
+        if self.0.len() < len {
+            return Err(invalid_data());
+        }
+
+        let (bytes, rest) = self.0.split_at(len);
+        self.0 = rest;
+        Ok(bytes)
+    }
+
+    fn read_u8(&mut self) -> io::Result<u8> {
+        Ok(self.read_bytes_exact(1)?[0])
+    }
+
+    fn read_u32(&mut self) -> io::Result<u32> {
+        let mut bytes = [0; 4];
+        bytes.copy_from_slice(self.read_bytes_exact(4)?);
+        Ok(u32::from_le_bytes(bytes))
+    }
+
+    fn read_u64(&mut self) -> io::Result<u64> {
+        let mut bytes = [0; 8];
+        bytes.copy_from_slice(self.read_bytes_exact(8)?);
+        Ok(u64::from_le_bytes(bytes))
+    }
+
+    fn read_len(&mut self) -> io::Result<usize> {
+        Ok(self.read_u32()? as usize)
+    }
+
+    fn read_bytes(&mut self) -> io::Result<&'a [u8]> {
+        let len = self.read_len()?;
+        self.read_bytes_exact(len)
+    }
+}
+
+/// Temporary file storing segments on disk.
+#[derive(Debug)]
+struct SpillFile {
+    file: File,
+
+    /// Size of all segments in the file.
+    len: u64,
+
+    /// Path of the file, which is removed on drop.
+    ///
+    /// On other platforms, the file is unlinked right after its creation instead.
+    #[cfg(windows)]
+    path: PathBuf,
+}
+
+impl SpillFile {
+    fn new() -> io::Result<Self> {
+        let count = SPILL_FILE_COUNT.fetch_add(1, Ordering::Relaxed);
+        let name = format!("alacritty-scrollback-{}-{}", process::id(), count);
+        let path = env::temp_dir().join(name);
+
+        let file = OpenOptions::new().read(true).write(true).create_new(true).open(&path)?;
+
+        // Keep the file accessible only through the open handle.
+        #[cfg(not(windows))]
+        std::fs::remove_file(&path)?;
+
+        Ok(Self {
+            file,
+            len: 0,
+            #[cfg(windows)]
+            path,
+        })
+    }
+
+    /// Append data to the end of the file, returning its offset.
+    fn write(&mut self, data: &[u8]) -> io::Result<u64> {
+        let offset = self.len;
+        self.file.seek(SeekFrom::Start(offset))?;
+        self.file.write_all(data)?;
+        self.len += data.len() as u64;
+
+        Ok(offset)
+    }
+
+    /// Read the data at the end of the file and remove it.
+    fn take(&mut self, offset: u64, size: usize) -> io::Result<Vec<u8>> {
+        let mut data = vec![0; size];
+        self.file.seek(SeekFrom::Start(offset))?;
+        self.file.read_exact(&mut data)?;
+
+        self.file.set_len(offset)?;
+        self.len = offset;
+
+        Ok(data)
+    }
+}
+
+#[cfg(windows)]
+impl Drop for SpillFile {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::sync::Arc;
+    use std::time::SystemTime;
+
+    use crate::ansi::{Color, Hyperlink, NamedColor};
+    use crate::index::Line;
+    use crate::term::cell::Flags;
+
+    /// Create a grid with numbered lines and a full history.
+    fn numbered_grid(history_size: usize) -> Grid<Cell> {
+        let mut grid = Grid::<Cell>::new(Line(2), Column(4), history_size);
+        for i in 0..history_size + 2 {
+            grid.scroll_up(&(Line(0)..Line(2)), &(Column(0)..Column(4)), Line(1));
+            grid[Line(1)][Column(0)].c = char::from(b'a' + i as u8);
+        }
+        grid
+    }
+
+    #[test]
+    fn archive_and_page_in() {
+        let mut grid = numbered_grid(3);
+        let mut archive = Archive::new();
+
+        // Scroll two more lines, pushing 'a' and 'b' out of the history.
+        for c in &['f', 'g'] {
+            archive.scroll_up(&mut grid, 1);
+            grid.scroll_up(&(Line(0)..Line(2)), &(Column(0)..Column(4)), Line(1));
+            grid[Line(1)][Column(0)].c = *c;
+        }
+        assert_eq!(archive.len(), 2);
+        assert_eq!(grid[grid.total_lines() - 1][Column(0)].c, 'c');
+
+        // Load archived lines above the history.
+        assert!(archive.page_in(&mut grid));
+        assert!(archive.is_empty());
+        assert_eq!(grid.history_size(), 5);
+        assert_eq!(grid[grid.total_lines() - 1][Column(0)].c, 'a');
+        assert_eq!(grid[grid.total_lines() - 2][Column(0)].c, 'b');
+        assert!(!archive.page_in(&mut grid));
+
+        // Return loaded lines to the archive once scrolling resumes.
+        archive.scroll_up(&mut grid, 1);
+        assert_eq!(grid.max_scroll_limit(), 3);
+        assert_eq!(archive.len(), 3);
+    }
+
+    #[test]
+    fn compressed_segments() {
+        let mut grid = numbered_grid(0);
+        let mut archive = Archive::new();
+
+        for _ in 0..SEGMENT_LINES + 1 {
+            archive.scroll_up(&mut grid, 1);
+            grid.scroll_up(&(Line(0)..Line(2)), &(Column(0)..Column(4)), Line(1));
+        }
+        assert_eq!(archive.segments.len(), 1);
+        assert_eq!(archive.len(), SEGMENT_LINES + 1);
+
+        // Pending lines are loaded before compressed segments.
+        assert!(archive.page_in(&mut grid));
+        assert_eq!(grid.history_size(), 1);
+        assert!(archive.page_in(&mut grid));
+        assert_eq!(grid.history_size(), SEGMENT_LINES + 1);
+        assert_eq!(grid[grid.total_lines() - 1][Column(0)].c, 'a');
+    }
+
+    #[test]
+    fn page_out_keeps_viewport() {
+        let mut grid = numbered_grid(3);
+        let mut archive = Archive::new();
+        for _ in 0..2 {
+            archive.scroll_up(&mut grid, 1);
+            grid.scroll_up(&(Line(0)..Line(2)), &(Column(0)..Column(4)), Line(1));
+        }
+
+        assert!(archive.page_in(&mut grid));
+        assert_eq!(grid.history_size(), 5);
+
+        // Lines above the viewport are returned to the archive.
+        archive.page_out(&mut grid, 4);
+        assert_eq!(grid.history_size(), 4);
+        assert_eq!(archive.len(), 1);
+        assert_eq!(archive.paged_lines, 1);
+
+        archive.page_out(&mut grid, 0);
+        assert_eq!(grid.history_size(), 3);
+        assert_eq!(archive.len(), 2);
+        assert_eq!(archive.paged_lines, 0);
+    }
+
+    #[test]
+    fn encode_rows() {
+        let mut row = Row::<Cell>::new(Column(5));
+        for (column, c) in "ab中 e".chars().enumerate() {
+            row[Column(column)].c = c;
+        }
+        row[Column(0)].fg = Color::Named(NamedColor::Red);
+        row[Column(1)].flags = Flags::BOLD | Flags::UNDERLINE;
+        row[Column(1)].set_underline_color(Some(Color::Indexed(3)));
+        row[Column(2)].flags = Flags::WIDE_CHAR;
+        row[Column(2)].push_zerowidth('\u{301}');
+        row[Column(3)].flags = Flags::WIDE_CHAR_SPACER;
+        let hyperlink = Hyperlink { id: Some(String::from("1")), uri: String::from("file:///") };
+        row[Column(4)].set_hyperlink(Some(Arc::new(hyperlink)));
+        row.occ = 5;
+        row.line_size = LineSize::DoubleHeightTop;
+        row.marks = PromptMarks::PROMPT_START | PromptMarks::OUTPUT_START;
+        row.timestamp = Some(UNIX_EPOCH + Duration::new(1_600_000_000, 123));
+
+        let mut plain = Row::new(Column(5));
+        plain.timestamp = Some(SystemTime::now());
+
+        let rows = vec![row, plain];
+        let decoded = decompress(&compress(&rows).unwrap()).unwrap();
+        assert_eq!(decoded, rows);
+        for (decoded, row) in decoded.iter().zip(&rows) {
+            assert_eq!(decoded.occ, row.occ);
+            assert_eq!(decoded.line_size, row.line_size);
+            assert_eq!(decoded.marks, row.marks);
+            assert_eq!(decoded.timestamp, row.timestamp);
+        }
+
+        // Truncated data is rejected instead of producing partial lines.
+        let mut encoded = Vec::new();
+        encode_row(&rows[0], &mut encoded).unwrap();
+        encoded.pop();
+        assert!(decode_row(&mut Reader(&encoded)).is_err());
+    }
+
+    #[test]
+    fn spill_file() {
+        let mut spill_file = SpillFile::new().unwrap();
+
+        let first = spill_file.write(b"first").unwrap();
+        let second = spill_file.write(b"second").unwrap();
+
+        assert_eq!(spill_file.take(second, 6).unwrap(), b"second");
+        assert_eq!(spill_file.take(first, 5).unwrap(), b"first");
+        assert_eq!(spill_file.len, 0);
+    }
+}
@@ -9,13 +9,14 @@ use crate::ansi::{CharsetIndex, StandardCharset};
 use crate::index::{Column, IndexRange, Line, Point};
 use crate::term::cell::{Flags, ResetDiscriminant};
 
+pub mod archive;
 pub mod resize;
 mod row;
 mod storage;
 #[cfg(test)]
 mod tests;
 
-pub use self::row::Row;
+pub use self::row::{PromptMarks, Row};
 use self::storage::Storage;
 
 /// Bidirectional iterator.
@@ -189,6 +190,26 @@ impl<T: GridCell + Default + PartialEq + Clone> Grid<T> {
         self.max_scroll_limit = history_size;
     }
 
+    /// Add rows above the oldest line of the scrollback history.
+    ///
+    /// The `rows` are ordered from oldest to newest. If necessary, the maximum history size is
+    /// increased to make room for all of them.
+    pub fn prepend_history(&mut self, rows: Vec<Row<T>>) {
+        let count = rows.len();
+        self.max_scroll_limit = max(self.max_scroll_limit, self.history_size() + count);
+
+        let total_lines = self.raw.len();
+        self.raw.initialize(count, self.cols);
+
+        for (i, mut row) in rows.into_iter().rev().enumerate() {
+            // Rows of a different width are truncated or padded, without reflowing them.
+            row.grow(self.cols);
+            row.shrink(self.cols);
+
+            self.raw[total_lines + i] = row;
+        }
+    }
+
     pub fn scroll_display(&mut self, scroll: Scroll) {
         self.display_offset = match scroll {
             Scroll::Delta(count) => min(
@@ -218,11 +239,17 @@ impl<T: GridCell + Default + PartialEq + Clone> Grid<T> {
     }
 
     #[inline]
-    pub fn scroll_down<D>(&mut self, region: &Range<Line>, positions: Line)
+    pub fn scroll_down<D>(&mut self, region: &Range<Line>, columns: &Range<Column>, positions: Line)
     where
         T: ResetDiscriminant<D>,
         D: PartialEq,
     {
+        // Rectangles which do not span the full width can't be rotated.
+        if !self.is_full_width(columns) {
+            self.scroll_rect_down(region, columns, positions);
+            return;
+        }
+
         let screen_lines = self.screen_lines().0;
 
         // When rotating the entire region, just reset everything.
@@ -282,11 +309,17 @@ impl<T: GridCell + Default + PartialEq + Clone> Grid<T> {
     /// Move lines at the bottom toward the top.
     ///
     /// This is the performance-sensitive part of scrolling.
-    pub fn scroll_up<D>(&mut self, region: &Range<Line>, positions: Line)
+    pub fn scroll_up<D>(&mut self, region: &Range<Line>, columns: &Range<Column>, positions: Line)
     where
         T: ResetDiscriminant<D>,
         D: PartialEq,
     {
+        // Rectangles which do not span the full width can't be rotated.
+        if !self.is_full_width(columns) {
+            self.scroll_rect_up(region, columns, positions);
+            return;
+        }
+
         let screen_lines = self.screen_lines().0;
 
         // When rotating the entire region with fixed lines at the top, just reset everything.
@@ -335,6 +368,54 @@ impl<T: GridCell + Default + PartialEq + Clone> Grid<T> {
         }
     }
 
+    /// Check if a column range covers the entire width of the grid.
+    #[inline]
+    fn is_full_width(&self, columns: &Range<Column>) -> bool {
+        columns.start == Column(0) && columns.end >= self.cols
+    }
+
+    /// Move cells inside a rectangle toward the top.
+    ///
+    /// Unlike full width scrolling, this never pushes lines into the scrollback history.
+    fn scroll_rect_up(&mut self, region: &Range<Line>, columns: &Range<Column>, positions: Line) {
+        let positions = min(positions, region.end - region.start);
+
+        for line in IndexRange(region.start..(region.end - positions)) {
+            for column in IndexRange(columns.clone()) {
+                let cell = self[line + positions][column].clone();
+                self[line][column] = cell;
+            }
+        }
+
+        for line in IndexRange((region.end - positions)..region.end) {
+            self.reset_rect_line(line, columns);
+        }
+    }
+
+    /// Move cells inside a rectangle toward the bottom.
+    fn scroll_rect_down(&mut self, region: &Range<Line>, columns: &Range<Column>, positions: Line) {
+        let positions = min(positions, region.end - region.start);
+
+        for line in IndexRange((region.start + positions)..region.end).rev() {
+            for column in IndexRange(columns.clone()) {
+                let cell = self[line - positions][column].clone();
+                self[line][column] = cell;
+            }
+        }
+
+        for line in IndexRange(region.start..(region.start + positions)) {
+            self.reset_rect_line(line, columns);
+        }
+    }
+
+    /// Reset the cells of a line inside a column range to the cursor template.
+    fn reset_rect_line(&mut self, line: Line, columns: &Range<Column>) {
+        let template = self.cursor.template.clone();
+        for cell in &mut self[line][columns.clone()] {
+            cell.reset(&template);
+        }
+    }
+
     pub fn clear_viewport<D>(&mut self)
     where
         T: ResetDiscriminant<D>,
@@ -356,7 +437,7 @@ impl<T: GridCell + Default + PartialEq + Clone> Grid<T> {
         self.display_offset = 0;
 
         // Clear the viewport.
-        self.scroll_up(&region, positions);
+        self.scroll_up(&region, &(Column(0)..self.cols), positions);
 
         // Reset rotated lines.
         for i in positions.0..self.lines.0 {
@@ -467,6 +548,20 @@ impl<T> Grid<T> {
         self.display_offset
     }
 
+    /// Maximum number of lines in the scrollback history.
+    #[inline]
+    pub fn max_scroll_limit(&self) -> usize {
+        self.max_scroll_limit
+    }
+
+    /// Check if the grid's dimensions are consistent, like after deserialization.
+    pub fn is_valid(&self) -> bool {
+        self.lines.0 > 0
+            && self.cols.0 > 0
+            && self.raw.is_valid(self.lines, self.cols)
+            && self.display_offset <= self.history_size()
+    }
+
     #[inline]
     pub fn cursor_cell(&mut self) -> &mut T {
         let point = self.cursor.point;
@@ -57,7 +57,7 @@ impl<T: GridCell + Default + PartialEq + Clone> Grid<T> {
         // Move existing lines up for every line that couldn't be pulled from history.
         if from_history != lines_added.0 {
             let delta = lines_added - from_history;
-            self.scroll_up(&(Line(0)..new_line_count), delta);
+            self.scroll_up(&(Line(0)..new_line_count), &(Column(0)..self.cols), delta);
         }
 
         // Move cursor down for every line pulled from history.
@@ -83,7 +83,8 @@ impl<T: GridCell + Default + PartialEq + Clone> Grid<T> {
         // Scroll up to keep content inside the window.
         let required_scrolling = (self.cursor.point.line + 1).saturating_sub(target.0);
         if required_scrolling > 0 {
-            self.scroll_up(&(Line(0)..self.lines), Line(required_scrolling));
+            let columns = Column(0)..self.cols;
+            self.scroll_up(&(Line(0)..self.lines), &columns, Line(required_scrolling));
 
             // Clamp cursors to the new viewport size.
             self.cursor.point.line = min(self.cursor.point.line, target - 1);
@@ -182,11 +183,15 @@ impl<T: GridCell + Default + PartialEq + Clone> Grid<T> {
                 let line_delta = (self.cursor.point.line - target.line).0;
 
                 if line_delta != 0 && row.is_clear() {
+                    last_row.marks.insert(row.marks);
                     continue;
                 }
 
                 cursor_line_delta += line_delta;
             } else if row.is_clear() {
+                // Keep shell integration marks of the removed line.
+                last_row.marks.insert(row.marks);
+
                 if i + reversed.len() >= self.lines.0 {
                     // Since we removed a line, rotate down the viewport.
                     self.display_offset = self.display_offset.saturating_sub(1);
@@ -352,6 +357,9 @@ impl<T: GridCell + Default + PartialEq + Clone> Grid<T> {
                         wrapped.resize_with(cols.0, T::default);
                     }
                     row = Row::from_vec(wrapped, occ);
+
+                    // Keep the arrival time of the line the cells were wrapped from.
+                    row.timestamp = new_raw.last().and_then(|row| row.timestamp);
                 }
             }
         }
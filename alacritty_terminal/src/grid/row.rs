@@ -5,13 +5,27 @@ use std::ops::{Index, IndexMut};
 use std::ops::{Range, RangeFrom, RangeFull, RangeTo, RangeToInclusive};
 use std::ptr;
 use std::slice;
+use std::time::SystemTime;
 
+use bitflags::bitflags;
 use serde::{Deserialize, Serialize};
 
+use crate::ansi::LineSize;
 use crate::grid::GridCell;
 use crate::index::Column;
 use crate::term::cell::ResetDiscriminant;
 
+bitflags! {
+    /// Shell integration marks, set through `OSC 133`.
+    #[derive(Serialize, Deserialize, Default)]
+    pub struct PromptMarks: u8 {
+        const PROMPT_START  = 0b0001;
+        const COMMAND_START = 0b0010;
+        const OUTPUT_START  = 0b0100;
+        const COMMAND_END   = 0b1000;
+    }
+}
+
 /// A row in the grid.
 #[derive(Serialize, Deserialize, Default, Clone, Debug)]
 pub struct Row<T> {
@@ -22,6 +36,18 @@ pub struct Row<T> {
     /// This is the upper bound on the number of elements in the row, which have been modified
     /// since the last reset. All cells after this point are guaranteed to be equal.
     pub(crate) occ: usize,
+
+    /// Shell integration marks placed on this row.
+    #[serde(default)]
+    pub(crate) marks: PromptMarks,
+
+    /// Size of the characters in this row.
+    #[serde(default)]
+    pub(crate) line_size: LineSize,
+
+    /// Time at which the first cell of this row was written.
+    #[serde(default)]
+    pub(crate) timestamp: Option<SystemTime>,
 }
 
 impl<T: PartialEq> PartialEq for Row<T> {
@@ -52,7 +78,13 @@ impl<T: Clone + Default> Row<T> {
             inner.set_len(columns.0);
         }
 
-        Row { inner, occ: 0 }
+        Row {
+            inner,
+            occ: 0,
+            marks: PromptMarks::empty(),
+            line_size: LineSize::Normal,
+            timestamp: None,
+        }
     }
 
     /// Increase the number of columns in the row.
@@ -111,6 +143,9 @@ impl<T: Clone + Default> Row<T> {
         }
 
         self.occ = 0;
+        self.marks = PromptMarks::empty();
+        self.line_size = LineSize::Normal;
+        self.timestamp = None;
     }
 }
 
@@ -118,7 +153,13 @@ impl<T: Clone + Default> Row<T> {
 impl<T> Row<T> {
     #[inline]
     pub fn from_vec(vec: Vec<T>, occ: usize) -> Row<T> {
-        Row { inner: vec, occ }
+        Row {
+            inner: vec,
+            occ,
+            marks: PromptMarks::empty(),
+            line_size: LineSize::Normal,
+            timestamp: None,
+        }
     }
 
     #[inline]
@@ -126,6 +167,24 @@ impl<T> Row<T> {
         self.inner.len()
     }
 
+    /// Shell integration marks placed on this row.
+    #[inline]
+    pub fn marks(&self) -> PromptMarks {
+        self.marks
+    }
+
+    /// Size of the characters in this row.
+    #[inline]
+    pub fn line_size(&self) -> LineSize {
+        self.line_size
+    }
+
+    /// Time at which the first cell of this row was written.
+    #[inline]
+    pub fn timestamp(&self) -> Option<SystemTime> {
+        self.timestamp
+    }
+
     #[inline]
     pub fn last(&self) -> Option<&T> {
         self.inner.last()
@@ -139,6 +139,15 @@ impl<T> Storage<T> {
         self.len
     }
 
+    /// Check if the buffer is consistent with the grid dimensions, like after deserialization.
+    pub fn is_valid(&self, lines: Line, cols: Column) -> bool {
+        self.visible_lines == lines
+            && lines.0 <= self.len
+            && self.len <= self.inner.len()
+            && self.zero < self.inner.len()
+            && self.inner.iter().all(|row| row.len() == cols.0 && row.occ <= cols.0)
+    }
+
     #[inline]
     pub fn swap_lines(&mut self, a: Line, b: Line) {
         let offset = self.inner.len() + self.zero + *self.visible_lines - 1;
@@ -147,37 +156,11 @@ impl<T> Storage<T> {
         self.inner.swap(a, b);
     }
 
-    /// Swap implementation for Row<T>.
-    ///
-    /// Exploits the known size of Row<T> to produce a slightly more efficient
-    /// swap than going through slice::swap.
-    ///
-    /// The default implementation from swap generates 8 movups and 4 movaps
-    /// instructions. This implementation achieves the swap in only 8 movups
-    /// instructions.
+    #[inline]
     pub fn swap(&mut self, a: usize, b: usize) {
-        debug_assert_eq!(mem::size_of::<Row<T>>(), mem::size_of::<usize>() * 4);
-
         let a = self.compute_index(a);
         let b = self.compute_index(b);
-
-        unsafe {
-            // Cast to a qword array to opt out of copy restrictions and avoid
-            // drop hazards. Byte array is no good here since for whatever
-            // reason LLVM won't optimized it.
-            let a_ptr = self.inner.as_mut_ptr().add(a) as *mut usize;
-            let b_ptr = self.inner.as_mut_ptr().add(b) as *mut usize;
-
-            // Copy 1 qword at a time.
-            //
-            // The optimizer unrolls this loop and vectorizes it.
-            let mut tmp: usize;
-            for i in 0..4 {
-                tmp = *a_ptr.offset(i);
-                *a_ptr.offset(i) = *b_ptr.offset(i);
-                *b_ptr.offset(i) = tmp;
-            }
-        }
+        self.inner.swap(a, b);
     }
 
     /// Rotate the grid, moving all lines up/down in history.
@@ -64,7 +64,7 @@ fn scroll_up() {
         grid[Line(i)][Column(0)] = i;
     }
 
-    grid.scroll_up::<usize>(&(Line(0)..Line(10)), Line(2));
+    grid.scroll_up::<usize>(&(Line(0)..Line(10)), &(Column(0)..Column(1)), Line(2));
 
     assert_eq!(grid[Line(0)][Column(0)], 2);
     assert_eq!(grid[Line(0)].occ, 1);
@@ -96,7 +96,7 @@ fn scroll_down() {
         grid[Line(i)][Column(0)] = i;
     }
 
-    grid.scroll_down::<usize>(&(Line(0)..Line(10)), Line(2));
+    grid.scroll_down::<usize>(&(Line(0)..Line(10)), &(Column(0)..Column(1)), Line(2));
 
     assert_eq!(grid[Line(0)][Column(0)], 0); // was 8.
     assert_eq!(grid[Line(0)].occ, 0);
@@ -120,6 +120,30 @@ fn scroll_down() {
     assert_eq!(grid[Line(9)].occ, 1);
 }
 
+// Scrolling a rectangle only moves cells within its columns.
+#[test]
+fn scroll_rect() {
+    let mut grid = Grid::<usize>::new(Line(3), Column(3), 10);
+    for i in 0..3 {
+        for j in 0..3 {
+            grid[Line(i)][Column(j)] = i * 3 + j + 1;
+        }
+    }
+
+    grid.scroll_up::<usize>(&(Line(0)..Line(3)), &(Column(1)..Column(2)), Line(1));
+
+    assert_eq!(grid[Line(0)][..], [1, 5, 3]);
+    assert_eq!(grid[Line(1)][..], [4, 8, 6]);
+    assert_eq!(grid[Line(2)][..], [7, 0, 9]);
+    assert_eq!(grid.history_size(), 0);
+
+    grid.scroll_down::<usize>(&(Line(1)..Line(3)), &(Column(0)..Column(2)), Line(1));
+
+    assert_eq!(grid[Line(0)][..], [1, 5, 3]);
+    assert_eq!(grid[Line(1)][..], [0, 0, 6]);
+    assert_eq!(grid[Line(2)][..], [4, 8, 9]);
+}
+
 // Test that GridIterator works.
 #[test]
 fn test_iter() {
@@ -346,6 +370,35 @@ fn shrink_reflow_disabled() {
     assert_eq!(grid[0][Column(1)], cell('2'));
 }
 
+#[test]
+fn reflow_prompt_marks() {
+    let mut grid = Grid::<Cell>::new(Line(3), Column(2), 2);
+    grid.cursor.point = Point::new(Line(2), Column(0));
+    grid[Line(0)][Column(0)] = cell('1');
+    grid[Line(0)][Column(1)] = wrap_cell('2');
+    grid[Line(0)].marks.insert(PromptMarks::PROMPT_START);
+    grid[Line(1)][Column(0)] = cell('3');
+    grid[Line(1)].marks.insert(PromptMarks::COMMAND_START);
+    grid[Line(2)][Column(0)] = cell('4');
+    grid[Line(2)].marks.insert(PromptMarks::OUTPUT_START);
+
+    grid.resize(true, Line(3), Column(3));
+
+    assert_eq!(grid[2][Column(2)], cell('3'));
+    assert_eq!(grid[2].marks(), PromptMarks::PROMPT_START | PromptMarks::COMMAND_START);
+    assert_eq!(grid[1][Column(0)], cell('4'));
+    assert_eq!(grid[1].marks(), PromptMarks::OUTPUT_START);
+    assert_eq!(grid[0].marks(), PromptMarks::empty());
+
+    grid.resize(true, Line(3), Column(2));
+
+    assert_eq!(grid.total_lines(), 4);
+    assert_eq!(grid[3].marks(), PromptMarks::PROMPT_START | PromptMarks::COMMAND_START);
+    assert_eq!(grid[2][Column(0)], cell('3'));
+    assert_eq!(grid[2].marks(), PromptMarks::empty());
+    assert_eq!(grid[1].marks(), PromptMarks::OUTPUT_START);
+}
+
 // https://github.com/rust-lang/rust-clippy/pull/6375
 #[allow(clippy::all)]
 fn cell(c: char) -> Cell {
@@ -5,9 +5,11 @@
 #![cfg_attr(feature = "cargo-clippy", deny(warnings))]
 
 pub mod ansi;
+pub mod asciicast;
 pub mod config;
 pub mod event;
 pub mod event_loop;
+pub mod graphics;
 pub mod grid;
 pub mod index;
 pub mod selection;
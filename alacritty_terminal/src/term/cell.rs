@@ -1,9 +1,11 @@
 use std::boxed::Box;
+use std::sync::Arc;
 
 use bitflags::bitflags;
 use serde::{Deserialize, Serialize};
 
-use crate::ansi::{Color, NamedColor};
+use crate::ansi::{Color, Hyperlink, NamedColor};
+use crate::graphics::GraphicCell;
 use crate::grid::{self, GridCell};
 use crate::index::Column;
 
@@ -24,6 +26,12 @@ bitflags! {
         const STRIKEOUT                 = 0b0000_0010_0000_0000;
         const LEADING_WIDE_CHAR_SPACER  = 0b0000_0100_0000_0000;
         const DOUBLE_UNDERLINE          = 0b0000_1000_0000_0000;
+        const UNDERCURL                 = 0b0001_0000_0000_0000;
+        const DOTTED_UNDERLINE          = 0b0010_0000_0000_0000;
+        const DASHED_UNDERLINE          = 0b0100_0000_0000_0000;
+        const ALL_UNDERLINES            = Self::UNDERLINE.bits | Self::DOUBLE_UNDERLINE.bits
+                                        | Self::UNDERCURL.bits | Self::DOTTED_UNDERLINE.bits
+                                        | Self::DASHED_UNDERLINE.bits;
     }
 }
 
@@ -53,6 +61,12 @@ impl ResetDiscriminant<Color> for Cell {
 #[derive(Serialize, Deserialize, Default, Debug, Clone, Eq, PartialEq)]
 struct CellExtra {
     zerowidth: Vec<char>,
+    #[serde(default)]
+    hyperlink: Option<Arc<Hyperlink>>,
+    #[serde(default)]
+    underline_color: Option<Color>,
+    #[serde(skip)]
+    graphic: Option<GraphicCell>,
 }
 
 /// Content and attributes of a single cell in the terminal grid.
@@ -92,6 +106,89 @@ impl Cell {
         self.extra.get_or_insert_with(Default::default).zerowidth.push(c);
     }
 
+    /// Hyperlink this cell is part of.
+    #[inline]
+    pub fn hyperlink(&self) -> Option<&Arc<Hyperlink>> {
+        self.extra.as_ref().and_then(|extra| extra.hyperlink.as_ref())
+    }
+
+    /// Set or clear the hyperlink of this cell.
+    #[inline]
+    pub fn set_hyperlink(&mut self, hyperlink: Option<Arc<Hyperlink>>) {
+        match hyperlink {
+            Some(hyperlink) => {
+                self.extra.get_or_insert_with(Default::default).hyperlink = Some(hyperlink)
+            },
+            None => {
+                if let Some(extra) = &mut self.extra {
+                    extra.hyperlink = None;
+                }
+            },
+        }
+    }
+
+    /// Underline color of this cell, `None` uses the foreground color.
+    #[inline]
+    pub fn underline_color(&self) -> Option<Color> {
+        self.extra.as_ref().and_then(|extra| extra.underline_color)
+    }
+
+    /// Set or clear the underline color of this cell.
+    #[inline]
+    pub fn set_underline_color(&mut self, color: Option<Color>) {
+        match color {
+            Some(color) => {
+                self.extra.get_or_insert_with(Default::default).underline_color = Some(color)
+            },
+            None => {
+                if let Some(extra) = &mut self.extra {
+                    extra.underline_color = None;
+                }
+            },
+        }
+    }
+
+    /// Graphic displayed in this cell.
+    #[inline]
+    pub fn graphic(&self) -> Option<&GraphicCell> {
+        self.extra.as_ref().and_then(|extra| extra.graphic.as_ref())
+    }
+
+    /// Display a piece of a graphic in this cell.
+    #[inline]
+    pub fn set_graphic(&mut self, graphic: GraphicCell) {
+        self.extra.get_or_insert_with(Default::default).graphic = Some(graphic);
+    }
+
+    /// Remove the graphic displayed in this cell.
+    #[inline]
+    pub fn clear_graphic(&mut self) {
+        if let Some(extra) = &mut self.extra {
+            extra.graphic = None;
+        }
+    }
+
+    /// Clear the characters of this cell without touching its attributes.
+    #[inline]
+    pub fn clear_content(&mut self) {
+        self.c = ' ';
+        self.flags
+            .remove(Flags::WIDE_CHAR | Flags::WIDE_CHAR_SPACER | Flags::LEADING_WIDE_CHAR_SPACER);
+
+        if let Some(extra) = &mut self.extra {
+            extra.zerowidth.clear();
+        }
+    }
+
+    /// Check if both cells have the same attributes, ignoring their character.
+    #[inline]
+    pub(crate) fn same_attributes(&self, other: &Cell) -> bool {
+        self.fg == other.fg
+            && self.bg == other.bg
+            && self.flags == other.flags
+            && self.extra == other.extra
+    }
+
     /// Free all dynamically allocated cell storage.
     #[inline]
     pub fn drop_extra(&mut self) {
@@ -109,14 +206,15 @@ impl GridCell for Cell {
             && self.fg == Color::Named(NamedColor::Foreground)
             && !self.flags.intersects(
                 Flags::INVERSE
-                    | Flags::UNDERLINE
-                    | Flags::DOUBLE_UNDERLINE
+                    | Flags::ALL_UNDERLINES
                     | Flags::STRIKEOUT
                     | Flags::WRAPLINE
                     | Flags::WIDE_CHAR_SPACER
                     | Flags::LEADING_WIDE_CHAR_SPACER,
             )
-            && self.extra.as_ref().map(|extra| extra.zerowidth.is_empty()) != Some(false)
+            && self.extra.as_ref().map(|extra| {
+                extra.zerowidth.is_empty() && extra.hyperlink.is_none() && extra.graphic.is_none()
+            }) != Some(false)
     }
 
     #[inline]
@@ -10,7 +10,7 @@ use serde_yaml::Value;
 use crate::ansi;
 use crate::config::Colors;
 
-pub const COUNT: usize = 269;
+pub const COUNT: usize = 271;
 
 /// Factor for automatic computation of dim colors used by terminal.
 pub const DIM_FACTOR: f32 = 0.66;
@@ -282,6 +282,12 @@ impl List {
         self[ansi::NamedColor::Foreground] = colors.primary.foreground;
         self[ansi::NamedColor::Background] = colors.primary.background;
 
+        // Cursor and selection colors are only used once modified, but can be queried.
+        let (fg, bg) = (colors.primary.foreground, colors.primary.background);
+        self[ansi::NamedColor::Cursor] = colors.cursor.background.color(fg, bg);
+        self[ansi::NamedColor::SelectionForeground] = colors.selection.foreground.color(fg, bg);
+        self[ansi::NamedColor::SelectionBackground] = colors.selection.background.color(fg, bg);
+
         // Dims.
         self[ansi::NamedColor::DimForeground] =
             colors.primary.dim_foreground.unwrap_or(colors.primary.foreground * DIM_FACTOR);
@@ -0,0 +1,470 @@
+//! Export of terminal content with its text attributes.
+
+use std::fmt::Write;
+
+use crate::ansi::{Color, NamedColor};
+use crate::config::Config;
+use crate::grid::Dimensions;
+use crate::index::{Column, Point};
+use crate::selection::SelectionRange;
+use crate::term::cell::{Cell, Flags};
+use crate::term::color;
+use crate::term::render::RenderableCell;
+use crate::term::Term;
+
+/// SGR parameters for each of the exported cell flags.
+const FLAG_PARAMETERS: [(Flags, &str); 11] = [
+    (Flags::BOLD, "1"),
+    (Flags::DIM, "2"),
+    (Flags::ITALIC, "3"),
+    (Flags::UNDERLINE, "4"),
+    (Flags::DOUBLE_UNDERLINE, "4:2"),
+    (Flags::UNDERCURL, "4:3"),
+    (Flags::DOTTED_UNDERLINE, "4:4"),
+    (Flags::DASHED_UNDERLINE, "4:5"),
+    (Flags::INVERSE, "7"),
+    (Flags::HIDDEN, "8"),
+    (Flags::STRIKEOUT, "9"),
+];
+
+/// CSS `text-decoration` values for each of the underline flags.
+const UNDERLINE_DECORATIONS: [(Flags, &str); 5] = [
+    (Flags::UNDERLINE, "underline"),
+    (Flags::DOUBLE_UNDERLINE, "underline double"),
+    (Flags::UNDERCURL, "underline wavy"),
+    (Flags::DOTTED_UNDERLINE, "underline dotted"),
+    (Flags::DASHED_UNDERLINE, "underline dashed"),
+];
+
+/// Output format of an export.
+trait Writer {
+    /// Append a cell, `None` marks the end of a line.
+    fn push(&mut self, cell: Option<&Cell>);
+
+    /// Complete the export.
+    fn finish(self) -> String;
+}
+
+/// Text attributes of a cell.
+#[derive(Debug, Copy, Clone, PartialEq)]
+struct Style {
+    fg: Color,
+    bg: Color,
+    flags: Flags,
+}
+
+impl Default for Style {
+    fn default() -> Self {
+        Self {
+            fg: Color::Named(NamedColor::Foreground),
+            bg: Color::Named(NamedColor::Background),
+            flags: Flags::empty(),
+        }
+    }
+}
+
+impl From<&Cell> for Style {
+    fn from(cell: &Cell) -> Self {
+        let mut flags = Flags::empty();
+        for (flag, _) in FLAG_PARAMETERS.iter() {
+            flags.set(*flag, cell.flags.contains(*flag));
+        }
+
+        Self { fg: cell.fg, bg: cell.bg, flags }
+    }
+}
+
+/// Writer for text with SGR escape sequences.
+#[derive(Default)]
+struct AnsiWriter {
+    text: String,
+    style: Style,
+}
+
+impl AnsiWriter {
+    fn set_style(&mut self, style: Style) {
+        if self.style == style {
+            return;
+        }
+        self.style = style;
+
+        self.text.push_str("\x1b[0");
+
+        for (flag, parameter) in FLAG_PARAMETERS.iter() {
+            if style.flags.contains(*flag) {
+                self.text.push(';');
+                self.text.push_str(parameter);
+            }
+        }
+
+        push_color(&mut self.text, style.fg, 30);
+        push_color(&mut self.text, style.bg, 40);
+
+        self.text.push('m');
+    }
+}
+
+impl Writer for AnsiWriter {
+    fn push(&mut self, cell: Option<&Cell>) {
+        let cell = match cell {
+            Some(cell) => cell,
+            None => {
+                // Reset attributes at the end of every line, so lines can be used on their own.
+                self.set_style(Style::default());
+                self.text.push('\n');
+                return;
+            },
+        };
+
+        self.set_style(Style::from(cell));
+
+        self.text.push(cell.c);
+        for c in cell.zerowidth().into_iter().flatten() {
+            self.text.push(*c);
+        }
+    }
+
+    fn finish(mut self) -> String {
+        self.set_style(Style::default());
+        self.text
+    }
+}
+
+/// Append the SGR parameters for a color.
+///
+/// The `base` is the parameter of the first regular color, `30` for the foreground and `40` for
+/// the background.
+fn push_color(text: &mut String, color: Color, base: u8) {
+    let _ = match color {
+        Color::Named(color) => match named_color_index(color) {
+            Some(index) if index < 8 => write!(text, ";{}", base + index),
+            Some(index) => write!(text, ";{}", base + 60 + index - 8),
+            None => Ok(()),
+        },
+        Color::Indexed(index) => write!(text, ";{};5;{}", base + 8, index),
+        Color::Spec(rgb) => write!(text, ";{};2;{};{};{}", base + 8, rgb.r, rgb.g, rgb.b),
+    };
+}
+
+/// Palette index of a named color.
+///
+/// Dim colors are mapped to their regular counterpart, since there is no SGR parameter for them.
+/// Colors which are not part of the palette, like the default foreground, have no index.
+fn named_color_index(color: NamedColor) -> Option<u8> {
+    let index = color as usize;
+    if index < 16 {
+        return Some(index as u8);
+    }
+
+    let dim_index = index.wrapping_sub(NamedColor::DimBlack as usize);
+    if dim_index < 8 {
+        Some(dim_index as u8)
+    } else {
+        None
+    }
+}
+
+/// Writer for an HTML `pre` element with inline styles.
+struct HtmlWriter<'a, C> {
+    config: &'a Config<C>,
+    colors: &'a color::List,
+    text: String,
+
+    /// Inline style of the open `span` element.
+    style: Option<String>,
+}
+
+impl<'a, C> HtmlWriter<'a, C> {
+    fn new(config: &'a Config<C>, colors: &'a color::List) -> Self {
+        let fg = colors[NamedColor::Foreground];
+        let bg = colors[NamedColor::Background];
+        let text = format!("<pre style=\"color: {}; background-color: {};\">", fg, bg);
+
+        Self { config, colors, text, style: None }
+    }
+
+    /// CSS declarations for a cell, `None` if it has the default style.
+    fn cell_style(&self, cell: &Cell) -> Option<String> {
+        let mut fg = RenderableCell::compute_fg_rgb(self.config, self.colors, cell.fg, cell.flags);
+        let mut bg = if cell.bg == Color::Named(NamedColor::Background) {
+            None
+        } else {
+            Some(RenderableCell::compute_bg_rgb(self.colors, cell.bg))
+        };
+
+        if cell.flags.contains(Flags::INVERSE) {
+            let inverse_fg = bg.unwrap_or(self.colors[NamedColor::Background]);
+            bg = Some(fg);
+            fg = inverse_fg;
+        }
+
+        let mut declarations = Vec::new();
+
+        if fg != self.colors[NamedColor::Foreground] {
+            declarations.push(format!("color: {};", fg));
+        }
+
+        if let Some(bg) = bg {
+            declarations.push(format!("background-color: {};", bg));
+        }
+
+        if cell.flags.contains(Flags::BOLD) {
+            declarations.push(String::from("font-weight: bold;"));
+        }
+
+        if cell.flags.contains(Flags::ITALIC) {
+            declarations.push(String::from("font-style: italic;"));
+        }
+
+        let underline = UNDERLINE_DECORATIONS.iter().find(|(flag, _)| cell.flags.contains(*flag));
+        let decoration = match (underline, cell.flags.contains(Flags::STRIKEOUT)) {
+            (Some((_, underline)), true) => format!("{} line-through", underline),
+            (Some((_, underline)), false) => (*underline).to_owned(),
+            (None, true) => String::from("line-through"),
+            (None, false) => String::new(),
+        };
+        if !decoration.is_empty() {
+            declarations.push(format!("text-decoration: {};", decoration));
+        }
+
+        if cell.flags.contains(Flags::HIDDEN) {
+            declarations.push(String::from("visibility: hidden;"));
+        }
+
+        if declarations.is_empty() {
+            None
+        } else {
+            Some(declarations.join(" "))
+        }
+    }
+
+    fn set_style(&mut self, style: Option<String>) {
+        if self.style == style {
+            return;
+        }
+
+        if self.style.is_some() {
+            self.text.push_str("</span>");
+        }
+
+        if let Some(style) = &style {
+            let _ = write!(self.text, "<span style=\"{}\">", style);
+        }
+
+        self.style = style;
+    }
+
+    fn push_char(&mut self, c: char) {
+        match c {
+            '&' => self.text.push_str("&amp;"),
+            '<' => self.text.push_str("&lt;"),
+            '>' => self.text.push_str("&gt;"),
+            '"' => self.text.push_str("&quot;"),
+            c => self.text.push(c),
+        }
+    }
+}
+
+impl<'a, C> Writer for HtmlWriter<'a, C> {
+    fn push(&mut self, cell: Option<&Cell>) {
+        let cell = match cell {
+            Some(cell) => cell,
+            None => {
+                self.set_style(None);
+                self.text.push('\n');
+                return;
+            },
+        };
+
+        self.set_style(self.cell_style(cell));
+
+        self.push_char(cell.c);
+        for c in cell.zerowidth().into_iter().flatten() {
+            self.push_char(*c);
+        }
+    }
+
+    fn finish(mut self) -> String {
+        self.set_style(None);
+        self.text.push_str("</pre>");
+        self.text
+    }
+}
+
+impl<T> Term<T> {
+    /// Convert range between two points to text with SGR escape sequences.
+    ///
+    /// This follows the same rules as [`Term::bounds_to_string`], with all text attributes
+    /// reset at the end of every line.
+    pub fn bounds_to_ansi(&self, start: Point<usize>, end: Point<usize>) -> String {
+        self.export_bounds(start, end, AnsiWriter::default())
+    }
+
+    /// Convert the active selection to text with SGR escape sequences.
+    pub fn selection_to_ansi(&self) -> Option<String> {
+        self.export_selection(AnsiWriter::default())
+    }
+
+    /// Convert range between two points to an HTML `pre` element.
+    ///
+    /// Colors are resolved using the terminal's current palette and applied through inline
+    /// styles, so the HTML does not depend on any external stylesheet.
+    pub fn bounds_to_html<C>(
+        &self,
+        config: &Config<C>,
+        start: Point<usize>,
+        end: Point<usize>,
+    ) -> String {
+        self.export_bounds(start, end, HtmlWriter::new(config, &self.colors))
+    }
+
+    /// Convert the active selection to an HTML `pre` element.
+    pub fn selection_to_html<C>(&self, config: &Config<C>) -> Option<String> {
+        self.export_selection(HtmlWriter::new(config, &self.colors))
+    }
+
+    fn export_bounds<W>(&self, start: Point<usize>, end: Point<usize>, mut writer: W) -> String
+    where
+        W: Writer,
+    {
+        self.visit_bounds(start, end, &mut writer);
+        writer.finish()
+    }
+
+    fn export_selection<W: Writer>(&self, mut writer: W) -> Option<String> {
+        let selection_range = self.selection.as_ref().and_then(|s| s.to_range(self))?;
+        let SelectionRange { start, end, is_block } = selection_range;
+
+        if !is_block {
+            self.visit_bounds(start, end, &mut writer);
+            return Some(writer.finish());
+        }
+
+        for line in (end.line + 1..=start.line).rev() {
+            self.visit_line(line, start.col..end.col, start.col.0 != 0, |cell| writer.push(cell));
+
+            // If the last column is included, newline is appended automatically.
+            if end.col != self.cols() - 1 {
+                writer.push(None);
+            }
+        }
+        self.visit_line(end.line, start.col..end.col, true, |cell| writer.push(cell));
+
+        Some(writer.finish())
+    }
+
+    /// Visit all cells between two points, following the rules of [`Term::bounds_to_string`].
+    fn visit_bounds<W: Writer>(&self, start: Point<usize>, end: Point<usize>, writer: &mut W) {
+        for line in (end.line..=start.line).rev() {
+            let start_col = if line == start.line { start.col } else { Column(0) };
+            let end_col = if line == end.line { end.col } else { self.cols() - 1 };
+
+            self.visit_line(line, start_col..end_col, line == end.line, |cell| writer.push(cell));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::io;
+
+    use crate::ansi;
+    use crate::event::{Event, EventListener};
+    use crate::index::Side;
+    use crate::selection::{Selection, SelectionType};
+    use crate::term::color::Rgb;
+    use crate::term::test::mock_term;
+    use crate::term::SizeInfo;
+
+    struct Mock;
+    impl EventListener for Mock {
+        fn send_event(&self, _event: Event) {}
+    }
+
+    #[test]
+    fn bounds_to_ansi() {
+        #[rustfmt::skip]
+        let mut term = mock_term("\
+            abc\r\n\
+            def\
+        ");
+        term.grid[1][Column(1)].flags.insert(Flags::BOLD);
+        term.grid[1][Column(1)].fg = Color::Named(NamedColor::BrightRed);
+        term.grid[1][Column(2)].fg = Color::Indexed(100);
+        term.grid[0][Column(0)].bg = Color::Spec(Rgb { r: 1, g: 2, b: 3 });
+        term.grid[0][Column(1)].fg = Color::Named(NamedColor::DimGreen);
+
+        let text = term.bounds_to_ansi(Point::new(1, Column(0)), Point::new(0, Column(2)));
+        assert_eq!(
+            text,
+            "a\x1b[0;1;91mb\x1b[0;38;5;100mc\x1b[0m\n\x1b[0;48;2;1;2;3md\x1b[0;32me\x1b[0mf\n"
+        );
+    }
+
+    #[test]
+    fn ansi_flags_roundtrip() {
+        let mut term = mock_term("abcdefghijk\r\n");
+        for (i, (flag, _)) in FLAG_PARAMETERS.iter().enumerate() {
+            term.grid[1][Column(i)].flags.insert(*flag);
+        }
+        let text = term.bounds_to_ansi(Point::new(1, Column(0)), Point::new(1, Column(10)));
+
+        // Parsing the export must restore the original flags.
+        let size = SizeInfo::new(11., 2., 1., 1., 0., 0., false);
+        let mut parsed = Term::new(&Config::<()>::default(), size, Mock);
+        let mut parser = ansi::Processor::new();
+        for byte in text.bytes() {
+            parser.advance(&mut parsed, byte, &mut io::sink());
+        }
+
+        for (i, (flag, _)) in FLAG_PARAMETERS.iter().enumerate() {
+            assert_eq!(parsed.grid[1][Column(i)].flags, *flag);
+        }
+    }
+
+    #[test]
+    fn block_selection_to_ansi() {
+        #[rustfmt::skip]
+        let mut term = mock_term("\
+            abc\r\n\
+            def\
+        ");
+        term.grid[0][Column(1)].flags.insert(Flags::BOLD);
+
+        let mut selection =
+            Selection::new(SelectionType::Block, Point::new(1, Column(1)), Side::Left);
+        selection.update(Point::new(0, Column(1)), Side::Right);
+        term.selection = Some(selection);
+
+        assert_eq!(term.selection_to_ansi().unwrap(), "b\n\x1b[0;1me\x1b[0m");
+    }
+
+    #[test]
+    fn bounds_to_html() {
+        #[rustfmt::skip]
+        let mut term = mock_term("\
+            a<b\r\n\
+            cd\
+        ");
+        term.colors[NamedColor::Foreground] = Rgb { r: 255, g: 255, b: 255 };
+        term.colors[NamedColor::Background] = Rgb { r: 0, g: 0, b: 0 };
+        term.colors[NamedColor::Red] = Rgb { r: 255, g: 0, b: 0 };
+        term.grid[1][Column(1)].fg = Color::Named(NamedColor::Red);
+        term.grid[1][Column(2)].fg = Color::Named(NamedColor::Red);
+        term.grid[1][Column(2)].flags.insert(Flags::UNDERLINE | Flags::STRIKEOUT);
+        term.grid[0][Column(0)].flags.insert(Flags::INVERSE | Flags::ITALIC);
+
+        let config = Config::<()>::default();
+        let html = term.bounds_to_html(&config, Point::new(1, Column(0)), Point::new(0, Column(2)));
+        assert_eq!(
+            html,
+            "<pre style=\"color: #ffffff; background-color: #000000;\">a\
+             <span style=\"color: #ff0000;\">&lt;</span>\
+             <span style=\"color: #ff0000; text-decoration: underline line-through;\">b</span>\n\
+             <span style=\"color: #000000; background-color: #ffffff; font-style: italic;\">c</span>\
+             d\n</pre>"
+        );
+    }
+}
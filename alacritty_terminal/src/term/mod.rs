@@ -1,24 +1,33 @@
 //! Exports the `Term` type which is a high-level API for the Grid.
 
 use std::cmp::{max, min};
+use std::collections::HashSet;
 use std::ops::{Index, IndexMut, Range};
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
-use std::time::{Duration, Instant};
+use std::time::{Duration, Instant, SystemTime};
 use std::{io, mem, ptr, str};
 
 use bitflags::bitflags;
-use log::{debug, trace};
+use log::{debug, info, trace};
 use serde::{Deserialize, Serialize};
 use unicode_width::UnicodeWidthChar;
 
 use crate::ansi::{
-    self, Attr, CharsetIndex, Color, CursorShape, CursorStyle, Handler, NamedColor, StandardCharset,
+    self, Attr, CharsetIndex, Color, CursorShape, CursorStyle, Handler, Hyperlink, KeyboardModes,
+    KeyboardModesApplyBehavior, LineSize, NamedColor, PromptMark, Rectangle, StandardCharset,
+};
+use crate::config::{
+    BellAnimation, BellConfig, Capability, Config, Permission, Security, LOG_TARGET_CONFIG,
 };
-use crate::config::{BellAnimation, BellConfig, Config};
 use crate::event::{Event, EventListener};
-use crate::grid::{Dimensions, Grid, IndexRegion, Scroll};
+use crate::graphics::{
+    kitty, GraphicCell, GraphicData, GraphicId, Graphics, KittyImage, UpdateQueues,
+};
+use crate::grid::archive::Archive;
+use crate::grid::{Dimensions, Grid, GridCell, IndexRegion, PromptMarks, Row, Scroll};
 use crate::index::{self, Boundary, Column, Direction, IndexRange, Line, Point, Side};
-use crate::selection::{Selection, SelectionRange};
+use crate::selection::{Selection, SelectionRange, SelectionType};
 use crate::term::cell::{Cell, Flags, LineLength};
 use crate::term::color::Rgb;
 use crate::term::render::RenderableContent;
@@ -27,12 +36,23 @@ use crate::vi_mode::{ViModeCursor, ViMotion};
 
 pub mod cell;
 pub mod color;
+pub mod export;
 pub mod render;
-mod search;
+pub mod search;
+pub mod session;
+mod terminfo;
 
 /// Max size of the window title stack.
 const TITLE_STACK_MAX_DEPTH: usize = 4096;
 
+/// Max size of the kitty keyboard protocol mode stack.
+const KEYBOARD_MODE_STACK_MAX_DEPTH: usize = TITLE_STACK_MAX_DEPTH;
+
+/// Cell attributes which can be changed by rectangle operations.
+const RECTANGLE_FLAGS: Flags = Flags::from_bits_truncate(
+    Flags::BOLD.bits() | Flags::ALL_UNDERLINES.bits() | Flags::INVERSE.bits(),
+);
+
 /// Default tab interval, corresponding to terminfo `it` value.
 const INITIAL_TABSTOPS: usize = 8;
 
@@ -46,27 +66,58 @@ pub const MIN_SCREEN_LINES: usize = 1;
 
 bitflags! {
     pub struct TermMode: u32 {
-        const NONE                = 0;
-        const SHOW_CURSOR         = 0b0000_0000_0000_0000_0001;
-        const APP_CURSOR          = 0b0000_0000_0000_0000_0010;
-        const APP_KEYPAD          = 0b0000_0000_0000_0000_0100;
-        const MOUSE_REPORT_CLICK  = 0b0000_0000_0000_0000_1000;
-        const BRACKETED_PASTE     = 0b0000_0000_0000_0001_0000;
-        const SGR_MOUSE           = 0b0000_0000_0000_0010_0000;
-        const MOUSE_MOTION        = 0b0000_0000_0000_0100_0000;
-        const LINE_WRAP           = 0b0000_0000_0000_1000_0000;
-        const LINE_FEED_NEW_LINE  = 0b0000_0000_0001_0000_0000;
-        const ORIGIN              = 0b0000_0000_0010_0000_0000;
-        const INSERT              = 0b0000_0000_0100_0000_0000;
-        const FOCUS_IN_OUT        = 0b0000_0000_1000_0000_0000;
-        const ALT_SCREEN          = 0b0000_0001_0000_0000_0000;
-        const MOUSE_DRAG          = 0b0000_0010_0000_0000_0000;
-        const MOUSE_MODE          = 0b0000_0010_0000_0100_1000;
-        const UTF8_MOUSE          = 0b0000_0100_0000_0000_0000;
-        const ALTERNATE_SCROLL    = 0b0000_1000_0000_0000_0000;
-        const VI                  = 0b0001_0000_0000_0000_0000;
-        const URGENCY_HINTS       = 0b0010_0000_0000_0000_0000;
-        const ANY                 = std::u32::MAX;
+        const NONE                    = 0;
+        const SHOW_CURSOR             = 0b0000_0000_0000_0000_0000_0000_0001;
+        const APP_CURSOR              = 0b0000_0000_0000_0000_0000_0000_0010;
+        const APP_KEYPAD              = 0b0000_0000_0000_0000_0000_0000_0100;
+        const MOUSE_REPORT_CLICK      = 0b0000_0000_0000_0000_0000_0000_1000;
+        const BRACKETED_PASTE         = 0b0000_0000_0000_0000_0000_0001_0000;
+        const SGR_MOUSE               = 0b0000_0000_0000_0000_0000_0010_0000;
+        const MOUSE_MOTION            = 0b0000_0000_0000_0000_0000_0100_0000;
+        const LINE_WRAP               = 0b0000_0000_0000_0000_0000_1000_0000;
+        const LINE_FEED_NEW_LINE      = 0b0000_0000_0000_0000_0001_0000_0000;
+        const ORIGIN                  = 0b0000_0000_0000_0000_0010_0000_0000;
+        const INSERT                  = 0b0000_0000_0000_0000_0100_0000_0000;
+        const FOCUS_IN_OUT            = 0b0000_0000_0000_0000_1000_0000_0000;
+        const ALT_SCREEN              = 0b0000_0000_0000_0001_0000_0000_0000;
+        const MOUSE_DRAG              = 0b0000_0000_0000_0010_0000_0000_0000;
+        const MOUSE_MODE              = 0b0000_0000_0000_0010_0000_0100_1000;
+        const UTF8_MOUSE              = 0b0000_0000_0000_0100_0000_0000_0000;
+        const ALTERNATE_SCROLL        = 0b0000_0000_0000_1000_0000_0000_0000;
+        const VI                      = 0b0000_0000_0001_0000_0000_0000_0000;
+        const URGENCY_HINTS           = 0b0000_0000_0010_0000_0000_0000_0000;
+        const SYNC_UPDATE             = 0b0000_0000_0100_0000_0000_0000_0000;
+        const DISAMBIGUATE_ESC_CODES  = 0b0000_0000_1000_0000_0000_0000_0000;
+        const REPORT_EVENT_TYPES      = 0b0000_0001_0000_0000_0000_0000_0000;
+        const REPORT_ALL_KEYS_AS_ESC  = 0b0000_0010_0000_0000_0000_0000_0000;
+        const LEFT_RIGHT_MARGIN       = 0b0000_0100_0000_0000_0000_0000_0000;
+        const KITTY_KEYBOARD_PROTOCOL = Self::DISAMBIGUATE_ESC_CODES.bits
+                                        | Self::REPORT_EVENT_TYPES.bits
+                                        | Self::REPORT_ALL_KEYS_AS_ESC.bits;
+        const ANY                     = std::u32::MAX;
+    }
+}
+
+/// Remove all kitty keyboard protocol modes which are not implemented.
+fn supported_keyboard_modes(mode: KeyboardModes) -> KeyboardModes {
+    mode & (KeyboardModes::DISAMBIGUATE_ESC_CODES
+        | KeyboardModes::REPORT_EVENT_TYPES
+        | KeyboardModes::REPORT_ALL_KEYS_AS_ESC)
+}
+
+impl From<KeyboardModes> for TermMode {
+    fn from(modes: KeyboardModes) -> Self {
+        let mut mode = Self::empty();
+        mode.set(
+            Self::DISAMBIGUATE_ESC_CODES,
+            modes.contains(KeyboardModes::DISAMBIGUATE_ESC_CODES),
+        );
+        mode.set(Self::REPORT_EVENT_TYPES, modes.contains(KeyboardModes::REPORT_EVENT_TYPES));
+        mode.set(
+            Self::REPORT_ALL_KEYS_AS_ESC,
+            modes.contains(KeyboardModes::REPORT_ALL_KEYS_AS_ESC),
+        );
+        mode
     }
 }
 
@@ -266,6 +317,11 @@ impl SizeInfo {
         self.screen_lines = Line(max(self.screen_lines.saturating_sub(count), MIN_SCREEN_LINES));
     }
 
+    #[inline]
+    pub fn reserve_cols(&mut self, count: usize) {
+        self.cols = Column(max(self.cols.saturating_sub(count), MIN_COLS));
+    }
+
     /// Check if coordinates are inside the terminal grid.
     ///
     /// The padding, message bar or search are not counted as part of the grid.
@@ -368,6 +424,9 @@ pub struct Term<T> {
     /// Index into `charsets`, pointing to what ASCII is currently being mapped to.
     active_charset: CharsetIndex,
 
+    /// Charset used for the next character only, set by single shifts.
+    single_shift: Option<CharsetIndex>,
+
     /// Tabstops.
     tabs: TabStops,
 
@@ -379,6 +438,12 @@ pub struct Term<T> {
     /// Range going from top to bottom of the terminal, indexed from the top of the viewport.
     scroll_region: Range<Line>,
 
+    /// Left and right margins.
+    ///
+    /// Range going from the left to the right of the terminal, covering all columns unless
+    /// DECLRMM is set.
+    horizontal_margins: Range<Column>,
+
     semantic_escape_chars: String,
 
     /// Colors used for rendering.
@@ -390,6 +455,9 @@ pub struct Term<T> {
     /// Original colors from config.
     original_colors: color::List,
 
+    /// Compressed lines which exceeded the scrollback history of the primary screen.
+    archive: Option<Archive>,
+
     /// Current style of the cursor.
     cursor_style: Option<CursorStyle>,
 
@@ -409,6 +477,24 @@ pub struct Term<T> {
     /// term is set.
     title_stack: Vec<Option<String>>,
 
+    /// Stack of kitty keyboard protocol modes, the last entry is the active one.
+    keyboard_mode_stack: Vec<KeyboardModes>,
+
+    /// Keyboard mode stack of the inactive screen buffer.
+    inactive_keyboard_mode_stack: Vec<KeyboardModes>,
+
+    /// Working directory reported by the shell.
+    working_directory: Option<PathBuf>,
+
+    /// Permissions for escape sequences exposing data outside of the terminal.
+    security: Security,
+
+    /// Application version reported to terminal queries.
+    version: String,
+
+    /// Inline graphics displayed in the grid.
+    graphics: Graphics,
+
     /// Current forward and backward buffer search regexes.
     regex_search: Option<RegexSearch>,
 
@@ -423,6 +509,19 @@ impl<T> Term<T> {
     where
         T: EventListener,
     {
+        // Load archived lines when scrolling beyond the top of the history.
+        let display_offset = self.grid.display_offset();
+        let history_size = self.history_size();
+        let beyond_history = match scroll {
+            Scroll::Delta(count) => display_offset as isize + count > history_size as isize,
+            Scroll::PageUp => display_offset + self.screen_lines().0 > history_size,
+            Scroll::Top => true,
+            Scroll::PageDown | Scroll::Bottom => false,
+        };
+        if beyond_history {
+            self.page_in_history();
+        }
+
         self.grid.scroll_display(scroll);
         self.event_proxy.send_event(Event::MouseCursorDirty);
         self.dirty = true;
@@ -439,6 +538,7 @@ impl<T> Term<T> {
         let tabs = TabStops::new(grid.cols());
 
         let scroll_region = Line(0)..grid.screen_lines();
+        let horizontal_margins = Column(0)..grid.cols();
 
         let colors = color::List::from(&config.colors);
 
@@ -448,13 +548,16 @@ impl<T> Term<T> {
             grid,
             inactive_grid: alt,
             active_charset: Default::default(),
+            single_shift: None,
             vi_mode_cursor: Default::default(),
             tabs,
             mode: Default::default(),
             scroll_region,
+            horizontal_margins,
             colors,
             color_modified: [false; color::COUNT],
             original_colors: colors,
+            archive: if config.scrolling.archive { Some(Archive::new()) } else { None },
             semantic_escape_chars: config.selection.semantic_escape_chars.to_owned(),
             cursor_style: None,
             default_cursor_style: config.cursor.style(),
@@ -463,6 +566,12 @@ impl<T> Term<T> {
             is_focused: true,
             title: None,
             title_stack: Vec::new(),
+            keyboard_mode_stack: Vec::new(),
+            inactive_keyboard_mode_stack: Vec::new(),
+            working_directory: None,
+            security: config.security,
+            version: config.version.clone(),
+            graphics: Graphics::default(),
             selection: None,
             regex_search: None,
             cell_width: size.cell_width as usize,
@@ -486,6 +595,8 @@ impl<T> Term<T> {
         self.visual_bell.update_config(config);
         self.default_cursor_style = config.cursor.style();
         self.vi_mode_cursor_style = config.cursor.vi_mode_style();
+        self.security = config.security;
+        self.version = config.version.clone();
 
         let title_event = match &self.title {
             Some(title) => Event::Title(title.clone()),
@@ -494,15 +605,152 @@ impl<T> Term<T> {
 
         self.event_proxy.send_event(title_event);
 
-        if self.mode.contains(TermMode::ALT_SCREEN) {
-            self.inactive_grid.update_history(config.scrolling.history() as usize);
+        // Archived lines are dropped when the archive is disabled.
+        if !config.scrolling.archive {
+            self.clear_archive();
+            self.archive = None;
+        } else if self.archive.is_none() {
+            self.archive = Some(Archive::new());
+        }
+
+        let history_size = config.scrolling.history() as usize;
+        let grid = if self.mode.contains(TermMode::ALT_SCREEN) {
+            &mut self.inactive_grid
         } else {
-            self.grid.update_history(config.scrolling.history() as usize);
+            &mut self.grid
+        };
+        match &mut self.archive {
+            Some(archive) => archive.shrink_history(grid, history_size),
+            None => grid.update_history(history_size),
+        }
+    }
+
+    /// Load the newest archived lines into the scrollback history.
+    ///
+    /// Returns `false` if there were no archived lines left.
+    pub fn page_in_history(&mut self) -> bool {
+        match &mut self.archive {
+            Some(archive) if !self.mode.contains(TermMode::ALT_SCREEN) => {
+                let paged_in = archive.page_in(&mut self.grid);
+                self.dirty |= paged_in;
+                paged_in
+            },
+            _ => false,
+        }
+    }
+
+    /// Return lines loaded by [`Term::page_in_history`] to the archive.
+    ///
+    /// Lines required for the current viewport are kept in the history.
+    pub fn page_out_history(&mut self) {
+        if let Some(archive) = &mut self.archive {
+            if !self.mode.contains(TermMode::ALT_SCREEN) {
+                let display_offset = self.grid.display_offset();
+                archive.page_out(&mut self.grid, display_offset);
+                self.dirty = true;
+            }
+        }
+    }
+
+    /// Remove all archived lines.
+    fn clear_archive(&mut self) {
+        let grid = if self.mode.contains(TermMode::ALT_SCREEN) {
+            &mut self.inactive_grid
+        } else {
+            &mut self.grid
+        };
+
+        if let Some(archive) = &mut self.archive {
+            archive.clear(grid);
+        }
+    }
+
+    /// Send an event requiring a capability, asking the user first if necessary.
+    fn send_permitted_event(&self, capability: Capability, event: Event)
+    where
+        T: EventListener,
+    {
+        match self.security.permission(capability) {
+            Permission::Allow => self.event_proxy.send_event(event),
+            Permission::Prompt => {
+                self.event_proxy.send_event(Event::PermissionRequest(capability, Box::new(event)));
+            },
+            Permission::Deny => {
+                info!(target: LOG_TARGET_CONFIG, "Denied escape sequence permission to {}", capability);
+            },
+        }
+    }
+
+    /// Write a response requiring a capability to the PTY.
+    fn write_permitted<W>(&self, writer: &mut W, capability: Capability, text: String)
+    where
+        T: EventListener,
+        W: io::Write,
+    {
+        match self.security.permission(capability) {
+            Permission::Allow => {
+                let _ = writer.write_all(text.as_bytes());
+            },
+            _ => self.send_permitted_event(capability, Event::PtyWrite(text)),
         }
     }
 
+    /// Check if escape sequences may read local files.
+    ///
+    /// Files are read while the escape sequence is processed, so prompting is treated like denying.
+    fn file_read_permitted(&self) -> bool {
+        match self.security.permission(Capability::FileRead) {
+            Permission::Allow => true,
+            _ => {
+                info!(target: LOG_TARGET_CONFIG, "Denied escape sequence permission to {}", Capability::FileRead);
+                false
+            },
+        }
+    }
+
+    /// Grid cell displayed at a point in the viewport.
+    ///
+    /// Cells in double-width lines cover two columns of the viewport.
+    pub fn grid_point(&self, mut point: Point) -> Point {
+        let line = self.grid.visible_to_buffer(point).line;
+        if self.grid[line].line_size() != LineSize::Normal {
+            point.col = Column(point.col.0 / 2);
+        }
+        point
+    }
+
+    /// Viewport column at which the grid cell at a point is displayed.
+    ///
+    /// This is the inverse of [`Term::grid_point`].
+    pub fn viewport_point(&self, mut point: Point) -> Point {
+        let line = self.grid.visible_to_buffer(point).line;
+        if self.grid[line].line_size() != LineSize::Normal {
+            point.col = min(Column(point.col.0 * 2), self.cols() - 1);
+        }
+        point
+    }
+
     /// Convert the active selection to a String.
     pub fn selection_to_string(&self) -> Option<String> {
+        self.selection_to_string_with(|_| None)
+    }
+
+    /// Convert the active selection to a String, prefixing every line with its arrival time.
+    ///
+    /// The `format` function is called with the timestamp of each line's first row, lines
+    /// without a timestamp are not prefixed.
+    pub fn selection_to_string_with_timestamps<F>(&self, format: F) -> Option<String>
+    where
+        F: Fn(SystemTime) -> String,
+    {
+        self.selection_to_string_with(|line| self.grid[line].timestamp.map(&format))
+    }
+
+    /// Convert the active selection to a String, with an optional prefix for every line.
+    fn selection_to_string_with<F>(&self, prefix: F) -> Option<String>
+    where
+        F: Fn(usize) -> Option<String>,
+    {
         let selection_range = self.selection.as_ref().and_then(|s| s.to_range(self))?;
         let SelectionRange { start, end, is_block } = selection_range;
 
@@ -510,6 +758,7 @@ impl<T> Term<T> {
 
         if is_block {
             for line in (end.line + 1..=start.line).rev() {
+                res += &prefix(line).unwrap_or_default();
                 res += &self.line_to_string(line, start.col..end.col, start.col.0 != 0);
 
                 // If the last column is included, newline is appended automatically.
@@ -517,9 +766,10 @@ impl<T> Term<T> {
                     res += "\n";
                 }
             }
+            res += &prefix(end.line).unwrap_or_default();
             res += &self.line_to_string(end.line, start.col..end.col, true);
         } else {
-            res = self.bounds_to_string(start, end);
+            res = self.bounds_to_string_with(start, end, prefix);
         }
 
         Some(res)
@@ -527,12 +777,26 @@ impl<T> Term<T> {
 
     /// Convert range between two points to a String.
     pub fn bounds_to_string(&self, start: Point<usize>, end: Point<usize>) -> String {
+        self.bounds_to_string_with(start, end, |_| None)
+    }
+
+    /// Convert range between two points to a String, with an optional prefix for every line.
+    ///
+    /// Rows continuing a wrapped line are not prefixed.
+    fn bounds_to_string_with<F>(&self, start: Point<usize>, end: Point<usize>, prefix: F) -> String
+    where
+        F: Fn(usize) -> Option<String>,
+    {
         let mut res = String::new();
 
         for line in (end.line..=start.line).rev() {
             let start_col = if line == start.line { start.col } else { Column(0) };
             let end_col = if line == end.line { end.col } else { self.cols() - 1 };
 
+            if res.is_empty() || res.ends_with('\n') {
+                res += &prefix(line).unwrap_or_default();
+            }
+
             res += &self.line_to_string(line, start_col..end_col, line == end.line);
         }
 
@@ -543,14 +807,46 @@ impl<T> Term<T> {
     fn line_to_string(
         &self,
         line: usize,
-        mut cols: Range<Column>,
+        cols: Range<Column>,
         include_wrapped_wide: bool,
     ) -> String {
         let mut text = String::new();
 
+        self.visit_line(line, cols, include_wrapped_wide, |cell| match cell {
+            Some(cell) => {
+                // Push cells primary character.
+                text.push(cell.c);
+
+                // Push zero-width characters.
+                for c in cell.zerowidth().into_iter().flatten() {
+                    text.push(*c);
+                }
+            },
+            None => text.push('\n'),
+        });
+
+        text
+    }
+
+    /// Visit all cells of a single line in the grid which contribute to its text.
+    ///
+    /// The end of a line which is not wrapped is signaled by visiting `None`.
+    fn visit_line<F>(
+        &self,
+        line: usize,
+        mut cols: Range<Column>,
+        include_wrapped_wide: bool,
+        mut f: F,
+    ) where
+        F: FnMut(Option<&Cell>),
+    {
         let grid_line = &self.grid[line];
         let line_length = min(grid_line.line_length(), cols.end + 1);
 
+        // Cells hidden by double-width lines are ignored.
+        let line_columns = self.row_columns(grid_line);
+        let line_length = min(line_length, line_columns);
+
         // Include wide char when trailing spacer is selected.
         if grid_line[cols.start].flags.contains(Flags::WIDE_CHAR_SPACER) {
             cols.start -= 1;
@@ -574,21 +870,15 @@ impl<T> Term<T> {
             }
 
             if !cell.flags.intersects(Flags::WIDE_CHAR_SPACER | Flags::LEADING_WIDE_CHAR_SPACER) {
-                // Push cells primary character.
-                text.push(cell.c);
-
-                // Push zero-width characters.
-                for c in cell.zerowidth().into_iter().flatten() {
-                    text.push(*c);
-                }
+                f(Some(cell));
             }
         }
 
-        if cols.end >= self.cols() - 1
+        if cols.end >= line_columns - 1
             && (line_length.0 == 0
                 || !self.grid[line][line_length - 1].flags.contains(Flags::WRAPLINE))
         {
-            text.push('\n');
+            f(None);
         }
 
         // If wide char is not part of the selection, but leading spacer is, include it.
@@ -597,10 +887,8 @@ impl<T> Term<T> {
             && grid_line[line_length - 1].flags.contains(Flags::LEADING_WIDE_CHAR_SPACER)
             && include_wrapped_wide
         {
-            text.push(self.grid[line - 1][Column(0)].c);
+            f(Some(&self.grid[line - 1][Column(0)]));
         }
-
-        text
     }
 
     pub fn visible_to_buffer(&self, point: Point) -> Point<usize> {
@@ -702,8 +990,21 @@ impl<T> Term<T> {
         self.vi_mode_cursor.point.col = min(self.vi_mode_cursor.point.col, num_cols - 1);
         self.vi_mode_cursor.point.line = min(self.vi_mode_cursor.point.line, num_lines - 1);
 
-        // Reset scrolling region.
+        // Reset scrolling region and margins.
         self.scroll_region = Line(0)..self.screen_lines();
+        self.horizontal_margins = Column(0)..self.cols();
+    }
+
+    /// Working directory reported by the shell using `OSC 7`.
+    #[inline]
+    pub fn working_directory(&self) -> Option<&Path> {
+        self.working_directory.as_deref()
+    }
+
+    /// Take the graphics which were added or removed since the last call.
+    #[inline]
+    pub fn graphics_take_queues(&mut self) -> Option<UpdateQueues> {
+        self.graphics.take_queues()
     }
 
     /// Active terminal modes.
@@ -724,11 +1025,25 @@ impl<T> Term<T> {
             // Reset alternate screen contents.
             let bg = self.inactive_grid.cursor.template.bg;
             self.inactive_grid.region_mut(..).each(|cell| *cell = bg.into());
+
+            // Start the alternate screen without any keyboard modes.
+            self.inactive_keyboard_mode_stack.clear();
         }
 
         mem::swap(&mut self.grid, &mut self.inactive_grid);
+        mem::swap(&mut self.keyboard_mode_stack, &mut self.inactive_keyboard_mode_stack);
         self.mode ^= TermMode::ALT_SCREEN;
         self.selection = None;
+
+        self.apply_keyboard_mode();
+    }
+
+    /// Update the terminal mode to match the active kitty keyboard protocol modes.
+    fn apply_keyboard_mode(&mut self) {
+        let mode = self.keyboard_mode_stack.last().copied().unwrap_or(KeyboardModes::NO_MODE);
+
+        self.mode.remove(TermMode::KITTY_KEYBOARD_PROTOCOL);
+        self.mode.insert(TermMode::from(mode));
     }
 
     /// Scroll screen down.
@@ -748,13 +1063,15 @@ impl<T> Term<T> {
         let absolute_region = (num_lines - region.end)..(num_lines - region.start);
 
         // Scroll selection.
+        let full_width = self.has_full_width_margins();
         self.selection = self
             .selection
             .take()
+            .filter(|_| full_width)
             .and_then(|s| s.rotate(self, &absolute_region, -(lines.0 as isize)));
 
         // Scroll between origin and bottom
-        self.grid.scroll_down(&region, lines);
+        self.grid.scroll_down(&region, &self.horizontal_margins, lines);
     }
 
     /// Scroll screen up
@@ -773,11 +1090,85 @@ impl<T> Term<T> {
         let absolute_region = (num_lines - region.end)..(num_lines - region.start);
 
         // Scroll selection.
-        self.selection =
-            self.selection.take().and_then(|s| s.rotate(self, &absolute_region, lines.0 as isize));
+        let full_width = self.has_full_width_margins();
+        self.selection = self
+            .selection
+            .take()
+            .filter(|_| full_width)
+            .and_then(|s| s.rotate(self, &absolute_region, lines.0 as isize));
+
+        // Archive lines pushed out of the scrollback history.
+        if origin == Line(0) && full_width && !self.mode.contains(TermMode::ALT_SCREEN) {
+            if let Some(archive) = &mut self.archive {
+                archive.scroll_up(&mut self.grid, lines.0);
+            }
+        }
 
         // Scroll from origin to bottom less number of lines.
-        self.grid.scroll_up(&region, lines);
+        self.grid.scroll_up(&region, &self.horizontal_margins, lines);
+    }
+
+    /// Check if the left and right margins cover all columns.
+    #[inline]
+    fn has_full_width_margins(&self) -> bool {
+        self.horizontal_margins.start == Column(0) && self.horizontal_margins.end == self.cols()
+    }
+
+    /// Column after the last one which can be written to from the cursor position.
+    ///
+    /// Text only wraps at the right margin when the cursor is inside of it.
+    #[inline]
+    fn right_edge(&self) -> Column {
+        let line_columns = self.line_columns(self.grid.cursor.point.line);
+        if self.grid.cursor.point.col < self.horizontal_margins.end {
+            min(self.horizontal_margins.end, line_columns)
+        } else {
+            line_columns
+        }
+    }
+
+    /// Number of columns available in a line of the active area.
+    #[inline]
+    fn line_columns(&self, line: Line) -> Column {
+        self.row_columns(&self.grid[line])
+    }
+
+    /// Number of columns available in a row.
+    ///
+    /// Double-width lines only fit half as many characters.
+    #[inline]
+    fn row_columns(&self, row: &Row<Cell>) -> Column {
+        match row.line_size() {
+            LineSize::Normal => self.cols(),
+            _ => Column(self.cols().0 / 2),
+        }
+    }
+
+    /// Area which can be modified by rectangle operations.
+    ///
+    /// In origin mode, rectangles are limited to the scrolling region and margins.
+    fn rectangle_area(&self) -> (Range<Line>, Range<Column>) {
+        if self.mode.contains(TermMode::ORIGIN) {
+            (self.scroll_region.clone(), self.horizontal_margins.clone())
+        } else {
+            (Line(0)..self.screen_lines(), Column(0)..self.cols())
+        }
+    }
+
+    /// Lines and columns covered by a rectangle, `None` if it is empty.
+    fn rectangle_bounds(&self, rect: Rectangle) -> Option<(Range<Line>, Range<Column>)> {
+        let (lines, cols) = self.rectangle_area();
+
+        let top = min(lines.start + rect.top.saturating_sub(1), lines.end);
+        let left = min(cols.start + rect.left.saturating_sub(1), cols.end);
+        let bottom = rect.bottom.map_or(lines.end, |bottom| min(lines.start + bottom, lines.end));
+        let right = rect.right.map_or(cols.end, |right| min(cols.start + right, cols.end));
+
+        if top >= bottom || left >= right {
+            return None;
+        }
+
+        Some((top..bottom, left..right))
     }
 
     fn deccolm(&mut self)
@@ -785,6 +1176,10 @@ impl<T> Term<T> {
         T: EventListener,
     {
         // Setting 132 column font makes no sense, but run the other side effects.
+        // Disable left and right margins.
+        self.mode.remove(TermMode::LEFT_RIGHT_MARGIN);
+        self.horizontal_margins = Column(0)..self.cols();
+
         // Clear scrolling region.
         self.set_scrolling_region(1, None);
 
@@ -908,6 +1303,56 @@ impl<T> Term<T> {
         }
     }
 
+    /// Find the closest line with a prompt mark in the specified direction.
+    ///
+    /// The `origin` line itself is never returned. Searching towards the `Left` goes up into the
+    /// history, while searching towards the `Right` goes down to the bottom of the screen.
+    pub fn prompt_search(&self, origin: usize, direction: Direction) -> Option<usize> {
+        let is_prompt = |line: &usize| self.grid[*line].marks.contains(PromptMarks::PROMPT_START);
+
+        match direction {
+            Direction::Left => (origin + 1..self.total_lines()).find(is_prompt),
+            Direction::Right => (0..min(origin, self.total_lines())).rev().find(is_prompt),
+        }
+    }
+
+    /// Find the oldest line which arrived at or after `time`.
+    pub fn timestamp_search(&self, time: SystemTime) -> Option<usize> {
+        (0..self.total_lines())
+            .rev()
+            .find(|&line| matches!(self.grid[line].timestamp, Some(timestamp) if timestamp >= time))
+    }
+
+    /// Line selection covering the output of the last command.
+    ///
+    /// Commands which are still running have their output selected up to the cursor line.
+    pub fn last_command_output(&self) -> Option<Selection> {
+        let cursor_line = self.screen_lines().0 - self.grid.cursor.point.line.0 - 1;
+        let boundary = PromptMarks::PROMPT_START | PromptMarks::COMMAND_END;
+
+        // Find the closest output start mark, keeping track of where its output ended.
+        let mut output_end = None;
+        for line in cursor_line..self.total_lines() {
+            let marks = self.grid[line].marks;
+
+            // Ignore commands which terminated without printing any output.
+            if marks.contains(PromptMarks::OUTPUT_START) && !marks.intersects(boundary) {
+                let end = output_end.map(|end| end + 1).unwrap_or(cursor_line);
+
+                let mut selection =
+                    Selection::new(SelectionType::Lines, Point::new(line, Column(0)), Side::Left);
+                selection.update(Point::new(end, self.cols() - 1), Side::Right);
+                return Some(selection);
+            }
+
+            if marks.intersects(boundary) {
+                output_end = Some(line);
+            }
+        }
+
+        None
+    }
+
     /// Jump to the end of a wide cell.
     pub fn expand_wide(&self, mut point: Point<usize>, direction: Direction) -> Point<usize> {
         let flags = self.grid[point.line][point.col].flags;
@@ -965,7 +1410,18 @@ impl<T> Term<T> {
 
         trace!("Wrapping input");
 
-        self.grid.cursor_cell().flags.insert(Flags::WRAPLINE);
+        // Text wrapped at the right margin does not continue on the next line.
+        let line = self.grid.cursor.point.line;
+        if self.right_edge() == self.line_columns(line) {
+            self.grid.cursor_cell().flags.insert(Flags::WRAPLINE);
+        }
+
+        // Wrap to the left margin when the cursor is inside the margins.
+        let col = if self.grid.cursor.point.col < self.horizontal_margins.end {
+            self.horizontal_margins.start
+        } else {
+            Column(0)
+        };
 
         if (self.grid.cursor.point.line + 1) >= self.scroll_region.end {
             self.linefeed();
@@ -973,7 +1429,7 @@ impl<T> Term<T> {
             self.grid.cursor.point.line += 1;
         }
 
-        self.grid.cursor.point.col = Column(0);
+        self.grid.cursor.point.col = col;
         self.grid.cursor.input_needs_wrap = false;
     }
 
@@ -983,14 +1439,24 @@ impl<T> Term<T> {
     where
         T: EventListener,
     {
-        let c = self.grid.cursor.charsets[self.active_charset].map(c);
         let fg = self.grid.cursor.template.fg;
         let bg = self.grid.cursor.template.bg;
         let flags = self.grid.cursor.template.flags;
+        let hyperlink = self.grid.cursor.template.hyperlink().cloned();
+        let underline_color = self.grid.cursor.template.underline_color();
+
+        // Record when content first arrived on this line.
+        let line = self.grid.cursor.point.line;
+        let row = &mut self.grid[line];
+        if row.timestamp.is_none() {
+            row.timestamp = Some(SystemTime::now());
+        }
 
         let cursor_cell = self.grid.cursor_cell();
 
         cursor_cell.drop_extra();
+        cursor_cell.set_hyperlink(hyperlink);
+        cursor_cell.set_underline_color(underline_color);
 
         cursor_cell.c = c;
         cursor_cell.fg = fg;
@@ -999,6 +1465,130 @@ impl<T> Term<T> {
 
         cursor_cell
     }
+
+    /// Display a kitty image at the cursor position.
+    fn place_kitty_image(&mut self, command: &kitty::Command, image: KittyImage)
+    where
+        T: EventListener,
+    {
+        // Placements with an ID replace the existing placement with the same ID.
+        let graphic_id = image.graphic.id();
+        if command.placement_id != 0 {
+            self.clear_graphics(false, |graphic| {
+                graphic.graphic.id() == graphic_id && graphic.placement_id == command.placement_id
+            });
+        }
+
+        let cell_width = max(self.cell_width, 1);
+        let cell_height = max(self.cell_height, 1);
+        let columns = (image.width - 1) / cell_width + 1;
+        let lines = (image.height - 1) / cell_height + 1;
+
+        let start = self.grid.cursor.point;
+        let end_col = min(start.col.0 + columns, self.cols().0);
+        for line in 0..lines {
+            // Scroll like text would, unless the cursor should stay in place.
+            let row_line = if command.move_cursor {
+                if line > 0 {
+                    self.linefeed();
+                }
+                self.grid.cursor.point.line
+            } else if start.line.0 + line < self.screen_lines().0 {
+                Line(start.line.0 + line)
+            } else {
+                break;
+            };
+
+            let row = &mut self.grid[row_line];
+            for col in start.col.0..end_col {
+                row[Column(col)].set_graphic(GraphicCell {
+                    graphic: image.graphic.clone(),
+                    offset_x: ((col - start.col.0) * cell_width) as u16,
+                    offset_y: (line * cell_height) as u16,
+                    placement_id: command.placement_id,
+                    z_index: command.z_index,
+                });
+            }
+        }
+
+        // Continue after the last column of the image, on its last line.
+        if command.move_cursor {
+            self.grid.cursor.point.col = Column(min(end_col, self.cols().0 - 1));
+            self.grid.cursor.input_needs_wrap = end_col >= self.cols().0;
+        }
+    }
+
+    /// Remove kitty image placements.
+    ///
+    /// With `delete_data` set, the data of images without any placements left is freed too.
+    fn delete_kitty_placements(&mut self, command: &kitty::Command) {
+        let image_id = command.image_id;
+        let placement_id = command.placement_id;
+        let removed = match command.delete {
+            Some(kitty::DeleteTarget::All) => self.clear_graphics(true, |_| true),
+            Some(kitty::DeleteTarget::Image) => {
+                let graphic_id = match self.graphics.kitty_image(image_id) {
+                    Some(image) => image.graphic.id(),
+                    None => return,
+                };
+
+                self.clear_graphics(false, |graphic| {
+                    graphic.graphic.id() == graphic_id
+                        && (placement_id == 0 || graphic.placement_id == placement_id)
+                });
+
+                // The image data is freed even if other placements are left.
+                if command.delete_data {
+                    self.graphics.remove_kitty_image(image_id);
+                }
+
+                return;
+            },
+            Some(kitty::DeleteTarget::Cursor) => {
+                let (graphic_id, placement_id) = match self.grid.cursor_cell().graphic() {
+                    Some(graphic) => (graphic.graphic.id(), graphic.placement_id),
+                    None => return,
+                };
+
+                self.clear_graphics(false, |graphic| {
+                    graphic.graphic.id() == graphic_id && graphic.placement_id == placement_id
+                })
+            },
+            Some(kitty::DeleteTarget::ZIndex) => {
+                let z_index = command.z_index;
+                self.clear_graphics(true, |graphic| graphic.z_index == z_index)
+            },
+            None => {
+                debug!("Unsupported kitty graphics delete command");
+                return;
+            },
+        };
+
+        if command.delete_data {
+            self.graphics.remove_unplaced_kitty_images(&removed);
+        }
+    }
+
+    /// Remove all graphics matching `predicate` from the grid.
+    ///
+    /// With `screen_only` set, the scrollback history is left untouched. Returns the IDs of all
+    /// removed graphics.
+    fn clear_graphics<F>(&mut self, screen_only: bool, predicate: F) -> HashSet<GraphicId>
+    where
+        F: Fn(&GraphicCell) -> bool,
+    {
+        let mut removed = HashSet::new();
+        let lines = if screen_only { self.screen_lines().0 } else { self.grid.total_lines() };
+        for line in 0..lines {
+            for cell in &mut self.grid[line] {
+                if let Some(graphic) = cell.graphic().filter(|graphic| predicate(graphic)) {
+                    removed.insert(graphic.graphic.id());
+                    cell.clear_graphic();
+                }
+            }
+        }
+        removed
+    }
 }
 
 impl<T> Dimensions for Term<T> {
@@ -1022,6 +1612,9 @@ impl<T: EventListener> Handler for Term<T> {
     /// A character to be displayed.
     #[inline(never)]
     fn input(&mut self, c: char) {
+        // Consume pending single shift, even if nothing is written.
+        let charset = self.single_shift.take().unwrap_or(self.active_charset);
+
         // Number of cells the char will occupy.
         let width = match c.width() {
             Some(width) => width,
@@ -1046,20 +1639,22 @@ impl<T: EventListener> Handler for Term<T> {
             return;
         }
 
+        let c = self.grid.cursor.charsets[charset].map(c);
+
         // Move cursor to next line.
         if self.grid.cursor.input_needs_wrap {
             self.wrapline();
         }
 
-        let num_cols = self.cols();
+        let right_edge = self.right_edge();
 
         // If in insert mode, first shift cells to the right.
-        if self.mode.contains(TermMode::INSERT) && self.grid.cursor.point.col + width < num_cols {
+        if self.mode.contains(TermMode::INSERT) && self.grid.cursor.point.col + width < right_edge {
             let line = self.grid.cursor.point.line;
             let col = self.grid.cursor.point.col;
             let row = &mut self.grid[line][..];
 
-            for col in (col.0..(num_cols - width).0).rev() {
+            for col in (col.0..(right_edge - width).0).rev() {
                 row.swap(col + width, col);
             }
         }
@@ -1067,7 +1662,7 @@ impl<T: EventListener> Handler for Term<T> {
         if width == 1 {
             self.write_at_cursor(c);
         } else {
-            if self.grid.cursor.point.col + 1 >= num_cols {
+            if self.grid.cursor.point.col + 1 >= right_edge {
                 if self.mode.contains(TermMode::LINE_WRAP) {
                     // Insert placeholder before wide char if glyph does not fit in this row.
                     self.write_at_cursor(' ').flags.insert(Flags::LEADING_WIDE_CHAR_SPACER);
@@ -1087,7 +1682,7 @@ impl<T: EventListener> Handler for Term<T> {
             self.write_at_cursor(' ').flags.insert(Flags::WIDE_CHAR_SPACER);
         }
 
-        if self.grid.cursor.point.col + 1 < num_cols {
+        if self.grid.cursor.point.col + 1 < self.right_edge() {
             self.grid.cursor.point.col += 1;
         } else {
             self.grid.cursor.input_needs_wrap = true;
@@ -1107,17 +1702,36 @@ impl<T: EventListener> Handler for Term<T> {
     #[inline]
     fn goto(&mut self, line: Line, col: Column) {
         trace!("Going to: line={}, col={}", line, col);
-        let (y_offset, max_y) = if self.mode.contains(TermMode::ORIGIN) {
-            (self.scroll_region.start, self.scroll_region.end - 1)
+        let (y_offset, max_y, x_offset, max_x) = if self.mode.contains(TermMode::ORIGIN) {
+            let margins = &self.horizontal_margins;
+            (self.scroll_region.start, self.scroll_region.end - 1, margins.start, margins.end - 1)
         } else {
-            (Line(0), self.screen_lines() - 1)
+            (Line(0), self.screen_lines() - 1, Column(0), self.cols() - 1)
         };
 
         self.grid.cursor.point.line = min(line + y_offset, max_y);
-        self.grid.cursor.point.col = min(col, self.cols() - 1);
+        let max_x = min(max_x, self.line_columns(self.grid.cursor.point.line) - 1);
+        self.grid.cursor.point.col = min(col + x_offset, max_x);
         self.grid.cursor.input_needs_wrap = false;
     }
 
+    #[inline]
+    fn set_line_size(&mut self, size: LineSize) {
+        trace!("Setting line size: {:?}", size);
+
+        let line = self.grid.cursor.point.line;
+        self.grid[line].line_size = size;
+
+        // Characters which no longer fit into the line are lost.
+        let line_columns = self.line_columns(line);
+        let bg = self.grid.cursor.template.bg;
+        for cell in &mut self.grid[line][line_columns..] {
+            *cell = bg.into();
+        }
+
+        self.grid.cursor.point.col = min(self.grid.cursor.point.col, line_columns - 1);
+    }
+
     #[inline]
     fn goto_line(&mut self, line: Line) {
         trace!("Going to line: {}", line);
@@ -1135,12 +1749,18 @@ impl<T: EventListener> Handler for Term<T> {
         let cursor = &self.grid.cursor;
         let bg = cursor.template.bg;
 
+        // Blanks are only inserted within the horizontal margins.
+        if !self.horizontal_margins.contains(&cursor.point.col) {
+            return;
+        }
+        let right = self.horizontal_margins.end;
+
         // Ensure inserting within terminal bounds
-        let count = min(count, self.cols() - cursor.point.col);
+        let count = min(count, right - cursor.point.col);
 
         let source = cursor.point.col;
         let destination = cursor.point.col + count;
-        let num_cells = (self.cols() - destination).0;
+        let num_cells = (right - destination).0;
 
         let line = cursor.point.line;
         let row = &mut self.grid[line][..];
@@ -1173,7 +1793,7 @@ impl<T: EventListener> Handler for Term<T> {
     #[inline]
     fn move_forward(&mut self, cols: Column) {
         trace!("Moving forward: {}", cols);
-        let num_cols = self.cols();
+        let num_cols = self.line_columns(self.grid.cursor.point.line);
         self.grid.cursor.point.col = min(self.grid.cursor.point.col + cols, num_cols - 1);
         self.grid.cursor.input_needs_wrap = false;
     }
@@ -1190,28 +1810,306 @@ impl<T: EventListener> Handler for Term<T> {
         match intermediate {
             None => {
                 trace!("Reporting primary device attributes");
-                let _ = writer.write_all(b"\x1b[?6c");
+                self.write_permitted(writer, Capability::Query, String::from("\x1b[?6;4c"));
             },
             Some('>') => {
                 trace!("Reporting secondary device attributes");
-                let version = version_number(env!("CARGO_PKG_VERSION"));
-                let _ = writer.write_all(format!("\x1b[>0;{};1c", version).as_bytes());
+                let version = version_number(&self.version);
+                let report = format!("\x1b[>0;{};1c", version);
+                self.write_permitted(writer, Capability::Query, report);
             },
             _ => debug!("Unsupported device attributes intermediate"),
         }
     }
 
     #[inline]
-    fn device_status<W: io::Write>(&mut self, writer: &mut W, arg: usize) {
-        trace!("Reporting device status: {}", arg);
-        match arg {
-            5 => {
-                let _ = writer.write_all(b"\x1b[0n");
-            },
-            6 => {
+    fn report_version<W: io::Write>(&mut self, writer: &mut W) {
+        trace!("Reporting terminal version");
+        let version = format!("\x1bP>|alacritty({})\x1b\\", self.version);
+        self.write_permitted(writer, Capability::Query, version);
+    }
+
+    #[inline]
+    fn report_unsupported<W: io::Write>(&mut self, writer: &mut W, report: String) {
+        trace!("Reporting unsupported query");
+        self.write_permitted(writer, Capability::Query, report);
+    }
+
+    #[inline]
+    fn report_mode<W: io::Write>(&mut self, writer: &mut W, mode: ansi::Mode) {
+        trace!("Reporting mode {:?}", mode);
+
+        let state = match mode {
+            ansi::Mode::CursorKeys => self.mode.contains(TermMode::APP_CURSOR).into(),
+            ansi::Mode::Insert => self.mode.contains(TermMode::INSERT).into(),
+            ansi::Mode::Origin => self.mode.contains(TermMode::ORIGIN).into(),
+            ansi::Mode::LeftRightMargin => self.mode.contains(TermMode::LEFT_RIGHT_MARGIN).into(),
+            ansi::Mode::LineWrap => self.mode.contains(TermMode::LINE_WRAP).into(),
+            ansi::Mode::BlinkingCursor => self.cursor_style().blinking.into(),
+            ansi::Mode::LineFeedNewLine => self.mode.contains(TermMode::LINE_FEED_NEW_LINE).into(),
+            ansi::Mode::ShowCursor => self.mode.contains(TermMode::SHOW_CURSOR).into(),
+            ansi::Mode::ReportMouseClicks => {
+                self.mode.contains(TermMode::MOUSE_REPORT_CLICK).into()
+            },
+            ansi::Mode::ReportCellMouseMotion => self.mode.contains(TermMode::MOUSE_DRAG).into(),
+            ansi::Mode::ReportAllMouseMotion => self.mode.contains(TermMode::MOUSE_MOTION).into(),
+            ansi::Mode::ReportFocusInOut => self.mode.contains(TermMode::FOCUS_IN_OUT).into(),
+            ansi::Mode::Utf8Mouse => self.mode.contains(TermMode::UTF8_MOUSE).into(),
+            ansi::Mode::SgrMouse => self.mode.contains(TermMode::SGR_MOUSE).into(),
+            ansi::Mode::AlternateScroll => self.mode.contains(TermMode::ALTERNATE_SCROLL).into(),
+            ansi::Mode::UrgencyHints => self.mode.contains(TermMode::URGENCY_HINTS).into(),
+            ansi::Mode::SwapScreenAndSetRestoreCursor => {
+                self.mode.contains(TermMode::ALT_SCREEN).into()
+            },
+            ansi::Mode::BracketedPaste => self.mode.contains(TermMode::BRACKETED_PASTE).into(),
+            ansi::Mode::SyncUpdate => self.mode.contains(TermMode::SYNC_UPDATE).into(),
+            // Column mode switching only clears the screen, the width is never changed.
+            ansi::Mode::DECCOLM => ModeState::PermanentlyReset,
+        };
+
+        let private = match mode {
+            ansi::Mode::Insert | ansi::Mode::LineFeedNewLine => "",
+            _ => "?",
+        };
+
+        let report = format!("\x1b[{}{};{}$y", private, mode as u16, state as u8);
+        self.write_permitted(writer, Capability::Query, report);
+    }
+
+    #[inline]
+    fn report_setting<W: io::Write>(&mut self, writer: &mut W, setting: ansi::Setting) {
+        trace!("Reporting setting {:?}", setting);
+
+        let value = match setting {
+            ansi::Setting::Attributes => format!("{}m", sgr_parameters(&self.grid.cursor.template)),
+            ansi::Setting::ScrollingRegion => {
+                format!("{};{}r", self.scroll_region.start.0 + 1, self.scroll_region.end.0)
+            },
+            ansi::Setting::LeftRightMargins => {
+                let margins = &self.horizontal_margins;
+                format!("{};{}s", margins.start.0 + 1, margins.end.0)
+            },
+            ansi::Setting::CursorStyle => {
+                let style = self.cursor_style();
+                let shape = match style.shape {
+                    CursorShape::Underline => 4,
+                    CursorShape::Beam => 6,
+                    CursorShape::Block | CursorShape::HollowBlock | CursorShape::Hidden => 2,
+                };
+                format!("{} q", shape - style.blinking as u8)
+            },
+        };
+
+        self.write_permitted(writer, Capability::Query, format!("\x1bP1$r{}\x1b\\", value));
+    }
+
+    #[inline]
+    fn report_capability<W: io::Write>(&mut self, writer: &mut W, name: &str) {
+        trace!("Reporting terminfo capability {:?}", name);
+
+        let hex = |text: &str| text.bytes().map(|byte| format!("{:02X}", byte)).collect::<String>();
+        let report = match terminfo::capability(name) {
+            Some("") => format!("\x1bP1+r{}\x1b\\", hex(name)),
+            Some(value) => format!("\x1bP1+r{}={}\x1b\\", hex(name), hex(value)),
+            None => format!("\x1bP0+r{}\x1b\\", hex(name)),
+        };
+        self.write_permitted(writer, Capability::Query, report);
+    }
+
+    #[inline]
+    fn copy_rectangle(&mut self, source: Rectangle, top: usize, left: usize) {
+        trace!("Copying rectangle {:?} to ({};{})", source, top, left);
+
+        let (lines, cols) = match self.rectangle_bounds(source) {
+            Some(bounds) => bounds,
+            None => return,
+        };
+
+        // Clip the destination to the available area.
+        let (area_lines, area_cols) = self.rectangle_area();
+        let top = min(area_lines.start + top.saturating_sub(1), area_lines.end);
+        let left = min(area_cols.start + left.saturating_sub(1), area_cols.end);
+        let height = min(lines.end - lines.start, area_lines.end - top);
+        let width = min(cols.end - cols.start, area_cols.end - left);
+        if height == Line(0) || width == Column(0) {
+            return;
+        }
+
+        // Copy the source first, since it might overlap with the destination.
+        let cells: Vec<Vec<Cell>> = self
+            .grid
+            .region(lines.start..lines.start + height)
+            .into_iter()
+            .map(|row| row[cols.start..cols.start + width].to_vec())
+            .collect();
+
+        for (row, cells) in self.grid.region_mut(top..top + height).into_iter().zip(cells) {
+            row[left..left + width].clone_from_slice(&cells);
+        }
+    }
+
+    #[inline]
+    fn fill_rectangle(&mut self, c: char, rect: Rectangle) {
+        trace!("Filling rectangle {:?} with {:?}", rect, c);
+
+        let (lines, cols) = match self.rectangle_bounds(rect) {
+            Some(bounds) => bounds,
+            None => return,
+        };
+
+        let mut template = self.grid.cursor.template.clone();
+        template.c = c;
+
+        for row in self.grid.region_mut(lines) {
+            for cell in &mut row[cols.clone()] {
+                *cell = template.clone();
+            }
+        }
+    }
+
+    #[inline]
+    fn erase_rectangle(&mut self, rect: Rectangle, selective: bool) {
+        trace!("Erasing rectangle {:?}: selective={}", rect, selective);
+
+        let (lines, cols) = match self.rectangle_bounds(rect) {
+            Some(bounds) => bounds,
+            None => return,
+        };
+
+        // Cleared cells have current background color set.
+        let bg = self.grid.cursor.template.bg;
+        for row in self.grid.region_mut(lines) {
+            for cell in &mut row[cols.clone()] {
+                if selective {
+                    cell.clear_content();
+                } else {
+                    *cell = bg.into();
+                }
+            }
+        }
+    }
+
+    #[inline]
+    fn set_rectangle_attributes(&mut self, rect: Rectangle, attrs: &[Attr]) {
+        trace!("Setting attributes {:?} in rectangle {:?}", attrs, rect);
+
+        let (lines, cols) = match self.rectangle_bounds(rect) {
+            Some(bounds) => bounds,
+            None => return,
+        };
+
+        // Combine all attributes into the flags which need to be added and removed.
+        let mut insert = Flags::empty();
+        let mut remove = Flags::empty();
+        for attr in attrs {
+            let (set, clear) = match attr {
+                Attr::Reset => (Flags::empty(), RECTANGLE_FLAGS),
+                Attr::Bold => (Flags::BOLD, Flags::empty()),
+                Attr::Underline => (Flags::UNDERLINE, Flags::ALL_UNDERLINES),
+                Attr::Reverse => (Flags::INVERSE, Flags::empty()),
+                Attr::CancelBold => (Flags::empty(), Flags::BOLD),
+                Attr::CancelUnderline => (Flags::empty(), Flags::ALL_UNDERLINES),
+                Attr::CancelReverse => (Flags::empty(), Flags::INVERSE),
+                _ => continue,
+            };
+
+            insert.remove(clear);
+            remove.insert(clear);
+            insert.insert(set);
+            remove.remove(set);
+        }
+
+        for row in self.grid.region_mut(lines) {
+            for cell in &mut row[cols.clone()] {
+                cell.flags.remove(remove);
+                cell.flags.insert(insert);
+            }
+        }
+    }
+
+    #[inline]
+    fn reverse_rectangle_attributes(&mut self, rect: Rectangle, attrs: &[Attr]) {
+        trace!("Reversing attributes {:?} in rectangle {:?}", attrs, rect);
+
+        let (lines, cols) = match self.rectangle_bounds(rect) {
+            Some(bounds) => bounds,
+            None => return,
+        };
+
+        let mut toggle = Flags::empty();
+        for attr in attrs {
+            match attr {
+                Attr::Reset => toggle.toggle(RECTANGLE_FLAGS),
+                Attr::Bold => toggle.toggle(Flags::BOLD),
+                Attr::Underline => toggle.toggle(Flags::UNDERLINE),
+                Attr::Reverse => toggle.toggle(Flags::INVERSE),
+                _ => (),
+            }
+        }
+
+        for row in self.grid.region_mut(lines) {
+            for cell in &mut row[cols.clone()] {
+                // Any underline style counts as underlined.
+                if toggle.contains(Flags::UNDERLINE) {
+                    if cell.flags.intersects(Flags::ALL_UNDERLINES) {
+                        cell.flags.remove(Flags::ALL_UNDERLINES);
+                    } else {
+                        cell.flags.insert(Flags::UNDERLINE);
+                    }
+                }
+
+                cell.flags.toggle(toggle & (Flags::BOLD | Flags::INVERSE));
+            }
+        }
+    }
+
+    #[inline]
+    fn report_rectangle_checksum<W: io::Write>(
+        &mut self,
+        writer: &mut W,
+        id: u16,
+        rect: Rectangle,
+    ) {
+        trace!("Reporting checksum of rectangle {:?}", rect);
+
+        let mut checksum = 0u16;
+        if let Some((lines, cols)) = self.rectangle_bounds(rect) {
+            for row in self.grid.region(lines) {
+                for cell in &row[cols.clone()] {
+                    // Wide characters are only counted once.
+                    if cell.flags.contains(Flags::WIDE_CHAR_SPACER) {
+                        continue;
+                    }
+
+                    checksum = checksum.wrapping_add(cell.c as u16);
+
+                    // Attributes are added using the same weights as xterm.
+                    if cell.flags.contains(Flags::BOLD) {
+                        checksum = checksum.wrapping_add(0x80);
+                    }
+                    if cell.flags.contains(Flags::INVERSE) {
+                        checksum = checksum.wrapping_add(0x20);
+                    }
+                    if cell.flags.intersects(Flags::ALL_UNDERLINES) {
+                        checksum = checksum.wrapping_add(0x10);
+                    }
+                }
+            }
+        }
+
+        let report = format!("\x1bP{}!~{:04X}\x1b\\", id, checksum.wrapping_neg());
+        self.write_permitted(writer, Capability::Query, report);
+    }
+
+    #[inline]
+    fn device_status<W: io::Write>(&mut self, writer: &mut W, arg: usize) {
+        trace!("Reporting device status: {}", arg);
+        match arg {
+            5 => self.write_permitted(writer, Capability::Query, String::from("\x1b[0n")),
+            6 => {
                 let pos = self.grid.cursor.point;
                 let response = format!("\x1b[{};{}R", pos.line + 1, pos.col + 1);
-                let _ = writer.write_all(response.as_bytes());
+                self.write_permitted(writer, Capability::Query, response);
             },
             _ => debug!("unknown device status query: {}", arg),
         };
@@ -1240,7 +2138,8 @@ impl<T: EventListener> Handler for Term<T> {
             return;
         }
 
-        while self.grid.cursor.point.col < self.cols() && count != 0 {
+        let num_cols = self.line_columns(self.grid.cursor.point.line);
+        while self.grid.cursor.point.col < num_cols && count != 0 {
             count -= 1;
 
             let c = self.grid.cursor.charsets[self.active_charset].map('\t');
@@ -1250,7 +2149,7 @@ impl<T: EventListener> Handler for Term<T> {
             }
 
             loop {
-                if (self.grid.cursor.point.col + 1) == self.cols() {
+                if (self.grid.cursor.point.col + 1) >= num_cols {
                     break;
                 }
 
@@ -1278,7 +2177,9 @@ impl<T: EventListener> Handler for Term<T> {
     #[inline]
     fn carriage_return(&mut self) {
         trace!("Carriage return");
-        self.grid.cursor.point.col = Column(0);
+        let left = self.horizontal_margins.start;
+        self.grid.cursor.point.col =
+            if self.grid.cursor.point.col >= left { left } else { Column(0) };
         self.grid.cursor.input_needs_wrap = false;
     }
 
@@ -1288,7 +2189,10 @@ impl<T: EventListener> Handler for Term<T> {
         trace!("Linefeed");
         let next = self.grid.cursor.point.line + 1;
         if next == self.scroll_region.end {
-            self.scroll_up(Line(1));
+            // Only the area inside the margins is scrolled, so the cursor has to be inside it.
+            if self.horizontal_margins.contains(&self.grid.cursor.point.col) {
+                self.scroll_up(Line(1));
+            }
         } else if next < self.screen_lines() {
             self.grid.cursor.point.line += 1;
         }
@@ -1361,7 +2265,9 @@ impl<T: EventListener> Handler for Term<T> {
         trace!("Inserting blank {} lines", lines);
 
         let origin = self.grid.cursor.point.line;
-        if self.scroll_region.contains(&origin) {
+        if self.scroll_region.contains(&origin)
+            && self.horizontal_margins.contains(&self.grid.cursor.point.col)
+        {
             self.scroll_down_relative(origin, lines);
         }
     }
@@ -1373,7 +2279,10 @@ impl<T: EventListener> Handler for Term<T> {
 
         trace!("Deleting {} lines", lines);
 
-        if lines.0 > 0 && self.scroll_region.contains(&self.grid.cursor.point.line) {
+        if lines.0 > 0
+            && self.scroll_region.contains(&self.grid.cursor.point.line)
+            && self.horizontal_margins.contains(&self.grid.cursor.point.col)
+        {
             self.scroll_up_relative(origin, lines);
         }
     }
@@ -1398,16 +2307,21 @@ impl<T: EventListener> Handler for Term<T> {
 
     #[inline]
     fn delete_chars(&mut self, count: Column) {
-        let cols = self.cols();
         let cursor = &self.grid.cursor;
         let bg = cursor.template.bg;
 
-        // Ensure deleting within terminal bounds.
-        let count = min(count, cols);
+        // Characters are only deleted within the horizontal margins.
+        if !self.horizontal_margins.contains(&cursor.point.col) {
+            return;
+        }
+        let right = self.horizontal_margins.end;
 
+        // Ensure deleting within terminal bounds.
         let start = cursor.point.col;
-        let end = min(start + count, cols - 1);
-        let num_cells = (cols - end).0;
+        let count = min(count, right - start);
+
+        let end = start + count;
+        let num_cells = (right - end).0;
 
         let line = cursor.point.line;
         let row = &mut self.grid[line][..];
@@ -1416,10 +2330,9 @@ impl<T: EventListener> Handler for Term<T> {
             row.swap(start.0 + offset, end.0 + offset);
         }
 
-        // Clear last `count` cells in the row. If deleting 1 char, need to delete
+        // Clear last `count` cells before the right margin. If deleting 1 char, need to delete
         // 1 cell.
-        let end = (cols - count).0;
-        for cell in &mut row[end..] {
+        for cell in &mut row[(right - count).0..right.0] {
             *cell = bg.into();
         }
     }
@@ -1507,17 +2420,17 @@ impl<T: EventListener> Handler for Term<T> {
     fn dynamic_color_sequence<W: io::Write>(
         &mut self,
         writer: &mut W,
-        code: u8,
+        prefix: &str,
         index: usize,
         terminator: &str,
     ) {
-        trace!("Writing escape sequence for dynamic color code {}: color[{}]", code, index);
+        trace!("Writing escape sequence for dynamic color {}: color[{}]", prefix, index);
         let color = self.colors[index];
         let response = format!(
             "\x1b]{};rgb:{1:02x}{1:02x}/{2:02x}{2:02x}/{3:02x}{3:02x}{4}",
-            code, color.r, color.g, color.b, terminator
+            prefix, color.r, color.g, color.b, terminator
         );
-        let _ = writer.write_all(response.as_bytes());
+        self.write_permitted(writer, Capability::ColorQuery, response);
     }
 
     /// Reset the indexed color to original value.
@@ -1539,7 +2452,8 @@ impl<T: EventListener> Handler for Term<T> {
 
         if let Ok(bytes) = base64::decode(base64) {
             if let Ok(text) = String::from_utf8(bytes) {
-                self.event_proxy.send_event(Event::ClipboardStore(clipboard_type, text));
+                let event = Event::ClipboardStore(clipboard_type, text);
+                self.send_permitted_event(Capability::ClipboardWrite, event);
             }
         }
     }
@@ -1555,13 +2469,14 @@ impl<T: EventListener> Handler for Term<T> {
 
         let terminator = terminator.to_owned();
 
-        self.event_proxy.send_event(Event::ClipboardLoad(
+        let event = Event::ClipboardLoad(
             clipboard_type,
             Arc::new(move |text| {
                 let base64 = base64::encode(&text);
                 format!("\x1b]52;{};{}{}", clipboard as char, base64, terminator)
             }),
-        ));
+        );
+        self.send_permitted_event(Capability::ClipboardRead, event);
     }
 
     #[inline]
@@ -1616,12 +2531,13 @@ impl<T: EventListener> Handler for Term<T> {
                 self.selection = self.selection.take().filter(|s| !s.intersects_range(..num_lines));
             },
             ansi::ClearMode::Saved if self.history_size() > 0 => {
+                self.clear_archive();
                 self.grid.clear_history();
 
                 self.selection = self.selection.take().filter(|s| !s.intersects_range(num_lines..));
             },
             // We have no history to clear.
-            ansi::ClearMode::Saved => (),
+            ansi::ClearMode::Saved => self.clear_archive(),
         }
     }
 
@@ -1641,18 +2557,25 @@ impl<T: EventListener> Handler for Term<T> {
     /// Reset all important fields in the term struct.
     #[inline]
     fn reset_state(&mut self) {
+        self.clear_archive();
+
         if self.mode.contains(TermMode::ALT_SCREEN) {
             mem::swap(&mut self.grid, &mut self.inactive_grid);
         }
         self.active_charset = Default::default();
+        self.single_shift = None;
         self.colors = self.original_colors;
         self.color_modified = [false; color::COUNT];
         self.cursor_style = None;
         self.grid.reset();
         self.inactive_grid.reset();
         self.scroll_region = Line(0)..self.screen_lines();
+        self.horizontal_margins = Column(0)..self.cols();
         self.tabs = TabStops::new(self.cols());
         self.title_stack = Vec::new();
+        self.keyboard_mode_stack = Vec::new();
+        self.inactive_keyboard_mode_stack = Vec::new();
+        self.graphics.clear_kitty_images();
         self.title = None;
         self.selection = None;
         self.regex_search = None;
@@ -1684,10 +2607,12 @@ impl<T: EventListener> Handler for Term<T> {
         match attr {
             Attr::Foreground(color) => cursor.template.fg = color,
             Attr::Background(color) => cursor.template.bg = color,
+            Attr::UnderlineColor(color) => cursor.template.set_underline_color(color),
             Attr::Reset => {
                 cursor.template.fg = Color::Named(NamedColor::Foreground);
                 cursor.template.bg = Color::Named(NamedColor::Background);
                 cursor.template.flags = Flags::empty();
+                cursor.template.set_underline_color(None);
             },
             Attr::Reverse => cursor.template.flags.insert(Flags::INVERSE),
             Attr::CancelReverse => cursor.template.flags.remove(Flags::INVERSE),
@@ -1698,16 +2623,26 @@ impl<T: EventListener> Handler for Term<T> {
             Attr::Italic => cursor.template.flags.insert(Flags::ITALIC),
             Attr::CancelItalic => cursor.template.flags.remove(Flags::ITALIC),
             Attr::Underline => {
-                cursor.template.flags.remove(Flags::DOUBLE_UNDERLINE);
+                cursor.template.flags.remove(Flags::ALL_UNDERLINES);
                 cursor.template.flags.insert(Flags::UNDERLINE);
             },
             Attr::DoubleUnderline => {
-                cursor.template.flags.remove(Flags::UNDERLINE);
+                cursor.template.flags.remove(Flags::ALL_UNDERLINES);
                 cursor.template.flags.insert(Flags::DOUBLE_UNDERLINE);
             },
-            Attr::CancelUnderline => {
-                cursor.template.flags.remove(Flags::UNDERLINE | Flags::DOUBLE_UNDERLINE);
+            Attr::Undercurl => {
+                cursor.template.flags.remove(Flags::ALL_UNDERLINES);
+                cursor.template.flags.insert(Flags::UNDERCURL);
+            },
+            Attr::DottedUnderline => {
+                cursor.template.flags.remove(Flags::ALL_UNDERLINES);
+                cursor.template.flags.insert(Flags::DOTTED_UNDERLINE);
             },
+            Attr::DashedUnderline => {
+                cursor.template.flags.remove(Flags::ALL_UNDERLINES);
+                cursor.template.flags.insert(Flags::DASHED_UNDERLINE);
+            },
+            Attr::CancelUnderline => cursor.template.flags.remove(Flags::ALL_UNDERLINES),
             Attr::Hidden => cursor.template.flags.insert(Flags::HIDDEN),
             Attr::CancelHidden => cursor.template.flags.remove(Flags::HIDDEN),
             Attr::Strike => cursor.template.flags.insert(Flags::STRIKEOUT),
@@ -1748,6 +2683,7 @@ impl<T: EventListener> Handler for Term<T> {
             },
             ansi::Mode::ReportFocusInOut => self.mode.insert(TermMode::FOCUS_IN_OUT),
             ansi::Mode::BracketedPaste => self.mode.insert(TermMode::BRACKETED_PASTE),
+            ansi::Mode::SyncUpdate => self.mode.insert(TermMode::SYNC_UPDATE),
             // Mouse encodings are mutually exclusive.
             ansi::Mode::SgrMouse => {
                 self.mode.remove(TermMode::UTF8_MOUSE);
@@ -1761,6 +2697,7 @@ impl<T: EventListener> Handler for Term<T> {
             ansi::Mode::LineWrap => self.mode.insert(TermMode::LINE_WRAP),
             ansi::Mode::LineFeedNewLine => self.mode.insert(TermMode::LINE_FEED_NEW_LINE),
             ansi::Mode::Origin => self.mode.insert(TermMode::ORIGIN),
+            ansi::Mode::LeftRightMargin => self.mode.insert(TermMode::LEFT_RIGHT_MARGIN),
             ansi::Mode::DECCOLM => self.deccolm(),
             ansi::Mode::Insert => self.mode.insert(TermMode::INSERT),
             ansi::Mode::BlinkingCursor => {
@@ -1797,12 +2734,17 @@ impl<T: EventListener> Handler for Term<T> {
             },
             ansi::Mode::ReportFocusInOut => self.mode.remove(TermMode::FOCUS_IN_OUT),
             ansi::Mode::BracketedPaste => self.mode.remove(TermMode::BRACKETED_PASTE),
+            ansi::Mode::SyncUpdate => self.mode.remove(TermMode::SYNC_UPDATE),
             ansi::Mode::SgrMouse => self.mode.remove(TermMode::SGR_MOUSE),
             ansi::Mode::Utf8Mouse => self.mode.remove(TermMode::UTF8_MOUSE),
             ansi::Mode::AlternateScroll => self.mode.remove(TermMode::ALTERNATE_SCROLL),
             ansi::Mode::LineWrap => self.mode.remove(TermMode::LINE_WRAP),
             ansi::Mode::LineFeedNewLine => self.mode.remove(TermMode::LINE_FEED_NEW_LINE),
             ansi::Mode::Origin => self.mode.remove(TermMode::ORIGIN),
+            ansi::Mode::LeftRightMargin => {
+                self.mode.remove(TermMode::LEFT_RIGHT_MARGIN);
+                self.horizontal_margins = Column(0)..self.cols();
+            },
             ansi::Mode::DECCOLM => self.deccolm(),
             ansi::Mode::Insert => self.mode.remove(TermMode::INSERT),
             ansi::Mode::BlinkingCursor => {
@@ -1837,6 +2779,31 @@ impl<T: EventListener> Handler for Term<T> {
         self.goto(Line(0), Column(0));
     }
 
+    #[inline]
+    fn set_left_right_margins(&mut self, left: usize, right: Option<usize>) {
+        // Without DECLRMM this escape saves the cursor position instead.
+        if !self.mode.contains(TermMode::LEFT_RIGHT_MARGIN) {
+            self.save_cursor_position();
+            return;
+        }
+
+        // Fallback to the last column as default.
+        let right = min(right.unwrap_or_else(|| self.cols().0), self.cols().0);
+
+        if left >= right {
+            debug!("Invalid left and right margins: ({};{})", left, right);
+            return;
+        }
+
+        let start = Column(left - 1);
+        let end = Column(right);
+
+        trace!("Setting left and right margins: ({};{})", start, end);
+
+        self.horizontal_margins = start..end;
+        self.goto(Line(0), Column(0));
+    }
+
     #[inline]
     fn set_keypad_application_mode(&mut self) {
         trace!("Setting keypad application mode");
@@ -1861,6 +2828,12 @@ impl<T: EventListener> Handler for Term<T> {
         self.active_charset = index;
     }
 
+    #[inline]
+    fn set_single_shift(&mut self, index: CharsetIndex) {
+        trace!("Setting single shift {:?}", index);
+        self.single_shift = Some(index);
+    }
+
     #[inline]
     fn set_cursor_style(&mut self, style: Option<CursorStyle>) {
         trace!("Setting cursor style {:?}", style);
@@ -1922,12 +2895,187 @@ impl<T: EventListener> Handler for Term<T> {
     fn text_area_size_pixels<W: io::Write>(&mut self, writer: &mut W) {
         let width = self.cell_width * self.cols().0;
         let height = self.cell_height * self.screen_lines().0;
-        let _ = write!(writer, "\x1b[4;{};{}t", height, width);
+        let report = format!("\x1b[4;{};{}t", height, width);
+        self.write_permitted(writer, Capability::Query, report);
     }
 
     #[inline]
     fn text_area_size_chars<W: io::Write>(&mut self, writer: &mut W) {
-        let _ = write!(writer, "\x1b[8;{};{}t", self.screen_lines(), self.cols());
+        let report = format!("\x1b[8;{};{}t", self.screen_lines(), self.cols());
+        self.write_permitted(writer, Capability::Query, report);
+    }
+
+    #[inline]
+    fn desktop_notification(&mut self, title: Option<String>, body: String) {
+        trace!("Requesting desktop notification {:?}: {}", title, body);
+        self.event_proxy.send_event(Event::Notify(title, body));
+    }
+
+    #[inline]
+    fn report_title<W: io::Write>(&mut self, writer: &mut W) {
+        trace!("Reporting window title");
+        let title = self.title.clone().unwrap_or_default();
+        self.write_permitted(writer, Capability::TitleReport, format!("\x1b]l{}\x1b\\", title));
+    }
+
+    #[inline]
+    fn set_hyperlink(&mut self, hyperlink: Option<Hyperlink>) {
+        trace!("Setting hyperlink: {:?}", hyperlink);
+        self.grid.cursor.template.set_hyperlink(hyperlink.map(Arc::new));
+    }
+
+    #[inline]
+    fn set_working_directory(&mut self, working_directory: PathBuf) {
+        trace!("Setting working directory to {:?}", working_directory);
+        self.working_directory = Some(working_directory);
+    }
+
+    #[inline]
+    fn insert_graphic(&mut self, graphic: GraphicData) {
+        trace!("Inserting {}x{} graphic", graphic.width, graphic.height);
+
+        let cell_width = max(self.cell_width, 1);
+        let cell_height = max(self.cell_height, 1);
+        let columns = (graphic.width - 1) / cell_width + 1;
+        let lines = (graphic.height - 1) / cell_height + 1;
+        let graphic = self.graphics.insert(graphic);
+
+        // Split the graphic into cells, scrolling the grid like text would.
+        let start_col = self.grid.cursor.point.col.0;
+        let end_col = min(start_col + columns, self.cols().0);
+        let template = self.grid.cursor.template.clone();
+        for line in 0..lines {
+            if line > 0 {
+                self.linefeed();
+            }
+
+            let cursor_line = self.grid.cursor.point.line;
+            let row = &mut self.grid[cursor_line];
+            for col in start_col..end_col {
+                let cell = &mut row[Column(col)];
+                cell.reset(&template);
+                cell.set_graphic(GraphicCell {
+                    graphic: graphic.clone(),
+                    offset_x: ((col - start_col) * cell_width) as u16,
+                    offset_y: (line * cell_height) as u16,
+                    placement_id: 0,
+                    z_index: 0,
+                });
+            }
+        }
+
+        // Continue below the graphic, at its first column.
+        self.linefeed();
+        self.grid.cursor.point.col = Column(start_col);
+        self.grid.cursor.input_needs_wrap = false;
+    }
+
+    #[inline]
+    fn kitty_graphics<W: io::Write>(&mut self, writer: &mut W, command: kitty::Command) {
+        let command = match self.graphics.kitty_chunk(command) {
+            Some(command) => command,
+            None => return,
+        };
+
+        trace!("Kitty graphics {:?} for image {}", command.action, command.image_id);
+
+        let result = match command.action {
+            _ if command.reads_file() && !self.file_read_permitted() => {
+                Err(kitty::Error::PermissionDenied)
+            },
+            kitty::Action::Transmit | kitty::Action::TransmitAndDisplay => {
+                command.load().map(|graphic| {
+                    let image = self.graphics.insert_kitty_image(command.image_id, graphic);
+                    if command.action == kitty::Action::TransmitAndDisplay {
+                        self.place_kitty_image(&command, image);
+                    }
+                })
+            },
+            kitty::Action::Query => command.load().map(|_| ()),
+            kitty::Action::Place => match self.graphics.kitty_image(command.image_id).cloned() {
+                Some(image) => {
+                    self.place_kitty_image(&command, image);
+                    Ok(())
+                },
+                None => Err(kitty::Error::NotFound(command.image_id)),
+            },
+            kitty::Action::Delete => {
+                self.delete_kitty_placements(&command);
+                return;
+            },
+        };
+
+        if let Err(err) = &result {
+            debug!("Kitty graphics command failed: {}", err);
+        }
+
+        if let Some(response) = command.response(&result) {
+            self.write_permitted(writer, Capability::Query, response);
+        }
+    }
+
+    #[inline]
+    fn push_keyboard_mode(&mut self, mode: KeyboardModes) {
+        trace!("Pushing {:?} onto keyboard mode stack", mode);
+
+        let mode = supported_keyboard_modes(mode);
+
+        if self.keyboard_mode_stack.len() >= KEYBOARD_MODE_STACK_MAX_DEPTH {
+            self.keyboard_mode_stack.remove(0);
+        }
+
+        self.keyboard_mode_stack.push(mode);
+        self.apply_keyboard_mode();
+    }
+
+    #[inline]
+    fn pop_keyboard_modes(&mut self, count: u16) {
+        trace!("Popping {} entries from keyboard mode stack", count);
+
+        let len = self.keyboard_mode_stack.len().saturating_sub(count as usize);
+        self.keyboard_mode_stack.truncate(len);
+        self.apply_keyboard_mode();
+    }
+
+    #[inline]
+    fn set_keyboard_mode(&mut self, mode: KeyboardModes, behavior: KeyboardModesApplyBehavior) {
+        trace!("Setting keyboard mode {:?} using {:?}", mode, behavior);
+
+        // Without an entry on the stack, the modifications create a new one.
+        if self.keyboard_mode_stack.is_empty() {
+            self.keyboard_mode_stack.push(KeyboardModes::NO_MODE);
+        }
+
+        let mode = supported_keyboard_modes(mode);
+        let active_mode = self.keyboard_mode_stack.last_mut().unwrap();
+        match behavior {
+            KeyboardModesApplyBehavior::Replace => *active_mode = mode,
+            KeyboardModesApplyBehavior::Union => active_mode.insert(mode),
+            KeyboardModesApplyBehavior::Difference => active_mode.remove(mode),
+        }
+
+        self.apply_keyboard_mode();
+    }
+
+    #[inline]
+    fn report_keyboard_mode<W: io::Write>(&mut self, writer: &mut W) {
+        let mode = self.keyboard_mode_stack.last().copied().unwrap_or(KeyboardModes::NO_MODE);
+        self.write_permitted(writer, Capability::Query, format!("\x1b[?{}u", mode.bits()));
+    }
+
+    #[inline]
+    fn set_prompt_mark(&mut self, mark: PromptMark) {
+        trace!("Setting prompt mark: {:?}", mark);
+
+        let mark = match mark {
+            PromptMark::PromptStart => PromptMarks::PROMPT_START,
+            PromptMark::CommandStart => PromptMarks::COMMAND_START,
+            PromptMark::OutputStart => PromptMarks::OUTPUT_START,
+            PromptMark::CommandEnd => PromptMarks::COMMAND_END,
+        };
+
+        let line = self.grid.cursor.point.line;
+        self.grid[line].marks.insert(mark);
     }
 }
 
@@ -1962,6 +3110,74 @@ struct TabStops {
     tabs: Vec<bool>,
 }
 
+/// Mode state reported by DECRQM.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum ModeState {
+    Set = 1,
+    Reset = 2,
+    PermanentlyReset = 4,
+}
+
+impl From<bool> for ModeState {
+    fn from(set: bool) -> Self {
+        if set {
+            ModeState::Set
+        } else {
+            ModeState::Reset
+        }
+    }
+}
+
+/// SGR parameters reproducing the attributes of a cell, starting with a reset.
+fn sgr_parameters(cell: &Cell) -> String {
+    let mut parameters = vec![String::from("0")];
+
+    let flag_parameters = [
+        (Flags::BOLD, "1"),
+        (Flags::DIM, "2"),
+        (Flags::ITALIC, "3"),
+        (Flags::UNDERLINE, "4"),
+        (Flags::DOUBLE_UNDERLINE, "4:2"),
+        (Flags::UNDERCURL, "4:3"),
+        (Flags::DOTTED_UNDERLINE, "4:4"),
+        (Flags::DASHED_UNDERLINE, "4:5"),
+        (Flags::INVERSE, "7"),
+        (Flags::HIDDEN, "8"),
+        (Flags::STRIKEOUT, "9"),
+    ];
+    for (flag, parameter) in flag_parameters.iter() {
+        if cell.flags.contains(*flag) {
+            parameters.push(String::from(*parameter));
+        }
+    }
+
+    parameters.extend(sgr_color(cell.fg, 30, 38));
+    parameters.extend(sgr_color(cell.bg, 40, 48));
+    parameters.extend(cell.underline_color().and_then(|color| sgr_color(color, 0, 58)));
+
+    parameters.join(";")
+}
+
+/// SGR parameter for a color, `None` for default colors.
+///
+/// A `base` of `0` always uses the extended color syntax with the `extended` parameter.
+fn sgr_color(color: Color, base: usize, extended: usize) -> Option<String> {
+    match color {
+        Color::Named(color) if base != 0 && (color as usize) < 8 => {
+            Some(format!("{}", base + color as usize))
+        },
+        Color::Named(color) if base != 0 && (color as usize) < 16 => {
+            Some(format!("{}", base + 60 + color as usize - 8))
+        },
+        Color::Named(color) if (color as usize) < 16 => {
+            Some(format!("{};5;{}", extended, color as usize))
+        },
+        Color::Named(_) => None,
+        Color::Indexed(index) => Some(format!("{};5;{}", extended, index)),
+        Color::Spec(rgb) => Some(format!("{};2;{};{};{}", extended, rgb.r, rgb.g, rgb.b)),
+    }
+}
+
 impl TabStops {
     #[inline]
     fn new(num_cols: Column) -> TabStops {
@@ -2077,9 +3293,13 @@ mod tests {
 
     use std::mem;
 
-    use crate::ansi::{self, CharsetIndex, Handler, StandardCharset};
-    use crate::config::MockConfig;
+    use crate::ansi::{
+        self, Attr, CharsetIndex, Color, Handler, KeyboardModes, KeyboardModesApplyBehavior,
+        StandardCharset,
+    };
+    use crate::config::{MockConfig, Permission};
     use crate::event::{Event, EventListener};
+    use crate::graphics::{kitty, GraphicData};
     use crate::grid::{Grid, Scroll};
     use crate::index::{Column, Line, Point, Side};
     use crate::selection::{Selection, SelectionType};
@@ -2194,6 +3414,78 @@ mod tests {
         assert_eq!(deserialized, grid);
     }
 
+    #[test]
+    fn last_command_output() {
+        let size = SizeInfo::new(10.0, 10.0, 1.0, 1.0, 0.0, 0.0, false);
+        let mut term = Term::new(&MockConfig::default(), size, Mock);
+
+        // Command without any output.
+        term.set_prompt_mark(ansi::PromptMark::PromptStart);
+        term.set_prompt_mark(ansi::PromptMark::CommandStart);
+        term.carriage_return();
+        term.linefeed();
+        term.set_prompt_mark(ansi::PromptMark::OutputStart);
+        term.set_prompt_mark(ansi::PromptMark::CommandEnd);
+        assert!(term.last_command_output().is_none());
+
+        // Command with two lines of output.
+        term.set_prompt_mark(ansi::PromptMark::PromptStart);
+        term.set_prompt_mark(ansi::PromptMark::CommandStart);
+        term.carriage_return();
+        term.linefeed();
+        term.set_prompt_mark(ansi::PromptMark::OutputStart);
+        term.input('a');
+        term.carriage_return();
+        term.linefeed();
+        term.input('b');
+        term.carriage_return();
+        term.linefeed();
+        term.set_prompt_mark(ansi::PromptMark::CommandEnd);
+        term.set_prompt_mark(ansi::PromptMark::PromptStart);
+
+        let selection = term.last_command_output().unwrap();
+        let range = selection.to_range(&term).unwrap();
+        assert_eq!(range.start, Point::new(7, Column(0)));
+        assert_eq!(range.end, Point::new(6, Column(9)));
+
+        assert_eq!(term.prompt_search(5, Direction::Left), Some(8));
+        assert_eq!(term.prompt_search(8, Direction::Left), Some(9));
+        assert_eq!(term.prompt_search(9, Direction::Left), None);
+        assert_eq!(term.prompt_search(8, Direction::Right), Some(5));
+    }
+
+    #[test]
+    fn line_timestamps() {
+        let size = SizeInfo::new(10.0, 10.0, 1.0, 1.0, 0.0, 0.0, false);
+        let mut term = Term::new(&MockConfig::default(), size, Mock);
+
+        term.input('a');
+        term.carriage_return();
+        term.linefeed();
+        term.input('b');
+
+        assert!(term.grid()[Line(0)].timestamp().is_some());
+        assert!(term.grid()[Line(2)].timestamp().is_none());
+
+        let epoch = SystemTime::UNIX_EPOCH;
+        term.grid_mut()[Line(0)].timestamp = Some(epoch + Duration::from_secs(10));
+        term.grid_mut()[Line(1)].timestamp = Some(epoch + Duration::from_secs(20));
+
+        assert_eq!(term.timestamp_search(epoch), Some(9));
+        assert_eq!(term.timestamp_search(epoch + Duration::from_secs(15)), Some(8));
+        assert_eq!(term.timestamp_search(epoch + Duration::from_secs(30)), None);
+
+        let mut selection =
+            Selection::new(SelectionType::Simple, Point::new(9, Column(0)), Side::Left);
+        selection.update(Point::new(8, Column(9)), Side::Right);
+        term.selection = Some(selection);
+
+        let format =
+            |time: SystemTime| format!("{} ", time.duration_since(epoch).unwrap().as_secs());
+        assert_eq!(term.selection_to_string_with_timestamps(format).unwrap(), "10 a\n20 b\n");
+        assert_eq!(term.selection_to_string().unwrap(), "a\nb\n");
+    }
+
     #[test]
     fn input_line_drawing_character() {
         let size = SizeInfo::new(21.0, 51.0, 3.0, 3.0, 0.0, 0.0, false);
@@ -2205,13 +3497,477 @@ mod tests {
         assert_eq!(term.grid()[&cursor].c, '▒');
     }
 
+    #[test]
+    fn input_national_and_supplemental_charsets() {
+        let size = SizeInfo::new(21.0, 51.0, 3.0, 3.0, 0.0, 0.0, false);
+        let mut term = Term::new(&MockConfig::default(), size, Mock);
+        term.configure_charset(CharsetIndex::G0, StandardCharset::German);
+        term.configure_charset(CharsetIndex::G2, StandardCharset::DecTechnical);
+        term.configure_charset(CharsetIndex::G3, StandardCharset::DecSupplementalGraphics);
+
+        // Single shifts only apply to the next character.
+        term.input('[');
+        term.set_single_shift(CharsetIndex::G2);
+        term.input('a');
+        term.input('a');
+        term.set_single_shift(CharsetIndex::G3);
+        term.input('W');
+
+        // Locking shifts stay active.
+        term.set_active_charset(CharsetIndex::G3);
+        term.input('1');
+        term.input('w');
+
+        let line: String = term.grid()[Line(0)][..Column(6)].iter().map(|cell| cell.c).collect();
+        assert_eq!(line, "ÄαaŒ±œ");
+    }
+
+    #[test]
+    fn input_hyperlink() {
+        let size = SizeInfo::new(21.0, 51.0, 3.0, 3.0, 0.0, 0.0, false);
+        let mut term = Term::new(&MockConfig::default(), size, Mock);
+        let hyperlink = Hyperlink { id: None, uri: String::from("https://example.org") };
+
+        term.set_hyperlink(Some(hyperlink.clone()));
+        term.input('a');
+        term.set_hyperlink(None);
+        term.input('b');
+
+        assert_eq!(term.grid()[Line(0)][Column(0)].hyperlink().map(|h| &**h), Some(&hyperlink));
+        assert_eq!(term.grid()[Line(0)][Column(1)].hyperlink(), None);
+    }
+
+    #[test]
+    fn input_underline_style() {
+        let size = SizeInfo::new(21.0, 51.0, 3.0, 3.0, 0.0, 0.0, false);
+        let mut term = Term::new(&MockConfig::default(), size, Mock);
+        let color = Color::Indexed(1);
+
+        term.terminal_attribute(Attr::Underline);
+        term.terminal_attribute(Attr::Undercurl);
+        term.terminal_attribute(Attr::UnderlineColor(Some(color)));
+        term.input('a');
+        term.terminal_attribute(Attr::CancelUnderline);
+        term.input('b');
+
+        let cell = &term.grid()[Line(0)][Column(0)];
+        assert!(cell.flags.contains(Flags::UNDERCURL));
+        assert!(!cell.flags.contains(Flags::UNDERLINE));
+        assert_eq!(cell.underline_color(), Some(color));
+
+        let cell = &term.grid()[Line(0)][Column(1)];
+        assert!(!cell.flags.intersects(Flags::ALL_UNDERLINES));
+        assert_eq!(cell.underline_color(), Some(color));
+
+        term.terminal_attribute(Attr::Reset);
+        term.input('c');
+        assert_eq!(term.grid()[Line(0)][Column(2)].underline_color(), None);
+    }
+
+    #[test]
+    fn keyboard_mode_stack() {
+        let size = SizeInfo::new(21.0, 51.0, 3.0, 3.0, 0.0, 0.0, false);
+        let mut term = Term::new(&MockConfig::default(), size, Mock);
+        let disambiguate = KeyboardModes::DISAMBIGUATE_ESC_CODES;
+        let events = KeyboardModes::REPORT_EVENT_TYPES;
+
+        term.push_keyboard_mode(disambiguate);
+        term.push_keyboard_mode(disambiguate | events);
+        assert!(term.mode().contains(TermMode::REPORT_EVENT_TYPES));
+
+        term.set_keyboard_mode(events, KeyboardModesApplyBehavior::Difference);
+        assert!(!term.mode().contains(TermMode::REPORT_EVENT_TYPES));
+        assert!(term.mode().contains(TermMode::DISAMBIGUATE_ESC_CODES));
+
+        let mut report = Vec::new();
+        term.report_keyboard_mode(&mut report);
+        assert_eq!(report, b"\x1b[?1u");
+
+        // Unsupported modes are neither applied nor reported.
+        let alternate_keys = KeyboardModes::REPORT_ALTERNATE_KEYS;
+        term.set_keyboard_mode(alternate_keys, KeyboardModesApplyBehavior::Union);
+        let mut report = Vec::new();
+        term.report_keyboard_mode(&mut report);
+        assert_eq!(report, b"\x1b[?1u");
+
+        // Alternate screen uses a separate stack.
+        term.set_mode(ansi::Mode::SwapScreenAndSetRestoreCursor);
+        assert!(!term.mode().intersects(TermMode::KITTY_KEYBOARD_PROTOCOL));
+        term.push_keyboard_mode(events);
+        term.unset_mode(ansi::Mode::SwapScreenAndSetRestoreCursor);
+        assert!(term.mode().contains(TermMode::DISAMBIGUATE_ESC_CODES));
+
+        term.pop_keyboard_modes(1);
+        assert!(term.mode().contains(TermMode::DISAMBIGUATE_ESC_CODES));
+        term.pop_keyboard_modes(5);
+        assert!(!term.mode().intersects(TermMode::KITTY_KEYBOARD_PROTOCOL));
+    }
+
+    #[test]
+    fn insert_graphic() {
+        let size = SizeInfo::new(21.0, 51.0, 3.0, 3.0, 0.0, 0.0, false);
+        let mut term = Term::new(&MockConfig::default(), size, Mock);
+        term.goto(Line(1), Column(1));
+
+        // Graphic covering 2x2 cells, with the last column and line partially filled.
+        let graphic = GraphicData { width: 5, height: 4, pixels: vec![255; 5 * 4 * 4] };
+        term.insert_graphic(graphic.clone());
+
+        let queues = term.graphics_take_queues().unwrap();
+        assert_eq!(queues.pending.len(), 1);
+        assert_eq!(queues.pending[0].1, graphic);
+
+        let cell = term.grid()[Line(2)][Column(2)].graphic().unwrap();
+        assert_eq!(cell.graphic.id(), queues.pending[0].0);
+        assert_eq!((cell.offset_x, cell.offset_y), (3, 3));
+        assert!(term.grid()[Line(1)][Column(0)].graphic().is_none());
+        assert!(term.grid()[Line(1)][Column(3)].graphic().is_none());
+        assert_eq!(term.grid.cursor.point, Point::new(Line(3), Column(1)));
+
+        // Text replaces the graphic, which is removed once no cell references it.
+        term.goto(Line(1), Column(1));
+        term.input('a');
+        assert!(term.grid()[Line(1)][Column(1)].graphic().is_none());
+        term.reset_state();
+        let queues = term.graphics_take_queues().unwrap();
+        assert_eq!(queues.remove_queue.len(), 1);
+    }
+
+    #[test]
+    fn kitty_graphics() {
+        let size = SizeInfo::new(21.0, 51.0, 3.0, 3.0, 0.0, 0.0, false);
+        let mut term = Term::new(&MockConfig::default(), size, Mock);
+        let mut writer = Vec::new();
+        let command = |term: &mut Term<Mock>, writer: &mut Vec<u8>, data: &[u8]| {
+            writer.clear();
+            term.kitty_graphics(writer, kitty::Command::parse(data).unwrap());
+        };
+
+        // Transmit a 5x4 image in two chunks, without displaying it.
+        let pixels = base64::encode(vec![255; 5 * 4 * 4]);
+        let (first, second) = pixels.split_at(40);
+        command(&mut term, &mut writer, format!("i=5,s=5,v=4,m=1;{}", first).as_bytes());
+        assert!(writer.is_empty());
+        command(&mut term, &mut writer, format!("m=0;{}", second).as_bytes());
+        assert_eq!(writer, b"\x1b_Gi=5;OK\x1b\\");
+        assert!(term.grid()[Line(0)][Column(0)].graphic().is_none());
+
+        // Place it below the text, keeping the cursor and existing text in place.
+        term.input('a');
+        term.goto(Line(0), Column(0));
+        command(&mut term, &mut writer, b"a=p,i=5,p=2,z=-1,C=1");
+        let cell = &term.grid()[Line(1)][Column(1)];
+        let graphic = cell.graphic().unwrap();
+        assert_eq!((graphic.offset_x, graphic.offset_y), (3, 3));
+        assert_eq!((graphic.placement_id, graphic.z_index), (2, -1));
+        assert_eq!(term.grid()[Line(0)][Column(0)].c, 'a');
+        assert_eq!(term.grid.cursor.point, Point::new(Line(0), Column(0)));
+
+        // Placing it with the same placement ID moves it.
+        term.goto(Line(3), Column(3));
+        command(&mut term, &mut writer, b"a=p,i=5,p=2,q=1");
+        assert!(writer.is_empty());
+        assert!(term.grid()[Line(1)][Column(1)].graphic().is_none());
+        assert!(term.grid()[Line(4)][Column(4)].graphic().is_some());
+        assert_eq!(term.grid.cursor.point, Point::new(Line(4), Column(5)));
+
+        // Deleting its placements keeps the image data around, unless requested otherwise.
+        command(&mut term, &mut writer, b"a=d,d=i,i=5");
+        assert!(term.grid()[Line(4)][Column(4)].graphic().is_none());
+        command(&mut term, &mut writer, b"a=p,i=5");
+        assert_eq!(writer, b"\x1b_Gi=5;OK\x1b\\");
+        command(&mut term, &mut writer, b"a=d,d=I,i=5");
+        command(&mut term, &mut writer, b"a=p,i=5");
+        assert_eq!(writer, b"\x1b_Gi=5;ENOENT:image 5 not found\x1b\\");
+
+        // Other uppercase targets free the data once no placements of the image are left.
+        command(&mut term, &mut writer, format!("i=6,s=5,v=4;{}", pixels).as_bytes());
+        command(&mut term, &mut writer, b"a=p,i=6");
+        command(&mut term, &mut writer, b"a=d,d=a");
+        command(&mut term, &mut writer, b"a=p,i=6");
+        assert_eq!(writer, b"\x1b_Gi=6;OK\x1b\\");
+        command(&mut term, &mut writer, b"a=d,d=A");
+        command(&mut term, &mut writer, b"a=p,i=6");
+        assert_eq!(writer, b"\x1b_Gi=6;ENOENT:image 6 not found\x1b\\");
+    }
+
+    #[test]
+    fn state_reports() {
+        let size = SizeInfo::new(21.0, 51.0, 3.0, 3.0, 0.0, 0.0, false);
+        let mut term = Term::new(&MockConfig::default(), size, Mock);
+        let mut writer = Vec::new();
+
+        term.set_mode(ansi::Mode::BracketedPaste);
+        term.report_mode(&mut writer, ansi::Mode::BracketedPaste);
+        term.report_mode(&mut writer, ansi::Mode::Insert);
+        term.report_mode(&mut writer, ansi::Mode::DECCOLM);
+        assert_eq!(writer, b"\x1b[?2004;1$y\x1b[4;2$y\x1b[?3;4$y");
+
+        writer.clear();
+        term.terminal_attribute(Attr::Bold);
+        term.terminal_attribute(Attr::Foreground(Color::Named(NamedColor::BrightRed)));
+        term.terminal_attribute(Attr::Background(Color::Indexed(100)));
+        term.report_setting(&mut writer, ansi::Setting::Attributes);
+        assert_eq!(writer, b"\x1bP1$r0;1;91;48;5;100m\x1b\\");
+
+        writer.clear();
+        term.set_scrolling_region(2, Some(5));
+        term.set_cursor_style(Some(CursorStyle { shape: CursorShape::Beam, blinking: true }));
+        term.report_setting(&mut writer, ansi::Setting::ScrollingRegion);
+        term.report_setting(&mut writer, ansi::Setting::CursorStyle);
+        assert_eq!(writer, b"\x1bP1$r2;5r\x1b\\\x1bP1$r5 q\x1b\\");
+
+        writer.clear();
+        term.report_capability(&mut writer, "Co");
+        term.report_capability(&mut writer, "Tc");
+        term.report_capability(&mut writer, "xx");
+        assert_eq!(writer, b"\x1bP1+r436F=323536\x1b\\\x1bP1+r5463\x1b\\\x1bP0+r7878\x1b\\");
+    }
+
+    #[test]
+    fn security_permissions() {
+        let size = SizeInfo::new(21.0, 51.0, 3.0, 3.0, 0.0, 0.0, false);
+        let mut config = MockConfig::default();
+        let mut term = Term::new(&config, size, Mock);
+        let mut writer = Vec::new();
+
+        // Title reporting is denied by default.
+        term.set_title(Some(String::from("title")));
+        term.report_title(&mut writer);
+        assert!(writer.is_empty());
+
+        config.security.title_reporting = Permission::Allow;
+        config.security.queries = Permission::Deny;
+        config.security.color_queries = Permission::Prompt;
+        term.update_config(&config);
+
+        term.report_title(&mut writer);
+        assert_eq!(writer, b"\x1b]ltitle\x1b\\");
+
+        // Prompted responses are sent as events instead.
+        writer.clear();
+        term.report_version(&mut writer);
+        term.report_mode(&mut writer, ansi::Mode::Insert);
+        term.dynamic_color_sequence(&mut writer, "10", NamedColor::Foreground as usize, "\x07");
+        assert!(writer.is_empty());
+
+        // Replies to all other queries are denied too.
+        term.identify_terminal(&mut writer, None);
+        term.device_status(&mut writer, 6);
+        term.text_area_size_chars(&mut writer);
+        term.report_keyboard_mode(&mut writer);
+        term.report_unsupported(&mut writer, String::from("\x1bP0$r\x1b\\"));
+        term.kitty_graphics(&mut writer, kitty::Command::parse(b"a=q,i=7;").unwrap());
+        assert!(writer.is_empty());
+
+        // File reads cannot be prompted for.
+        config.security.queries = Permission::Allow;
+        config.security.file_reads = Permission::Prompt;
+        term.update_config(&config);
+        let command = format!("a=q,t=f,i=7;{}", base64::encode("/etc/hostname"));
+        term.kitty_graphics(&mut writer, kitty::Command::parse(command.as_bytes()).unwrap());
+        assert_eq!(writer, b"\x1b_Gi=7;EPERM:reading files is not permitted\x1b\\");
+    }
+
+    #[test]
+    fn report_application_version() {
+        let size = SizeInfo::new(21.0, 51.0, 3.0, 3.0, 0.0, 0.0, false);
+        let mut config = MockConfig::default();
+        config.version = String::from("1.2.3-dev");
+        config.security.queries = Permission::Allow;
+        let mut term = Term::new(&config, size, Mock);
+        let mut writer = Vec::new();
+
+        term.identify_terminal(&mut writer, Some('>'));
+        term.report_version(&mut writer);
+        assert_eq!(writer, b"\x1b[>0;10203;1c\x1bP>|alacritty(1.2.3-dev)\x1b\\");
+    }
+
+    #[test]
+    fn left_right_margins() {
+        let size = SizeInfo::new(21.0, 51.0, 3.0, 3.0, 0.0, 0.0, false);
+        let mut term = Term::new(&MockConfig::default(), size, Mock);
+        let row = |term: &Term<Mock>, line| {
+            term.grid[Line(line)][..].iter().map(|cell| cell.c).collect::<String>()
+        };
+
+        for (line, text) in ["abcdefg", "hijklmn"].iter().enumerate() {
+            for (col, c) in text.chars().enumerate() {
+                term.grid[Line(line)][Column(col)].c = c;
+            }
+        }
+
+        // Margins are ignored without DECLRMM.
+        term.set_left_right_margins(2, Some(4));
+        assert_eq!(term.horizontal_margins, Column(0)..Column(7));
+
+        term.set_mode(ansi::Mode::LeftRightMargin);
+        term.set_left_right_margins(2, Some(4));
+        assert_eq!(term.horizontal_margins, Column(1)..Column(4));
+
+        // Lines are only deleted within the margins.
+        term.goto(Line(0), Column(1));
+        term.delete_lines(Line(1));
+        assert_eq!(row(&term, 0), "aijkefg");
+        assert_eq!(row(&term, 1), "h   lmn");
+
+        // Characters are only deleted within the margins.
+        term.delete_chars(Column(1));
+        assert_eq!(row(&term, 0), "ajk efg");
+
+        // Input wraps to the left margin.
+        term.goto(Line(2), Column(2));
+        for c in "xyz".chars() {
+            term.input(c);
+        }
+        assert_eq!(term.grid.cursor.point, Point::new(Line(3), Column(2)));
+        assert_eq!(term.grid[Line(3)][Column(1)].c, 'z');
+        assert!(!term.grid[Line(2)][Column(3)].flags.contains(Flags::WRAPLINE));
+
+        // Linefeeds only scroll while the cursor is inside the margins.
+        term.grid[Line(16)][Column(2)].c = 'q';
+        term.grid[Line(16)][Column(5)].c = 'r';
+        term.goto(Line(16), Column(5));
+        term.linefeed();
+        assert_eq!(term.grid[Line(16)][Column(2)].c, 'q');
+        term.goto(Line(16), Column(2));
+        term.linefeed();
+        assert_eq!(term.grid[Line(15)][Column(2)].c, 'q');
+        assert_eq!(term.grid[Line(15)][Column(5)].c, ' ');
+        assert_eq!(term.grid[Line(16)][Column(5)].c, 'r');
+
+        // Resetting DECLRMM clears the margins.
+        term.unset_mode(ansi::Mode::LeftRightMargin);
+        assert_eq!(term.horizontal_margins, Column(0)..Column(7));
+    }
+
+    #[test]
+    fn left_right_margins_outside_screen() {
+        let size = SizeInfo::new(9.0, 9.0, 3.0, 3.0, 0.0, 0.0, false);
+        let mut term = Term::new(&MockConfig::default(), size, Mock);
+        term.set_mode(ansi::Mode::LeftRightMargin);
+
+        // Margins entirely outside the screen are ignored.
+        term.set_left_right_margins(93, Some(96));
+        assert_eq!(term.horizontal_margins, Column(0)..Column(3));
+        term.set_left_right_margins(4, Some(9));
+        assert_eq!(term.horizontal_margins, Column(0)..Column(3));
+
+        // Input still wraps normally.
+        for c in "abcdefg".chars() {
+            term.input(c);
+        }
+        assert_eq!(term.grid.cursor.point, Point::new(Line(2), Column(1)));
+
+        // The right margin is clamped to the last column.
+        term.set_left_right_margins(2, Some(9));
+        assert_eq!(term.horizontal_margins, Column(1)..Column(3));
+    }
+
+    #[test]
+    fn rectangle_operations() {
+        let size = SizeInfo::new(21.0, 51.0, 3.0, 3.0, 0.0, 0.0, false);
+        let mut term = Term::new(&MockConfig::default(), size, Mock);
+        let mut writer = Vec::new();
+        let rect = |top, left, bottom, right| Rectangle {
+            top,
+            left,
+            bottom: Some(bottom),
+            right: Some(right),
+        };
+
+        term.fill_rectangle('x', rect(2, 2, 3, 4));
+        assert_eq!(term.grid[Line(1)][Column(0)].c, ' ');
+        assert_eq!(term.grid[Line(1)][Column(1)].c, 'x');
+        assert_eq!(term.grid[Line(2)][Column(3)].c, 'x');
+        assert_eq!(term.grid[Line(2)][Column(4)].c, ' ');
+
+        // Overlapping copies use the original source content.
+        term.copy_rectangle(rect(2, 2, 3, 4), 3, 3);
+        assert_eq!(term.grid[Line(2)][Column(1)].c, 'x');
+        assert_eq!(term.grid[Line(2)][Column(2)].c, 'x');
+        assert_eq!(term.grid[Line(3)][Column(4)].c, 'x');
+        assert_eq!(term.grid[Line(3)][Column(5)].c, ' ');
+
+        term.set_rectangle_attributes(rect(2, 2, 2, 2), &[Attr::Bold, Attr::Reverse]);
+        assert!(term.grid[Line(1)][Column(1)].flags.contains(Flags::BOLD | Flags::INVERSE));
+
+        term.reverse_rectangle_attributes(rect(2, 2, 2, 2), &[Attr::Bold]);
+        assert_eq!(term.grid[Line(1)][Column(1)].flags, Flags::INVERSE);
+
+        // Checksum of `x` and inverse.
+        term.report_rectangle_checksum(&mut writer, 7, rect(2, 2, 2, 2));
+        assert_eq!(writer, b"\x1bP7!~FF68\x1b\\");
+
+        // Selective erase only clears the characters.
+        term.erase_rectangle(rect(2, 2, 2, 2), true);
+        assert_eq!(term.grid[Line(1)][Column(1)].c, ' ');
+        assert_eq!(term.grid[Line(1)][Column(1)].flags, Flags::INVERSE);
+
+        term.erase_rectangle(rect(1, 1, 17, 7), false);
+        writer.clear();
+        term.report_rectangle_checksum(
+            &mut writer,
+            0,
+            Rectangle { top: 1, left: 1, bottom: None, right: None },
+        );
+        assert_eq!(
+            writer,
+            format!("\x1bP0!~{:04X}\x1b\\", 0u16.wrapping_sub(7 * 17 * 0x20)).as_bytes()
+        );
+    }
+
+    #[test]
+    fn double_width_lines() {
+        let size = SizeInfo::new(21.0, 51.0, 3.0, 3.0, 0.0, 0.0, false);
+        let mut term = Term::new(&MockConfig::default(), size, Mock);
+
+        for (col, c) in "abcdefg".chars().enumerate() {
+            term.grid[Line(0)][Column(col)].c = c;
+        }
+
+        // Characters beyond the middle of the line are lost.
+        term.goto(Line(0), Column(5));
+        term.set_line_size(LineSize::DoubleWidth);
+        assert_eq!(term.grid.cursor.point.col, Column(2));
+        assert_eq!(term.grid[Line(0)][Column(3)].c, ' ');
+
+        term.move_forward(Column(10));
+        assert_eq!(term.grid.cursor.point.col, Column(2));
+
+        // Input wraps in the middle of the line.
+        term.input('x');
+        term.input('y');
+        assert_eq!(term.grid.cursor.point, Point::new(Line(1), Column(1)));
+        assert_eq!(term.grid[Line(1)][Column(0)].c, 'y');
+
+        // Only the visible cells are selected.
+        let mut selection =
+            Selection::new(SelectionType::Simple, Point { line: 16, col: Column(0) }, Side::Left);
+        selection.update(Point { line: 16, col: Column(6) }, Side::Right);
+        term.selection = Some(selection);
+        assert_eq!(term.selection_to_string(), Some("abx".into()));
+
+        // Viewport columns are mapped to the doubled cells.
+        assert_eq!(term.grid_point(Point::new(Line(0), Column(5))).col, Column(2));
+        assert_eq!(term.grid_point(Point::new(Line(1), Column(5))).col, Column(5));
+        assert_eq!(term.viewport_point(Point::new(Line(0), Column(2))).col, Column(4));
+        assert_eq!(term.viewport_point(Point::new(Line(0), Column(5))).col, Column(6));
+        assert_eq!(term.viewport_point(Point::new(Line(1), Column(2))).col, Column(2));
+
+        term.goto(Line(0), Column(0));
+        term.set_line_size(LineSize::Normal);
+        term.goto(Line(0), Column(6));
+        assert_eq!(term.grid.cursor.point.col, Column(6));
+    }
+
     #[test]
     fn clear_saved_lines() {
         let size = SizeInfo::new(21.0, 51.0, 3.0, 3.0, 0.0, 0.0, false);
         let mut term = Term::new(&MockConfig::default(), size, Mock);
 
         // Add one line of scrollback.
-        term.grid.scroll_up(&(Line(0)..Line(1)), Line(1));
+        term.grid.scroll_up(&(Line(0)..Line(1)), &(Column(0)..term.cols()), Line(1));
 
         // Clear the history.
         term.clear_screen(ansi::ClearMode::Saved);
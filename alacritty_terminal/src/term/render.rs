@@ -1,25 +1,22 @@
-use std::cmp::max;
 use std::iter;
 use std::iter::Peekable;
 use std::mem;
 use std::ops::RangeInclusive;
+use std::sync::Arc;
 
-use crate::ansi::{Color, CursorShape, NamedColor};
+use crate::ansi::{Color, CursorShape, Hyperlink, LineSize, NamedColor};
 use crate::config::Config;
+use crate::graphics::GraphicCell;
 use crate::grid::{Dimensions, DisplayIter, Indexed};
-use crate::index::{Column, Direction, Line, Point};
+use crate::index::{Column, Line, Point};
 use crate::selection::SelectionRange;
 use crate::term::cell::{Cell, Flags};
 use crate::term::color::{self, CellRgb, Rgb, DIM_FACTOR};
-use crate::term::search::RegexIter;
 use crate::term::{Term, TermMode};
 
 /// Minimum contrast between a fixed cursor color and the cell's background.
 pub const MIN_CURSOR_CONTRAST: f64 = 1.5;
 
-/// Maximum number of linewraps followed outside of the viewport during search highlighting.
-const MAX_SEARCH_LINES: usize = 100;
-
 /// Renderable terminal content.
 ///
 /// This provides the terminal cursor and an iterator over all non-empty cells.
@@ -85,6 +82,15 @@ impl<'a, T, C> RenderableContent<'a, T, C> {
         self.cursor
     }
 
+    /// Color modified by escape sequences, falling back to the configured one.
+    fn dynamic_color(&self, color: NamedColor, config_color: CellRgb) -> CellRgb {
+        if self.term.color_modified[color as usize] {
+            CellRgb::Rgb(self.term.colors[color])
+        } else {
+            config_color
+        }
+    }
+
     /// Assemble the information required to render the terminal cursor.
     ///
     /// This will return `None` when there is no cursor visible.
@@ -107,11 +113,7 @@ impl<'a, T, C> RenderableContent<'a, T, C> {
         } else {
             self.config.colors.cursor
         };
-        let mut cursor_color = if self.term.color_modified[NamedColor::Cursor as usize] {
-            CellRgb::Rgb(self.term.colors[NamedColor::Cursor])
-        } else {
-            color.background
-        };
+        let mut cursor_color = self.dynamic_color(NamedColor::Cursor, color.background);
         let mut text_color = color.foreground;
 
         // Invert the cursor if it has a fixed background close to the cell's background.
@@ -127,8 +129,17 @@ impl<'a, T, C> RenderableContent<'a, T, C> {
         let text_color = text_color.color(cell.fg, cell.bg);
         let cursor_color = cursor_color.color(cell.fg, cell.bg);
 
+        // Cover both columns of cells in double-width lines.
+        let mut point = self.cursor_point;
+        let is_wide = if cell.line_size == LineSize::Normal {
+            is_wide
+        } else {
+            point.col = Column(point.col.0 * 2);
+            true
+        };
+
         Some(RenderableCursor {
-            point: self.cursor_point,
+            point,
             shape: self.cursor_shape,
             cursor_color,
             text_color,
@@ -172,8 +183,11 @@ impl<'a, T, C> Iterator for RenderableContent<'a, T, C> {
                 let cell = self.display_iter.next()?;
                 let cell = RenderableCell::new(self, cell);
 
-                // Skip empty cells and wide char spacers.
-                if !cell.is_empty() && !cell.flags.contains(Flags::WIDE_CHAR_SPACER) {
+                // Skip empty cells, wide char spacers and cells hidden by double-width lines.
+                if !cell.is_empty()
+                    && !cell.flags.contains(Flags::WIDE_CHAR_SPACER)
+                    && !cell.is_hidden(self)
+                {
                     return Some(cell);
                 }
             }
@@ -190,9 +204,13 @@ pub struct RenderableCell {
     pub column: Column,
     pub fg: Rgb,
     pub bg: Rgb,
+    pub underline: Rgb,
     pub bg_alpha: f32,
     pub flags: Flags,
     pub is_match: bool,
+    pub hyperlink: Option<Arc<Hyperlink>>,
+    pub graphic: Option<GraphicCell>,
+    pub line_size: LineSize,
 }
 
 impl RenderableCell {
@@ -218,8 +236,15 @@ impl RenderableCell {
         let mut is_match = false;
 
         if is_selected {
-            let config_bg = content.config.colors.selection.background;
-            let selected_fg = content.config.colors.selection.foreground.color(fg_rgb, bg_rgb);
+            let config_bg = content.dynamic_color(
+                NamedColor::SelectionBackground,
+                content.config.colors.selection.background,
+            );
+            let config_fg = content.dynamic_color(
+                NamedColor::SelectionForeground,
+                content.config.colors.selection.foreground,
+            );
+            let selected_fg = config_fg.color(fg_rgb, bg_rgb);
             bg_rgb = config_bg.color(fg_rgb, bg_rgb);
             fg_rgb = selected_fg;
 
@@ -245,16 +270,35 @@ impl RenderableCell {
             is_match = true;
         }
 
+        // Cells in double-width lines cover two columns.
+        let line_size = grid[grid.visible_to_buffer(point).line].line_size();
+        let column = match line_size {
+            LineSize::Normal => cell.column,
+            _ => Column(cell.column.0 * 2),
+        };
+
+        // Use the foreground color for underlines, unless it was set explicitly.
+        let underline = match cell.underline_color() {
+            Some(color) if !is_selected && !is_match => {
+                Self::compute_bg_rgb(&content.term.colors, color)
+            },
+            _ => fg_rgb,
+        };
+
         RenderableCell {
             character: cell.c,
             zerowidth: cell.zerowidth().map(|zerowidth| zerowidth.to_vec()),
             line: cell.line,
-            column: cell.column,
+            column,
             fg: fg_rgb,
             bg: bg_rgb,
+            underline,
             bg_alpha,
             flags: cell.flags,
             is_match,
+            hyperlink: cell.hyperlink().cloned(),
+            graphic: cell.graphic().cloned(),
+            line_size,
         }
     }
 
@@ -263,16 +307,28 @@ impl RenderableCell {
         Point::new(self.line, self.column)
     }
 
+    /// Check if the cell is hidden, because it does not fit into a double-width line.
+    fn is_hidden<T, C>(&self, content: &RenderableContent<'_, T, C>) -> bool {
+        self.line_size != LineSize::Normal && self.column + 1 >= content.term.cols()
+    }
+
     /// Check if cell contains any renderable content.
     fn is_empty(&self) -> bool {
         self.bg_alpha == 0.
-            && !self.flags.intersects(Flags::UNDERLINE | Flags::STRIKEOUT | Flags::DOUBLE_UNDERLINE)
+            && !self.flags.intersects(Flags::ALL_UNDERLINES | Flags::STRIKEOUT)
             && self.character == ' '
             && self.zerowidth.is_none()
+            && self.hyperlink.is_none()
+            && self.graphic.is_none()
     }
 
     /// Get the RGB color from a cell's foreground color.
-    fn compute_fg_rgb<C>(config: &Config<C>, colors: &color::List, fg: Color, flags: Flags) -> Rgb {
+    pub(crate) fn compute_fg_rgb<C>(
+        config: &Config<C>,
+        colors: &color::List,
+        fg: Color,
+        flags: Flags,
+    ) -> Rgb {
         match fg {
             Color::Spec(rgb) => match flags & Flags::DIM {
                 Flags::DIM => rgb * DIM_FACTOR,
@@ -314,7 +370,7 @@ impl RenderableCell {
 
     /// Get the RGB color from a cell's background color.
     #[inline]
-    fn compute_bg_rgb(colors: &color::List, bg: Color) -> Rgb {
+    pub(crate) fn compute_bg_rgb(colors: &color::List, bg: Color) -> Rgb {
         match bg {
             Color::Spec(rgb) => rgb,
             Color::Named(ansi) => colors[ansi],
@@ -375,40 +431,11 @@ struct RenderableSearch<'a> {
 impl<'a> RenderableSearch<'a> {
     /// Create a new renderable search iterator.
     fn new<T>(term: &'a Term<T>) -> Self {
-        // Avoid constructing search if there is none.
-        if term.regex_search.is_none() {
-            let iter: MatchIter<'a> = Box::new(iter::empty());
-            return Self { iter: iter.peekable() };
-        }
-
-        let viewport_end = term.grid().display_offset();
-        let viewport_start = viewport_end + term.screen_lines().0 - 1;
-
-        // Compute start of the first and end of the last line.
-        let start_point = Point::new(viewport_start, Column(0));
-        let mut start = term.line_search_left(start_point);
-        let end_point = Point::new(viewport_end, term.cols() - 1);
-        let mut end = term.line_search_right(end_point);
-
-        // Set upper bound on search before/after the viewport to prevent excessive blocking.
-        if start.line > viewport_start + MAX_SEARCH_LINES {
-            if start.line == 0 {
-                // Do not highlight anything if this line is the last.
-                let iter: MatchIter<'a> = Box::new(iter::empty());
-                return Self { iter: iter.peekable() };
-            } else {
-                // Start at next line if this one is too long.
-                start.line -= 1;
-            }
-        }
-        end.line = max(end.line, viewport_end.saturating_sub(MAX_SEARCH_LINES));
-
         // Create an iterater for the current regex search for all visible matches.
-        let iter: MatchIter<'a> = Box::new(
-            RegexIter::new(start, end, Direction::Right, &term)
-                .skip_while(move |rm| rm.end().line > viewport_start)
-                .take_while(move |rm| rm.start().line >= viewport_end),
-        );
+        let iter: MatchIter<'a> = match &term.regex_search {
+            Some(regex) => Box::new(term.visible_regex_matches(regex)),
+            None => Box::new(iter::empty()),
+        };
 
         Self { iter: iter.peekable() }
     }
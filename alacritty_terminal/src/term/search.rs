@@ -1,4 +1,4 @@
-use std::cmp::min;
+use std::cmp::{max, min};
 use std::mem;
 use std::ops::RangeInclusive;
 
@@ -12,9 +12,13 @@ use crate::term::Term;
 /// Used to match equal brackets, when performing a bracket-pair selection.
 const BRACKET_PAIRS: [(char, char); 4] = [('(', ')'), ('[', ']'), ('{', '}'), ('<', '>')];
 
+/// Maximum number of linewraps followed outside of the viewport during visible match search.
+const MAX_SEARCH_LINES: usize = 100;
+
 pub type Match = RangeInclusive<Point<usize>>;
 
 /// Terminal regex search state.
+#[derive(Clone, Debug)]
 pub struct RegexSearch {
     /// Locate end of match searching right.
     right_fdfa: DenseDFA<Vec<usize>, usize>,
@@ -100,7 +104,8 @@ impl<T> Term<T> {
             _ => end.sub_absolute(self, Boundary::Wrap, 1),
         };
 
-        let mut regex_iter = RegexIter::new(start, end, Direction::Right, &self).peekable();
+        let regex = self.regex_search.as_ref()?;
+        let mut regex_iter = RegexIter::new(start, end, Direction::Right, &self, regex).peekable();
 
         // Check if there's any match at all.
         let first_match = regex_iter.peek()?.clone();
@@ -135,7 +140,8 @@ impl<T> Term<T> {
             _ => end.add_absolute(self, Boundary::Wrap, 1),
         };
 
-        let mut regex_iter = RegexIter::new(start, end, Direction::Left, &self).peekable();
+        let regex = self.regex_search.as_ref()?;
+        let mut regex_iter = RegexIter::new(start, end, Direction::Left, &self, regex).peekable();
 
         // Check if there's any match at all.
         let first_match = regex_iter.peek()?.clone();
@@ -165,8 +171,13 @@ impl<T> Term<T> {
     /// Find the next regex match to the left of the origin point.
     ///
     /// The origin is always included in the regex.
-    pub fn regex_search_left(&self, start: Point<usize>, end: Point<usize>) -> Option<Match> {
-        let RegexSearch { left_fdfa: fdfa, left_rdfa: rdfa, .. } = self.regex_search.as_ref()?;
+    pub fn regex_search_left(
+        &self,
+        regex: &RegexSearch,
+        start: Point<usize>,
+        end: Point<usize>,
+    ) -> Option<Match> {
+        let RegexSearch { left_fdfa: fdfa, left_rdfa: rdfa, .. } = regex;
 
         // Find start and end of match.
         let match_start = self.regex_search(start, end, Direction::Left, &fdfa)?;
@@ -178,8 +189,13 @@ impl<T> Term<T> {
     /// Find the next regex match to the right of the origin point.
     ///
     /// The origin is always included in the regex.
-    pub fn regex_search_right(&self, start: Point<usize>, end: Point<usize>) -> Option<Match> {
-        let RegexSearch { right_fdfa: fdfa, right_rdfa: rdfa, .. } = self.regex_search.as_ref()?;
+    pub fn regex_search_right(
+        &self,
+        regex: &RegexSearch,
+        start: Point<usize>,
+        end: Point<usize>,
+    ) -> Option<Match> {
+        let RegexSearch { right_fdfa: fdfa, right_rdfa: rdfa, .. } = regex;
 
         // Find start and end of match.
         let match_end = self.regex_search(start, end, Direction::Right, &fdfa)?;
@@ -188,6 +204,29 @@ impl<T> Term<T> {
         Some(match_start..=match_end)
     }
 
+    /// Iterate over all regex matches which are at least partially visible.
+    pub fn visible_regex_matches<'a>(
+        &'a self,
+        regex: &'a RegexSearch,
+    ) -> impl Iterator<Item = Match> + 'a {
+        let viewport_end = self.grid.display_offset();
+        let viewport_start = viewport_end + self.screen_lines().0 - 1;
+
+        // Compute start of the first and end of the last line.
+        let start_point = Point::new(viewport_start, Column(0));
+        let mut start = self.line_search_left(start_point);
+        let end_point = Point::new(viewport_end, self.cols() - 1);
+        let mut end = self.line_search_right(end_point);
+
+        // Set upper bound on search before/after the viewport to prevent excessive blocking.
+        start.line = min(start.line, viewport_start + MAX_SEARCH_LINES);
+        end.line = max(end.line, viewport_end.saturating_sub(MAX_SEARCH_LINES));
+
+        RegexIter::new(start, end, Direction::Right, self, regex)
+            .skip_while(move |rm| rm.end().line > viewport_start)
+            .take_while(move |rm| rm.start().line >= viewport_end)
+    }
+
     /// Find the next regex match.
     ///
     /// This will always return the side of the first match which is farthest from the start point.
@@ -437,6 +476,7 @@ pub struct RegexIter<'a, T> {
     end: Point<usize>,
     direction: Direction,
     term: &'a Term<T>,
+    regex: &'a RegexSearch,
     done: bool,
 }
 
@@ -446,8 +486,9 @@ impl<'a, T> RegexIter<'a, T> {
         end: Point<usize>,
         direction: Direction,
         term: &'a Term<T>,
+        regex: &'a RegexSearch,
     ) -> Self {
-        Self { point: start, done: false, end, direction, term }
+        Self { point: start, done: false, end, direction, term, regex }
     }
 
     /// Skip one cell, advancing the origin point to the next one.
@@ -463,8 +504,8 @@ impl<'a, T> RegexIter<'a, T> {
     /// Get the next match in the specified direction.
     fn next_match(&self) -> Option<Match> {
         match self.direction {
-            Direction::Right => self.term.regex_search_right(self.point, self.end),
-            Direction::Left => self.term.regex_search_left(self.point, self.end),
+            Direction::Right => self.term.regex_search_right(self.regex, self.point, self.end),
+            Direction::Left => self.term.regex_search_left(self.regex, self.point, self.end),
         }
     }
 }
@@ -498,7 +539,7 @@ mod tests {
     #[test]
     fn regex_right() {
         #[rustfmt::skip]
-        let mut term = mock_term("\
+        let term = mock_term("\
             testing66\r\n\
             Alacritty\n\
             123\r\n\
@@ -507,18 +548,18 @@ mod tests {
         ");
 
         // Check regex across wrapped and unwrapped lines.
-        term.regex_search = Some(RegexSearch::new("Ala.*123").unwrap());
+        let dfas = RegexSearch::new("Ala.*123").unwrap();
         let start = Point::new(3, Column(0));
         let end = Point::new(0, Column(2));
         let match_start = Point::new(3, Column(0));
         let match_end = Point::new(2, Column(2));
-        assert_eq!(term.regex_search_right(start, end), Some(match_start..=match_end));
+        assert_eq!(term.regex_search_right(&dfas, start, end), Some(match_start..=match_end));
     }
 
     #[test]
     fn regex_left() {
         #[rustfmt::skip]
-        let mut term = mock_term("\
+        let term = mock_term("\
             testing66\r\n\
             Alacritty\n\
             123\r\n\
@@ -527,209 +568,209 @@ mod tests {
         ");
 
         // Check regex across wrapped and unwrapped lines.
-        term.regex_search = Some(RegexSearch::new("Ala.*123").unwrap());
+        let dfas = RegexSearch::new("Ala.*123").unwrap();
         let start = Point::new(0, Column(2));
         let end = Point::new(3, Column(0));
         let match_start = Point::new(3, Column(0));
         let match_end = Point::new(2, Column(2));
-        assert_eq!(term.regex_search_left(start, end), Some(match_start..=match_end));
+        assert_eq!(term.regex_search_left(&dfas, start, end), Some(match_start..=match_end));
     }
 
     #[test]
     fn nested_regex() {
         #[rustfmt::skip]
-        let mut term = mock_term("\
+        let term = mock_term("\
             Ala -> Alacritty -> critty\r\n\
             critty\
         ");
 
         // Greedy stopped at linebreak.
-        term.regex_search = Some(RegexSearch::new("Ala.*critty").unwrap());
+        let dfas = RegexSearch::new("Ala.*critty").unwrap();
         let start = Point::new(1, Column(0));
         let end = Point::new(1, Column(25));
-        assert_eq!(term.regex_search_right(start, end), Some(start..=end));
+        assert_eq!(term.regex_search_right(&dfas, start, end), Some(start..=end));
 
         // Greedy stopped at dead state.
-        term.regex_search = Some(RegexSearch::new("Ala[^y]*critty").unwrap());
+        let dfas = RegexSearch::new("Ala[^y]*critty").unwrap();
         let start = Point::new(1, Column(0));
         let end = Point::new(1, Column(15));
-        assert_eq!(term.regex_search_right(start, end), Some(start..=end));
+        assert_eq!(term.regex_search_right(&dfas, start, end), Some(start..=end));
     }
 
     #[test]
     fn no_match_right() {
         #[rustfmt::skip]
-        let mut term = mock_term("\
+        let term = mock_term("\
             first line\n\
             broken second\r\n\
             third\
         ");
 
-        term.regex_search = Some(RegexSearch::new("nothing").unwrap());
+        let dfas = RegexSearch::new("nothing").unwrap();
         let start = Point::new(2, Column(0));
         let end = Point::new(0, Column(4));
-        assert_eq!(term.regex_search_right(start, end), None);
+        assert_eq!(term.regex_search_right(&dfas, start, end), None);
     }
 
     #[test]
     fn no_match_left() {
         #[rustfmt::skip]
-        let mut term = mock_term("\
+        let term = mock_term("\
             first line\n\
             broken second\r\n\
             third\
         ");
 
-        term.regex_search = Some(RegexSearch::new("nothing").unwrap());
+        let dfas = RegexSearch::new("nothing").unwrap();
         let start = Point::new(0, Column(4));
         let end = Point::new(2, Column(0));
-        assert_eq!(term.regex_search_left(start, end), None);
+        assert_eq!(term.regex_search_left(&dfas, start, end), None);
     }
 
     #[test]
     fn include_linebreak_left() {
         #[rustfmt::skip]
-        let mut term = mock_term("\
+        let term = mock_term("\
             testing123\r\n\
             xxx\
         ");
 
         // Make sure the cell containing the linebreak is not skipped.
-        term.regex_search = Some(RegexSearch::new("te.*123").unwrap());
+        let dfas = RegexSearch::new("te.*123").unwrap();
         let start = Point::new(0, Column(0));
         let end = Point::new(1, Column(0));
         let match_start = Point::new(1, Column(0));
         let match_end = Point::new(1, Column(9));
-        assert_eq!(term.regex_search_left(start, end), Some(match_start..=match_end));
+        assert_eq!(term.regex_search_left(&dfas, start, end), Some(match_start..=match_end));
     }
 
     #[test]
     fn include_linebreak_right() {
         #[rustfmt::skip]
-        let mut term = mock_term("\
+        let term = mock_term("\
             xxx\r\n\
             testing123\
         ");
 
         // Make sure the cell containing the linebreak is not skipped.
-        term.regex_search = Some(RegexSearch::new("te.*123").unwrap());
+        let dfas = RegexSearch::new("te.*123").unwrap();
         let start = Point::new(1, Column(2));
         let end = Point::new(0, Column(9));
         let match_start = Point::new(0, Column(0));
-        assert_eq!(term.regex_search_right(start, end), Some(match_start..=end));
+        assert_eq!(term.regex_search_right(&dfas, start, end), Some(match_start..=end));
     }
 
     #[test]
     fn skip_dead_cell() {
-        let mut term = mock_term("alacritty");
+        let term = mock_term("alacritty");
 
         // Make sure dead state cell is skipped when reversing.
-        term.regex_search = Some(RegexSearch::new("alacrit").unwrap());
+        let dfas = RegexSearch::new("alacrit").unwrap();
         let start = Point::new(0, Column(0));
         let end = Point::new(0, Column(6));
-        assert_eq!(term.regex_search_right(start, end), Some(start..=end));
+        assert_eq!(term.regex_search_right(&dfas, start, end), Some(start..=end));
     }
 
     #[test]
     fn reverse_search_dead_recovery() {
-        let mut term = mock_term("zooo lense");
+        let term = mock_term("zooo lense");
 
         // Make sure the reverse DFA operates the same as a forward DFA.
-        term.regex_search = Some(RegexSearch::new("zoo").unwrap());
+        let dfas = RegexSearch::new("zoo").unwrap();
         let start = Point::new(0, Column(9));
         let end = Point::new(0, Column(0));
         let match_start = Point::new(0, Column(0));
         let match_end = Point::new(0, Column(2));
-        assert_eq!(term.regex_search_left(start, end), Some(match_start..=match_end));
+        assert_eq!(term.regex_search_left(&dfas, start, end), Some(match_start..=match_end));
     }
 
     #[test]
     fn multibyte_unicode() {
-        let mut term = mock_term("testвосибing");
+        let term = mock_term("testвосибing");
 
-        term.regex_search = Some(RegexSearch::new("te.*ing").unwrap());
+        let dfas = RegexSearch::new("te.*ing").unwrap();
         let start = Point::new(0, Column(0));
         let end = Point::new(0, Column(11));
-        assert_eq!(term.regex_search_right(start, end), Some(start..=end));
+        assert_eq!(term.regex_search_right(&dfas, start, end), Some(start..=end));
 
-        term.regex_search = Some(RegexSearch::new("te.*ing").unwrap());
+        let dfas = RegexSearch::new("te.*ing").unwrap();
         let start = Point::new(0, Column(11));
         let end = Point::new(0, Column(0));
-        assert_eq!(term.regex_search_left(start, end), Some(end..=start));
+        assert_eq!(term.regex_search_left(&dfas, start, end), Some(end..=start));
     }
 
     #[test]
     fn fullwidth() {
-        let mut term = mock_term("a🦇x🦇");
+        let term = mock_term("a🦇x🦇");
 
-        term.regex_search = Some(RegexSearch::new("[^ ]*").unwrap());
+        let dfas = RegexSearch::new("[^ ]*").unwrap();
         let start = Point::new(0, Column(0));
         let end = Point::new(0, Column(5));
-        assert_eq!(term.regex_search_right(start, end), Some(start..=end));
+        assert_eq!(term.regex_search_right(&dfas, start, end), Some(start..=end));
 
-        term.regex_search = Some(RegexSearch::new("[^ ]*").unwrap());
+        let dfas = RegexSearch::new("[^ ]*").unwrap();
         let start = Point::new(0, Column(5));
         let end = Point::new(0, Column(0));
-        assert_eq!(term.regex_search_left(start, end), Some(end..=start));
+        assert_eq!(term.regex_search_left(&dfas, start, end), Some(end..=start));
     }
 
     #[test]
     fn singlecell_fullwidth() {
-        let mut term = mock_term("🦇");
+        let term = mock_term("🦇");
 
-        term.regex_search = Some(RegexSearch::new("🦇").unwrap());
+        let dfas = RegexSearch::new("🦇").unwrap();
         let start = Point::new(0, Column(0));
         let end = Point::new(0, Column(1));
-        assert_eq!(term.regex_search_right(start, end), Some(start..=end));
+        assert_eq!(term.regex_search_right(&dfas, start, end), Some(start..=end));
 
-        term.regex_search = Some(RegexSearch::new("🦇").unwrap());
+        let dfas = RegexSearch::new("🦇").unwrap();
         let start = Point::new(0, Column(1));
         let end = Point::new(0, Column(0));
-        assert_eq!(term.regex_search_left(start, end), Some(end..=start));
+        assert_eq!(term.regex_search_left(&dfas, start, end), Some(end..=start));
     }
 
     #[test]
     fn wrapping() {
         #[rustfmt::skip]
-        let mut term = mock_term("\
+        let term = mock_term("\
             xxx\r\n\
             xxx\
         ");
 
-        term.regex_search = Some(RegexSearch::new("xxx").unwrap());
+        let dfas = RegexSearch::new("xxx").unwrap();
         let start = Point::new(0, Column(2));
         let end = Point::new(1, Column(2));
         let match_start = Point::new(1, Column(0));
-        assert_eq!(term.regex_search_right(start, end), Some(match_start..=end));
+        assert_eq!(term.regex_search_right(&dfas, start, end), Some(match_start..=end));
 
-        term.regex_search = Some(RegexSearch::new("xxx").unwrap());
+        let dfas = RegexSearch::new("xxx").unwrap();
         let start = Point::new(1, Column(0));
         let end = Point::new(0, Column(0));
         let match_end = Point::new(0, Column(2));
-        assert_eq!(term.regex_search_left(start, end), Some(end..=match_end));
+        assert_eq!(term.regex_search_left(&dfas, start, end), Some(end..=match_end));
     }
 
     #[test]
     fn wrapping_into_fullwidth() {
         #[rustfmt::skip]
-        let mut term = mock_term("\
+        let term = mock_term("\
             🦇xx\r\n\
             xx🦇\
         ");
 
-        term.regex_search = Some(RegexSearch::new("🦇x").unwrap());
+        let dfas = RegexSearch::new("🦇x").unwrap();
         let start = Point::new(0, Column(0));
         let end = Point::new(1, Column(3));
         let match_start = Point::new(1, Column(0));
         let match_end = Point::new(1, Column(2));
-        assert_eq!(term.regex_search_right(start, end), Some(match_start..=match_end));
+        assert_eq!(term.regex_search_right(&dfas, start, end), Some(match_start..=match_end));
 
-        term.regex_search = Some(RegexSearch::new("x🦇").unwrap());
+        let dfas = RegexSearch::new("x🦇").unwrap();
         let start = Point::new(1, Column(2));
         let end = Point::new(0, Column(0));
         let match_start = Point::new(0, Column(1));
         let match_end = Point::new(0, Column(3));
-        assert_eq!(term.regex_search_left(start, end), Some(match_start..=match_end));
+        assert_eq!(term.regex_search_left(&dfas, start, end), Some(match_start..=match_end));
     }
 
     #[test]
@@ -741,32 +782,32 @@ mod tests {
         ");
         term.grid[1][Column(3)].flags.insert(Flags::LEADING_WIDE_CHAR_SPACER);
 
-        term.regex_search = Some(RegexSearch::new("🦇x").unwrap());
+        let dfas = RegexSearch::new("🦇x").unwrap();
         let start = Point::new(1, Column(0));
         let end = Point::new(0, Column(3));
         let match_start = Point::new(1, Column(3));
         let match_end = Point::new(0, Column(2));
-        assert_eq!(term.regex_search_right(start, end), Some(match_start..=match_end));
+        assert_eq!(term.regex_search_right(&dfas, start, end), Some(match_start..=match_end));
 
-        term.regex_search = Some(RegexSearch::new("🦇x").unwrap());
+        let dfas = RegexSearch::new("🦇x").unwrap();
         let start = Point::new(0, Column(3));
         let end = Point::new(1, Column(0));
         let match_start = Point::new(1, Column(3));
         let match_end = Point::new(0, Column(2));
-        assert_eq!(term.regex_search_left(start, end), Some(match_start..=match_end));
+        assert_eq!(term.regex_search_left(&dfas, start, end), Some(match_start..=match_end));
 
-        term.regex_search = Some(RegexSearch::new("x🦇").unwrap());
+        let dfas = RegexSearch::new("x🦇").unwrap();
         let start = Point::new(1, Column(0));
         let end = Point::new(0, Column(3));
         let match_start = Point::new(1, Column(2));
         let match_end = Point::new(0, Column(1));
-        assert_eq!(term.regex_search_right(start, end), Some(match_start..=match_end));
+        assert_eq!(term.regex_search_right(&dfas, start, end), Some(match_start..=match_end));
 
-        term.regex_search = Some(RegexSearch::new("x🦇").unwrap());
+        let dfas = RegexSearch::new("x🦇").unwrap();
         let start = Point::new(0, Column(3));
         let end = Point::new(1, Column(0));
         let match_start = Point::new(1, Column(2));
         let match_end = Point::new(0, Column(1));
-        assert_eq!(term.regex_search_left(start, end), Some(match_start..=match_end));
+        assert_eq!(term.regex_search_left(&dfas, start, end), Some(match_start..=match_end));
     }
 }
@@ -0,0 +1,170 @@
+//! Terminal state which can be saved and restored across sessions.
+
+use std::path::PathBuf;
+
+use log::error;
+use serde::{Deserialize, Serialize};
+
+use crate::ansi::Handler;
+use crate::event::EventListener;
+use crate::grid::{Cursor, Dimensions, Grid};
+use crate::index::{Column, Line};
+use crate::term::cell::Cell;
+use crate::term::color::{self, Rgb};
+use crate::term::{Term, TermMode};
+
+/// Snapshot of a terminal's state.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Session {
+    /// Primary screen content, including the scrollback history.
+    pub grid: Grid<Cell>,
+
+    /// Window title set by the application.
+    #[serde(default)]
+    pub title: Option<String>,
+
+    /// Working directory of the shell.
+    #[serde(default)]
+    pub working_directory: Option<PathBuf>,
+
+    /// Colors modified through escape sequences, by palette index.
+    #[serde(default)]
+    pub colors: Vec<(usize, Rgb)>,
+}
+
+impl<T> Term<T> {
+    /// Create a snapshot of the terminal's state.
+    pub fn session(&self) -> Session {
+        // The primary screen is inactive while the alternate screen is shown.
+        let mut grid = if self.mode.contains(TermMode::ALT_SCREEN) {
+            self.inactive_grid.clone()
+        } else {
+            self.grid.clone()
+        };
+        grid.truncate();
+
+        let colors = (0..color::COUNT)
+            .filter(|&index| self.color_modified[index])
+            .map(|index| (index, self.colors[index]))
+            .collect();
+
+        Session {
+            grid,
+            title: self.title.clone(),
+            working_directory: self.working_directory.clone(),
+            colors,
+        }
+    }
+
+    /// Restore a snapshot of a previous terminal.
+    ///
+    /// The content of the previous terminal is moved into the scrollback history, leaving an
+    /// empty screen for the new shell.
+    pub fn restore_session(&mut self, session: Session)
+    where
+        T: EventListener,
+    {
+        let mut grid = session.grid;
+
+        // Find the last line with content, everything below it is dropped.
+        let content_line = if grid.is_valid() {
+            (0..grid.screen_lines().0).rev().find(|&line| !grid[Line(line)].is_clear())
+        } else {
+            error!("Ignoring invalid session content");
+            None
+        };
+
+        if let Some(content_line) = content_line {
+            grid.cursor = Cursor::default();
+            grid.cursor.point.line = Line(content_line);
+
+            // Fit the previous content into the current terminal dimensions.
+            grid.update_history(self.grid.max_scroll_limit());
+            grid.resize(true, self.screen_lines(), self.cols());
+
+            // Push all content into history.
+            let num_lines = self.screen_lines();
+            let positions = grid.cursor.point.line + 1;
+            grid.scroll_up(&(Line(0)..num_lines), &(Column(0)..self.cols()), positions);
+
+            grid.cursor = self.grid.cursor.clone();
+            grid.saved_cursor = self.grid.saved_cursor.clone();
+            self.grid = grid;
+            self.selection = None;
+        }
+
+        for (index, color) in session.colors.into_iter().filter(|(index, _)| *index < color::COUNT)
+        {
+            self.set_color(index, color);
+        }
+
+        if session.title.is_some() {
+            self.set_title(session.title);
+        }
+
+        self.working_directory = session.working_directory;
+        self.dirty = true;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::ansi::NamedColor;
+    use crate::config::Config;
+    use crate::event::Event;
+    use crate::term::test::mock_term;
+    use crate::term::SizeInfo;
+
+    struct Mock;
+    impl EventListener for Mock {
+        fn send_event(&self, _event: Event) {}
+    }
+
+    #[test]
+    fn restore_into_history() {
+        #[rustfmt::skip]
+        let mut previous = mock_term("\
+            first\r\n\
+            second\r\n\
+            \r\n\
+            \r\n\
+        ");
+        previous.colors[NamedColor::Background] = Rgb { r: 1, g: 2, b: 3 };
+        previous.color_modified[NamedColor::Background as usize] = true;
+        previous.title = Some("title".into());
+
+        let session: Session =
+            serde_json::from_str(&serde_json::to_string(&previous.session()).unwrap()).unwrap();
+
+        let size = SizeInfo::new(10., 3., 1., 1., 0., 0., false);
+        let mut term = Term::new(&Config::<()>::default(), size, Mock);
+        term.restore_session(session);
+
+        // The previous content is moved above the visible area.
+        assert_eq!(term.history_size(), 2);
+        assert_eq!(term.grid()[4][Column(0)].c, 'f');
+        assert_eq!(term.grid()[3][Column(5)].c, 'd');
+        assert!((0..3).all(|line| term.grid()[line].is_clear()));
+
+        assert_eq!(term.colors[NamedColor::Background], Rgb { r: 1, g: 2, b: 3 });
+        assert_eq!(term.title.as_deref(), Some("title"));
+    }
+
+    #[test]
+    fn restore_invalid_grid() {
+        let previous = mock_term("first\r\nsecond");
+        let mut session = serde_json::to_value(previous.session()).unwrap();
+        session["grid"]["cols"] = 1000.into();
+        session["grid"]["display_offset"] = 5.into();
+        let session: Session = serde_json::from_value(session).unwrap();
+
+        let size = SizeInfo::new(10., 3., 1., 1., 0., 0., false);
+        let mut term = Term::new(&Config::<()>::default(), size, Mock);
+        term.restore_session(session);
+
+        assert_eq!(term.history_size(), 0);
+        assert!((0..3).all(|line| term.grid()[line].is_clear()));
+    }
+}
@@ -0,0 +1,59 @@
+//! Built-in terminfo capabilities reported through XTGETTCAP.
+//!
+//! The values are based on `extra/alacritty.info`, allowing applications to query capabilities
+//! when the terminfo database on the host is missing or outdated.
+
+/// Capabilities as `(name, value)` pairs, with an empty value for boolean capabilities.
+const CAPABILITIES: &[(&str, &str)] = &[
+    // Terminal name.
+    ("TN", "alacritty"),
+    ("name", "alacritty"),
+    // Colors.
+    ("Co", "256"),
+    ("colors", "256"),
+    ("RGB", "8/8/8"),
+    ("Tc", ""),
+    ("ccc", ""),
+    ("AX", ""),
+    ("bce", ""),
+    ("op", "\x1b[39;49m"),
+    ("setrgbf", "\x1b[38:2:%p1%d:%p2%d:%p3%dm"),
+    ("setrgbb", "\x1b[48:2:%p1%d:%p2%d:%p3%dm"),
+    // Text attributes.
+    ("sitm", "\x1b[3m"),
+    ("ritm", "\x1b[23m"),
+    ("smxx", "\x1b[9m"),
+    ("rmxx", "\x1b[29m"),
+    ("Smulx", "\x1b[4:%p1%dm"),
+    ("Setulc", "\x1b[58:2::%p1%{65536}%/%d:%p1%{256}%/%{255}%&%d:%p1%{255}%&%d%;m"),
+    // Cursor style and color.
+    ("Ss", "\x1b[%p1%d q"),
+    ("Se", "\x1b[0 q"),
+    ("Cs", "\x1b]12;%p1%s\x07"),
+    ("Cr", "\x1b]112\x07"),
+    // Clipboard.
+    ("Ms", "\x1b]52;%p1%s;%p2%s\x07"),
+    // Synchronized updates.
+    ("Sync", "\x1b[?2026%?%p1%{1}%-%tl%eh%;"),
+    // Bracketed paste.
+    ("BE", "\x1b[?2004h"),
+    ("BD", "\x1b[?2004l"),
+    ("PS", "\x1b[200~"),
+    ("PE", "\x1b[201~"),
+    // Focus reporting.
+    ("fe", "\x1b[?1004h"),
+    ("fd", "\x1b[?1004l"),
+    // Alternate screen.
+    ("smcup", "\x1b[?1049h\x1b[22;0;0t"),
+    ("rmcup", "\x1b[?1049l\x1b[23;0;0t"),
+    // Keys.
+    ("kbs", "\x7f"),
+    ("kdch1", "\x1b[3~"),
+    ("khome", "\x1bOH"),
+    ("kend", "\x1bOF"),
+];
+
+/// Value of the terminfo capability `name`.
+pub fn capability(name: &str) -> Option<&'static str> {
+    CAPABILITIES.iter().find(|(capability, _)| *capability == name).map(|(_, value)| *value)
+}
@@ -1,6 +1,6 @@
 //! TTY related functionality.
 
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::{env, io};
 
 use crate::config::Config;
@@ -59,8 +59,11 @@ pub trait EventedPty: EventedReadWrite {
     fn next_child_event(&mut self) -> Option<ChildEvent>;
 }
 
+/// Environment variable advertising the IPC socket to child processes.
+pub const ALACRITTY_SOCKET_ENV: &str = "ALACRITTY_SOCKET";
+
 /// Setup environment variables.
-pub fn setup_env<C>(config: &Config<C>) {
+pub fn setup_env<C>(config: &Config<C>, ipc_socket: Option<&Path>) {
     // Default to 'alacritty' terminfo if it is available, otherwise
     // default to 'xterm-256color'. May be overridden by user's config
     // below.
@@ -73,6 +76,11 @@ pub fn setup_env<C>(config: &Config<C>) {
     // Prevent child processes from inheriting startup notification env.
     env::remove_var("DESKTOP_STARTUP_ID");
 
+    // Allow child processes to send IPC messages to this instance.
+    if let Some(ipc_socket) = ipc_socket {
+        env::set_var(ALACRITTY_SOCKET_ENV, ipc_socket);
+    }
+
     // Set env vars from config.
     for (key, value) in config.env.iter() {
         env::set_var(key, value);
@@ -13,7 +13,6 @@ use std::os::unix::{
 };
 use std::process::{Child, Command, Stdio};
 use std::ptr;
-use std::sync::atomic::{AtomicI32, AtomicUsize, Ordering};
 
 use libc::{self, c_int, pid_t, winsize, TIOCSCTTY};
 use log::error;
@@ -28,14 +27,6 @@ use crate::event::OnResize;
 use crate::term::SizeInfo;
 use crate::tty::{ChildEvent, EventedPty, EventedReadWrite};
 
-/// Process ID of child process.
-///
-/// Necessary to put this in static storage for `SIGCHLD` to have access.
-static PID: AtomicUsize = AtomicUsize::new(0);
-
-/// File descriptor of terminal master.
-static FD: AtomicI32 = AtomicI32::new(-1);
-
 macro_rules! die {
     ($($arg:tt)*) => {{
         error!($($arg)*);
@@ -43,14 +34,6 @@ macro_rules! die {
     }}
 }
 
-pub fn child_pid() -> pid_t {
-    PID.load(Ordering::Relaxed) as pid_t
-}
-
-pub fn master_fd() -> RawFd {
-    FD.load(Ordering::Relaxed) as RawFd
-}
-
 /// Get raw fds for master/slave ends of a new PTY.
 fn make_pty(size: winsize) -> (RawFd, RawFd) {
     let mut win_size = size;
@@ -138,6 +121,18 @@ pub struct Pty {
     signals_token: mio::Token,
 }
 
+impl Pty {
+    /// Shell process spawned inside the PTY.
+    pub fn child(&self) -> &Child {
+        &self.child
+    }
+
+    /// File descriptor of the PTY master.
+    pub fn file(&self) -> &File {
+        &self.fd
+    }
+}
+
 #[cfg(target_os = "macos")]
 fn default_shell(pw: &Passwd<'_>) -> Program {
     let shell_name = pw.shell.rsplit('/').next().unwrap();
@@ -151,6 +146,13 @@ fn default_shell(pw: &Passwd<'_>) -> Program {
     Program::Just(env::var("SHELL").unwrap_or_else(|_| pw.shell.to_owned()))
 }
 
+/// Get the hostname of the local machine.
+pub fn hostname() -> Option<String> {
+    let mut buf = [0u8; 256];
+    let hostname = nix::unistd::gethostname(&mut buf).ok()?;
+    hostname.to_str().ok().map(String::from)
+}
+
 /// Create a new TTY and return a handle to interact with it.
 pub fn new<C>(config: &Config<C>, size: &SizeInfo, window_id: Option<usize>) -> Pty {
     let (master, slave) = make_pty(size.to_winsize());
@@ -231,10 +233,6 @@ pub fn new<C>(config: &Config<C>, size: &SizeInfo, window_id: Option<usize>) ->
 
     match builder.spawn() {
         Ok(child) => {
-            // Remember master FD and child PID so other modules can use it.
-            PID.store(child.id() as usize, Ordering::Relaxed);
-            FD.store(master, Ordering::Relaxed);
-
             unsafe {
                 // Maybe this should be done outside of this function so nonblocking
                 // isn't forced upon consumers. Although maybe it should be?
@@ -255,6 +253,18 @@ pub fn new<C>(config: &Config<C>, size: &SizeInfo, window_id: Option<usize>) ->
     }
 }
 
+impl Drop for Pty {
+    fn drop(&mut self) {
+        // Make sure the shell is terminated and reaped, since Alacritty can outlive its PTYs.
+        if let Ok(None) = self.child.try_wait() {
+            unsafe {
+                libc::kill(self.child.id() as pid_t, libc::SIGHUP);
+            }
+            let _ = self.child.wait();
+        }
+    }
+}
+
 impl EventedReadWrite for Pty {
     type Reader = File;
     type Writer = File;
@@ -1,3 +1,4 @@
+use std::env;
 use std::ffi::OsStr;
 use std::io;
 use std::iter::once;
@@ -175,6 +176,11 @@ fn cmdline<C>(config: &Config<C>) -> String {
         .join(" ")
 }
 
+/// Get the hostname of the local machine.
+pub fn hostname() -> Option<String> {
+    env::var("COMPUTERNAME").ok()
+}
+
 /// Converts the string slice into a Windows-standard representation for "W"-
 /// suffixed function variants, which accept UTF-16 encoded string values.
 pub fn win32_string<S: AsRef<OsStr> + ?Sized>(value: &S) -> Vec<u16> {
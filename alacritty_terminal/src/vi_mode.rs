@@ -49,6 +49,10 @@ pub enum ViMotion {
     WordRightEnd,
     /// Move to opposing bracket.
     Bracket,
+    /// Move to start of the previous shell prompt.
+    PreviousPrompt,
+    /// Move to start of the next shell prompt.
+    NextPrompt,
 }
 
 /// Cursor tracking vi mode position.
@@ -152,6 +156,16 @@ impl ViModeCursor {
             ViMotion::Bracket => {
                 buffer_point = term.bracket_search(buffer_point).unwrap_or(buffer_point);
             },
+            ViMotion::PreviousPrompt => {
+                if let Some(line) = term.prompt_search(buffer_point.line, Direction::Left) {
+                    buffer_point = Point::new(line, Column(0));
+                }
+            },
+            ViMotion::NextPrompt => {
+                if let Some(line) = term.prompt_search(buffer_point.line, Direction::Right) {
+                    buffer_point = Point::new(line, Column(0));
+                }
+            },
         }
 
         term.scroll_to_point(buffer_point);
@@ -398,6 +412,7 @@ mod tests {
 
     use crate::config::MockConfig;
     use crate::event::Event;
+    use crate::grid::PromptMarks;
     use crate::index::{Column, Line};
     use crate::term::{SizeInfo, Term};
 
@@ -515,6 +530,30 @@ mod tests {
         assert_eq!(cursor.point, Point::new(Line(0), Column(0)));
     }
 
+    #[test]
+    fn motion_prompt() {
+        let mut term = term();
+        term.grid_mut()[Line(2)].marks.insert(PromptMarks::PROMPT_START);
+        term.grid_mut()[Line(10)].marks.insert(PromptMarks::PROMPT_START);
+
+        let mut cursor = ViModeCursor::new(Point::new(Line(15), Column(5)));
+
+        cursor = cursor.motion(&mut term, ViMotion::PreviousPrompt);
+        assert_eq!(cursor.point, Point::new(Line(10), Column(0)));
+
+        cursor = cursor.motion(&mut term, ViMotion::PreviousPrompt);
+        assert_eq!(cursor.point, Point::new(Line(2), Column(0)));
+
+        cursor = cursor.motion(&mut term, ViMotion::PreviousPrompt);
+        assert_eq!(cursor.point, Point::new(Line(2), Column(0)));
+
+        cursor = cursor.motion(&mut term, ViMotion::NextPrompt);
+        assert_eq!(cursor.point, Point::new(Line(10), Column(0)));
+
+        cursor = cursor.motion(&mut term, ViMotion::NextPrompt);
+        assert_eq!(cursor.point, Point::new(Line(10), Column(0)));
+    }
+
     fn motion_semantic_term() -> Term<Mock> {
         let mut term = term();
 
@@ -653,7 +692,8 @@ mod tests {
     #[test]
     fn scroll_semantic() {
         let mut term = term();
-        term.grid_mut().scroll_up(&(Line(0)..Line(20)), Line(5));
+        let columns = Column(0)..term.cols();
+        term.grid_mut().scroll_up(&(Line(0)..Line(20)), &columns, Line(5));
 
         let mut cursor = ViModeCursor::new(Point::new(Line(0), Column(0)));
 
@@ -729,7 +769,8 @@ mod tests {
     #[test]
     fn scroll_word() {
         let mut term = term();
-        term.grid_mut().scroll_up(&(Line(0)..Line(20)), Line(5));
+        let columns = Column(0)..term.cols();
+        term.grid_mut().scroll_up(&(Line(0)..Line(20)), &columns, Line(5));
 
         let mut cursor = ViModeCursor::new(Point::new(Line(0), Column(0)));
 
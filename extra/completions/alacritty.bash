@@ -11,7 +11,7 @@ _alacritty()
     cur="${COMP_WORDS[COMP_CWORD]}"
     prev="${COMP_WORDS[COMP_CWORD-1]}"
     prevprev="${COMP_WORDS[COMP_CWORD-2]}"
-    opts="-h --help -V --version --print-events -q -qq -v -vv -vvv --ref-test --hold -e --command --config-file -o --option -t --title --embed --class --working-directory"
+    opts="-h --help -V --version --print-events -q -qq -v -vv -vvv --ref-test --headless --hold --restore -e --command --config-file -o --option -t --title --embed --class --working-directory --record --replay --replay-speed --script msg"
 
     # If `--command` or `-e` is used, stop completing
     for i in "${!COMP_WORDS[@]}"; do
@@ -23,19 +23,34 @@ _alacritty()
         fi
     done
 
+    # Complete the `msg` subcommand's messages
+    if [[ "${COMP_WORDS[1]}" == "msg" ]]; then
+        case "${prev}" in
+            --socket | -s)
+                local IFS=$'\n'
+                compopt -o filenames
+                COMPREPLY=( $(compgen -f -- "${cur}") )
+                return 0;;
+            msg)
+                COMPREPLY=( $(compgen -W "-s --socket create-window reload-config config action get-working-directory" -- "${cur}") )
+                return 0;;
+        esac
+        return 0
+    fi
+
     # Match the previous word
     case "${prev}" in
         --command | -e)
             # Complete all commands in $PATH
             COMPREPLY=( $(compgen -c -- "${cur}") )
             return 0;;
-        --config-file)
+        --config-file | --record | --replay | --script)
             # Path based completion
             local IFS=$'\n'
             compopt -o filenames
             COMPREPLY=( $(compgen -f -- "${cur}") )
             return 0;;
-        --dimensions | -d | --class | --title | -t)
+        --dimensions | -d | --class | --title | -t | --replay-speed)
             # Don't complete here
             return 0;;
         --working-directory)
@@ -28,9 +28,31 @@ complete -c alacritty \
   -a '(__fish_complete_directories (commandline -ct))' \
   -l "working-directory" \
   -d "Start shell in specified directory"
+complete -c alacritty \
+  -l "headless" \
+  -d "Run the shell without a window, driven by a script"
 complete -c alacritty \
   -l "hold" \
   -d "Remain open after child process exits"
+complete -c alacritty \
+  -l "restore" \
+  -d "Restore the last saved session"
+complete -c alacritty \
+  -r \
+  -l "record" \
+  -d "Record the terminal in the asciicast format"
+complete -c alacritty \
+  -r \
+  -l "replay" \
+  -d "Replay an asciicast recording instead of starting a shell"
+complete -c alacritty \
+  -x \
+  -l "replay-speed" \
+  -d "Playback speed of the replayed recording"
+complete -c alacritty \
+  -r \
+  -l "script" \
+  -d "Script for the headless mode"
 complete -c alacritty \
   -s "o" \
   -l "option" \
@@ -72,3 +94,22 @@ complete \
   -s "e" \
   -l "command" \
   -d "Execute command (must be last arg)"
+
+# Subcommands
+complete \
+  -c alacritty \
+  -n "__fish_use_subcommand" \
+  -f \
+  -a "msg" \
+  -d "Send a message to a running Alacritty instance"
+complete \
+  -c alacritty \
+  -n "__fish_seen_subcommand_from msg" \
+  -f \
+  -a "create-window reload-config config action get-working-directory"
+complete \
+  -c alacritty \
+  -n "__fish_seen_subcommand_from msg" \
+  -s "s" \
+  -l "socket" \
+  -d "IPC socket connection path override"